@@ -0,0 +1,369 @@
+//! Offline cache for bookmark favicons and preview images
+//!
+//! Remote images are downloaded into a content-addressed directory under the
+//! data dir (`assets/<sha256>.<ext>`) and served to the webview through the
+//! `carbon-asset://` URI scheme, so the bookmarks grid works offline and
+//! does not hit third-party servers on every render.
+
+use crate::metadata;
+use crate::{AppData, Bookmark};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::http::{header, Request, Response, StatusCode};
+
+pub const ASSET_SCHEME: &str = "carbon-asset";
+const MAX_ASSET_BYTES: usize = 5 * 1024 * 1024;
+const CONCURRENT_DOWNLOADS: usize = 6;
+/// Unreferenced files younger than this are kept, so a download that has
+/// not been applied to its bookmark yet survives a GC run
+const GC_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A remote image mirrored into the local asset cache
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CachedAsset {
+    /// The remote URL the file was downloaded from
+    pub source: String,
+    /// File name inside the cache: `<sha256>.<ext>`
+    pub file: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssetFailure {
+    #[serde(rename = "bookmarkId")]
+    pub bookmark_id: String,
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct AssetCacheReport {
+    pub cached: usize,
+    pub failed: Vec<AssetFailure>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssetCacheResult {
+    pub data: AppData,
+    pub report: AssetCacheReport,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct AssetGcReport {
+    #[serde(rename = "removedFiles")]
+    pub removed_files: usize,
+    #[serde(rename = "freedBytes")]
+    pub freed_bytes: u64,
+}
+
+pub fn get_assets_dir(data_dir: &Path) -> PathBuf {
+    let dir = data_dir.join("assets");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
+/// Prefix the webview puts in front of a file name served by a custom URI
+/// scheme (`ASSET_SCHEME` for `CachedAsset::file`) to load it.
+///
+/// Custom schemes are exposed as `http://<scheme>.localhost` on Windows and Android
+pub fn base_url(scheme: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/", scheme)
+    } else {
+        format!("{}://localhost/", scheme)
+    }
+}
+
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    Some(match content_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" | "image/pjpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/bmp" => "bmp",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" | "image/ico" => "ico",
+        _ => return None,
+    })
+}
+
+pub fn content_type_for(extension: &str) -> &'static str {
+    match extension {
+        "png" => "image/png",
+        "jpg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Identify an image from its magic bytes (servers often mislabel icons)
+fn sniff_extension(bytes: &[u8]) -> Option<&'static str> {
+    let head = &bytes[..bytes.len().min(256)];
+    if head.starts_with(b"\x89PNG") {
+        Some("png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if head.starts_with(b"GIF8") {
+        Some("gif")
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        Some("webp")
+    } else if head.starts_with(&[0, 0, 1, 0]) {
+        Some("ico")
+    } else if head.starts_with(b"BM") {
+        Some("bmp")
+    } else if String::from_utf8_lossy(head).contains("<svg") {
+        Some("svg")
+    } else {
+        None
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Store bytes under their content hash; identical files are stored once
+pub fn store(dir: &Path, bytes: &[u8], extension: &str) -> Result<String, String> {
+    let file = format!("{}.{}", hex(&Sha256::digest(bytes)), extension);
+    let path = dir.join(&file);
+    if !path.exists() {
+        // Write to a temp file first so a crash never leaves a truncated asset
+        let tmp = dir.join(format!("{}.tmp", file));
+        fs::write(&tmp, bytes).map_err(|e| format!("Failed to write asset: {}", e))?;
+        fs::rename(&tmp, &path).map_err(|e| format!("Failed to store asset: {}", e))?;
+    } else if let Ok(existing) = fs::OpenOptions::new().write(true).open(&path) {
+        // Reused files restart their GC grace period like new ones
+        existing.set_modified(SystemTime::now()).ok();
+    }
+    Ok(file)
+}
+
+/// Download an image into the cache
+pub async fn download(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+) -> Result<CachedAsset, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Server responded with {}", response.status()));
+    }
+    let declared = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    // Read one byte past the limit to detect oversized files
+    let bytes = metadata::read_limited(response, MAX_ASSET_BYTES + 1).await?;
+    if bytes.len() > MAX_ASSET_BYTES {
+        return Err("Image is too large to cache".to_string());
+    }
+    let extension = extension_for(&declared)
+        .or_else(|| sniff_extension(&bytes))
+        .ok_or_else(|| format!("Not an image ({})", declared))?;
+
+    Ok(CachedAsset {
+        source: url.to_string(),
+        file: store(dir, &bytes, extension)?,
+    })
+}
+
+fn needs_download(remote: &Option<String>, cached: &Option<CachedAsset>, dir: &Path) -> bool {
+    match (remote, cached) {
+        (Some(url), Some(asset)) => asset.source != *url || !dir.join(&asset.file).exists(),
+        (Some(url), None) => url.starts_with("http://") || url.starts_with("https://"),
+        (None, _) => false,
+    }
+}
+
+/// Which image of a bookmark a download belongs to
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Slot {
+    Favicon,
+    Image,
+}
+
+/// Download missing or stale favicons and preview images.
+///
+/// Works on a snapshot of the bookmarks and returns the assets per bookmark
+/// id, so the caller can apply them to fresh data once downloads finish.
+pub async fn cache_bookmarks(
+    client: &reqwest::Client,
+    dir: &Path,
+    bookmarks: &[Bookmark],
+    only: Option<&HashSet<String>>,
+) -> (
+    HashMap<String, (Option<CachedAsset>, Option<CachedAsset>)>,
+    AssetCacheReport,
+) {
+    // Many bookmarks share a favicon; download every URL once
+    let mut wanted: HashMap<String, Vec<(String, Slot)>> = HashMap::new();
+    for bookmark in bookmarks {
+        if only.map(|ids| !ids.contains(&bookmark.id)).unwrap_or(false) {
+            continue;
+        }
+        let slots = [
+            (&bookmark.favicon, &bookmark.favicon_asset, Slot::Favicon),
+            (&bookmark.image, &bookmark.image_asset, Slot::Image),
+        ];
+        for (remote, cached, slot) in slots {
+            if needs_download(remote, cached, dir) {
+                if let Some(url) = remote {
+                    wanted
+                        .entry(url.clone())
+                        .or_default()
+                        .push((bookmark.id.clone(), slot));
+                }
+            }
+        }
+    }
+
+    let results: Vec<(String, Result<CachedAsset, String>)> = stream::iter(wanted.keys().cloned())
+        .map(|url| async move {
+            let result = download(client, dir, &url).await;
+            (url, result)
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect()
+        .await;
+
+    let mut report = AssetCacheReport::default();
+    let mut assets: HashMap<String, (Option<CachedAsset>, Option<CachedAsset>)> = HashMap::new();
+    for (url, result) in results {
+        for (bookmark_id, slot) in wanted.get(&url).into_iter().flatten() {
+            match &result {
+                Ok(asset) => {
+                    report.cached += 1;
+                    let entry = assets.entry(bookmark_id.clone()).or_default();
+                    match slot {
+                        Slot::Favicon => entry.0 = Some(asset.clone()),
+                        Slot::Image => entry.1 = Some(asset.clone()),
+                    }
+                }
+                Err(e) => report.failed.push(AssetFailure {
+                    bookmark_id: bookmark_id.clone(),
+                    url: url.clone(),
+                    error: e.clone(),
+                }),
+            }
+        }
+    }
+
+    (assets, report)
+}
+
+/// Delete files in `dir` that are not in `keep` and were last written
+/// longer than `grace` ago. `*.tmp` files are being written by `store` and
+/// are left alone.
+pub fn remove_unreferenced(dir: &Path, keep: &HashSet<String>, grace: Duration) -> AssetGcReport {
+    let mut report = AssetGcReport::default();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return report;
+    };
+    let now = SystemTime::now();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if keep.contains(&name) || name.ends_with(".tmp") {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|m| now.duration_since(m).ok())
+            .unwrap_or_default();
+        if !metadata.is_file() || age < grace {
+            continue;
+        }
+        match fs::remove_file(entry.path()) {
+            Ok(_) => {
+                report.removed_files += 1;
+                report.freed_bytes += metadata.len();
+            }
+            Err(e) => log::warn!("Failed to remove {}: {}", entry.path().display(), e),
+        }
+    }
+    report
+}
+
+/// Delete cached files no bookmark references any more
+pub fn collect_garbage(dir: &Path, data: &AppData) -> AssetGcReport {
+    let referenced: HashSet<String> = data
+        .bookmarks
+        .iter()
+        .flat_map(|b| [&b.favicon_asset, &b.image_asset])
+        .filter_map(|asset| asset.as_ref().map(|a| a.file.clone()))
+        .collect();
+    remove_unreferenced(dir, &referenced, GC_GRACE)
+}
+
+/// Only plain `<hex>.<ext>` names are served, so requests cannot escape the cache dir
+pub fn valid_file_name(name: &str) -> bool {
+    match name.split_once('.') {
+        Some((hash, ext)) => {
+            !hash.is_empty()
+                && hash.chars().all(|c| c.is_ascii_hexdigit())
+                && !ext.is_empty()
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
+
+fn error_response(status: StatusCode) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .body(Vec::new())
+        .unwrap_or_default()
+}
+
+/// Handler for `carbon-asset://localhost/<file>` requests
+pub fn serve(dir: &Path, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    serve_with(dir, request, content_type_for)
+}
+
+/// Serve a content-addressed file from `dir`, typed by its extension
+pub fn serve_with(
+    dir: &Path,
+    request: &Request<Vec<u8>>,
+    content_type: fn(&str) -> &'static str,
+) -> Response<Vec<u8>> {
+    let name = request.uri().path().trim_start_matches('/');
+    if !valid_file_name(name) {
+        return error_response(StatusCode::BAD_REQUEST);
+    }
+    match fs::read(dir.join(name)) {
+        Ok(bytes) => {
+            let extension = name.rsplit('.').next().unwrap_or_default();
+            Response::builder()
+                .header(header::CONTENT_TYPE, content_type(extension))
+                // Content-addressed files never change
+                .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                .body(bytes)
+                .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+        Err(_) => error_response(StatusCode::NOT_FOUND),
+    }
+}
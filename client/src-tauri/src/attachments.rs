@@ -0,0 +1,383 @@
+//! File attachments for notes and cards
+//!
+//! Files are stored once under their content hash in `attachments/` in the
+//! data dir (`<sha256>.<ext>`) and served to the webview through the
+//! `carbon-attachment://` URI scheme. Notes and cards list what they hold in
+//! `attachments`; Markdown embeds a file as `![name](attachment:<file>)`.
+//!
+//! Files nothing refers to any more are removed by `collect_garbage`, but
+//! only after a grace period, so an image pasted into a note that has not
+//! been saved yet (or an edit that is about to be undone) keeps its file.
+
+use crate::asset_cache::{self, AssetGcReport};
+use crate::search::DocKind;
+use crate::{AppData, Board, Note};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::http::{Request, Response};
+
+pub const ATTACHMENT_SCHEME: &str = "carbon-attachment";
+/// How Markdown refers to a stored file: `attachment:<file>`
+pub const LINK_PREFIX: &str = "attachment:";
+const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+/// Unreferenced files younger than this are kept
+const GC_GRACE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A file attached to a note or card
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// File name inside the store: `<sha256>.<ext>`
+    pub file: String,
+    /// Original file name, for display and downloads
+    pub name: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: u64,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AttachResult {
+    pub data: AppData,
+    pub attachment: Attachment,
+}
+
+pub fn get_attachments_dir(data_dir: &Path) -> PathBuf {
+    let dir = data_dir.join("attachments");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
+pub fn content_type_for(extension: &str) -> &'static str {
+    match extension {
+        "pdf" => "application/pdf",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        image => asset_cache::content_type_for(image),
+    }
+}
+
+fn extension_for_mime(mime_type: &str) -> Option<&'static str> {
+    Some(match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        _ => return None,
+    })
+}
+
+/// Extension to store a file under, from its name or else its MIME type
+fn extension(name: &str, mime_type: &str) -> String {
+    let from_name = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    match from_name {
+        Some(ext) if ext == "jpeg" => "jpg".to_string(),
+        Some(ext) => ext,
+        None => extension_for_mime(mime_type).unwrap_or("bin").to_string(),
+    }
+}
+
+/// Add bytes to the store; identical files are stored once
+pub fn store_bytes(
+    dir: &Path,
+    bytes: &[u8],
+    name: &str,
+    mime_type: Option<&str>,
+    now: &str,
+) -> Result<Attachment, String> {
+    if bytes.len() as u64 > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "{} is larger than {} MB",
+            name,
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ));
+    }
+    let extension = extension(name, mime_type.unwrap_or_default());
+    let file = asset_cache::store(dir, bytes, &extension)?;
+    let mime_type = mime_type
+        .filter(|m| !m.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| content_type_for(&extension).to_string());
+    Ok(Attachment {
+        file,
+        name: name.to_string(),
+        mime_type,
+        size: bytes.len() as u64,
+        created_at: now.to_string(),
+    })
+}
+
+/// Copy a file from disk into the store
+pub fn store_file(dir: &Path, path: &Path, now: &str) -> Result<Attachment, String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "{} is larger than {} MB",
+            path.display(),
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ));
+    }
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string());
+    store_bytes(dir, &bytes, &name, None, now)
+}
+
+fn attachments_of<'a>(
+    data: &'a mut AppData,
+    kind: DocKind,
+    id: &str,
+) -> Result<&'a mut Vec<Attachment>, String> {
+    match kind {
+        DocKind::Note => data
+            .notes
+            .iter_mut()
+            .find(|n| n.id == id)
+            .map(|n| &mut n.attachments)
+            .ok_or_else(|| format!("Note not found: {}", id)),
+        DocKind::Card => data
+            .cards_mut()
+            .find(|c| c.id == id)
+            .map(|c| &mut c.attachments)
+            .ok_or_else(|| format!("Card not found: {}", id)),
+        DocKind::Bookmark => Err("Bookmarks cannot have attachments".to_string()),
+    }
+}
+
+fn touch_note(data: &mut AppData, kind: DocKind, id: &str, now: &str) {
+    if kind == DocKind::Note {
+        if let Some(note) = data.notes.iter_mut().find(|n| n.id == id) {
+            note.updated_at = now.to_string();
+        }
+    }
+}
+
+/// Add a stored file to a note or card; attaching the same file twice is a no-op
+pub fn attach(
+    data: &mut AppData,
+    kind: DocKind,
+    id: &str,
+    attachment: &Attachment,
+    now: &str,
+) -> Result<(), String> {
+    let attachments = attachments_of(data, kind, id)?;
+    if attachments.iter().any(|a| a.file == attachment.file) {
+        return Ok(());
+    }
+    attachments.push(attachment.clone());
+    touch_note(data, kind, id, now);
+    Ok(())
+}
+
+/// Remove a file from a note or card; the file itself goes at the next
+/// garbage collection if nothing else uses it
+pub fn detach(
+    data: &mut AppData,
+    kind: DocKind,
+    id: &str,
+    file: &str,
+    now: &str,
+) -> Result<(), String> {
+    let attachments = attachments_of(data, kind, id)?;
+    let before = attachments.len();
+    attachments.retain(|a| a.file != file);
+    if attachments.len() == before {
+        return Err(format!("Attachment not found: {}", file));
+    }
+    touch_note(data, kind, id, now);
+    Ok(())
+}
+
+/// Stored files a Markdown text embeds or links to
+pub fn references(text: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for (start, _) in text.match_indices(LINK_PREFIX) {
+        let rest = &text[start + LINK_PREFIX.len()..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+            .unwrap_or(rest.len());
+        let file = &rest[..end];
+        if asset_cache::valid_file_name(file) && !out.iter().any(|f| f == file) {
+            out.push(file.to_string());
+        }
+    }
+    out
+}
+
+/// Every stored file the given boards and notes use
+pub fn referenced_in(boards: &[Board], notes: &[Note]) -> HashSet<String> {
+    let mut files = HashSet::new();
+    for note in notes {
+        files.extend(note.attachments.iter().map(|a| a.file.clone()));
+        files.extend(references(&note.content));
+    }
+    for board in boards {
+        let cards = board
+            .columns
+            .iter()
+            .flat_map(|c| c.cards.iter())
+            .chain(board.archived_cards.iter());
+        for card in cards {
+            files.extend(card.attachments.iter().map(|a| a.file.clone()));
+            files.extend(references(&card.description));
+        }
+    }
+    files
+}
+
+pub fn referenced(data: &AppData) -> HashSet<String> {
+    referenced_in(&data.boards, &data.notes)
+}
+
+/// List files a note's text embeds that are missing from `attachments`,
+/// e.g. after the text was edited in the vault
+pub fn adopt_references(dir: &Path, note: &mut Note) {
+    for file in references(&note.content) {
+        if note.attachments.iter().any(|a| a.file == file) {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(dir.join(&file)) else {
+            continue;
+        };
+        let extension = file.rsplit('.').next().unwrap_or_default();
+        note.attachments.push(Attachment {
+            name: file.clone(),
+            mime_type: content_type_for(extension).to_string(),
+            size: metadata.len(),
+            created_at: note.updated_at.clone(),
+            file,
+        });
+    }
+}
+
+/// Delete stored files nothing refers to any more
+pub fn collect_garbage(dir: &Path, data: &AppData) -> AssetGcReport {
+    asset_cache::remove_unreferenced(dir, &referenced(data), GC_GRACE)
+}
+
+/// Handler for `carbon-attachment://localhost/<file>` requests
+pub fn serve(dir: &Path, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    asset_cache::serve_with(dir, request, content_type_for)
+}
+
+// ============================================
+// Sync
+// ============================================
+
+/// Copy files between the store and a folder that travels with the notes
+/// (the vault's `attachments/`): whichever side lacks a used file gets it
+pub fn mirror(dir: &Path, folder: &Path, files: &HashSet<String>) -> Result<(), String> {
+    for file in files {
+        let stored = dir.join(file);
+        let mirrored = folder.join(file);
+        match (stored.exists(), mirrored.exists()) {
+            (true, false) => {
+                fs::create_dir_all(folder)
+                    .map_err(|e| format!("Failed to create attachments folder: {}", e))?;
+                fs::copy(&stored, &mirrored)
+                    .map_err(|e| format!("Failed to copy attachment {}: {}", file, e))?;
+            }
+            (false, true) => {
+                let bytes = fs::read(&mirrored)
+                    .map_err(|e| format!("Failed to read attachment {}: {}", file, e))?;
+                // Only accept the file if it really is what its name says
+                let extension = file.rsplit('.').next().unwrap_or_default();
+                if asset_cache::store(dir, &bytes, extension)? != *file {
+                    log::warn!("Attachment {} does not match its hash; ignored", file);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Names of files already uploaded to iCloud, kept in the data dir
+#[cfg(target_os = "macos")]
+const CLOUD_STATE_FILE: &str = "cloud-attachments.json";
+
+/// Upload used files iCloud does not have yet and download the ones
+/// `remote` (data pulled from iCloud) uses that are missing here
+#[cfg(target_os = "macos")]
+pub fn sync_cloud(
+    data_dir: &Path,
+    local: &HashSet<String>,
+    remote: &HashSet<String>,
+    upload: impl Fn(&str, &Path) -> Result<(), String>,
+    download: impl Fn(&str, &Path) -> Result<(), String>,
+) -> Result<(), String> {
+    let dir = get_attachments_dir(data_dir);
+    let state_path = data_dir.join(CLOUD_STATE_FILE);
+    let mut uploaded: HashSet<String> = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    for file in local {
+        let path = dir.join(file);
+        if uploaded.contains(file) || !path.exists() {
+            continue;
+        }
+        match upload(file, &path) {
+            Ok(()) => {
+                uploaded.insert(file.clone());
+            }
+            Err(e) => log::warn!("Failed to upload attachment {}: {}", file, e),
+        }
+    }
+    for file in remote {
+        let path = dir.join(file);
+        if path.exists() {
+            continue;
+        }
+        // Download next to the store and only take the file in if it really
+        // is what its name says, as `mirror` does
+        let tmp = dir.join(format!("{}.download.tmp", file));
+        let stored = download(file, &tmp).and_then(|_| {
+            let bytes = fs::read(&tmp).map_err(|e| e.to_string());
+            fs::remove_file(&tmp).ok();
+            let extension = file.rsplit('.').next().unwrap_or_default();
+            asset_cache::store(&dir, &bytes?, extension)
+        });
+        match stored {
+            Ok(name) if name == *file => {
+                uploaded.insert(file.clone());
+            }
+            Ok(_) => log::warn!(
+                "Downloaded attachment {} does not match its hash; ignored",
+                file
+            ),
+            Err(e) => log::warn!("Failed to download attachment {}: {}", file, e),
+        }
+    }
+
+    let json = serde_json::to_string(&uploaded)
+        .map_err(|e| format!("Failed to serialize attachment sync state: {}", e))?;
+    fs::write(&state_path, json)
+        .map_err(|e| format!("Failed to write attachment sync state: {}", e))
+}
@@ -0,0 +1,346 @@
+//! Quick capture: one line of text turned into a card, note or bookmark
+//!
+//! The input may start with `card:`, `note:` or `bookmark:` (a pasted URL
+//! alone is a bookmark; anything else a card). The first line can contain:
+//!
+//! - `#bug` a card label, or a bookmark tag
+//! - `!high` a card priority (`low`, `medium`, `high`, `critical`)
+//! - `@board:Backend`, `@column:"In Progress"` where a card goes
+//! - a URL, which a bookmark points at
+//!
+//! Whatever is left is the title; further lines are the card description or
+//! note content. Cards without a board go to the active board's first column.
+
+use crate::metadata::BookmarkMetadata;
+use crate::tags;
+use crate::transfer::BUILT_IN_LABELS;
+use crate::{AppData, Bookmark, Card, Note};
+use serde::{Deserialize, Serialize};
+
+/// Keys of `PRIORITIES` in boardStore.js
+const PRIORITIES: [&str; 4] = ["low", "medium", "high", "critical"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureKind {
+    Card,
+    Note,
+    Bookmark,
+}
+
+/// Parsed capture input and where it will be stored
+#[derive(Debug, Serialize, Clone)]
+pub struct CapturePlan {
+    pub kind: CaptureKind,
+    pub title: String,
+    pub body: String,
+    pub url: Option<String>,
+    /// Card labels
+    pub labels: Vec<String>,
+    /// Bookmark tag names
+    pub tags: Vec<String>,
+    pub priority: Option<String>,
+    #[serde(rename = "boardId")]
+    pub board_id: Option<String>,
+    #[serde(rename = "boardName")]
+    pub board_name: Option<String>,
+    #[serde(rename = "columnId")]
+    pub column_id: Option<String>,
+    #[serde(rename = "columnName")]
+    pub column_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CaptureResult {
+    pub data: AppData,
+    pub kind: CaptureKind,
+    /// Id of the new card, note or bookmark, or of the existing bookmark
+    /// when the URL was already saved
+    pub id: String,
+    /// Set when nothing was added because the bookmark exists already
+    #[serde(rename = "alreadyExisted")]
+    pub already_existed: bool,
+}
+
+/// Split a line into words, keeping `"quoted text"` together
+fn words(line: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut word = String::new();
+    let mut in_quotes = false;
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !word.is_empty() {
+                    out.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        out.push(word);
+    }
+    out
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+fn is_url(word: &str) -> bool {
+    let lower = word.to_ascii_lowercase();
+    (lower.starts_with("http://") || lower.starts_with("https://")) && url::Url::parse(word).is_ok()
+}
+
+/// Find by exact name first, then by name prefix (both case-insensitive)
+fn find_named<'a, T>(items: &'a [T], name: &str, get: impl Fn(&T) -> &str) -> Option<&'a T> {
+    let name = name.to_lowercase();
+    items
+        .iter()
+        .find(|item| get(item).to_lowercase() == name)
+        .or_else(|| {
+            items
+                .iter()
+                .find(|item| get(item).to_lowercase().starts_with(&name))
+        })
+}
+
+/// Parse capture input and resolve the board and column of a card
+pub fn plan(data: &AppData, input: &str) -> Result<CapturePlan, String> {
+    let input = input.trim();
+    let (first_line, body) = match input.split_once('\n') {
+        Some((first, rest)) => (first.trim(), rest.trim().to_string()),
+        None => (input, String::new()),
+    };
+
+    let mut kind = None;
+    let mut line = first_line;
+    for (prefix, prefix_kind) in [
+        ("card:", CaptureKind::Card),
+        ("task:", CaptureKind::Card),
+        ("note:", CaptureKind::Note),
+        ("bookmark:", CaptureKind::Bookmark),
+        ("bm:", CaptureKind::Bookmark),
+        ("link:", CaptureKind::Bookmark),
+    ] {
+        if let Some(rest) = strip_prefix_ignore_case(line, prefix) {
+            kind = Some(prefix_kind);
+            line = rest.trim_start();
+            break;
+        }
+    }
+
+    let words = words(line);
+    let kind = kind.unwrap_or(if words.iter().any(|w| is_url(w)) {
+        CaptureKind::Bookmark
+    } else {
+        CaptureKind::Card
+    });
+
+    // Hashtags and URLs that mean nothing for this kind stay in the title
+    let mut title_words: Vec<String> = Vec::new();
+    let mut labels = Vec::new();
+    let mut tag_names = Vec::new();
+    let mut url = None;
+    let mut priority = None;
+    let mut board = None;
+    let mut column = None;
+    for word in words {
+        let lower = word.to_lowercase();
+        if let Some(name) = strip_prefix_ignore_case(&word, "@board:") {
+            board = Some(name.to_string());
+        } else if let Some(name) = strip_prefix_ignore_case(&word, "@column:")
+            .or_else(|| strip_prefix_ignore_case(&word, "@col:"))
+        {
+            column = Some(name.to_string());
+        } else if let Some(level) = lower.strip_prefix('!').filter(|l| PRIORITIES.contains(l)) {
+            priority = Some(level.to_string());
+        } else if kind == CaptureKind::Card
+            && BUILT_IN_LABELS.iter().any(|l| lower == format!("#{}", l))
+        {
+            labels.push(lower[1..].to_string());
+        } else if kind == CaptureKind::Bookmark && word.len() > 1 && word.starts_with('#') {
+            tag_names.push(word[1..].to_string());
+        } else if kind == CaptureKind::Bookmark && url.is_none() && is_url(&word) {
+            url = Some(word);
+        } else {
+            title_words.push(word);
+        }
+    }
+    let title = title_words.join(" ");
+
+    let mut plan = CapturePlan {
+        kind,
+        title,
+        body,
+        url,
+        labels,
+        tags: tag_names,
+        priority: None,
+        board_id: None,
+        board_name: None,
+        column_id: None,
+        column_name: None,
+    };
+
+    match kind {
+        CaptureKind::Card => {
+            if plan.title.is_empty() {
+                return Err("A card needs a title".to_string());
+            }
+            plan.priority = priority;
+            let target = match &board {
+                Some(name) => find_named(&data.boards, name, |b| &b.name)
+                    .ok_or_else(|| format!("No board named {}", name))?,
+                None => data
+                    .active_board
+                    .as_deref()
+                    .and_then(|id| data.boards.iter().find(|b| b.id == id))
+                    .or_else(|| data.boards.first())
+                    .ok_or_else(|| "There is no board to add the card to".to_string())?,
+            };
+            let target_column = match &column {
+                Some(name) => find_named(&target.columns, name, |c| &c.title)
+                    .ok_or_else(|| format!("No column named {} on {}", name, target.name))?,
+                None => target
+                    .columns
+                    .first()
+                    .ok_or_else(|| format!("{} has no columns", target.name))?,
+            };
+            plan.board_id = Some(target.id.clone());
+            plan.board_name = Some(target.name.clone());
+            plan.column_id = Some(target_column.id.clone());
+            plan.column_name = Some(target_column.title.clone());
+        }
+        CaptureKind::Note => {
+            if plan.title.is_empty() && plan.body.is_empty() {
+                return Err("The note is empty".to_string());
+            }
+        }
+        CaptureKind::Bookmark => {
+            if plan.url.is_none() {
+                return Err("A bookmark needs a URL".to_string());
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// Add what `plan` describes to `data`; `metadata` fills in a bookmark's
+/// title, description and images when it was fetched
+pub fn apply(
+    data: &mut AppData,
+    plan: &CapturePlan,
+    metadata: Option<BookmarkMetadata>,
+    now: &str,
+) -> Result<(String, bool), String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    match plan.kind {
+        CaptureKind::Card => {
+            let column = data
+                .boards
+                .iter_mut()
+                .find(|b| Some(&b.id) == plan.board_id.as_ref())
+                .and_then(|b| {
+                    b.columns
+                        .iter_mut()
+                        .find(|c| Some(&c.id) == plan.column_id.as_ref())
+                })
+                .ok_or_else(|| "The column for this card no longer exists".to_string())?;
+            column.cards.push(Card {
+                id: id.clone(),
+                title: plan.title.clone(),
+                description: plan.body.clone(),
+                labels: plan.labels.clone(),
+                priority: plan.priority.clone(),
+                checklist: vec![],
+                created_at: now.to_string(),
+                archived_at: None,
+                original_column_id: None,
+                column_history: vec![],
+                parent_id: None,
+                blocked_by: vec![],
+                related_to: vec![],
+                attachments: vec![],
+                task_sync: false,
+            });
+        }
+        CaptureKind::Note => {
+            let title = if plan.title.is_empty() {
+                "Untitled Note".to_string()
+            } else {
+                plan.title.clone()
+            };
+            data.notes.insert(
+                0,
+                Note {
+                    id: id.clone(),
+                    title,
+                    content: plan.body.clone(),
+                    is_pinned: false,
+                    created_at: now.to_string(),
+                    updated_at: now.to_string(),
+                    attachments: vec![],
+                    notebook_id: None,
+                    tags: vec![],
+                    journal_date: None,
+                    checklist: vec![],
+                    task_sync: false,
+                },
+            );
+        }
+        CaptureKind::Bookmark => {
+            let raw = plan.url.as_deref().unwrap_or_default();
+            let url = crate::duplicates::normalize_url(raw)?;
+            if let Some(existing) = crate::duplicates::find_matches(data, &url).first() {
+                return Ok((existing.id.clone(), true));
+            }
+
+            let metadata = metadata.unwrap_or_default();
+            let title = if !plan.title.is_empty() {
+                plan.title.clone()
+            } else {
+                metadata.title.clone().unwrap_or_else(|| url.clone())
+            };
+            let description = if plan.body.is_empty() {
+                metadata.description.clone().unwrap_or_default()
+            } else {
+                plan.body.clone()
+            };
+            let mut tag_keys: Vec<String> = Vec::new();
+            for name in &plan.tags {
+                if let Some((key, _)) = tags::ensure_tag(data, name) {
+                    if !tag_keys.contains(&key) {
+                        tag_keys.push(key);
+                    }
+                }
+            }
+            data.bookmarks.insert(
+                0,
+                Bookmark {
+                    id: id.clone(),
+                    title,
+                    url,
+                    description,
+                    favicon: metadata.favicon,
+                    image: metadata.image,
+                    favicon_asset: None,
+                    image_asset: None,
+                    link_check: None,
+                    snapshot: None,
+                    tags: tag_keys,
+                    collection_id: None,
+                    folder_id: None,
+                    is_favorite: false,
+                    is_archived: false,
+                    created_at: now.to_string(),
+                    order: None,
+                },
+            );
+        }
+    }
+    Ok((id, false))
+}
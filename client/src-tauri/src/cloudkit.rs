@@ -10,6 +10,8 @@ use std::os::raw::c_char;
 #[cfg(all(target_os = "macos", not(debug_assertions)))]
 use std::ptr;
 
+use std::path::Path;
+
 const CLOUDKIT_UNAVAILABLE_MSG: &str = "CloudKit is only available on macOS release builds";
 
 /// Sync status enum matching the Swift side
@@ -83,6 +85,16 @@ extern "C" {
     fn cloudkit_setup_subscriptions() -> bool;
     fn cloudkit_free_string(ptr: *mut c_char);
     fn cloudkit_delete_data() -> bool;
+    fn cloudkit_upload_attachment(
+        name: *const c_char,
+        path: *const c_char,
+        out_error: *mut *mut c_char,
+    ) -> bool;
+    fn cloudkit_download_attachment(
+        name: *const c_char,
+        destination: *const c_char,
+        out_error: *mut *mut c_char,
+    ) -> bool;
 }
 
 /// Helper to convert C string to Rust String and free it
@@ -410,6 +422,58 @@ impl CloudKit {
     pub fn delete_data() -> bool {
         false
     }
+
+    /// Upload an attachment file as its own record, named after the file
+    #[cfg(all(target_os = "macos", not(debug_assertions)))]
+    pub fn upload_attachment(name: &str, path: &Path) -> Result<(), String> {
+        let name_cstring = CString::new(name).map_err(|_| "Invalid attachment name".to_string())?;
+        let path_cstring = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| "Invalid attachment path".to_string())?;
+        let mut error_ptr: *mut c_char = ptr::null_mut();
+
+        unsafe {
+            let success = cloudkit_upload_attachment(name_cstring.as_ptr(), path_cstring.as_ptr(), &mut error_ptr);
+            let error = c_string_to_rust(error_ptr);
+            if success {
+                Ok(())
+            } else {
+                Err(error.unwrap_or_else(|| "Attachment upload failed".to_string()))
+            }
+        }
+    }
+
+    #[cfg(any(not(target_os = "macos"), debug_assertions))]
+    pub fn upload_attachment(_name: &str, _path: &Path) -> Result<(), String> {
+        Err(CLOUDKIT_UNAVAILABLE_MSG.to_string())
+    }
+
+    /// Download an attachment file to `destination`
+    #[cfg(all(target_os = "macos", not(debug_assertions)))]
+    pub fn download_attachment(name: &str, destination: &Path) -> Result<(), String> {
+        let name_cstring = CString::new(name).map_err(|_| "Invalid attachment name".to_string())?;
+        let destination_cstring = CString::new(destination.to_string_lossy().as_bytes())
+            .map_err(|_| "Invalid attachment path".to_string())?;
+        let mut error_ptr: *mut c_char = ptr::null_mut();
+
+        unsafe {
+            let success = cloudkit_download_attachment(
+                name_cstring.as_ptr(),
+                destination_cstring.as_ptr(),
+                &mut error_ptr,
+            );
+            let error = c_string_to_rust(error_ptr);
+            if success {
+                Ok(())
+            } else {
+                Err(error.unwrap_or_else(|| "Attachment download failed".to_string()))
+            }
+        }
+    }
+
+    #[cfg(any(not(target_os = "macos"), debug_assertions))]
+    pub fn download_attachment(_name: &str, _destination: &Path) -> Result<(), String> {
+        Err(CLOUDKIT_UNAVAILABLE_MSG.to_string())
+    }
 }
 
 /// Serde-compatible sync result for Tauri commands
@@ -0,0 +1,425 @@
+//! Converting between notes, cards and bookmarks
+//!
+//! A note becomes a card with its Markdown task list items (`- [ ] text`,
+//! `- [x] text`) turned into checklist items, and a card becomes a note with
+//! its checklist written out as a task list. URLs in a note or card
+//! description can be pulled out into bookmarks.
+//!
+//! Back-references are plain wiki-links (see `links`): a converted item
+//! that is kept links to its counterpart and the other way round, and every
+//! extracted bookmark links to where it came from, so the source lists its
+//! bookmarks as backlinks.
+
+use crate::journal::link_text;
+use crate::search::DocKind;
+use crate::tasks::{is_fence, split_tasks, task_list};
+use crate::{AppData, Bookmark, Card, Note};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConvertResult {
+    pub data: AppData,
+    /// Id of the new card or note
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExtractResult {
+    pub data: AppData,
+    /// Bookmarks added for URLs that were not saved yet
+    pub created: Vec<String>,
+    /// Bookmarks that already had one of the URLs
+    pub existing: Vec<String>,
+}
+
+fn link_prefix(kind: DocKind) -> &'static str {
+    match kind {
+        DocKind::Card => "card",
+        DocKind::Bookmark => "bookmark",
+        DocKind::Note => "note",
+    }
+}
+
+fn wiki_link(kind: DocKind, id: &str, title: &str) -> String {
+    let title = link_text(title);
+    if title.is_empty() {
+        format!("[[{}:{}]]", link_prefix(kind), id)
+    } else {
+        format!("[[{}:{}|{}]]", link_prefix(kind), id, title)
+    }
+}
+
+/// Append a paragraph to Markdown text
+fn append(text: &mut String, paragraph: &str) {
+    let trimmed = text.trim_end().len();
+    text.truncate(trimmed);
+    if !text.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str(paragraph);
+}
+
+// ============================================
+// Notes and cards
+// ============================================
+
+fn take_card(data: &mut AppData, card_id: &str) -> Option<Card> {
+    for board in &mut data.boards {
+        for column in &mut board.columns {
+            if let Some(index) = column.cards.iter().position(|c| c.id == card_id) {
+                return Some(column.cards.remove(index));
+            }
+        }
+        if let Some(index) = board.archived_cards.iter().position(|c| c.id == card_id) {
+            return Some(board.archived_cards.remove(index));
+        }
+    }
+    None
+}
+
+/// Drop relations other cards have to a card that is gone
+fn forget_card(data: &mut AppData, card_id: &str) {
+    for card in data.cards_mut() {
+        card.blocked_by.retain(|id| id != card_id);
+        card.related_to.retain(|id| id != card_id);
+        if card.parent_id.as_deref() == Some(card_id) {
+            card.parent_id = None;
+        }
+    }
+}
+
+/// Turn a note into a card at the end of a column (the board's first
+/// column if `column_id` is `None`). Returns the card id.
+///
+/// When `keep_original` is false the note is removed; otherwise the note and
+/// the card link to each other.
+pub fn note_to_card(
+    data: &mut AppData,
+    note_id: &str,
+    board_id: &str,
+    column_id: Option<&str>,
+    keep_original: bool,
+    now: &str,
+) -> Result<String, String> {
+    let note = data
+        .notes
+        .iter()
+        .find(|n| n.id == note_id)
+        .cloned()
+        .ok_or_else(|| format!("Note not found: {}", note_id))?;
+    let board = data
+        .boards
+        .iter()
+        .find(|b| b.id == board_id)
+        .ok_or_else(|| format!("Board not found: {}", board_id))?;
+    let column_index = match column_id {
+        Some(column_id) => board
+            .columns
+            .iter()
+            .position(|c| c.id == column_id)
+            .ok_or_else(|| format!("Column not found: {}", column_id))?,
+        None if board.columns.is_empty() => {
+            return Err(format!("{} has no columns", board.name));
+        }
+        None => 0,
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let (mut description, checklist) = split_tasks(&note.content);
+    if keep_original {
+        append(
+            &mut description,
+            &format!(
+                "Converted from {}",
+                wiki_link(DocKind::Note, &note.id, &note.title)
+            ),
+        );
+    }
+    let card = Card {
+        id: id.clone(),
+        title: note.title.clone(),
+        description,
+        labels: vec![],
+        priority: None,
+        checklist,
+        created_at: now.to_string(),
+        archived_at: None,
+        original_column_id: None,
+        column_history: vec![],
+        parent_id: None,
+        blocked_by: vec![],
+        related_to: vec![],
+        attachments: note.attachments.clone(),
+        task_sync: false,
+    };
+
+    if keep_original {
+        if let Some(note) = data.notes.iter_mut().find(|n| n.id == note_id) {
+            append(
+                &mut note.content,
+                &format!(
+                    "Converted to {}",
+                    wiki_link(DocKind::Card, &id, &card.title)
+                ),
+            );
+            note.updated_at = now.to_string();
+        }
+    } else {
+        data.notes.retain(|n| n.id != note_id);
+    }
+    if let Some(board) = data.boards.iter_mut().find(|b| b.id == board_id) {
+        board.columns[column_index].cards.push(card);
+    }
+    Ok(id)
+}
+
+/// Turn a card into a note, optionally inside a notebook. Returns the note
+/// id.
+///
+/// When `keep_original` is false the card is removed (along with relations
+/// other cards have to it); otherwise the card and the note link to each
+/// other.
+pub fn card_to_note(
+    data: &mut AppData,
+    card_id: &str,
+    notebook_id: Option<&str>,
+    keep_original: bool,
+    now: &str,
+) -> Result<String, String> {
+    if let Some(notebook_id) = notebook_id {
+        if !data.notebooks.iter().any(|n| n.id == notebook_id) {
+            return Err(format!("Notebook not found: {}", notebook_id));
+        }
+    }
+    let card = data
+        .cards()
+        .find(|c| c.id == card_id)
+        .cloned()
+        .ok_or_else(|| format!("Card not found: {}", card_id))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut content = card.description.clone();
+    if !card.checklist.is_empty() {
+        append(&mut content, &task_list(&card.checklist));
+    }
+    if keep_original {
+        append(
+            &mut content,
+            &format!(
+                "Converted from {}",
+                wiki_link(DocKind::Card, &card.id, &card.title)
+            ),
+        );
+    }
+    let title = if card.title.trim().is_empty() {
+        "Untitled Note".to_string()
+    } else {
+        card.title.clone()
+    };
+
+    if keep_original {
+        if let Some(card) = data.cards_mut().find(|c| c.id == card_id) {
+            append(
+                &mut card.description,
+                &format!("Converted to {}", wiki_link(DocKind::Note, &id, &title)),
+            );
+        }
+    } else {
+        take_card(data, card_id);
+        forget_card(data, card_id);
+    }
+    data.notes.insert(
+        0,
+        Note {
+            id: id.clone(),
+            title,
+            content,
+            is_pinned: false,
+            created_at: now.to_string(),
+            updated_at: now.to_string(),
+            attachments: card.attachments,
+            notebook_id: notebook_id.map(str::to_string),
+            tags: vec![],
+            journal_date: None,
+            checklist: vec![],
+            task_sync: false,
+        },
+    );
+    Ok(id)
+}
+
+// ============================================
+// URLs to bookmarks
+// ============================================
+
+/// End of a URL starting at the beginning of `text`. Parentheses are kept
+/// while balanced, so `[text](url)` and `(see url)` end before the `)`.
+fn url_end(text: &str) -> usize {
+    let mut depth = 0usize;
+    let mut end = text.len();
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                end = i;
+                break;
+            }
+            ')' => depth -= 1,
+            c if c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`' | '[' | ']') => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    text[..end]
+        .trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_', '\''])
+        .len()
+}
+
+/// Web URLs in Markdown text with the text of `[text](url)` links, skipping
+/// images, code spans and fenced code blocks
+pub fn find_urls(text: &str) -> Vec<(String, Option<String>)> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let lower = line.to_ascii_lowercase();
+        let mut from = 0;
+        while let Some(found) = lower[from..].find("http") {
+            let start = from + found;
+            from = start + 4;
+            let rest = &lower[start..];
+            if !(rest.starts_with("http://") || rest.starts_with("https://")) {
+                continue;
+            }
+            let before = &line[..start];
+            if before
+                .chars()
+                .last()
+                .map(|c| c.is_alphanumeric())
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            // Inside a code span when an odd number of backticks precede it
+            if before.matches('`').count() % 2 == 1 {
+                continue;
+            }
+            let end = start + url_end(&line[start..]);
+            from = end.max(from);
+            let url = &line[start..end];
+            if url::Url::parse(url).is_err() {
+                continue;
+            }
+
+            let link = before
+                .strip_suffix("](")
+                .and_then(|head| head.rfind('[').map(|open| (head, open)));
+            match link {
+                // An image, not a link
+                Some((head, open)) if head[..open].ends_with('!') => {}
+                Some((head, open)) => {
+                    let title = head[open + 1..].trim().to_string();
+                    out.push((url.to_string(), Some(title).filter(|t| !t.is_empty())));
+                }
+                None => out.push((url.to_string(), None)),
+            }
+        }
+    }
+    out
+}
+
+/// Add a bookmark for every URL in a note or card description.
+///
+/// URLs that are already bookmarked are not added again. New and existing
+/// bookmarks get a link back to the source in their description (once).
+/// Returns (created, existing) bookmark ids.
+pub fn extract_bookmarks(
+    data: &mut AppData,
+    kind: DocKind,
+    id: &str,
+    now: &str,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let (text, title, tags) = match kind {
+        DocKind::Note => {
+            let note = data
+                .notes
+                .iter()
+                .find(|n| n.id == id)
+                .ok_or_else(|| format!("Note not found: {}", id))?;
+            // Notes and bookmarks share tags
+            (note.content.clone(), note.title.clone(), note.tags.clone())
+        }
+        DocKind::Card => {
+            let card = data
+                .cards()
+                .find(|c| c.id == id)
+                .ok_or_else(|| format!("Card not found: {}", id))?;
+            (card.description.clone(), card.title.clone(), vec![])
+        }
+        DocKind::Bookmark => {
+            return Err("Bookmarks can only be extracted from notes and cards".to_string());
+        }
+    };
+    let source = wiki_link(kind, id, &title);
+    // Matches the link with or without display text
+    let source_id = format!("[[{}:{}", link_prefix(kind), id);
+
+    let mut seen = HashSet::new();
+    let mut created = Vec::new();
+    let mut existing = Vec::new();
+    for (raw, link_title) in find_urls(&text) {
+        let Ok(url) = crate::duplicates::normalize_url(&raw) else {
+            continue;
+        };
+        if !seen.insert(url.clone()) {
+            continue;
+        }
+
+        if let Some(found) = crate::duplicates::find_matches(data, &url).first() {
+            if let Some(bookmark) = data.bookmarks.iter_mut().find(|b| b.id == found.id) {
+                if !bookmark.description.contains(&source_id) {
+                    append(&mut bookmark.description, &format!("From {}", source));
+                }
+            }
+            if !existing.contains(&found.id) {
+                existing.push(found.id.clone());
+            }
+            continue;
+        }
+
+        let bookmark_id = uuid::Uuid::new_v4().to_string();
+        data.bookmarks.insert(
+            0,
+            Bookmark {
+                id: bookmark_id.clone(),
+                title: link_title.unwrap_or_else(|| url.clone()),
+                url,
+                description: format!("From {}", source),
+                favicon: None,
+                image: None,
+                favicon_asset: None,
+                image_asset: None,
+                link_check: None,
+                snapshot: None,
+                tags: tags.clone(),
+                collection_id: None,
+                folder_id: None,
+                is_favorite: false,
+                is_archived: false,
+                created_at: now.to_string(),
+                order: None,
+            },
+        );
+        created.push(bookmark_id);
+    }
+    Ok((created, existing))
+}
@@ -0,0 +1,567 @@
+//! Card relationships: subtasks, blockers and related cards
+//!
+//! Relations are stored on the cards themselves (`parentId`, `blockedBy`,
+//! `relatedTo`) and may point at cards on any board. "Blocks" is never
+//! stored; it is the inverse of `blockedBy`.
+
+use crate::{AppData, Card};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    /// The target card is the parent of this card
+    #[serde(rename = "parent")]
+    Parent,
+    /// This card blocks the target card
+    #[serde(rename = "blocks")]
+    Blocks,
+    /// This card is blocked by the target card
+    #[serde(rename = "blockedBy")]
+    BlockedBy,
+    /// Symmetric "relates to" link
+    #[serde(rename = "relatesTo")]
+    RelatesTo,
+}
+
+/// Where a card lives, used to resolve ids across boards
+#[derive(Debug, Serialize, Clone)]
+pub struct CardRef {
+    #[serde(rename = "cardId")]
+    pub card_id: String,
+    pub title: String,
+    #[serde(rename = "boardId")]
+    pub board_id: String,
+    #[serde(rename = "columnId")]
+    pub column_id: Option<String>,
+    /// Archived, or sitting in its board's last column
+    pub done: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RelationIssue {
+    #[serde(rename = "cardId")]
+    pub card_id: String,
+    pub kind: RelationKind,
+    #[serde(rename = "targetId")]
+    pub target_id: String,
+    /// One of "missingTarget", "selfReference" or "cycle"
+    pub problem: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CardRelations {
+    pub card: CardRef,
+    pub parent: Option<CardRef>,
+    pub children: Vec<CardRef>,
+    pub blocks: Vec<CardRef>,
+    #[serde(rename = "blockedBy")]
+    pub blocked_by: Vec<CardRef>,
+    #[serde(rename = "relatedTo")]
+    pub related_to: Vec<CardRef>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BlockedCard {
+    pub card: CardRef,
+    /// Blockers that are not done yet
+    #[serde(rename = "blockedBy")]
+    pub blocked_by: Vec<CardRef>,
+}
+
+/// Resolve every card id (including archived cards) to its location
+pub fn index_cards(data: &AppData) -> HashMap<String, CardRef> {
+    let mut index = HashMap::new();
+    for board in &data.boards {
+        let done_column = board.columns.last().map(|c| c.id.as_str());
+        for column in &board.columns {
+            for card in &column.cards {
+                index.insert(
+                    card.id.clone(),
+                    CardRef {
+                        card_id: card.id.clone(),
+                        title: card.title.clone(),
+                        board_id: board.id.clone(),
+                        column_id: Some(column.id.clone()),
+                        done: Some(column.id.as_str()) == done_column,
+                    },
+                );
+            }
+        }
+        for card in &board.archived_cards {
+            index.insert(
+                card.id.clone(),
+                CardRef {
+                    card_id: card.id.clone(),
+                    title: card.title.clone(),
+                    board_id: board.id.clone(),
+                    column_id: None,
+                    done: true,
+                },
+            );
+        }
+    }
+    index
+}
+
+fn find_card_mut<'a>(data: &'a mut AppData, card_id: &str) -> Option<&'a mut Card> {
+    data.cards_mut().find(|c| c.id == card_id)
+}
+
+/// Follow `next` edges from `from`; true if `to` is reachable
+fn reachable(edges: &HashMap<&str, Vec<&str>>, from: &str, to: &str) -> bool {
+    let mut stack = vec![from];
+    let mut seen = HashSet::new();
+    while let Some(id) = stack.pop() {
+        if id == to {
+            return true;
+        }
+        if seen.insert(id) {
+            if let Some(next) = edges.get(id) {
+                stack.extend(next.iter().copied());
+            }
+        }
+    }
+    false
+}
+
+fn blocked_by_edges(data: &AppData) -> HashMap<&str, Vec<&str>> {
+    data.cards()
+        .map(|c| {
+            (
+                c.id.as_str(),
+                c.blocked_by.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect()
+}
+
+fn parent_edges(data: &AppData) -> HashMap<&str, Vec<&str>> {
+    data.cards()
+        .map(|c| {
+            (
+                c.id.as_str(),
+                c.parent_id.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect()
+}
+
+/// Check that every referenced card exists and that neither the parent
+/// hierarchy nor the blocker graph contains a cycle.
+pub fn validate_relations(data: &AppData) -> Vec<RelationIssue> {
+    let index = index_cards(data);
+    let blockers = blocked_by_edges(data);
+    let parents = parent_edges(data);
+    let mut issues = Vec::new();
+
+    let mut check = |card: &Card,
+                     kind: RelationKind,
+                     target: &str,
+                     edges: Option<&HashMap<&str, Vec<&str>>>| {
+        let problem = if target == card.id {
+            Some("selfReference")
+        } else if !index.contains_key(target) {
+            Some("missingTarget")
+        } else if edges
+            .map(|e| reachable(e, target, &card.id))
+            .unwrap_or(false)
+        {
+            Some("cycle")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            issues.push(RelationIssue {
+                card_id: card.id.clone(),
+                kind,
+                target_id: target.to_string(),
+                problem: problem.to_string(),
+            });
+        }
+    };
+
+    for card in data.cards() {
+        if let Some(parent) = &card.parent_id {
+            check(card, RelationKind::Parent, parent, Some(&parents));
+        }
+        for blocker in &card.blocked_by {
+            check(card, RelationKind::BlockedBy, blocker, Some(&blockers));
+        }
+        for related in &card.related_to {
+            check(card, RelationKind::RelatesTo, related, None);
+        }
+    }
+
+    issues
+}
+
+/// Add a relation after checking that both cards exist and that the link
+/// would not introduce a cycle.
+pub fn set_relation(
+    data: &mut AppData,
+    card_id: &str,
+    kind: RelationKind,
+    target_id: &str,
+) -> Result<(), String> {
+    let index = index_cards(data);
+    if !index.contains_key(card_id) {
+        return Err(format!("Card not found: {}", card_id));
+    }
+    if !index.contains_key(target_id) {
+        return Err(format!("Card not found: {}", target_id));
+    }
+    if card_id == target_id {
+        return Err("A card cannot be related to itself".to_string());
+    }
+
+    match kind {
+        RelationKind::Parent => {
+            // The new parent must not already descend from this card
+            if reachable(&parent_edges(data), target_id, card_id) {
+                return Err("Setting this parent would create a cycle".to_string());
+            }
+            if let Some(card) = find_card_mut(data, card_id) {
+                card.parent_id = Some(target_id.to_string());
+            }
+        }
+        RelationKind::Blocks | RelationKind::BlockedBy => {
+            let (blocked, blocker) = if kind == RelationKind::Blocks {
+                (target_id, card_id)
+            } else {
+                (card_id, target_id)
+            };
+            if reachable(&blocked_by_edges(data), blocker, blocked) {
+                return Err("This dependency would create a cycle".to_string());
+            }
+            if let Some(card) = find_card_mut(data, blocked) {
+                if !card.blocked_by.iter().any(|id| id == blocker) {
+                    card.blocked_by.push(blocker.to_string());
+                }
+            }
+        }
+        RelationKind::RelatesTo => {
+            for (from, to) in [(card_id, target_id), (target_id, card_id)] {
+                if let Some(card) = find_card_mut(data, from) {
+                    if !card.related_to.iter().any(|id| id == to) {
+                        card.related_to.push(to.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove a relation; removing one that does not exist is a no-op
+pub fn remove_relation(
+    data: &mut AppData,
+    card_id: &str,
+    kind: RelationKind,
+    target_id: &str,
+) -> Result<(), String> {
+    match kind {
+        RelationKind::Parent => {
+            let card = find_card_mut(data, card_id)
+                .ok_or_else(|| format!("Card not found: {}", card_id))?;
+            if card.parent_id.as_deref() == Some(target_id) {
+                card.parent_id = None;
+            }
+        }
+        RelationKind::Blocks | RelationKind::BlockedBy => {
+            let (blocked, blocker) = if kind == RelationKind::Blocks {
+                (target_id, card_id)
+            } else {
+                (card_id, target_id)
+            };
+            let card = find_card_mut(data, blocked)
+                .ok_or_else(|| format!("Card not found: {}", blocked))?;
+            card.blocked_by.retain(|id| id != blocker);
+        }
+        RelationKind::RelatesTo => {
+            for (from, to) in [(card_id, target_id), (target_id, card_id)] {
+                if let Some(card) = find_card_mut(data, from) {
+                    card.related_to.retain(|id| id != to);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// All relations of one card, resolved to card references
+pub fn card_relations(data: &AppData, card_id: &str) -> Result<CardRelations, String> {
+    let index = index_cards(data);
+    let card = data
+        .cards()
+        .find(|c| c.id == card_id)
+        .ok_or_else(|| format!("Card not found: {}", card_id))?;
+    let resolve = |ids: &mut dyn Iterator<Item = &String>| -> Vec<CardRef> {
+        ids.filter_map(|id| index.get(id).cloned()).collect()
+    };
+
+    Ok(CardRelations {
+        card: index[card_id].clone(),
+        parent: card
+            .parent_id
+            .as_ref()
+            .and_then(|id| index.get(id).cloned()),
+        children: resolve(
+            &mut data
+                .cards()
+                .filter(|c| c.parent_id.as_deref() == Some(card_id))
+                .map(|c| &c.id),
+        ),
+        blocks: resolve(
+            &mut data
+                .cards()
+                .filter(|c| c.blocked_by.iter().any(|id| id == card_id))
+                .map(|c| &c.id),
+        ),
+        blocked_by: resolve(&mut card.blocked_by.iter()),
+        related_to: resolve(&mut card.related_to.iter()),
+    })
+}
+
+/// Open cards on a board that still wait on at least one open blocker
+pub fn blocked_cards(data: &AppData, board_id: &str) -> Vec<BlockedCard> {
+    let index = index_cards(data);
+    let Some(board) = data.boards.iter().find(|b| b.id == board_id) else {
+        return Vec::new();
+    };
+
+    board
+        .columns
+        .iter()
+        .flat_map(|c| c.cards.iter())
+        .filter_map(|card| {
+            let this = index.get(&card.id)?;
+            if this.done {
+                return None;
+            }
+            let open: Vec<CardRef> = card
+                .blocked_by
+                .iter()
+                .filter_map(|id| index.get(id))
+                .filter(|blocker| !blocker.done)
+                .cloned()
+                .collect();
+            (!open.is_empty()).then(|| BlockedCard {
+                card: this.clone(),
+                blocked_by: open,
+            })
+        })
+        .collect()
+}
+
+/// The longest chain of open dependencies ending at a card on the board,
+/// ordered from the first card that has to be finished to the last.
+///
+/// Blockers on other boards are followed, so the chain may leave the board.
+pub fn critical_chain(data: &AppData, board_id: &str) -> Vec<CardRef> {
+    let index = index_cards(data);
+    let edges = blocked_by_edges(data);
+    let Some(board) = data.boards.iter().find(|b| b.id == board_id) else {
+        return Vec::new();
+    };
+
+    // Longest path (in cards) ending at `id` through open blockers
+    fn depth<'a>(
+        id: &'a str,
+        edges: &HashMap<&'a str, Vec<&'a str>>,
+        index: &HashMap<String, CardRef>,
+        memo: &mut HashMap<&'a str, (usize, Option<&'a str>)>,
+        visiting: &mut HashSet<&'a str>,
+    ) -> usize {
+        if let Some((d, _)) = memo.get(id) {
+            return *d;
+        }
+        // Guard against cycles in hand-edited data
+        if !visiting.insert(id) {
+            return 0;
+        }
+        let mut best = (1, None);
+        for blocker in edges.get(id).into_iter().flatten().copied() {
+            let open = index.get(blocker).map(|c| !c.done).unwrap_or(false);
+            if !open {
+                continue;
+            }
+            let d = depth(blocker, edges, index, memo, visiting) + 1;
+            if d > best.0 {
+                best = (d, Some(blocker));
+            }
+        }
+        visiting.remove(id);
+        memo.insert(id, best);
+        best.0
+    }
+
+    let mut memo = HashMap::new();
+    let mut visiting = HashSet::new();
+    let mut end: Option<(&str, usize)> = None;
+    for card in board.columns.iter().flat_map(|c| c.cards.iter()) {
+        if index.get(&card.id).map(|c| c.done).unwrap_or(true) {
+            continue;
+        }
+        let d = depth(&card.id, &edges, &index, &mut memo, &mut visiting);
+        if end.map(|(_, best)| d > best).unwrap_or(true) {
+            end = Some((card.id.as_str(), d));
+        }
+    }
+
+    // A single card on its own is not a chain
+    let Some((end, _)) = end.filter(|(_, d)| *d > 1) else {
+        return Vec::new();
+    };
+
+    let mut chain = Vec::new();
+    let mut current = Some(end);
+    while let Some(id) = current {
+        if let Some(card) = index.get(id) {
+            chain.push(card.clone());
+        }
+        current = memo.get(id).and_then(|(_, next)| *next);
+    }
+    chain.reverse();
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn card(id: &str, blocked_by: &[&str]) -> serde_json::Value {
+        json!({
+            "id": id,
+            "title": id,
+            "blockedBy": blocked_by,
+            "createdAt": "2025-01-01T00:00:00Z",
+        })
+    }
+
+    fn board(id: &str, columns: Vec<(&str, Vec<serde_json::Value>)>) -> crate::Board {
+        let columns: Vec<_> = columns
+            .into_iter()
+            .map(|(id, cards)| json!({ "id": id, "title": id, "cards": cards }))
+            .collect();
+        serde_json::from_value(json!({ "id": id, "name": id, "columns": columns })).unwrap()
+    }
+
+    /// Board `a` has cards 1-4 open and 5 done; board `b` has 6 open and 7 done
+    fn data() -> AppData {
+        let mut data = crate::get_default_data();
+        data.boards = vec![
+            board(
+                "a",
+                vec![
+                    ("todo", vec![card("1", &[]), card("2", &[]), card("3", &[])]),
+                    ("doing", vec![card("4", &[])]),
+                    ("done", vec![card("5", &[])]),
+                ],
+            ),
+            board(
+                "b",
+                vec![
+                    ("todo", vec![card("6", &[])]),
+                    ("done", vec![card("7", &[])]),
+                ],
+            ),
+        ];
+        data
+    }
+
+    fn ids(cards: &[CardRef]) -> Vec<&str> {
+        cards.iter().map(|c| c.card_id.as_str()).collect()
+    }
+
+    #[test]
+    fn direct_and_indirect_cycles_are_rejected() {
+        let mut data = data();
+        set_relation(&mut data, "1", RelationKind::BlockedBy, "2").unwrap();
+        assert!(set_relation(&mut data, "2", RelationKind::BlockedBy, "1").is_err());
+        assert!(set_relation(&mut data, "1", RelationKind::Blocks, "2").is_err());
+
+        set_relation(&mut data, "2", RelationKind::BlockedBy, "6").unwrap();
+        assert!(set_relation(&mut data, "6", RelationKind::BlockedBy, "1").is_err());
+        assert!(set_relation(&mut data, "1", RelationKind::Blocks, "6").is_err());
+        assert!(validate_relations(&data).is_empty());
+    }
+
+    #[test]
+    fn parent_cycles_and_self_links_are_rejected() {
+        let mut data = data();
+        set_relation(&mut data, "2", RelationKind::Parent, "1").unwrap();
+        set_relation(&mut data, "3", RelationKind::Parent, "2").unwrap();
+        assert!(set_relation(&mut data, "1", RelationKind::Parent, "3").is_err());
+        assert!(set_relation(&mut data, "1", RelationKind::RelatesTo, "1").is_err());
+        assert!(set_relation(&mut data, "1", RelationKind::BlockedBy, "missing").is_err());
+    }
+
+    #[test]
+    fn validation_reports_dangling_ids_and_cycles() {
+        let mut data = data();
+        data.boards[0].columns[0].cards[0].blocked_by = vec!["gone".to_string(), "2".to_string()];
+        data.boards[0].columns[0].cards[1].blocked_by = vec!["1".to_string()];
+        data.boards[0].columns[0].cards[2].related_to = vec!["3".to_string()];
+
+        let found = validate_relations(&data);
+        let mut issues: Vec<(&str, &str, &str)> = found
+            .iter()
+            .map(|i| (i.card_id.as_str(), i.target_id.as_str(), i.problem.as_str()))
+            .collect();
+        issues.sort();
+        assert_eq!(
+            issues,
+            [
+                ("1", "2", "cycle"),
+                ("1", "gone", "missingTarget"),
+                ("2", "1", "cycle"),
+                ("3", "3", "selfReference"),
+            ]
+        );
+    }
+
+    #[test]
+    fn critical_chain_follows_blockers_on_other_boards() {
+        let mut data = data();
+        // 4 waits on 1, which waits on 6 on board b; 3 waits on done card 5
+        set_relation(&mut data, "4", RelationKind::BlockedBy, "1").unwrap();
+        set_relation(&mut data, "1", RelationKind::BlockedBy, "6").unwrap();
+        set_relation(&mut data, "3", RelationKind::BlockedBy, "5").unwrap();
+
+        let chain = critical_chain(&data, "a");
+        assert_eq!(ids(&chain), ["6", "1", "4"]);
+        assert_eq!(chain[0].board_id, "b");
+
+        let blocked = blocked_cards(&data, "a");
+        let blocked: Vec<&str> = blocked.iter().map(|b| b.card.card_id.as_str()).collect();
+        assert_eq!(blocked, ["1", "4"]);
+    }
+
+    #[test]
+    fn critical_chain_skips_done_blockers() {
+        let mut data = data();
+        set_relation(&mut data, "1", RelationKind::BlockedBy, "7").unwrap();
+        assert!(critical_chain(&data, "a").is_empty());
+    }
+
+    #[test]
+    fn relations_resolve_both_directions() {
+        let mut data = data();
+        set_relation(&mut data, "1", RelationKind::Blocks, "2").unwrap();
+        set_relation(&mut data, "1", RelationKind::RelatesTo, "6").unwrap();
+
+        let relations = card_relations(&data, "1").unwrap();
+        assert_eq!(ids(&relations.blocks), ["2"]);
+        assert_eq!(ids(&relations.related_to), ["6"]);
+        assert_eq!(ids(&card_relations(&data, "6").unwrap().related_to), ["1"]);
+
+        remove_relation(&mut data, "6", RelationKind::RelatesTo, "1").unwrap();
+        assert!(card_relations(&data, "1").unwrap().related_to.is_empty());
+        remove_relation(&mut data, "2", RelationKind::BlockedBy, "1").unwrap();
+        assert!(card_relations(&data, "1").unwrap().blocks.is_empty());
+    }
+}
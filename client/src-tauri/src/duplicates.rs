@@ -0,0 +1,429 @@
+//! URL canonicalization and duplicate bookmark detection
+//!
+//! `normalize_url` cleans a URL for storage (lowercase host, no tracking
+//! parameters, consistent trailing slash). `duplicate_key` goes further and
+//! ignores the scheme and a leading `www.`, so `http://`, `https://www.` and
+//! `?utm_source=` variants of a page all compare equal.
+
+use crate::search::DocKind;
+use crate::{links, AppData, Bookmark};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Query parameters that only track where a click came from
+const TRACKING_PARAMS: [&str; 22] = [
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "wickedid",
+    "ref_src",
+    "ref_url",
+    "spm",
+];
+const TRACKING_PREFIXES: [&str; 3] = ["utm_", "pk_", "hsa_"];
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    TRACKING_PARAMS.contains(&name.as_str())
+        || TRACKING_PREFIXES.iter().any(|p| name.starts_with(p))
+}
+
+/// Canonical form of a URL for storing.
+///
+/// Lowercases scheme and host, drops default ports, tracking parameters,
+/// empty queries and plain `#section` fragments (`#/route` and `#!` app
+/// routes are kept), and removes a trailing slash from non-root paths.
+pub fn normalize_url(input: &str) -> Result<String, String> {
+    let mut url = Url::parse(input.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        // Only web URLs have a meaningful canonical form
+        return Ok(url.to_string());
+    }
+
+    // The url crate already lowercases the scheme and host and drops default ports
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else if kept.len() != url.query_pairs().count() {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+
+    let keep_fragment = url
+        .fragment()
+        .map(|f| f.starts_with('/') || f.starts_with('!'))
+        .unwrap_or(false);
+    if !keep_fragment {
+        url.set_fragment(None);
+    }
+
+    let path = url.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        url.set_path(path.trim_end_matches('/'));
+    }
+
+    Ok(url.to_string())
+}
+
+/// Key under which two bookmarks count as the same page
+pub fn duplicate_key(input: &str) -> String {
+    let Ok(normalized) = normalize_url(input) else {
+        return input.trim().to_string();
+    };
+    let Ok(mut url) = Url::parse(&normalized) else {
+        return normalized;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return normalized;
+    }
+    if let Some(host) = url.host_str().and_then(|h| h.strip_prefix("www.")) {
+        let host = host.to_string();
+        url.set_host(Some(&host)).ok();
+    }
+    // Sort parameters so their order does not matter
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    if !pairs.is_empty() {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let mut key = url.to_string();
+    if let Some(rest) = key
+        .strip_prefix("https://")
+        .or_else(|| key.strip_prefix("http://"))
+    {
+        key = rest.to_string();
+    }
+    key
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DuplicateBookmark {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DuplicateGroup {
+    pub key: String,
+    /// Oldest first; the first bookmark is the one a merge keeps
+    pub bookmarks: Vec<DuplicateBookmark>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MergeResult {
+    pub data: AppData,
+    /// Number of bookmarks folded into another one
+    pub merged: usize,
+}
+
+/// What merging one group of duplicates did
+#[derive(Debug, Default)]
+pub struct Merged {
+    /// Number of bookmarks folded into the survivor
+    pub removed: usize,
+    /// `(from, to)` bookmark ids when the survivor took over another
+    /// bookmark's snapshot, whose files are still stored under `from`
+    pub snapshot: Option<(String, String)>,
+}
+
+fn summary(bookmark: &Bookmark) -> DuplicateBookmark {
+    DuplicateBookmark {
+        id: bookmark.id.clone(),
+        title: bookmark.title.clone(),
+        url: bookmark.url.clone(),
+        created_at: bookmark.created_at.clone(),
+    }
+}
+
+fn created_order(a: &Bookmark, b: &Bookmark) -> std::cmp::Ordering {
+    let parse = |s: &str| chrono::DateTime::parse_from_rfc3339(s).ok();
+    match (parse(&a.created_at), parse(&b.created_at)) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        _ => a.created_at.cmp(&b.created_at),
+    }
+}
+
+/// Existing bookmarks pointing at the same page as `url`
+pub fn find_matches(data: &AppData, url: &str) -> Vec<DuplicateBookmark> {
+    let key = duplicate_key(url);
+    data.bookmarks
+        .iter()
+        .filter(|b| duplicate_key(&b.url) == key)
+        .map(summary)
+        .collect()
+}
+
+/// All groups of two or more bookmarks sharing a duplicate key
+pub fn find_groups(data: &AppData) -> Vec<DuplicateGroup> {
+    let mut order = Vec::new();
+    let mut groups: HashMap<String, Vec<&Bookmark>> = HashMap::new();
+    for bookmark in &data.bookmarks {
+        let key = duplicate_key(&bookmark.url);
+        let group = groups.entry(key.clone()).or_default();
+        if group.is_empty() {
+            order.push(key);
+        }
+        group.push(bookmark);
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| {
+            let mut members = groups.remove(&key)?;
+            if members.len() < 2 {
+                return None;
+            }
+            members.sort_by(|a, b| created_order(a, b));
+            Some(DuplicateGroup {
+                key,
+                bookmarks: members.into_iter().map(summary).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Make sure `ids` name existing bookmarks that are all duplicates of each other
+pub fn validate_group(data: &AppData, ids: &[String]) -> Result<(), String> {
+    let mut keys = HashSet::new();
+    for id in ids {
+        let bookmark = data
+            .bookmarks
+            .iter()
+            .find(|b| &b.id == id)
+            .ok_or_else(|| format!("Bookmark not found: {}", id))?;
+        keys.insert(duplicate_key(&bookmark.url));
+    }
+    if keys.len() > 1 {
+        return Err("Only bookmarks pointing at the same page can be merged".to_string());
+    }
+    Ok(())
+}
+
+/// Fold the bookmarks with the given ids into the oldest one.
+///
+/// The survivor keeps the earliest `created_at` and the union of all tags,
+/// is a favorite if any copy was, and borrows fields it is missing from the
+/// others. `[[bookmark:ID]]` links to the removed bookmarks are pointed at
+/// the survivor.
+pub fn merge(data: &mut AppData, ids: &[String], now: &str) -> Merged {
+    let wanted: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    let mut members: Vec<Bookmark> = data
+        .bookmarks
+        .iter()
+        .filter(|b| wanted.contains(b.id.as_str()))
+        .cloned()
+        .collect();
+    if members.len() < 2 {
+        return Merged::default();
+    }
+    members.sort_by(created_order);
+
+    let mut survivor = members.remove(0);
+    let mut snapshot_from = None;
+    for other in &members {
+        for tag in &other.tags {
+            if !survivor.tags.contains(tag) {
+                survivor.tags.push(tag.clone());
+            }
+        }
+        survivor.is_favorite |= other.is_favorite;
+        survivor.is_archived &= other.is_archived;
+        if survivor.description.trim().is_empty() {
+            survivor.description = other.description.clone();
+        }
+        if survivor.title.trim().is_empty() {
+            survivor.title = other.title.clone();
+        }
+        survivor.favicon = survivor.favicon.take().or_else(|| other.favicon.clone());
+        survivor.image = survivor.image.take().or_else(|| other.image.clone());
+        survivor.favicon_asset = survivor
+            .favicon_asset
+            .take()
+            .or_else(|| other.favicon_asset.clone());
+        survivor.image_asset = survivor
+            .image_asset
+            .take()
+            .or_else(|| other.image_asset.clone());
+        survivor.folder_id = survivor
+            .folder_id
+            .take()
+            .or_else(|| other.folder_id.clone());
+        survivor.collection_id = survivor
+            .collection_id
+            .take()
+            .or_else(|| other.collection_id.clone());
+        if survivor.snapshot.is_none() && other.snapshot.is_some() {
+            survivor.snapshot = other.snapshot.clone();
+            snapshot_from = Some(other.id.clone());
+        }
+        survivor.link_check = survivor
+            .link_check
+            .take()
+            .or_else(|| other.link_check.clone());
+    }
+    if let Ok(url) = normalize_url(&survivor.url) {
+        survivor.url = url;
+    }
+
+    let moved: HashMap<String, String> = members
+        .into_iter()
+        .map(|b| (b.id, survivor.id.clone()))
+        .collect();
+    data.bookmarks.retain(|b| !moved.contains_key(&b.id));
+    let merged = Merged {
+        removed: moved.len(),
+        snapshot: snapshot_from.map(|from| (from, survivor.id.clone())),
+    };
+    if let Some(slot) = data.bookmarks.iter_mut().find(|b| b.id == survivor.id) {
+        *slot = survivor;
+    }
+    links::retarget(data, DocKind::Bookmark, &moved, now);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalize_strips_tracking_parameters() {
+        assert_eq!(
+            normalize_url("https://Example.com/post?utm_source=feed&id=7&fbclid=x&UTM_Medium=y")
+                .unwrap(),
+            "https://example.com/post?id=7"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/post?utm_source=feed&gclid=1").unwrap(),
+            "https://example.com/post"
+        );
+        assert!(normalize_url("not a url").is_err());
+    }
+
+    #[test]
+    fn normalize_keeps_route_fragments_only() {
+        assert_eq!(
+            normalize_url("https://app.example/#/inbox/3").unwrap(),
+            "https://app.example/#/inbox/3"
+        );
+        assert_eq!(
+            normalize_url("https://app.example/#!/settings").unwrap(),
+            "https://app.example/#!/settings"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/docs#install").unwrap(),
+            "https://example.com/docs"
+        );
+    }
+
+    #[test]
+    fn normalize_trims_trailing_slashes_below_the_root() {
+        assert_eq!(
+            normalize_url("https://example.com/docs/").unwrap(),
+            "https://example.com/docs"
+        );
+        assert_eq!(
+            normalize_url("https://example.com").unwrap(),
+            "https://example.com/"
+        );
+        assert_eq!(
+            normalize_url("https://example.com:443/a").unwrap(),
+            "https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn duplicate_key_ignores_scheme_www_and_parameter_order() {
+        let key = duplicate_key("https://example.com/a?x=1&y=2");
+        assert_eq!(key, "example.com/a?x=1&y=2");
+        for variant in [
+            "http://example.com/a?x=1&y=2",
+            "https://www.example.com/a?y=2&x=1",
+            "http://WWW.example.com/a/?x=1&utm_campaign=z&y=2#top",
+        ] {
+            assert_eq!(duplicate_key(variant), key, "{}", variant);
+        }
+        assert_ne!(duplicate_key("https://example.com/a?x=2&y=1"), key);
+        assert_ne!(duplicate_key("https://sub.example.com/a?x=1&y=2"), key);
+    }
+
+    fn data() -> AppData {
+        let mut data = crate::get_default_data();
+        data.bookmarks = serde_json::from_value(json!([
+            { "id": "new", "title": "Newest", "url": "http://www.example.com/a/", "tags": ["b", "c"],
+              "isFavorite": true, "createdAt": "2025-03-01T00:00:00Z" },
+            { "id": "old", "title": "Oldest", "url": "https://example.com/a?utm_source=x", "tags": ["a", "b"],
+              "description": "", "createdAt": "2025-01-01T00:00:00+02:00" },
+            { "id": "mid", "title": "Middle", "url": "https://example.com/a", "description": "Kept",
+              "folderId": "f", "createdAt": "2025-02-01T00:00:00Z" },
+            { "id": "other", "title": "Other", "url": "https://other.example/", "createdAt": "2025-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+        data.notes = serde_json::from_value(json!([
+            { "id": "n", "title": "N", "content": "See [[bookmark:new|the page]]",
+              "createdAt": "2025-01-01T00:00:00Z", "updatedAt": "2025-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn groups_list_duplicates_oldest_first() {
+        let data = data();
+        let groups = find_groups(&data);
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<&str> = groups[0].bookmarks.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, ["old", "mid", "new"]);
+        assert_eq!(find_matches(&data, "https://www.example.com/a").len(), 3);
+
+        assert!(validate_group(&data, &["old".to_string(), "new".to_string()]).is_ok());
+        assert!(validate_group(&data, &["old".to_string(), "other".to_string()]).is_err());
+        assert!(validate_group(&data, &["gone".to_string()]).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_earliest_bookmark_and_all_tags() {
+        let mut data = data();
+        let ids: Vec<String> = ["new", "old", "mid"].map(String::from).to_vec();
+        let merged = merge(&mut data, &ids, "2025-06-01T00:00:00Z");
+        assert_eq!(merged.removed, 2);
+
+        assert_eq!(data.bookmarks.len(), 2);
+        let survivor = data.bookmarks.iter().find(|b| b.id == "old").unwrap();
+        assert_eq!(survivor.created_at, "2025-01-01T00:00:00+02:00");
+        assert_eq!(survivor.title, "Oldest");
+        assert_eq!(survivor.url, "https://example.com/a");
+        assert_eq!(survivor.tags, ["a", "b", "c"]);
+        assert_eq!(survivor.description, "Kept");
+        assert_eq!(survivor.folder_id.as_deref(), Some("f"));
+        assert!(survivor.is_favorite);
+
+        assert_eq!(data.notes[0].content, "See [[bookmark:old|the page]]");
+    }
+}
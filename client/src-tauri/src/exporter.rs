@@ -0,0 +1,402 @@
+//! Bookmark export
+//!
+//! Writes bookmarks as a Netscape Bookmark File (importable into any
+//! browser), CSV or JSON Lines. Collections and folders become nested
+//! `<H3>` folders in the HTML export and plain columns in CSV, with nested
+//! folders written as a path (`Rust / Async`).
+
+use crate::folders;
+use crate::html;
+use crate::{AppData, Bookmark, BookmarkFolder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    NetscapeHtml,
+    Csv,
+    Jsonl,
+}
+
+/// Which bookmarks to export; the default is every non-archived bookmark
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ExportFilter {
+    /// A collection id, including the built-in `all`, `favorites` and `archive`
+    #[serde(rename = "collectionId", default)]
+    pub collection_id: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(rename = "favoritesOnly", default)]
+    pub favorites_only: bool,
+    #[serde(rename = "includeArchived", default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportSummary {
+    pub path: String,
+    pub format: ExportFormat,
+    pub bookmarks: usize,
+}
+
+pub fn default_file_name(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::NetscapeHtml => "carbon-bookmarks.html",
+        ExportFormat::Csv => "carbon-bookmarks.csv",
+        ExportFormat::Jsonl => "carbon-bookmarks.jsonl",
+    }
+}
+
+/// Name and extensions for the save dialog's file type filter
+pub fn file_filter(format: ExportFormat) -> (&'static str, &'static [&'static str]) {
+    match format {
+        ExportFormat::NetscapeHtml => ("Bookmarks HTML", &["html", "htm"]),
+        ExportFormat::Csv => ("CSV", &["csv"]),
+        ExportFormat::Jsonl => ("JSON Lines", &["jsonl"]),
+    }
+}
+
+pub fn filter_bookmarks<'a>(data: &'a AppData, filter: &ExportFilter) -> Vec<&'a Bookmark> {
+    data.bookmarks
+        .iter()
+        .filter(|b| match filter.collection_id.as_deref() {
+            None | Some("all") => filter.include_archived || !b.is_archived,
+            Some("favorites") => b.is_favorite && (filter.include_archived || !b.is_archived),
+            Some("archive") => b.is_archived,
+            Some(id) => {
+                b.collection_id.as_deref() == Some(id)
+                    && (filter.include_archived || !b.is_archived)
+            }
+        })
+        .filter(|b| !filter.favorites_only || b.is_favorite)
+        .filter(|b| match &filter.tag {
+            Some(tag) => b.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => true,
+        })
+        .collect()
+}
+
+fn unix_seconds(timestamp: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|d| d.timestamp())
+}
+
+// ============================================
+// Netscape Bookmark File
+// ============================================
+
+fn write_link(out: &mut String, bookmark: &Bookmark, indent: &str) {
+    out.push_str(&format!(
+        "{}<DT><A HREF=\"{}\"",
+        indent,
+        html::escape(&bookmark.url)
+    ));
+    if let Some(added) = unix_seconds(&bookmark.created_at) {
+        out.push_str(&format!(" ADD_DATE=\"{}\"", added));
+    }
+    if !bookmark.tags.is_empty() {
+        out.push_str(&format!(
+            " TAGS=\"{}\"",
+            html::escape(&bookmark.tags.join(","))
+        ));
+    }
+    out.push_str(&format!(">{}</A>\n", html::escape(&bookmark.title)));
+    if !bookmark.description.trim().is_empty() {
+        out.push_str(&format!(
+            "{}<DD>{}\n",
+            indent,
+            html::escape(bookmark.description.trim())
+        ));
+    }
+}
+
+fn open_folder(out: &mut String, name: &str, indent: &str) {
+    out.push_str(&format!("{}<DT><H3>{}</H3>\n", indent, html::escape(name)));
+    out.push_str(&format!("{}<DL><p>\n", indent));
+}
+
+fn close_folder(out: &mut String, indent: &str) {
+    out.push_str(&format!("{}</DL><p>\n", indent));
+}
+
+/// Bookmarks grouped by where they are written in the HTML export
+struct Places<'a> {
+    data: &'a AppData,
+    /// Keyed by collection and folder; a bookmark in a folder is listed under
+    /// the folder's collection
+    links: HashMap<(Option<&'a str>, Option<&'a str>), Vec<&'a Bookmark>>,
+    /// Folders with bookmarks in them or in a subfolder
+    used_folders: HashSet<&'a str>,
+}
+
+impl<'a> Places<'a> {
+    /// The custom collection `id` names, if any. Built-in collections (all,
+    /// favorites, archive) are views, not folders, and deleted ones are gone.
+    fn collection(&self, id: Option<&'a str>) -> Option<&'a str> {
+        id.filter(|id| {
+            self.data
+                .collections
+                .iter()
+                .any(|c| c.id == *id && c.is_custom == Some(true))
+        })
+    }
+
+    fn folders(&self, collection: Option<&str>, parent: Option<&str>) -> Vec<&'a BookmarkFolder> {
+        let mut folders: Vec<_> = self
+            .data
+            .bookmark_folders
+            .iter()
+            .filter(|f| self.used_folders.contains(f.id.as_str()))
+            .filter(|f| f.parent_id.as_deref() == parent)
+            .filter(|f| {
+                parent.is_some() || self.collection(f.collection_id.as_deref()) == collection
+            })
+            .collect();
+        folders.sort_by_key(|f| f.order.unwrap_or(i32::MAX));
+        folders
+    }
+
+    fn write_folder(
+        &self,
+        out: &mut String,
+        collection: Option<&str>,
+        folder: &BookmarkFolder,
+        indent: &str,
+    ) {
+        let inner = format!("{}    ", indent);
+        open_folder(out, &folder.name, indent);
+        for child in self.folders(collection, Some(&folder.id)) {
+            self.write_folder(out, collection, child, &inner);
+        }
+        for link in self
+            .links
+            .get(&(collection, Some(folder.id.as_str())))
+            .into_iter()
+            .flatten()
+        {
+            write_link(out, link, &inner);
+        }
+        close_folder(out, indent);
+    }
+
+    fn write_place(&self, out: &mut String, collection: Option<&str>, indent: &str) {
+        for folder in self.folders(collection, None) {
+            self.write_folder(out, collection, folder, indent);
+        }
+        for link in self.links.get(&(collection, None)).into_iter().flatten() {
+            write_link(out, link, indent);
+        }
+    }
+}
+
+pub fn to_netscape<'a>(data: &'a AppData, bookmarks: &[&'a Bookmark]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <!-- This is an automatically generated file.\n     It will be read and overwritten.\n     DO NOT EDIT! -->\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n<H1>Bookmarks</H1>\n<DL><p>\n",
+    );
+
+    let mut places = Places {
+        data,
+        links: HashMap::new(),
+        used_folders: HashSet::new(),
+    };
+    for bookmark in bookmarks {
+        let folder = bookmark
+            .folder_id
+            .as_deref()
+            .and_then(|id| data.bookmark_folders.iter().find(|f| f.id == id));
+        let place = match folder {
+            Some(folder) => {
+                places.used_folders.insert(&folder.id);
+                for ancestor in folders::ancestors(data, &folder.id) {
+                    if let Some(ancestor) = data.bookmark_folders.iter().find(|f| f.id == ancestor)
+                    {
+                        places.used_folders.insert(&ancestor.id);
+                    }
+                }
+                (
+                    places.collection(folder.collection_id.as_deref()),
+                    Some(folder.id.as_str()),
+                )
+            }
+            None => (places.collection(bookmark.collection_id.as_deref()), None),
+        };
+        places.links.entry(place).or_default().push(bookmark);
+    }
+
+    for collection in data
+        .collections
+        .iter()
+        .filter(|c| c.is_custom == Some(true))
+    {
+        let id = Some(collection.id.as_str());
+        if !places.links.keys().any(|(c, _)| *c == id) {
+            continue;
+        }
+        open_folder(&mut out, &collection.name, "    ");
+        places.write_place(&mut out, id, "        ");
+        close_folder(&mut out, "    ");
+    }
+    places.write_place(&mut out, None, "    ");
+
+    out.push_str("</DL><p>\n");
+    out
+}
+
+// ============================================
+// CSV
+// ============================================
+
+fn csv_field(value: &str) -> String {
+    // A leading =, +, - or @ would run as a formula in spreadsheet apps
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn to_csv(data: &AppData, bookmarks: &[&Bookmark]) -> String {
+    let collection_name = |id: &Option<String>| {
+        id.as_deref()
+            .and_then(|id| data.collections.iter().find(|c| c.id == id))
+            .map(|c| c.name.clone())
+            .unwrap_or_default()
+    };
+    let folder_name = |id: &Option<String>| {
+        id.as_deref()
+            .map(|id| folders::path(data, id))
+            .unwrap_or_default()
+    };
+
+    let mut out = String::from(
+        "title,url,description,tags,collection,folder,favorite,archived,created_at\r\n",
+    );
+    for b in bookmarks {
+        let row = [
+            csv_field(&b.title),
+            csv_field(&b.url),
+            csv_field(&b.description),
+            csv_field(&b.tags.join(",")),
+            csv_field(&collection_name(&b.collection_id)),
+            csv_field(&folder_name(&b.folder_id)),
+            b.is_favorite.to_string(),
+            b.is_archived.to_string(),
+            csv_field(&b.created_at),
+        ];
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+// ============================================
+// JSON Lines
+// ============================================
+
+pub fn to_jsonl(bookmarks: &[&Bookmark]) -> Result<String, String> {
+    let mut out = String::new();
+    for bookmark in bookmarks {
+        let line = serde_json::to_string(bookmark)
+            .map_err(|e| format!("Failed to serialize bookmark: {}", e))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Render the filtered bookmarks; returns the file content and bookmark count
+pub fn export(
+    data: &AppData,
+    format: ExportFormat,
+    filter: &ExportFilter,
+) -> Result<(String, usize), String> {
+    let bookmarks = filter_bookmarks(data, filter);
+    let content = match format {
+        ExportFormat::NetscapeHtml => to_netscape(data, &bookmarks),
+        ExportFormat::Csv => to_csv(data, &bookmarks),
+        ExportFormat::Jsonl => to_jsonl(&bookmarks)?,
+    };
+    Ok((content, bookmarks.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> AppData {
+        let mut data = crate::get_default_data();
+        data.collections.push(
+            serde_json::from_value(json!({
+                "id": "work",
+                "name": "Work",
+                "icon": "folder",
+                "isCustom": true,
+            }))
+            .unwrap(),
+        );
+        data.bookmark_folders = serde_json::from_value(json!([
+            { "id": "docs", "name": "Docs", "collectionId": "work", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "faves", "name": "Faves", "collectionId": "favorites", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "old", "name": "Old", "collectionId": "deleted", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "inner", "name": "Inner", "collectionId": "deleted", "parentId": "old", "createdAt": "2025-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+        data.bookmarks = serde_json::from_value(json!([
+            { "id": "1", "title": "In docs", "url": "https://a.com", "folderId": "docs", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "2", "title": "In faves", "url": "https://b.com", "folderId": "faves", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "3", "title": "In inner", "url": "https://c.com", "folderId": "inner", "createdAt": "2025-01-01T00:00:00Z" },
+            { "id": "4", "title": "Loose", "url": "https://d.com", "collectionId": "archive", "createdAt": "2025-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn netscape_export_nests_collections_and_folders() {
+        let (html, count) = export(
+            &data(),
+            ExportFormat::NetscapeHtml,
+            &ExportFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(count, 4);
+        let work = html.find("<H3>Work</H3>").unwrap();
+        let docs = html.find("<H3>Docs</H3>").unwrap();
+        let link = html.find(">In docs</A>").unwrap();
+        assert!(work < docs && docs < link);
+    }
+
+    #[test]
+    fn folders_with_a_stale_collection_are_exported_at_the_top_level() {
+        let (html, _) = export(
+            &data(),
+            ExportFormat::NetscapeHtml,
+            &ExportFilter::default(),
+        )
+        .unwrap();
+        for text in [
+            "<H3>Faves</H3>",
+            ">In faves</A>",
+            "<H3>Old</H3>",
+            "<H3>Inner</H3>",
+            ">In inner</A>",
+            ">Loose</A>",
+        ] {
+            assert!(html.contains(text), "{} missing from\n{}", text, html);
+        }
+        let old = html.find("<H3>Old</H3>").unwrap();
+        let inner = html.find("<H3>Inner</H3>").unwrap();
+        assert!(old < inner);
+    }
+}
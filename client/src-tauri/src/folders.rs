@@ -0,0 +1,229 @@
+//! Nested bookmark folders
+//!
+//! A folder's `parent_id` points at another folder in the same collection,
+//! or is `None` at the top level. Older data has no parents at all, which
+//! reads as every folder being top-level; `migrate` repairs anything else
+//! that does not form a tree (missing parents, cycles, collection mismatches).
+
+use crate::parent_tree::{self, TreeItem};
+use crate::{AppData, BookmarkFolder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// What happens to a folder's contents when it is deleted
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeleteMode {
+    /// Delete every subfolder too; their bookmarks leave the folder
+    Cascade,
+    /// Move subfolders and bookmarks up into the deleted folder's parent
+    Reparent,
+}
+
+/// A folder with its subfolders, for the sidebar
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: BookmarkFolder,
+    /// Non-archived bookmarks directly in this folder
+    #[serde(rename = "bookmarkCount")]
+    pub bookmark_count: usize,
+    /// Non-archived bookmarks in this folder and all subfolders
+    #[serde(rename = "totalCount")]
+    pub total_count: usize,
+    pub children: Vec<FolderNode>,
+}
+
+impl TreeItem for BookmarkFolder {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+    fn set_parent_id(&mut self, parent_id: Option<String>) {
+        self.parent_id = parent_id;
+    }
+    fn order(&self) -> Option<i32> {
+        self.order
+    }
+}
+
+fn find<'a>(data: &'a AppData, id: &str) -> Option<&'a BookmarkFolder> {
+    parent_tree::find(&data.bookmark_folders, id)
+}
+
+/// Ids of the folder's ancestors, nearest first
+pub fn ancestors(data: &AppData, id: &str) -> Vec<String> {
+    parent_tree::ancestors(&data.bookmark_folders, id)
+}
+
+/// The folder and all folders below it
+pub fn subtree(data: &AppData, id: &str) -> HashSet<String> {
+    parent_tree::subtree(&data.bookmark_folders, id)
+}
+
+/// Folder names from the top level down, e.g. `Rust / Async`
+pub fn path(data: &AppData, id: &str) -> String {
+    let mut names: Vec<&str> = ancestors(data, id)
+        .iter()
+        .rev()
+        .filter_map(|a| find(data, a))
+        .map(|f| f.name.as_str())
+        .collect();
+    if let Some(folder) = find(data, id) {
+        names.push(&folder.name);
+    }
+    names.join(" / ")
+}
+
+/// Repair folder parents so they form a tree.
+///
+/// Parents that no longer exist and parents that would close a cycle are
+/// cleared, and subfolders take their parent's collection. Returns whether
+/// anything changed.
+pub fn migrate(data: &mut AppData) -> bool {
+    let mut changed = parent_tree::repair(&mut data.bookmark_folders);
+
+    // Parents are now acyclic, so walking up always ends at a top-level folder
+    for index in 0..data.bookmark_folders.len() {
+        let id = data.bookmark_folders[index].id.clone();
+        let Some(root) = ancestors(data, &id).pop() else {
+            continue;
+        };
+        let collection = find(data, &root).and_then(|f| f.collection_id.clone());
+        if data.bookmark_folders[index].collection_id != collection {
+            data.bookmark_folders[index].collection_id = collection;
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn next_order(data: &AppData, parent_id: Option<&str>, collection_id: Option<&str>) -> i32 {
+    data.bookmark_folders
+        .iter()
+        .filter(|f| f.parent_id.as_deref() == parent_id)
+        .filter(|f| parent_id.is_some() || f.collection_id.as_deref() == collection_id)
+        .filter_map(|f| f.order)
+        .max()
+        .map(|o| o + 1)
+        .unwrap_or(0)
+}
+
+/// Move a folder under `parent_id`, or to the top level of `collection_id`
+/// (its current collection if `None`). Subfolders and bookmarks follow it
+/// into the new collection.
+pub fn move_folder(
+    data: &mut AppData,
+    folder_id: &str,
+    parent_id: Option<&str>,
+    collection_id: Option<&str>,
+) -> Result<(), String> {
+    let folder = find(data, folder_id).ok_or_else(|| format!("Folder not found: {}", folder_id))?;
+    let collection = match parent_id {
+        Some(parent_id) => {
+            let parent =
+                find(data, parent_id).ok_or_else(|| format!("Folder not found: {}", parent_id))?;
+            if parent_id == folder_id || ancestors(data, parent_id).iter().any(|a| a == folder_id) {
+                return Err("A folder cannot be moved into itself or its subfolders".to_string());
+            }
+            parent.collection_id.clone()
+        }
+        None => collection_id
+            .map(str::to_string)
+            .or_else(|| folder.collection_id.clone()),
+    };
+
+    let order = next_order(data, parent_id, collection.as_deref());
+    let moved = subtree(data, folder_id);
+    for folder in &mut data.bookmark_folders {
+        if folder.id == folder_id {
+            folder.parent_id = parent_id.map(str::to_string);
+            folder.order = Some(order);
+        }
+        if moved.contains(&folder.id) {
+            folder.collection_id = collection.clone();
+        }
+    }
+    for bookmark in &mut data.bookmarks {
+        if bookmark
+            .folder_id
+            .as_ref()
+            .map(|f| moved.contains(f))
+            .unwrap_or(false)
+        {
+            bookmark.collection_id = collection.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Delete a folder, either with its subfolders or moving its contents up
+pub fn delete_folder(data: &mut AppData, folder_id: &str, mode: DeleteMode) -> Result<(), String> {
+    let folder = find(data, folder_id).ok_or_else(|| format!("Folder not found: {}", folder_id))?;
+    let parent = folder.parent_id.clone();
+    let collection = folder.collection_id.clone();
+
+    let removed: HashSet<String> = match mode {
+        DeleteMode::Cascade => subtree(data, folder_id),
+        DeleteMode::Reparent => HashSet::from([folder_id.to_string()]),
+    };
+    // Where the removed folders' bookmarks end up
+    let target = match mode {
+        DeleteMode::Cascade => None,
+        DeleteMode::Reparent => parent.clone(),
+    };
+
+    if mode == DeleteMode::Reparent {
+        let mut order = next_order(data, parent.as_deref(), collection.as_deref());
+        for folder in &mut data.bookmark_folders {
+            if folder.parent_id.as_deref() == Some(folder_id) {
+                folder.parent_id = parent.clone();
+                folder.order = Some(order);
+                order += 1;
+            }
+        }
+    }
+    data.bookmark_folders.retain(|f| !removed.contains(&f.id));
+    for bookmark in &mut data.bookmarks {
+        if bookmark
+            .folder_id
+            .as_ref()
+            .map(|f| removed.contains(f))
+            .unwrap_or(false)
+        {
+            bookmark.folder_id = target.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Folder tree of one collection, or of every collection if `None`
+pub fn tree(data: &AppData, collection_id: Option<&str>) -> Vec<FolderNode> {
+    let mut direct: HashMap<&str, usize> = HashMap::new();
+    for bookmark in data.bookmarks.iter().filter(|b| !b.is_archived) {
+        if let Some(folder) = bookmark.folder_id.as_deref() {
+            *direct.entry(folder).or_default() += 1;
+        }
+    }
+
+    let roots: Vec<&BookmarkFolder> = data
+        .bookmark_folders
+        .iter()
+        .filter(|f| f.parent_id.is_none())
+        .filter(|f| collection_id.is_none() || f.collection_id.as_deref() == collection_id)
+        .collect();
+    parent_tree::build(&data.bookmark_folders, roots, &|folder,
+                                                        children: Vec<
+        FolderNode,
+    >| {
+        let bookmark_count = direct.get(folder.id.as_str()).copied().unwrap_or(0);
+        FolderNode {
+            folder: folder.clone(),
+            bookmark_count,
+            total_count: bookmark_count + children.iter().map(|c| c.total_count).sum::<usize>(),
+            children,
+        }
+    })
+}
@@ -0,0 +1,860 @@
+//! Undo/redo history for `AppData` mutations
+//!
+//! Every persisted write is diffed against the previous data and stored as
+//! an operation made of field-level changes. Arrays of objects with an `id`
+//! (boards, cards, notes, ...) record inserted and removed elements and a
+//! reorder by id, so adding a note stores that note and not every note.
+//! The stacks are bounded and kept in `history.json` next to the data file
+//! so they survive restarts.
+//!
+//! The file is rewritten when an operation is added, undone or redone.
+//! Writes merged into the last operation only update it in memory; they
+//! reach the file with the next operation or `flush` on exit.
+
+use crate::AppData;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Maximum number of operations kept on each stack
+const MAX_DEPTH: usize = 100;
+
+/// Writes touching the same fields within this window are merged, so typing
+/// into a card description becomes a single undo step
+const COALESCE_WINDOW_MS: i64 = 2000;
+
+/// Local UI state that should never be undone
+const IGNORED_KEYS: [&str; 5] = [
+    "activeBoard",
+    "activeView",
+    "theme",
+    "lastModified",
+    "syncEnabled",
+];
+
+static HISTORY: Mutex<Option<History>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+    /// The element of an array whose `id` is this
+    Id {
+        id: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    /// Replace the value at `path`
+    #[default]
+    Set,
+    /// Insert `after` into the array at `path`, at `index`
+    Insert,
+    /// Remove `before` (found by id) from the array at `path`; it was at `index`
+    Remove,
+    /// Put the elements of the array at `path` from the id order in `before`
+    /// into the id order in `after`
+    Reorder,
+}
+
+/// A single change; for `Set`, `None` means the key did not exist
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Change {
+    #[serde(default)]
+    pub kind: ChangeKind,
+    pub path: Vec<PathSegment>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl Change {
+    fn set(path: &[PathSegment], before: Option<&Value>, after: Option<&Value>) -> Change {
+        Change {
+            kind: ChangeKind::Set,
+            path: path.to_vec(),
+            before: before.cloned(),
+            after: after.cloned(),
+            index: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Operation {
+    pub id: String,
+    pub label: String,
+    pub timestamp: String,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct History {
+    #[serde(default)]
+    pub undo: Vec<Operation>,
+    #[serde(default)]
+    pub redo: Vec<Operation>,
+    /// The last operation changed since the file was written
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+/// Operation summary for the frontend (without the change payloads)
+#[derive(Debug, Serialize, Clone)]
+pub struct OperationSummary {
+    pub id: String,
+    pub label: String,
+    pub timestamp: String,
+    #[serde(rename = "changeCount")]
+    pub change_count: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryState {
+    pub undo: Vec<OperationSummary>,
+    pub redo: Vec<OperationSummary>,
+}
+
+fn get_history_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("history.json")
+}
+
+fn load_history(data_dir: &Path) -> History {
+    fs::read_to_string(get_history_file_path(data_dir))
+        .ok()
+        .and_then(|content| match serde_json::from_str::<History>(&content) {
+            Ok(history) => Some(history),
+            Err(e) => {
+                log::warn!("Discarding unreadable undo history: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save_history(data_dir: &Path, history: &mut History) {
+    history.unsaved = false;
+    match serde_json::to_string(history) {
+        Ok(json) => {
+            if let Err(e) = fs::write(get_history_file_path(data_dir), json) {
+                log::error!("Failed to write undo history: {}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to serialize undo history: {}", e),
+    }
+}
+
+/// Run `f` against the cached history, loading it from disk on first use
+fn with_history<T>(data_dir: &Path, f: impl FnOnce(&mut History) -> T) -> T {
+    let mut guard = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    let history = guard.get_or_insert_with(|| load_history(data_dir));
+    f(history)
+}
+
+fn to_tracked_value(data: &AppData) -> Result<Value, String> {
+    let mut value =
+        serde_json::to_value(data).map_err(|e| format!("Failed to serialize data: {}", e))?;
+    if let Value::Object(map) = &mut value {
+        for key in IGNORED_KEYS {
+            map.remove(key);
+        }
+    }
+    Ok(value)
+}
+
+fn item_id(item: &Value) -> Option<&str> {
+    item.get("id")?.as_str()
+}
+
+/// Ids of `items` if every element is an object with a unique string id
+fn keyed_ids(items: &[Value]) -> Option<Vec<&str>> {
+    let ids: Vec<&str> = items.iter().map(item_id).collect::<Option<_>>()?;
+    let unique: HashSet<&str> = ids.iter().copied().collect();
+    (unique.len() == ids.len()).then_some(ids)
+}
+
+/// Collect the changes that turn `before` into `after`.
+///
+/// Objects are diffed key by key, and so are arrays of objects with ids
+/// (see `diff_keyed`). Any other array that changed is replaced whole.
+fn diff(path: &mut Vec<PathSegment>, before: &Value, after: &Value, changes: &mut Vec<Change>) {
+    if before == after {
+        return;
+    }
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old) in a {
+                path.push(PathSegment::Key(key.clone()));
+                match b.get(key) {
+                    Some(new) => diff(path, old, new, changes),
+                    None => changes.push(Change::set(path, Some(old), None)),
+                }
+                path.pop();
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    path.push(PathSegment::Key(key.clone()));
+                    changes.push(Change::set(path, None, Some(new)));
+                    path.pop();
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => match (keyed_ids(a), keyed_ids(b)) {
+            (Some(a_ids), Some(b_ids)) => diff_keyed(path, (a, &a_ids), (b, &b_ids), changes),
+            _ => changes.push(Change::set(path, Some(before), Some(after))),
+        },
+        _ => changes.push(Change::set(path, Some(before), Some(after))),
+    }
+}
+
+/// Diff two arrays of objects with ids.
+///
+/// Replayed in order, the changes remove elements (last first), reorder the
+/// ones both sides share, insert new elements (first first) and then edit
+/// shared elements by id. Undo replays them backwards, so every step finds
+/// the array as it left it.
+fn diff_keyed(
+    path: &mut Vec<PathSegment>,
+    (a, a_ids): (&[Value], &[&str]),
+    (b, b_ids): (&[Value], &[&str]),
+    changes: &mut Vec<Change>,
+) {
+    let in_a: HashSet<&str> = a_ids.iter().copied().collect();
+    let in_b: HashSet<&str> = b_ids.iter().copied().collect();
+
+    for (index, item) in a.iter().enumerate().rev() {
+        if !in_b.contains(a_ids[index]) {
+            changes.push(Change {
+                kind: ChangeKind::Remove,
+                path: path.clone(),
+                before: Some(item.clone()),
+                after: None,
+                index: Some(index),
+            });
+        }
+    }
+    let kept_a: Vec<&str> = a_ids
+        .iter()
+        .copied()
+        .filter(|id| in_b.contains(id))
+        .collect();
+    let kept_b: Vec<&str> = b_ids
+        .iter()
+        .copied()
+        .filter(|id| in_a.contains(id))
+        .collect();
+    if kept_a != kept_b {
+        changes.push(Change {
+            kind: ChangeKind::Reorder,
+            path: path.clone(),
+            before: Some(Value::from(kept_a)),
+            after: Some(Value::from(kept_b)),
+            index: None,
+        });
+    }
+    for (index, item) in b.iter().enumerate() {
+        if !in_a.contains(b_ids[index]) {
+            changes.push(Change {
+                kind: ChangeKind::Insert,
+                path: path.clone(),
+                before: None,
+                after: Some(item.clone()),
+                index: Some(index),
+            });
+        }
+    }
+
+    let old: HashMap<&str, &Value> = a_ids.iter().copied().zip(a).collect();
+    for (id, new) in b_ids.iter().zip(b) {
+        if let Some(old) = old.get(id) {
+            path.push(PathSegment::Id { id: id.to_string() });
+            diff(path, old, new, changes);
+            path.pop();
+        }
+    }
+}
+
+fn mismatch() -> String {
+    "History no longer matches the current data".to_string()
+}
+
+fn child<'v>(value: &'v Value, segment: &PathSegment) -> Option<&'v Value> {
+    match segment {
+        PathSegment::Key(key) => value.get(key.as_str()),
+        PathSegment::Index(i) => value.get(*i),
+        PathSegment::Id { id } => value
+            .as_array()?
+            .iter()
+            .find(|item| item_id(item) == Some(id.as_str())),
+    }
+}
+
+fn resolve<'v>(root: &'v mut Value, path: &[PathSegment]) -> Result<&'v mut Value, String> {
+    let mut target = root;
+    for segment in path {
+        target = match segment {
+            PathSegment::Key(key) => target.get_mut(key.as_str()),
+            PathSegment::Index(i) => target.get_mut(*i),
+            PathSegment::Id { id } => target.as_array_mut().and_then(|items| {
+                items
+                    .iter_mut()
+                    .find(|item| item_id(item) == Some(id.as_str()))
+            }),
+        }
+        .ok_or_else(mismatch)?;
+    }
+    Ok(target)
+}
+
+/// Set (or remove, for `None`) the value at `path`, which must currently be
+/// `expected`
+fn set(
+    root: &mut Value,
+    path: &[PathSegment],
+    expected: Option<&Value>,
+    value: Option<&Value>,
+) -> Result<(), String> {
+    let Some((last, parents)) = path.split_last() else {
+        if Some(&*root) != expected {
+            return Err(mismatch());
+        }
+        if let Some(value) = value {
+            *root = value.clone();
+        }
+        return Ok(());
+    };
+
+    let target = resolve(root, parents)?;
+    if child(target, last) != expected {
+        return Err(mismatch());
+    }
+    match (target, last, value) {
+        (Value::Object(map), PathSegment::Key(key), Some(value)) => {
+            map.insert(key.clone(), value.clone());
+        }
+        (Value::Object(map), PathSegment::Key(key), None) => {
+            map.remove(key);
+        }
+        (Value::Array(items), PathSegment::Index(i), Some(value)) if *i < items.len() => {
+            items[*i] = value.clone();
+        }
+        (Value::Array(items), PathSegment::Id { id }, Some(value)) => {
+            let item = items
+                .iter_mut()
+                .find(|item| item_id(item) == Some(id.as_str()))
+                .ok_or_else(mismatch)?;
+            *item = value.clone();
+        }
+        _ => return Err(mismatch()),
+    }
+    Ok(())
+}
+
+fn id_list(value: Option<&Value>) -> Result<Vec<&str>, String> {
+    value
+        .and_then(Value::as_array)
+        .and_then(|ids| ids.iter().map(Value::as_str).collect())
+        .ok_or_else(mismatch)
+}
+
+/// Apply one change, forwards (`before` → `after`) or backwards
+fn apply(root: &mut Value, change: &Change, forward: bool) -> Result<(), String> {
+    let (from, to) = if forward {
+        (change.before.as_ref(), change.after.as_ref())
+    } else {
+        (change.after.as_ref(), change.before.as_ref())
+    };
+    if change.kind == ChangeKind::Set {
+        return set(root, &change.path, from, to);
+    }
+
+    let items = resolve(root, &change.path)?
+        .as_array_mut()
+        .ok_or_else(mismatch)?;
+    match (change.kind, to) {
+        (ChangeKind::Reorder, _) => {
+            let (from, to) = (id_list(from)?, id_list(to)?);
+            if keyed_ids(items).as_deref() != Some(from.as_slice()) {
+                return Err(mismatch());
+            }
+            let mut by_id: HashMap<String, Value> = items
+                .drain(..)
+                .map(|item| (item_id(&item).unwrap_or_default().to_string(), item))
+                .collect();
+            for id in to {
+                items.push(by_id.remove(id).ok_or_else(mismatch)?);
+            }
+            if !by_id.is_empty() {
+                return Err(mismatch());
+            }
+        }
+        // Inserting: forwards for `Insert`, backwards for `Remove`
+        (_, Some(item)) => {
+            let index = change
+                .index
+                .filter(|i| *i <= items.len())
+                .ok_or_else(mismatch)?;
+            if items.iter().any(|i| item_id(i) == item_id(item)) {
+                return Err(mismatch());
+            }
+            items.insert(index, item.clone());
+        }
+        (_, None) => {
+            let item = from.ok_or_else(mismatch)?;
+            let position = items.iter().position(|i| i == item).ok_or_else(mismatch)?;
+            items.remove(position);
+        }
+    }
+    Ok(())
+}
+
+/// A readable label for writes coming from the frontend
+fn describe(changes: &[Change]) -> String {
+    // Name the innermost collection the first change touches
+    let area = changes
+        .first()
+        .and_then(|c| {
+            c.path.iter().rev().find_map(|segment| match segment {
+                PathSegment::Key(key) => match key.as_str() {
+                    "boards" => Some("board"),
+                    "columns" => Some("column"),
+                    "cards" | "archivedCards" => Some("card"),
+                    "checklist" => Some("checklist item"),
+                    "bookmarks" => Some("bookmark"),
+                    "bookmarkFolders" => Some("bookmark folder"),
+                    "collections" => Some("collection"),
+                    "customTags" => Some("tag"),
+                    "notes" => Some("note"),
+                    _ => None,
+                },
+                PathSegment::Index(_) | PathSegment::Id { .. } => None,
+            })
+        })
+        .unwrap_or("data");
+
+    let removed = changes.iter().any(|c| match (c.kind, &c.before, &c.after) {
+        (ChangeKind::Remove, _, _) | (ChangeKind::Set, Some(_), None) => true,
+        (ChangeKind::Set, Some(Value::Array(a)), Some(Value::Array(b))) => b.len() < a.len(),
+        _ => false,
+    });
+    let added = changes.iter().any(|c| match (c.kind, &c.before, &c.after) {
+        (ChangeKind::Insert, _, _) | (ChangeKind::Set, None, Some(_)) => true,
+        (ChangeKind::Set, Some(Value::Array(a)), Some(Value::Array(b))) => b.len() > a.len(),
+        _ => false,
+    });
+
+    match (removed, added) {
+        (true, false) => format!("Delete {}", area),
+        (false, true) => format!("Add {}", area),
+        _ => format!("Edit {}", area),
+    }
+}
+
+/// Whether `b` only sets the same fields `a` set, so it can be merged into `a`
+fn same_fields(a: &[Change], b: &[Change]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(x, y)| {
+            x.kind == ChangeKind::Set && y.kind == ChangeKind::Set && x.path == y.path
+        })
+}
+
+fn elapsed_ms(from: &str, to: &str) -> Option<i64> {
+    let from = chrono::DateTime::parse_from_rfc3339(from).ok()?;
+    let to = chrono::DateTime::parse_from_rfc3339(to).ok()?;
+    Some((to - from).num_milliseconds())
+}
+
+/// Record the difference between `previous` and `data` as an undoable
+/// operation. `label` is derived from the changes when not given.
+pub fn record(
+    data_dir: &Path,
+    previous: Option<&AppData>,
+    data: &AppData,
+    label: Option<&str>,
+    now: &str,
+) -> Result<(), String> {
+    let Some(previous) = previous else {
+        return Ok(());
+    };
+    let mut changes = Vec::new();
+    diff(
+        &mut Vec::new(),
+        &to_tracked_value(previous)?,
+        &to_tracked_value(data)?,
+        &mut changes,
+    );
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let label = label
+        .map(str::to_string)
+        .unwrap_or_else(|| describe(&changes));
+
+    with_history(data_dir, |history| {
+        if history.push(label, changes, now) {
+            save_history(data_dir, history);
+        }
+    });
+    Ok(())
+}
+
+impl History {
+    /// Add an operation, or merge it into the last one when it sets the same
+    /// fields again shortly after. Returns whether the file needs rewriting.
+    fn push(&mut self, label: String, changes: Vec<Change>, now: &str) -> bool {
+        self.redo.clear();
+
+        let coalesce = self.undo.last().map(|last| {
+            last.label == label
+                && same_fields(&last.changes, &changes)
+                && elapsed_ms(&last.timestamp, now)
+                    .map(|ms| ms < COALESCE_WINDOW_MS)
+                    .unwrap_or(false)
+        });
+        if coalesce == Some(true) {
+            if let Some(last) = self.undo.last_mut() {
+                for (merged, change) in last.changes.iter_mut().zip(changes) {
+                    merged.after = change.after;
+                }
+                last.timestamp = now.to_string();
+            }
+            self.unsaved = true;
+            return false;
+        }
+
+        self.undo.push(Operation {
+            id: uuid::Uuid::new_v4().to_string(),
+            label,
+            timestamp: now.to_string(),
+            changes,
+        });
+        if self.undo.len() > MAX_DEPTH {
+            let excess = self.undo.len() - MAX_DEPTH;
+            self.undo.drain(..excess);
+        }
+        true
+    }
+}
+
+/// Apply one side of an operation to a serialized `AppData`
+fn replay_value(value: &mut Value, operation: &Operation, forward: bool) -> Result<(), String> {
+    if forward {
+        for change in &operation.changes {
+            apply(value, change, true)?;
+        }
+    } else {
+        for change in operation.changes.iter().rev() {
+            apply(value, change, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply one side of an operation to `data`, returning the resulting data.
+///
+/// Fails without changing anything if `data` is not what the operation
+/// left behind (or, going forwards, what it started from).
+fn replay(data: &AppData, operation: &Operation, forward: bool) -> Result<AppData, String> {
+    let mut value =
+        serde_json::to_value(data).map_err(|e| format!("Failed to serialize data: {}", e))?;
+    replay_value(&mut value, operation, forward)?;
+    serde_json::from_value(value).map_err(|e| format!("Failed to restore data: {}", e))
+}
+
+/// Undo the most recent operation. Returns `None` when there is nothing to undo.
+pub fn undo(data_dir: &Path, data: &AppData) -> Result<Option<AppData>, String> {
+    with_history(data_dir, |history| {
+        let Some(operation) = history.undo.pop() else {
+            return Ok(None);
+        };
+        match replay(data, &operation, false) {
+            Ok(restored) => {
+                history.redo.push(operation);
+                save_history(data_dir, history);
+                Ok(Some(restored))
+            }
+            Err(e) => {
+                // The data no longer lines up with this operation; drop it
+                log::warn!("Dropping undo step '{}': {}", operation.label, e);
+                save_history(data_dir, history);
+                Err(e)
+            }
+        }
+    })
+}
+
+/// Redo the most recently undone operation
+pub fn redo(data_dir: &Path, data: &AppData) -> Result<Option<AppData>, String> {
+    with_history(data_dir, |history| {
+        let Some(operation) = history.redo.pop() else {
+            return Ok(None);
+        };
+        match replay(data, &operation, true) {
+            Ok(restored) => {
+                history.undo.push(operation);
+                save_history(data_dir, history);
+                Ok(Some(restored))
+            }
+            Err(e) => {
+                log::warn!("Dropping redo step '{}': {}", operation.label, e);
+                save_history(data_dir, history);
+                Err(e)
+            }
+        }
+    })
+}
+
+/// Most recent operations first
+pub fn state(data_dir: &Path) -> HistoryState {
+    let summarize = |ops: &Vec<Operation>| {
+        ops.iter()
+            .rev()
+            .map(|op| OperationSummary {
+                id: op.id.clone(),
+                label: op.label.clone(),
+                timestamp: op.timestamp.clone(),
+                change_count: op.changes.len(),
+            })
+            .collect()
+    };
+    with_history(data_dir, |history| HistoryState {
+        undo: summarize(&history.undo),
+        redo: summarize(&history.redo),
+    })
+}
+
+/// Write out writes merged into the last operation since it was saved
+pub fn flush(data_dir: &Path) {
+    with_history(data_dir, |history| {
+        if history.unsaved {
+            save_history(data_dir, history);
+        }
+    });
+}
+
+pub fn clear(data_dir: &Path) {
+    with_history(data_dir, |history| {
+        *history = History::default();
+        save_history(data_dir, history);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn changes(before: &Value, after: &Value) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff(&mut Vec::new(), before, after, &mut changes);
+        changes
+    }
+
+    fn operation(changes: Vec<Change>) -> Operation {
+        Operation {
+            id: "op".to_string(),
+            label: "Edit".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            changes,
+        }
+    }
+
+    /// Diff, redo onto `before` and undo from `after`
+    fn round_trip(before: Value, after: Value) -> Vec<Change> {
+        let op = operation(changes(&before, &after));
+        let mut value = before.clone();
+        replay_value(&mut value, &op, true).unwrap();
+        assert_eq!(value, after);
+        replay_value(&mut value, &op, false).unwrap();
+        assert_eq!(value, before);
+        op.changes
+    }
+
+    #[test]
+    fn diff_records_only_changed_fields() {
+        let changes = round_trip(
+            json!({"title": "a", "color": "red", "old": 1}),
+            json!({"title": "b", "color": "red", "new": 2}),
+        );
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|c| c.kind == ChangeKind::Set));
+        let title = changes
+            .iter()
+            .find(|c| c.path == [PathSegment::Key("title".to_string())])
+            .unwrap();
+        assert_eq!(title.before, Some(json!("a")));
+        assert_eq!(title.after, Some(json!("b")));
+    }
+
+    #[test]
+    fn diff_inserts_and_removes_elements_by_id() {
+        let changes = round_trip(
+            json!({"notes": [{"id": "a", "body": "x"}, {"id": "b", "body": "y"}]}),
+            json!({"notes": [{"id": "c", "body": "z"}, {"id": "b", "body": "y"}]}),
+        );
+        let kinds: Vec<ChangeKind> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, [ChangeKind::Remove, ChangeKind::Insert]);
+        assert_eq!(changes[0].before, Some(json!({"id": "a", "body": "x"})));
+        assert_eq!(changes[1].after, Some(json!({"id": "c", "body": "z"})));
+        assert_eq!(changes[1].index, Some(0));
+    }
+
+    #[test]
+    fn diff_reorders_and_edits_elements_by_id() {
+        let changes = round_trip(
+            json!({"cards": [{"id": "a", "t": 1}, {"id": "b", "t": 2}, {"id": "c", "t": 3}]}),
+            json!({"cards": [{"id": "c", "t": 3}, {"id": "a", "t": 9}, {"id": "b", "t": 2}]}),
+        );
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, ChangeKind::Reorder);
+        assert_eq!(changes[0].before, Some(json!(["a", "b", "c"])));
+        assert_eq!(changes[0].after, Some(json!(["c", "a", "b"])));
+        assert_eq!(
+            changes[1].path,
+            [
+                PathSegment::Key("cards".to_string()),
+                PathSegment::Id {
+                    id: "a".to_string()
+                },
+                PathSegment::Key("t".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_round_trips_nested_moves_inserts_and_removals() {
+        round_trip(
+            json!({"columns": [
+                {"id": "todo", "cards": [{"id": "1"}, {"id": "2"}, {"id": "3"}, {"id": "4"}]},
+                {"id": "done", "cards": [{"id": "5"}]},
+            ]}),
+            json!({"columns": [
+                {"id": "done", "cards": [{"id": "3"}, {"id": "5"}, {"id": "6"}]},
+                {"id": "todo", "cards": [{"id": "7"}, {"id": "4"}, {"id": "1"}]},
+            ]}),
+        );
+    }
+
+    #[test]
+    fn diff_replaces_arrays_without_ids() {
+        let changes = round_trip(json!({"tags": ["a", "b"]}), json!({"tags": ["b", "c"]}));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Set);
+        assert_eq!(changes[0].after, Some(json!(["b", "c"])));
+    }
+
+    #[test]
+    fn replay_refuses_data_that_changed_since() {
+        let before = json!({"title": "a", "notes": [{"id": "n", "body": "x"}]});
+        let after = json!({"title": "b", "notes": [{"id": "n", "body": "x"}, {"id": "m"}]});
+        let op = operation(changes(&before, &after));
+
+        let mut edited = json!({"title": "c", "notes": [{"id": "n", "body": "x"}, {"id": "m"}]});
+        assert!(replay_value(&mut edited.clone(), &op, false).is_err());
+        edited["title"] = json!("b");
+        edited["notes"][1]["body"] = json!("typed later");
+        assert!(replay_value(&mut edited, &op, false).is_err());
+
+        // Redo also checks it starts from `before`
+        let mut undone = before.clone();
+        undone["title"] = json!("z");
+        assert!(replay_value(&mut undone, &op, true).is_err());
+    }
+
+    #[test]
+    fn replay_undoes_and_redoes_app_data() {
+        let before = crate::get_default_data();
+        let mut after = before.clone();
+        after.boards[0].name = "Renamed".to_string();
+        let mut copy = after.boards[0].clone();
+        copy.id = "copy".to_string();
+        after.boards.push(copy);
+        let op = operation(changes(
+            &to_tracked_value(&before).unwrap(),
+            &to_tracked_value(&after).unwrap(),
+        ));
+
+        let undone = replay(&after, &op, false).unwrap();
+        assert_eq!(undone.boards.len(), before.boards.len());
+        assert_eq!(undone.boards[0].name, before.boards[0].name);
+        let redone = replay(&undone, &op, true).unwrap();
+        assert_eq!(redone.boards.len(), before.boards.len() + 1);
+        assert_eq!(redone.boards[0].name, "Renamed");
+    }
+
+    fn title_change(before: &str, after: &str) -> Vec<Change> {
+        changes(&json!({"title": before}), &json!({"title": after}))
+    }
+
+    #[test]
+    fn push_coalesces_repeated_edits_within_the_window() {
+        let mut history = History::default();
+        assert!(history.push(
+            "Edit card".into(),
+            title_change("a", "ab"),
+            "2026-01-01T00:00:00Z"
+        ));
+        assert!(!history.push(
+            "Edit card".into(),
+            title_change("ab", "abc"),
+            "2026-01-01T00:00:01Z"
+        ));
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo[0].changes[0].before, Some(json!("a")));
+        assert_eq!(history.undo[0].changes[0].after, Some(json!("abc")));
+        assert!(history.unsaved);
+
+        // The window runs from the last merged write
+        assert!(!history.push(
+            "Edit card".into(),
+            title_change("abc", "abcd"),
+            "2026-01-01T00:00:02.500Z"
+        ));
+        assert_eq!(history.undo.len(), 1);
+
+        assert!(history.push(
+            "Edit card".into(),
+            title_change("abcd", "x"),
+            "2026-01-01T00:00:05Z"
+        ));
+        assert!(history.push(
+            "Edit board".into(),
+            title_change("x", "y"),
+            "2026-01-01T00:00:05.100Z"
+        ));
+        assert_eq!(history.undo.len(), 3);
+    }
+
+    #[test]
+    fn push_never_coalesces_inserts() {
+        let mut history = History::default();
+        let add = |id: &str| changes(&json!({"notes": []}), &json!({"notes": [{"id": id}]}));
+        history.push("Add note".into(), add("a"), "2026-01-01T00:00:00Z");
+        history.push("Add note".into(), add("a"), "2026-01-01T00:00:00.500Z");
+        assert_eq!(history.undo.len(), 2);
+    }
+
+    #[test]
+    fn push_clears_redo_and_trims_to_max_depth() {
+        let mut history = History::default();
+        history.redo.push(operation(title_change("a", "b")));
+        for i in 0..MAX_DEPTH + 5 {
+            let now = format!("2026-01-01T00:{:02}:{:02}Z", i / 60, i % 60);
+            history.push(format!("Edit {}", i), title_change("a", "b"), &now);
+        }
+        assert!(history.redo.is_empty());
+        assert_eq!(history.undo.len(), MAX_DEPTH);
+        assert_eq!(history.undo[0].label, "Edit 5");
+        assert_eq!(
+            history.undo[MAX_DEPTH - 1].label,
+            format!("Edit {}", MAX_DEPTH + 4)
+        );
+    }
+}
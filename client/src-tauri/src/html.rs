@@ -0,0 +1,479 @@
+//! Minimal, forgiving HTML scanning helpers
+//!
+//! This is not a full HTML parser: it walks tags and attributes well enough
+//! to read page metadata and bookmark export files, skipping comments and
+//! the contents of raw-text elements like `<script>`. `Document` builds a
+//! loose element tree on top of it for content extraction.
+
+/// Elements whose content is raw text and must not be scanned for tags
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "title"];
+
+/// Elements that never have content or a closing tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Deepest nesting `Document` builds; elements below it become siblings so
+/// recursive walks over the tree stay within the stack
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// Lowercased tag name
+    pub name: String,
+    /// Lowercased attribute names with entity-decoded values
+    pub attrs: Vec<(String, String)>,
+    pub closing: bool,
+    /// Written as `<tag ... />`
+    pub self_closing: bool,
+    /// Byte offset of `<`
+    pub start: usize,
+    /// Byte offset just past `>`
+    pub end: usize,
+}
+
+impl Tag {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_open(&self, name: &str) -> bool {
+        !self.closing && self.name == name
+    }
+}
+
+/// Iterator over the tags of an HTML document
+pub struct Tags<'a> {
+    html: &'a str,
+    pos: usize,
+    raw_until: Option<String>,
+}
+
+pub fn tags(html: &str) -> Tags<'_> {
+    Tags {
+        html,
+        pos: 0,
+        raw_until: None,
+    }
+}
+
+/// Case-insensitive ASCII search
+pub fn find_ascii_ci(haystack: &str, needle: &str) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+impl Iterator for Tags<'_> {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Tag> {
+        let html = self.html;
+        let bytes = html.as_bytes();
+        let len = html.len();
+
+        loop {
+            if let Some(name) = self.raw_until.take() {
+                match find_ascii_ci(&html[self.pos..], &format!("</{}", name)) {
+                    Some(offset) => self.pos += offset,
+                    None => {
+                        self.pos = len;
+                        return None;
+                    }
+                }
+            }
+
+            let start = self.pos + html[self.pos..].find('<')?;
+            let after = &html[start + 1..];
+
+            if after.starts_with("!--") {
+                self.pos = match after.find("-->") {
+                    Some(end) => start + 1 + end + 3,
+                    None => len,
+                };
+                continue;
+            }
+            if after.starts_with('!') || after.starts_with('?') {
+                self.pos = match after.find('>') {
+                    Some(end) => start + 1 + end + 1,
+                    None => len,
+                };
+                continue;
+            }
+
+            let closing = after.starts_with('/');
+            let name_start = start + 1 + usize::from(closing);
+            let name_len = html[name_start..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+                .unwrap_or(len - name_start);
+            if name_len == 0 {
+                // A stray `<` in text
+                self.pos = start + 1;
+                continue;
+            }
+            let name = html[name_start..name_start + name_len].to_ascii_lowercase();
+
+            let mut attrs = Vec::new();
+            let mut self_closing = false;
+            let mut i = name_start + name_len;
+            loop {
+                while i < len && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                if i >= len {
+                    break;
+                }
+                match bytes[i] {
+                    b'>' => {
+                        i += 1;
+                        break;
+                    }
+                    b'/' => {
+                        self_closing = true;
+                        i += 1;
+                        continue;
+                    }
+                    _ => {}
+                }
+
+                let key_start = i;
+                while i < len
+                    && !bytes[i].is_ascii_whitespace()
+                    && !matches!(bytes[i], b'=' | b'>' | b'/')
+                {
+                    i += 1;
+                }
+                let key = html[key_start..i].to_ascii_lowercase();
+                while i < len && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+
+                let mut value = String::new();
+                if i < len && bytes[i] == b'=' {
+                    i += 1;
+                    while i < len && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    if i < len && (bytes[i] == b'"' || bytes[i] == b'\'') {
+                        let quote = bytes[i];
+                        let value_start = i + 1;
+                        let value_end = bytes[value_start..]
+                            .iter()
+                            .position(|b| *b == quote)
+                            .map(|p| value_start + p)
+                            .unwrap_or(len);
+                        value = decode_entities(&html[value_start..value_end]);
+                        i = (value_end + 1).min(len);
+                    } else {
+                        let value_start = i;
+                        while i < len && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                            i += 1;
+                        }
+                        value = decode_entities(&html[value_start..i]);
+                    }
+                }
+                if !key.is_empty() {
+                    attrs.push((key, value));
+                } else if i == key_start {
+                    // Unparseable byte; skip it so we always make progress
+                    i += 1;
+                }
+            }
+
+            self.pos = i;
+            if !closing && !self_closing && RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                self.raw_until = Some(name.clone());
+            }
+            return Some(Tag {
+                name,
+                attrs,
+                closing,
+                self_closing,
+                start,
+                end: i,
+            });
+        }
+    }
+}
+
+fn named_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" => "\u{a0}",
+        "ndash" => "\u{2013}",
+        "mdash" => "\u{2014}",
+        "hellip" => "\u{2026}",
+        "lsquo" => "\u{2018}",
+        "rsquo" => "\u{2019}",
+        "ldquo" => "\u{201c}",
+        "rdquo" => "\u{201d}",
+        "laquo" => "\u{ab}",
+        "raquo" => "\u{bb}",
+        "copy" => "\u{a9}",
+        "reg" => "\u{ae}",
+        "trade" => "\u{2122}",
+        "middot" => "\u{b7}",
+        "bull" => "\u{2022}",
+        _ => return None,
+    })
+}
+
+/// Decode character references; unknown entities are left as they are
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let end = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+            .map(|e| e + 1)
+            .unwrap_or(rest.len());
+        let name = &rest[1..end];
+        let decoded = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X"))
+        {
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .map(String::from)
+        } else if let Some(dec) = name.strip_prefix('#') {
+            dec.parse::<u32>()
+                .ok()
+                .and_then(char::from_u32)
+                .map(String::from)
+        } else {
+            named_entity(name).map(String::from)
+        };
+
+        match decoded {
+            Some(decoded) => {
+                out.push_str(&decoded);
+                let consumed = if rest[end..].starts_with(';') {
+                    end + 1
+                } else {
+                    end
+                };
+                rest = &rest[consumed..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Collapse runs of whitespace into single spaces and trim
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Escape text for use in HTML content or a quoted attribute value
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Element(Tag),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// A loosely built element tree.
+///
+/// Nodes are stored in document order, so a parent always has a lower index
+/// than its children. Node 0 is a synthetic root.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub nodes: Vec<Node>,
+}
+
+/// Open elements that a new `<name>` start tag implicitly closes
+fn implicitly_closes(name: &str, open: &str) -> bool {
+    match name {
+        "p" | "div" | "ul" | "ol" | "table" | "pre" | "blockquote" | "section" | "article"
+        | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => open == "p",
+        "li" => open == "li" || open == "p",
+        "dt" | "dd" => matches!(open, "dt" | "dd" | "p"),
+        "tr" => matches!(open, "tr" | "td" | "th"),
+        "td" | "th" => matches!(open, "td" | "th"),
+        "option" => open == "option",
+        _ => false,
+    }
+}
+
+impl Document {
+    pub fn parse(html: &str) -> Document {
+        let mut nodes = vec![Node {
+            kind: NodeKind::Element(Tag {
+                name: "#root".to_string(),
+                attrs: vec![],
+                closing: false,
+                self_closing: false,
+                start: 0,
+                end: 0,
+            }),
+            parent: None,
+            children: vec![],
+        }];
+        let mut stack = vec![0];
+        let mut pos = 0;
+
+        fn push(nodes: &mut Vec<Node>, parent: usize, kind: NodeKind) -> usize {
+            let id = nodes.len();
+            nodes.push(Node {
+                kind,
+                parent: Some(parent),
+                children: vec![],
+            });
+            nodes[parent].children.push(id);
+            id
+        }
+
+        for tag in tags(html) {
+            let top = *stack.last().unwrap_or(&0);
+            if tag.start > pos {
+                let text = decode_entities(&html[pos..tag.start]);
+                push(&mut nodes, top, NodeKind::Text(text));
+            }
+            pos = tag.end;
+
+            if tag.closing {
+                let matching = stack
+                    .iter()
+                    .rposition(|&id| nodes[id].name() == Some(tag.name.as_str()));
+                // Stray end tags are ignored
+                if let Some(index) = matching.filter(|&i| i > 0) {
+                    stack.truncate(index);
+                }
+                continue;
+            }
+
+            while stack.len() > 1 {
+                let open = nodes[*stack.last().unwrap_or(&0)]
+                    .name()
+                    .unwrap_or_default();
+                if !implicitly_closes(&tag.name, open) {
+                    break;
+                }
+                stack.pop();
+            }
+            let parent = *stack.last().unwrap_or(&0);
+            let is_void = tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str());
+            let id = push(&mut nodes, parent, NodeKind::Element(tag));
+            if !is_void && stack.len() < MAX_DEPTH {
+                stack.push(id);
+            }
+        }
+        if pos < html.len() {
+            let top = *stack.last().unwrap_or(&0);
+            push(
+                &mut nodes,
+                top,
+                NodeKind::Text(decode_entities(&html[pos..])),
+            );
+        }
+
+        Document { nodes }
+    }
+
+    pub fn tag(&self, id: usize) -> Option<&Tag> {
+        match &self.nodes[id].kind {
+            NodeKind::Element(tag) => Some(tag),
+            NodeKind::Text(_) => None,
+        }
+    }
+
+    /// Element name of a node, `None` for text
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.nodes[id].name()
+    }
+
+    pub fn attr(&self, id: usize, name: &str) -> Option<&str> {
+        self.tag(id).and_then(|t| t.attr(name))
+    }
+
+    /// Element children of a node
+    pub fn elements(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[id]
+            .children
+            .iter()
+            .copied()
+            .filter(move |&c| self.tag(c).is_some())
+    }
+
+    /// All text below a node, with whitespace collapsed
+    pub fn text(&self, id: usize) -> String {
+        let mut out = String::new();
+        self.collect_text(id, &mut out);
+        collapse_whitespace(&out)
+    }
+
+    fn collect_text(&self, id: usize, out: &mut String) {
+        match &self.nodes[id].kind {
+            NodeKind::Text(text) => out.push_str(text),
+            NodeKind::Element(tag) => {
+                if matches!(tag.name.as_str(), "script" | "style" | "template") {
+                    return;
+                }
+                for &child in &self.nodes[id].children {
+                    self.collect_text(child, out);
+                }
+                if matches!(tag.name.as_str(), "p" | "br" | "div" | "li" | "td" | "th") {
+                    out.push(' ');
+                }
+            }
+        }
+    }
+
+    /// The first element with the given name, in document order
+    pub fn find(&self, name: &str) -> Option<usize> {
+        (0..self.nodes.len()).find(|&id| self.name(id) == Some(name))
+    }
+}
+
+impl Node {
+    pub fn name(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Element(tag) => Some(tag.name.as_str()),
+            NodeKind::Text(_) => None,
+        }
+    }
+}
@@ -0,0 +1,970 @@
+//! Browser bookmark import
+//!
+//! Reads the Netscape Bookmark File format every browser exports, Chrome's
+//! `Bookmarks` JSON and Firefox's `places.sqlite` (plus the read-later
+//! service exports in `services`) into a common list of `ImportedBookmark`s,
+//! then merges them into `AppData`: top-level browser folders become
+//! collections, deeper folders become bookmark folders, and pages that
+//! already exist are skipped.
+
+use crate::duplicates;
+use crate::html;
+use crate::services;
+use crate::tags;
+use crate::{AppData, Bookmark, BookmarkFolder, Collection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Browser root folders that hold bookmarks but are not folders of their own
+const ROOT_FOLDER_NAMES: [&str; 9] = [
+    "bookmarks bar",
+    "bookmarks toolbar",
+    "bookmarks menu",
+    "other bookmarks",
+    "mobile bookmarks",
+    "favorites bar",
+    "favourites bar",
+    "unsorted bookmarks",
+    "bookmarks",
+];
+
+/// Color given to folders created by an import (the frontend's default)
+const IMPORTED_FOLDER_COLOR: &str = "bg-cyber-cyan";
+
+/// Seconds between 1601-01-01 (Chrome/Windows epoch) and 1970-01-01
+const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat {
+    NetscapeHtml,
+    ChromeJson,
+    FirefoxPlaces,
+    PocketHtml,
+    PocketCsv,
+    RaindropCsv,
+    PinboardJson,
+}
+
+/// A bookmark read from another app, before it is mapped onto `AppData`
+#[derive(Debug, Clone, Default)]
+pub struct ImportedBookmark {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+    /// Folder names below the browser's root folders, outermost first
+    pub folders: Vec<String>,
+    pub tags: Vec<String>,
+    pub created_at: Option<String>,
+    pub is_favorite: bool,
+    pub is_archived: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    #[serde(rename = "skippedDuplicates")]
+    pub skipped_duplicates: usize,
+    #[serde(rename = "skippedInvalid")]
+    pub skipped_invalid: usize,
+    #[serde(rename = "collectionsCreated")]
+    pub collections_created: usize,
+    #[serde(rename = "foldersCreated")]
+    pub folders_created: usize,
+    #[serde(rename = "tagsCreated")]
+    pub tags_created: usize,
+    /// What happened to each bookmark in the file, in file order
+    pub items: Vec<ImportPreview>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PreviewStatus {
+    New,
+    Duplicate,
+    Invalid,
+}
+
+/// One bookmark of an import as it would be (or was) added
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportPreview {
+    pub title: String,
+    pub url: String,
+    pub status: PreviewStatus,
+    pub collection: Option<String>,
+    /// Folder path inside the collection, e.g. `Rust / Async`
+    pub folder: Option<String>,
+    /// Tag keys, including ones the import creates
+    pub tags: Vec<String>,
+    #[serde(rename = "isFavorite")]
+    pub is_favorite: bool,
+    #[serde(rename = "isArchived")]
+    pub is_archived: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportResult {
+    /// The updated data; `None` for a dry run, which changes nothing
+    pub data: Option<AppData>,
+    pub report: ImportReport,
+}
+
+/// A bookmarks file found in a local browser profile
+#[derive(Debug, Serialize, Clone)]
+pub struct BrowserSource {
+    pub browser: String,
+    pub profile: String,
+    pub path: String,
+    pub format: ImportFormat,
+}
+
+pub fn unix_to_rfc3339(seconds: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(seconds, 0).map(|d| d.to_rfc3339())
+}
+
+fn is_root_folder(name: &str) -> bool {
+    ROOT_FOLDER_NAMES.contains(&name.trim().to_lowercase().as_str())
+}
+
+pub fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// ============================================
+// Format detection
+// ============================================
+
+pub fn detect_format(bytes: &[u8]) -> ImportFormat {
+    if bytes.starts_with(b"SQLite format 3\0") {
+        return ImportFormat::FirefoxPlaces;
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with('{') {
+        ImportFormat::ChromeJson
+    } else if head.starts_with('[') {
+        ImportFormat::PinboardJson
+    } else if head.starts_with('<') {
+        if services::looks_like_pocket_html(head) {
+            ImportFormat::PocketHtml
+        } else {
+            ImportFormat::NetscapeHtml
+        }
+    } else {
+        services::detect_csv(head).unwrap_or(ImportFormat::NetscapeHtml)
+    }
+}
+
+/// Read bookmarks from a file in any supported format
+pub fn read_file(
+    path: &Path,
+    format: Option<ImportFormat>,
+) -> Result<Vec<ImportedBookmark>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    match format.unwrap_or_else(|| detect_format(&bytes)) {
+        ImportFormat::NetscapeHtml => Ok(parse_netscape(&String::from_utf8_lossy(&bytes))),
+        ImportFormat::ChromeJson => parse_chrome(&String::from_utf8_lossy(&bytes)),
+        ImportFormat::FirefoxPlaces => read_firefox_places(path),
+        ImportFormat::PocketHtml => Ok(services::parse_pocket_html(&String::from_utf8_lossy(
+            &bytes,
+        ))),
+        ImportFormat::PocketCsv => services::parse_pocket_csv(&String::from_utf8_lossy(&bytes)),
+        ImportFormat::RaindropCsv => services::parse_raindrop_csv(&String::from_utf8_lossy(&bytes)),
+        ImportFormat::PinboardJson => services::parse_pinboard(&String::from_utf8_lossy(&bytes)),
+    }
+}
+
+// ============================================
+// Netscape Bookmark File
+// ============================================
+
+/// Parse a Netscape Bookmark File (`bookmarks.html`)
+///
+/// The format is loose HTML: `<DT><H3>` names a folder whose contents follow
+/// in a `<DL>`, `<DT><A>` is a bookmark and an optional `<DD>` holds its
+/// description. End tags for `<DT>`/`<DD>`/`<P>` are usually missing.
+pub fn parse_netscape(document: &str) -> Vec<ImportedBookmark> {
+    let mut bookmarks: Vec<ImportedBookmark> = Vec::new();
+    // One entry per open <DL>; `None` for the document root and browser roots
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut pending_folder: Option<Option<String>> = None;
+    let mut open_heading: Option<(usize, bool)> = None;
+    let mut open_link: Option<(usize, html::Tag)> = None;
+    let mut open_description: Option<usize> = None;
+
+    let text = |start: usize, end: usize| {
+        html::collapse_whitespace(&html::decode_entities(&document[start..end]))
+    };
+
+    for tag in html::tags(document) {
+        if let Some(start) = open_description.take() {
+            let description = text(start, tag.start);
+            if let Some(last) = bookmarks.last_mut() {
+                if last.description.is_empty() {
+                    last.description = description;
+                }
+            }
+        }
+
+        match (tag.name.as_str(), tag.closing) {
+            ("h3", false) => {
+                let is_root = tag.attr("personal_toolbar_folder").is_some()
+                    || tag.attr("unfiled_bookmarks_folder").is_some();
+                open_heading = Some((tag.end, is_root));
+            }
+            ("h3", true) => {
+                if let Some((start, is_root)) = open_heading.take() {
+                    let name = text(start, tag.start);
+                    let is_root = is_root || (stack.len() <= 1 && is_root_folder(&name));
+                    pending_folder = Some((!is_root && !name.is_empty()).then_some(name));
+                }
+            }
+            ("dl", false) => stack.push(pending_folder.take().flatten()),
+            ("dl", true) => {
+                stack.pop();
+            }
+            ("a", false) => open_link = Some((tag.end, tag)),
+            ("a", true) => {
+                let Some((start, link)) = open_link.take() else {
+                    continue;
+                };
+                let Some(url) = link.attr("href").map(str::trim).filter(|u| !u.is_empty()) else {
+                    continue;
+                };
+                // Firefox exports live bookmarks and smart folders as `place:` URLs
+                if url.starts_with("place:") || url.starts_with("javascript:") {
+                    continue;
+                }
+                bookmarks.push(ImportedBookmark {
+                    title: text(start, tag.start),
+                    url: url.to_string(),
+                    folders: stack.iter().flatten().cloned().collect(),
+                    tags: link.attr("tags").map(split_tags).unwrap_or_default(),
+                    created_at: link
+                        .attr("add_date")
+                        .and_then(|d| d.trim().parse::<i64>().ok())
+                        .and_then(unix_to_rfc3339),
+                    ..Default::default()
+                });
+            }
+            ("dd", false) => open_description = Some(tag.end),
+            _ => {}
+        }
+    }
+
+    bookmarks
+}
+
+// ============================================
+// Chrome / Chromium `Bookmarks` JSON
+// ============================================
+
+#[derive(Deserialize)]
+struct ChromeFile {
+    roots: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ChromeNode {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    url: Option<String>,
+    /// Microseconds since 1601-01-01, as a string
+    #[serde(default)]
+    date_added: Option<String>,
+    #[serde(default)]
+    children: Vec<ChromeNode>,
+}
+
+fn chrome_date(value: &str) -> Option<String> {
+    let micros = value.parse::<i64>().ok().filter(|m| *m > 0)?;
+    unix_to_rfc3339(micros / 1_000_000 - WINDOWS_EPOCH_OFFSET)
+}
+
+fn walk_chrome(node: &ChromeNode, folders: &mut Vec<String>, out: &mut Vec<ImportedBookmark>) {
+    match node.kind.as_str() {
+        "url" => {
+            if let Some(url) = node.url.as_ref().filter(|u| !u.starts_with("javascript:")) {
+                out.push(ImportedBookmark {
+                    title: node.name.clone(),
+                    url: url.clone(),
+                    folders: folders.clone(),
+                    created_at: node.date_added.as_deref().and_then(chrome_date),
+                    ..Default::default()
+                });
+            }
+        }
+        "folder" => {
+            folders.push(node.name.clone());
+            for child in &node.children {
+                walk_chrome(child, folders, out);
+            }
+            folders.pop();
+        }
+        _ => {}
+    }
+}
+
+pub fn parse_chrome(json: &str) -> Result<Vec<ImportedBookmark>, String> {
+    let file: ChromeFile = serde_json::from_str(json.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Not a Chrome bookmarks file: {}", e))?;
+    let mut out = Vec::new();
+    // bookmark_bar, other and synced are containers, not folders
+    for key in ["bookmark_bar", "other", "synced"] {
+        let Some(root) = file.roots.get(key) else {
+            continue;
+        };
+        let Ok(root) = serde_json::from_value::<ChromeNode>(root.clone()) else {
+            continue;
+        };
+        for child in &root.children {
+            walk_chrome(child, &mut vec![], &mut out);
+        }
+    }
+    Ok(out)
+}
+
+// ============================================
+// Firefox `places.sqlite`
+// ============================================
+
+const FIREFOX_TAGS_ROOT: &str = "tags________";
+const FIREFOX_ROOTS: [&str; 5] = [
+    "root________",
+    "menu________",
+    "toolbar_____",
+    "unfiled_____",
+    "mobile______",
+];
+
+struct PlacesRow {
+    id: i64,
+    kind: i64,
+    parent: i64,
+    title: String,
+    date_added: Option<i64>,
+    guid: String,
+    place: Option<i64>,
+    url: Option<String>,
+}
+
+/// Read bookmarks straight from a Firefox profile database.
+///
+/// Firefox keeps the database locked while running, so a copy is read.
+pub fn read_firefox_places(path: &Path) -> Result<Vec<ImportedBookmark>, String> {
+    let copy = std::env::temp_dir().join(format!("carbon-places-{}.sqlite", uuid::Uuid::new_v4()));
+    fs::copy(path, &copy).map_err(|e| format!("Failed to copy places.sqlite: {}", e))?;
+    // Recent changes may still live in the write-ahead log
+    let wal = PathBuf::from(format!("{}-wal", path.display()));
+    if wal.exists() {
+        fs::copy(&wal, PathBuf::from(format!("{}-wal", copy.display()))).ok();
+    }
+
+    let result = query_places(&copy);
+    fs::remove_file(&copy).ok();
+    fs::remove_file(PathBuf::from(format!("{}-wal", copy.display()))).ok();
+    fs::remove_file(PathBuf::from(format!("{}-shm", copy.display()))).ok();
+    result
+}
+
+fn query_places(path: &Path) -> Result<Vec<ImportedBookmark>, String> {
+    let db_error = |e: rusqlite::Error| format!("Failed to read places.sqlite: {}", e);
+    let conn = rusqlite::Connection::open(path).map_err(db_error)?;
+
+    let mut statement = conn
+        .prepare(
+            "SELECT b.id, b.type, b.parent, IFNULL(b.title, ''), b.dateAdded, b.guid, b.fk, p.url \
+             FROM moz_bookmarks b LEFT JOIN moz_places p ON b.fk = p.id \
+             ORDER BY b.parent, b.position",
+        )
+        .map_err(db_error)?;
+    let rows: Vec<PlacesRow> = statement
+        .query_map([], |row| {
+            Ok(PlacesRow {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                parent: row.get(2)?,
+                title: row.get(3)?,
+                date_added: row.get(4)?,
+                guid: row.get(5)?,
+                place: row.get(6)?,
+                url: row.get(7)?,
+            })
+        })
+        .map_err(db_error)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let by_id: HashMap<i64, &PlacesRow> = rows.iter().map(|r| (r.id, r)).collect();
+    let tags_root = rows
+        .iter()
+        .find(|r| r.guid == FIREFOX_TAGS_ROOT)
+        .map(|r| r.id);
+
+    // Tags are folders under the tags root holding one entry per tagged place
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in &rows {
+        let Some(tag) = by_id.get(&row.parent) else {
+            continue;
+        };
+        if Some(tag.parent) == tags_root {
+            if let Some(place) = row.place {
+                tags.entry(place).or_default().push(tag.title.clone());
+            }
+        }
+    }
+
+    let folder_path = |mut parent: i64| -> Option<Vec<String>> {
+        let mut path = Vec::new();
+        while let Some(folder) = by_id.get(&parent) {
+            if Some(folder.id) == tags_root {
+                return None;
+            }
+            if FIREFOX_ROOTS.contains(&folder.guid.as_str()) {
+                break;
+            }
+            path.push(folder.title.clone());
+            parent = folder.parent;
+        }
+        path.reverse();
+        Some(path)
+    };
+
+    Ok(rows
+        .iter()
+        .filter(|r| r.kind == 1)
+        .filter_map(|row| {
+            let url = row.url.clone().filter(|u| !u.starts_with("place:"))?;
+            Some(ImportedBookmark {
+                title: row.title.clone(),
+                url,
+                folders: folder_path(row.parent)?,
+                tags: row
+                    .place
+                    .and_then(|p| tags.get(&p).cloned())
+                    .unwrap_or_default(),
+                created_at: row
+                    .date_added
+                    .and_then(|us| unix_to_rfc3339(us / 1_000_000)),
+                ..Default::default()
+            })
+        })
+        .collect())
+}
+
+// ============================================
+// Local browser profiles
+// ============================================
+
+/// Immediate subdirectories of `dir` containing `file`
+fn profile_files(dir: &Path, file: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut found: Vec<(String, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join(file).is_file())
+        .map(|p| {
+            let profile = p
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (profile, p.join(file))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// Bookmark files of Chromium-based browsers and Firefox on this machine
+pub fn find_browser_sources() -> Vec<BrowserSource> {
+    let mut chromium: Vec<(&str, PathBuf)> = Vec::new();
+    let mut firefox: Vec<PathBuf> = Vec::new();
+
+    if cfg!(target_os = "macos") {
+        if let Some(support) = dirs::data_dir() {
+            chromium.push(("Chrome", support.join("Google/Chrome")));
+            chromium.push(("Chromium", support.join("Chromium")));
+            chromium.push(("Brave", support.join("BraveSoftware/Brave-Browser")));
+            chromium.push(("Edge", support.join("Microsoft Edge")));
+            firefox.push(support.join("Firefox/Profiles"));
+        }
+    } else if cfg!(windows) {
+        if let Some(local) = dirs::data_local_dir() {
+            chromium.push(("Chrome", local.join("Google/Chrome/User Data")));
+            chromium.push(("Brave", local.join("BraveSoftware/Brave-Browser/User Data")));
+            chromium.push(("Edge", local.join("Microsoft/Edge/User Data")));
+        }
+        if let Some(roaming) = dirs::data_dir() {
+            firefox.push(roaming.join("Mozilla/Firefox/Profiles"));
+        }
+    } else {
+        if let Some(config) = dirs::config_dir() {
+            chromium.push(("Chrome", config.join("google-chrome")));
+            chromium.push(("Chromium", config.join("chromium")));
+            chromium.push(("Brave", config.join("BraveSoftware/Brave-Browser")));
+            chromium.push(("Edge", config.join("microsoft-edge")));
+        }
+        if let Some(home) = dirs::home_dir() {
+            firefox.push(home.join(".mozilla/firefox"));
+        }
+    }
+
+    let mut sources = Vec::new();
+    for (browser, dir) in chromium {
+        for (profile, path) in profile_files(&dir, "Bookmarks") {
+            sources.push(BrowserSource {
+                browser: browser.to_string(),
+                profile,
+                path: path.to_string_lossy().to_string(),
+                format: ImportFormat::ChromeJson,
+            });
+        }
+    }
+    for dir in firefox {
+        for (profile, path) in profile_files(&dir, "places.sqlite") {
+            sources.push(BrowserSource {
+                browser: "Firefox".to_string(),
+                profile,
+                path: path.to_string_lossy().to_string(),
+                format: ImportFormat::FirefoxPlaces,
+            });
+        }
+    }
+    sources
+}
+
+// ============================================
+// Merging into AppData
+// ============================================
+
+fn find_or_create_collection(data: &mut AppData, name: &str, report: &mut ImportReport) -> String {
+    if let Some(existing) = data.collections.iter().find(|c| {
+        c.is_custom == Some(true) && c.query.is_none() && c.name.eq_ignore_ascii_case(name)
+    }) {
+        return existing.id.clone();
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    data.collections.push(Collection {
+        id: id.clone(),
+        name: name.to_string(),
+        icon: "folder".to_string(),
+        is_custom: Some(true),
+        query: None,
+    });
+    report.collections_created += 1;
+    id
+}
+
+fn find_or_create_folder(
+    data: &mut AppData,
+    name: &str,
+    collection_id: &str,
+    parent_id: Option<&str>,
+    now: &str,
+    report: &mut ImportReport,
+) -> String {
+    if let Some(existing) = data.bookmark_folders.iter().find(|f| {
+        f.collection_id.as_deref() == Some(collection_id)
+            && f.parent_id.as_deref() == parent_id
+            && f.name.eq_ignore_ascii_case(name)
+    }) {
+        return existing.id.clone();
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    data.bookmark_folders.push(BookmarkFolder {
+        id: id.clone(),
+        name: name.to_string(),
+        color: Some(IMPORTED_FOLDER_COLOR.to_string()),
+        collection_id: Some(collection_id.to_string()),
+        parent_id: parent_id.map(str::to_string),
+        order: Some(data.bookmark_folders.len() as i32),
+        created_at: now.to_string(),
+    });
+    report.folders_created += 1;
+    id
+}
+
+/// Add imported bookmarks to `data`, skipping pages that already exist.
+///
+/// The outermost folder maps to a custom collection and any deeper folders
+/// to nested bookmark folders in it.
+/// Tags are stored by key, creating custom tags for unknown names.
+pub fn apply(data: &mut AppData, items: Vec<ImportedBookmark>, now: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut seen: HashSet<String> = data
+        .bookmarks
+        .iter()
+        .map(|b| duplicates::duplicate_key(&b.url))
+        .collect();
+
+    let mut added = Vec::new();
+    for item in items {
+        let mut preview = ImportPreview {
+            title: item.title.trim().to_string(),
+            url: item.url.clone(),
+            status: PreviewStatus::New,
+            collection: item.folders.first().map(|f| f.trim().to_string()),
+            folder: None,
+            tags: vec![],
+            is_favorite: item.is_favorite,
+            is_archived: item.is_archived,
+        };
+        let Ok(url) = duplicates::normalize_url(&item.url) else {
+            report.skipped_invalid += 1;
+            preview.status = PreviewStatus::Invalid;
+            report.items.push(preview);
+            continue;
+        };
+        if !seen.insert(duplicates::duplicate_key(&url)) {
+            report.skipped_duplicates += 1;
+            preview.status = PreviewStatus::Duplicate;
+            report.items.push(preview);
+            continue;
+        }
+
+        let folders: Vec<&str> = item
+            .folders
+            .iter()
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+            .collect();
+        let (collection_id, folder_id) = match folders.split_first() {
+            Some((collection, rest)) => {
+                let collection_id = find_or_create_collection(data, collection, &mut report);
+                let mut folder_id: Option<String> = None;
+                for name in rest {
+                    folder_id = Some(find_or_create_folder(
+                        data,
+                        name,
+                        &collection_id,
+                        folder_id.as_deref(),
+                        now,
+                        &mut report,
+                    ));
+                }
+                if !rest.is_empty() {
+                    preview.folder = Some(rest.join(" / "));
+                }
+                (Some(collection_id), folder_id)
+            }
+            None => (None, None),
+        };
+
+        let mut tag_keys: Vec<String> = Vec::new();
+        for name in &item.tags {
+            if let Some((key, created)) = tags::ensure_tag(data, name) {
+                if created {
+                    report.tags_created += 1;
+                }
+                if !tag_keys.contains(&key) {
+                    tag_keys.push(key);
+                }
+            }
+        }
+        preview.tags = tag_keys.clone();
+
+        let title = if item.title.trim().is_empty() {
+            url.clone()
+        } else {
+            item.title.trim().to_string()
+        };
+        preview.url = url.clone();
+        report.items.push(preview);
+        added.push(Bookmark {
+            id: uuid::Uuid::new_v4().to_string(),
+            title,
+            url,
+            description: item.description,
+            favicon: None,
+            image: None,
+            favicon_asset: None,
+            image_asset: None,
+            link_check: None,
+            snapshot: None,
+            tags: tag_keys,
+            collection_id,
+            folder_id,
+            is_favorite: item.is_favorite,
+            is_archived: item.is_archived,
+            created_at: item.created_at.unwrap_or_else(|| now.to_string()),
+            order: None,
+        });
+    }
+
+    report.imported = added.len();
+    // New bookmarks go first, like ones added in the app
+    data.bookmarks.splice(0..0, added);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NETSCAPE: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1" PERSONAL_TOOLBAR_FOLDER="true">Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/a" ADD_DATE="1700000000" TAGS="rust, async,">Example &amp; A</A>
+        <DD>First   description
+        <DT><H3>Dev</H3>
+        <DL><p>
+            <DT><H3>Rust</H3>
+            <DL><p>
+                <DT><A HREF="https://doc.rust-lang.org/">Docs</A>
+            </DL><p>
+            <DT><A HREF="place:sort=8&maxResults=10">Recent</A>
+            <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+            <DT><H3>Bookmarks</H3>
+            <DL><p>
+                <DT><A HREF="https://nested.example/">Nested</A>
+            </DL><p>
+            <DT><A HREF="https://dev.example/">Dev home</A>
+        </DL><p>
+    </DL><p>
+    <DT><H3>Other bookmarks</H3>
+    <DL><p>
+        <DT><A HREF="https://other.example/">Other</A>
+    </DL><p>
+</DL><p>
+"#;
+
+    fn find<'a>(items: &'a [ImportedBookmark], title: &str) -> &'a ImportedBookmark {
+        items.iter().find(|b| b.title == title).unwrap()
+    }
+
+    #[test]
+    fn netscape_follows_nested_lists() {
+        let items = parse_netscape(NETSCAPE);
+        let titles: Vec<&str> = items.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["Example & A", "Docs", "Nested", "Dev home", "Other"]
+        );
+        assert_eq!(find(&items, "Docs").folders, ["Dev", "Rust"]);
+        assert_eq!(find(&items, "Dev home").folders, ["Dev"]);
+        // A nested folder that happens to share a root's name is kept
+        assert_eq!(find(&items, "Nested").folders, ["Dev", "Bookmarks"]);
+    }
+
+    #[test]
+    fn netscape_drops_browser_root_folders() {
+        let items = parse_netscape(NETSCAPE);
+        assert!(find(&items, "Example & A").folders.is_empty());
+        assert!(find(&items, "Other").folders.is_empty());
+    }
+
+    #[test]
+    fn netscape_reads_dates_tags_and_descriptions() {
+        let items = parse_netscape(NETSCAPE);
+        let first = find(&items, "Example & A");
+        assert_eq!(first.url, "https://example.com/a");
+        assert_eq!(first.tags, ["rust", "async"]);
+        assert_eq!(
+            first.created_at.as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert_eq!(first.description, "First description");
+        assert_eq!(find(&items, "Docs").created_at, None);
+    }
+
+    #[test]
+    fn netscape_skips_place_and_javascript_links() {
+        let items = parse_netscape(NETSCAPE);
+        assert!(items
+            .iter()
+            .all(|b| !b.url.starts_with("place:") && !b.url.starts_with("javascript:")));
+    }
+
+    #[test]
+    fn chrome_reads_folders_below_the_roots() {
+        let file = json!({
+            "version": 1,
+            "roots": {
+                "bookmark_bar": { "type": "folder", "name": "Bookmarks bar", "children": [
+                    { "type": "url", "name": "A", "url": "https://a.example/", "date_added": "13350000000000000" },
+                    { "type": "folder", "name": "Work", "children": [
+                        { "type": "url", "name": "B", "url": "https://b.example/" },
+                        { "type": "url", "name": "Bookmarklet", "url": "javascript:void(0)" },
+                    ]},
+                ]},
+                "other": { "type": "folder", "name": "Other bookmarks", "children": [
+                    { "type": "url", "name": "C", "url": "https://c.example/", "date_added": "0" },
+                ]},
+                "synced": { "type": "folder", "name": "Mobile bookmarks", "children": [] },
+            },
+        });
+        let items = parse_chrome(&format!("\u{feff}{}", file)).unwrap();
+        let titles: Vec<&str> = items.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, ["A", "B", "C"]);
+        assert!(items[0].folders.is_empty());
+        assert_eq!(
+            items[0].created_at.as_deref(),
+            Some("2024-01-17T21:20:00+00:00")
+        );
+        assert_eq!(items[1].folders, ["Work"]);
+        assert_eq!(items[2].created_at, None);
+
+        assert!(parse_chrome("{\"not\": \"bookmarks\"}").is_err());
+    }
+
+    #[test]
+    fn places_reads_folders_and_tags() {
+        let path =
+            std::env::temp_dir().join(format!("carbon-test-{}.sqlite", uuid::Uuid::new_v4()));
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT);
+             CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER,
+                 parent INTEGER, position INTEGER, title TEXT, dateAdded INTEGER, guid TEXT);
+             INSERT INTO moz_places VALUES
+                 (100, 'https://rust-lang.org/'),
+                 (101, 'place:sort=8'),
+                 (102, 'https://unfiled.example/');
+             INSERT INTO moz_bookmarks VALUES
+                 (1, 2, NULL, 0, 0, '', 0, 'root________'),
+                 (2, 2, NULL, 1, 0, 'menu', 0, 'menu________'),
+                 (3, 2, NULL, 1, 1, 'toolbar', 0, 'toolbar_____'),
+                 (4, 2, NULL, 1, 2, 'tags', 0, 'tags________'),
+                 (5, 2, NULL, 1, 3, 'unfiled', 0, 'unfiled_____'),
+                 (10, 2, NULL, 3, 0, 'Dev', 0, 'dev_________'),
+                 (11, 1, 100, 10, 0, 'Rust', 1700000000000000, 'rust________'),
+                 (12, 1, 101, 2, 0, 'Most visited', 0, 'smart_______'),
+                 (13, 2, NULL, 4, 0, 'lang', 0, 'lang________'),
+                 (14, 1, 100, 13, 0, NULL, 0, 'tagentry____'),
+                 (15, 1, 102, 5, 0, 'Unfiled', 0, 'unfiled_item');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let items = query_places(&path);
+        fs::remove_file(&path).ok();
+        let items = items.unwrap();
+
+        let titles: Vec<&str> = items.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, ["Unfiled", "Rust"]);
+        let rust = find(&items, "Rust");
+        assert_eq!(rust.folders, ["Dev"]);
+        assert_eq!(rust.tags, ["lang"]);
+        assert_eq!(
+            rust.created_at.as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert!(find(&items, "Unfiled").folders.is_empty());
+    }
+
+    fn item(title: &str, url: &str, folders: &[&str], tags: &[&str]) -> ImportedBookmark {
+        ImportedBookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            folders: folders.iter().map(|f| f.to_string()).collect(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_skips_existing_and_repeated_pages() {
+        let mut data = crate::get_default_data();
+        data.bookmarks = serde_json::from_value(json!([
+            { "id": "old", "title": "Old", "url": "https://example.com/a", "createdAt": "2025-01-01T00:00:00Z" },
+        ]))
+        .unwrap();
+
+        let report = apply(
+            &mut data,
+            vec![
+                item(
+                    "Again",
+                    "http://www.example.com/a?utm_source=feed",
+                    &[],
+                    &[],
+                ),
+                item("Broken", "not a url", &[], &[]),
+                item("New", "https://new.example/", &[], &[]),
+                item("New again", "https://new.example", &[], &[]),
+            ],
+            "2025-06-01T00:00:00Z",
+        );
+        let statuses: Vec<PreviewStatus> = report.items.iter().map(|i| i.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                PreviewStatus::Duplicate,
+                PreviewStatus::Invalid,
+                PreviewStatus::New,
+                PreviewStatus::Duplicate,
+            ]
+        );
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped_duplicates, 2);
+        assert_eq!(report.skipped_invalid, 1);
+        assert_eq!(data.bookmarks.len(), 2);
+        assert_eq!(data.bookmarks[0].title, "New");
+        assert_eq!(data.bookmarks[0].created_at, "2025-06-01T00:00:00Z");
+    }
+
+    #[test]
+    fn apply_maps_folders_to_collections_and_reuses_them() {
+        let mut data = crate::get_default_data();
+        let now = "2025-06-01T00:00:00Z";
+        let report = apply(
+            &mut data,
+            vec![
+                item(
+                    "Docs",
+                    "https://doc.rust-lang.org/",
+                    &["Dev", "Rust"],
+                    &["Rust", "rust"],
+                ),
+                item("Home", "https://dev.example/", &["Dev"], &[]),
+            ],
+            now,
+        );
+        assert_eq!(report.collections_created, 1);
+        assert_eq!(report.folders_created, 1);
+        assert_eq!(report.items[0].folder.as_deref(), Some("Rust"));
+        assert_eq!(report.items[0].tags, ["rust"]);
+
+        let docs = data
+            .bookmarks
+            .iter()
+            .find(|b| b.title == "Docs")
+            .unwrap()
+            .clone();
+        let folder = data
+            .bookmark_folders
+            .iter()
+            .find(|f| Some(&f.id) == docs.folder_id.as_ref())
+            .unwrap();
+        assert_eq!(folder.name, "Rust");
+        assert_eq!(folder.collection_id, docs.collection_id);
+        assert_eq!(docs.tags, ["rust"]);
+
+        // Names match case-insensitively on the next import
+        let report = apply(
+            &mut data,
+            vec![item("Book", "https://book.example/", &["dev", "RUST"], &[])],
+            now,
+        );
+        assert_eq!(report.collections_created, 0);
+        assert_eq!(report.folders_created, 0);
+        let book = data.bookmarks.iter().find(|b| b.title == "Book").unwrap();
+        assert_eq!(book.folder_id, docs.folder_id);
+    }
+}
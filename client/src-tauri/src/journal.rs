@@ -0,0 +1,377 @@
+//! Daily notes
+//!
+//! Each day gets at most one note, marked by `Note::journal_date`
+//! (`YYYY-MM-DD` in local time) so changing the title format never loses
+//! track of it. New daily notes are filled from a template with
+//! placeholders:
+//!
+//! - `{{date}}` (`2024-05-17`) or `{{date:%A, %B %-d}}` for any strftime format
+//! - `{{title}}`, `{{weekday}}`
+//! - `{{yesterday}}` / `{{tomorrow}}`: wiki-links to the neighbouring days
+//! - `{{open_cards}}`: cards not yet in the last column of their board
+//! - `{{rollup}}`: where the rollup goes (appended when missing)
+//!
+//! The rollup lists cards completed and created that day. It lives under
+//! its own heading, which Carbon owns: `refresh` rewrites it whenever data
+//! is saved, so anything typed into it is replaced.
+//!
+//! The template is per machine and lives in `journal.json` in the data
+//! directory, like board templates.
+
+use crate::{AppData, Card, Note};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "journal.json";
+/// Heading of the section `refresh` keeps up to date
+const ROLLUP_HEADING: &str = "## Daily rollup";
+const DEFAULT_TITLE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_TEMPLATE: &str = "## Open cards\n\n{{open_cards}}\n\n## Notes\n\n\n\n{{rollup}}";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalSettings {
+    #[serde(default = "default_template")]
+    pub template: String,
+    /// strftime format of daily note titles
+    #[serde(rename = "titleFormat", default = "default_title_format")]
+    pub title_format: String,
+    /// Notebook new daily notes go into
+    #[serde(rename = "notebookId", default)]
+    pub notebook_id: Option<String>,
+}
+
+impl Default for JournalSettings {
+    fn default() -> Self {
+        JournalSettings {
+            template: default_template(),
+            title_format: default_title_format(),
+            notebook_id: None,
+        }
+    }
+}
+
+fn default_template() -> String {
+    DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_title_format() -> String {
+    DEFAULT_TITLE_FORMAT.to_string()
+}
+
+/// A day that has a daily note, for calendars and prev/next navigation
+#[derive(Debug, Serialize, Clone)]
+pub struct JournalEntry {
+    pub date: String,
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DailyNoteResult {
+    pub data: AppData,
+    pub note: Note,
+    pub created: bool,
+}
+
+pub fn load_settings(data_dir: &Path) -> JournalSettings {
+    fs::read_to_string(data_dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(data_dir: &Path, settings: &JournalSettings) -> Result<(), String> {
+    let title = format_date(today(), &settings.title_format)?;
+    if title.trim().is_empty() {
+        return Err("The title format must produce a title".to_string());
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize journal settings: {}", e))?;
+    fs::write(data_dir.join(SETTINGS_FILE), json)
+        .map_err(|e| format!("Failed to write journal settings: {}", e))
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date (expected YYYY-MM-DD): {}", value))
+}
+
+/// Local calendar day of an RFC 3339 timestamp
+fn local_date(timestamp: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|at| at.with_timezone(&Local).date_naive())
+}
+
+fn format_date(date: NaiveDate, format: &str) -> Result<String, String> {
+    // Writing reports bad specifiers as an error where `to_string` panics
+    let mut out = String::new();
+    write!(out, "{}", date.format(format))
+        .map_err(|_| format!("Invalid date format: {}", format))?;
+    Ok(out)
+}
+
+pub fn title_for(settings: &JournalSettings, date: NaiveDate) -> Result<String, String> {
+    format_date(date, &settings.title_format)
+}
+
+fn find(data: &AppData, date: NaiveDate) -> Option<usize> {
+    let key = date.to_string();
+    data.notes
+        .iter()
+        .position(|n| n.journal_date.as_deref() == Some(key.as_str()))
+}
+
+/// Text usable as wiki-link display text (`[[...|text]]`)
+pub(crate) fn link_text(title: &str) -> String {
+    title.replace(['[', ']', '|'], "").trim().to_string()
+}
+
+fn card_link(card: &Card) -> String {
+    format!("[[card:{}|{}]]", card.id, link_text(&card.title))
+}
+
+fn open_cards(data: &AppData) -> String {
+    let mut lines = Vec::new();
+    for board in &data.boards {
+        // The last column is "done", as in flow metrics
+        let open = board.columns.len().saturating_sub(1);
+        for column in &board.columns[..open] {
+            for card in &column.cards {
+                lines.push(format!(
+                    "- {} · {} / {}",
+                    card_link(card),
+                    board.name,
+                    column.title
+                ));
+            }
+        }
+    }
+    if lines.is_empty() {
+        return "_No open cards_".to_string();
+    }
+    lines.join("\n")
+}
+
+/// The rollup section for `date`, heading included
+fn rollup(data: &AppData, date: NaiveDate) -> String {
+    let mut completed = Vec::new();
+    let mut created = Vec::new();
+    for board in &data.boards {
+        let done = board.columns.last().map(|c| c.id.as_str());
+        let cards = board
+            .columns
+            .iter()
+            .flat_map(|c| c.cards.iter().map(move |card| (card, Some(c.id.as_str()))))
+            .chain(
+                board
+                    .archived_cards
+                    .iter()
+                    .map(|card| (card, card.original_column_id.as_deref())),
+            );
+        for (card, column) in cards {
+            let line = format!("- {} · {}", card_link(card), board.name);
+            if local_date(&card.created_at) == Some(date) {
+                created.push(line.clone());
+            }
+            if done.is_some() && column == done {
+                let finished = card
+                    .column_history
+                    .iter()
+                    .rev()
+                    .find(|t| Some(t.column_id.as_str()) == done)
+                    .and_then(|t| local_date(&t.entered_at));
+                if finished == Some(date) {
+                    completed.push(line);
+                }
+            }
+        }
+    }
+
+    let list = |lines: Vec<String>| {
+        if lines.is_empty() {
+            "_None_".to_string()
+        } else {
+            lines.join("\n")
+        }
+    };
+    format!(
+        "{}\n\n### Completed\n\n{}\n\n### Created\n\n{}\n",
+        ROLLUP_HEADING,
+        list(completed),
+        list(created)
+    )
+}
+
+/// Byte range of the rollup section: its heading up to the next heading of
+/// the same or a higher level
+fn rollup_range(content: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    let mut start = None;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        match start {
+            None if trimmed == ROLLUP_HEADING => start = Some(offset),
+            Some(start) if trimmed.starts_with("# ") || trimmed.starts_with("## ") => {
+                return Some((start, offset));
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    start.map(|start| (start, content.len()))
+}
+
+fn render(
+    data: &AppData,
+    settings: &JournalSettings,
+    date: NaiveDate,
+    title: &str,
+) -> Result<String, String> {
+    let day_link = |day: NaiveDate| -> Result<String, String> {
+        Ok(format!("[[{}]]", title_for(settings, day)?))
+    };
+
+    let template = &settings.template;
+    let mut out = String::new();
+    let mut rest = template.as_str();
+    let mut has_rollup = false;
+    while let Some(open) = rest.find("{{") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("}}") else {
+            out.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+        let name = after[..close].trim();
+        let value = match name {
+            "date" => date.to_string(),
+            "title" => title.to_string(),
+            "weekday" => format_date(date, "%A")?,
+            "yesterday" => day_link(date - Duration::days(1))?,
+            "tomorrow" => day_link(date + Duration::days(1))?,
+            "open_cards" => open_cards(data),
+            "rollup" => {
+                has_rollup = true;
+                rollup(data, date)
+            }
+            _ => match name.strip_prefix("date:") {
+                Some(format) => format_date(date, format)?,
+                // Unknown placeholders are left for the user to see
+                None => format!("{{{{{}}}}}", &after[..close]),
+            },
+        };
+        out.push_str(&value);
+        rest = &after[close + 2..];
+    }
+    out.push_str(rest);
+
+    if !has_rollup {
+        let trimmed = out.trim_end().len();
+        out.truncate(trimmed);
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(&rollup(data, date));
+    }
+    Ok(out)
+}
+
+/// Rewrite the rollup of the daily note for `date`, if there is one.
+/// Returns whether the note changed.
+pub fn refresh(data: &mut AppData, date: NaiveDate, now: &str) -> bool {
+    let Some(index) = find(data, date) else {
+        return false;
+    };
+    let Some((start, end)) = rollup_range(&data.notes[index].content) else {
+        return false;
+    };
+    let mut section = rollup(data, date);
+    if end < data.notes[index].content.len() {
+        section.push('\n');
+    }
+
+    let note = &mut data.notes[index];
+    if note.content[start..end] == section {
+        return false;
+    }
+    note.content.replace_range(start..end, &section);
+    note.updated_at = now.to_string();
+    true
+}
+
+/// The daily note for `date`, created from the template if there is none.
+///
+/// Returns the note id, whether it was created and whether `data` changed.
+pub fn get_or_create(
+    data: &mut AppData,
+    settings: &JournalSettings,
+    date: NaiveDate,
+    now: &str,
+) -> Result<(String, bool, bool), String> {
+    if let Some(index) = find(data, date) {
+        let changed = refresh(data, date, now);
+        return Ok((data.notes[index].id.clone(), false, changed));
+    }
+
+    let title = title_for(settings, date)?;
+    let content = render(data, settings, date, &title)?;
+    let notebook_id = settings
+        .notebook_id
+        .clone()
+        .filter(|id| data.notebooks.iter().any(|n| n.id == *id));
+    let id = uuid::Uuid::new_v4().to_string();
+    data.notes.insert(
+        0,
+        Note {
+            id: id.clone(),
+            title,
+            content,
+            is_pinned: false,
+            created_at: now.to_string(),
+            updated_at: now.to_string(),
+            attachments: vec![],
+            notebook_id,
+            tags: vec![],
+            journal_date: Some(date.to_string()),
+            checklist: vec![],
+            task_sync: false,
+        },
+    );
+    Ok((id, true, true))
+}
+
+/// Days with a daily note between `from` and `to` (inclusive), oldest first
+pub fn entries(
+    data: &AppData,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Vec<JournalEntry> {
+    let mut entries: Vec<JournalEntry> = data
+        .notes
+        .iter()
+        .filter_map(|note| {
+            let date = parse_date(note.journal_date.as_deref()?).ok()?;
+            if from.map(|f| date < f).unwrap_or(false) || to.map(|t| date > t).unwrap_or(false) {
+                return None;
+            }
+            Some(JournalEntry {
+                date: date.to_string(),
+                note_id: note.id.clone(),
+                title: note.title.clone(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.date.cmp(&b.date));
+    entries.dedup_by(|a, b| a.date == b.date);
+    entries
+}
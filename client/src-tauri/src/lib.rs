@@ -10,6 +10,147 @@ mod cloudkit;
 #[cfg(target_os = "macos")]
 use cloudkit::{AccountStatusJson, CloudKit, SyncResultJson, SyncStatusJson};
 
+// Kanban flow metrics (cycle time, lead time, throughput, CFD)
+mod metrics;
+
+use metrics::{BoardMetrics, MetricsOptions};
+
+// Subtasks, blockers and related cards
+mod dependencies;
+
+use dependencies::{BlockedCard, CardRef, CardRelations, RelationIssue, RelationKind};
+
+// Moving and copying cards/columns between boards
+mod transfer;
+
+use transfer::{TransferMode, TransferReceipt, TransferRequest, TransferResult};
+
+// Board templates (built-in and user-saved)
+mod templates;
+
+use templates::BoardTemplate;
+
+// Undo/redo stack around AppData writes
+mod history;
+
+use history::HistoryState;
+
+// Bookmark page metadata (title, description, OpenGraph, favicon)
+mod html;
+mod metadata;
+
+use metadata::BookmarkMetadata;
+
+// Offline cache for bookmark favicons and preview images
+mod asset_cache;
+
+use asset_cache::{AssetCacheResult, AssetGcReport, CachedAsset};
+
+// Background broken link checker for bookmarks
+mod linkcheck;
+
+use linkcheck::{LinkAction, LinkCheck, LinkCheckProgress, LinkIssue, LinkState};
+
+// Offline page snapshots with readability extraction
+mod readability;
+mod snapshot;
+
+use snapshot::{PageSnapshot, ReaderView};
+
+// URL canonicalization and duplicate bookmark detection
+mod duplicates;
+
+use duplicates::{DuplicateBookmark, DuplicateGroup, MergeResult};
+
+// Browser bookmark import (Netscape HTML, Chrome JSON, Firefox places.sqlite)
+mod importer;
+// Read-later service exports (Pocket, Raindrop, Pinboard)
+mod services;
+// Bookmark tag keys and custom tag creation
+mod tags;
+
+use importer::{BrowserSource, ImportFormat, ImportResult};
+
+// Bookmark export (Netscape HTML, CSV, JSON Lines)
+mod exporter;
+
+use exporter::{ExportFilter, ExportFormat, ExportSummary};
+use tauri_plugin_dialog::DialogExt;
+
+// Smart collections (saved bookmark queries)
+mod smart;
+
+use smart::SmartCollectionMatches;
+
+// Trees of parent ids shared by bookmark folders and notebooks
+mod parent_tree;
+// Nested bookmark folders
+mod folders;
+
+use folders::{DeleteMode, FolderNode};
+
+// Notebooks and note tags
+mod notebooks;
+
+use notebooks::{NoteFilter, NotebookTree};
+
+// Full-text search over cards, bookmarks and notes
+mod search;
+
+use search::{SearchHit, SearchOptions};
+
+// Quick capture window and input parser
+mod capture;
+
+use capture::{CapturePlan, CaptureResult};
+use tauri::{Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+/// Label of the quick capture window
+const CAPTURE_WINDOW: &str = "capture";
+/// Global shortcut that opens the quick capture window
+const CAPTURE_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+// Wiki-links and backlinks between notes, cards and bookmarks
+mod links;
+
+use links::{Backlink, RenameResult, UnresolvedLink};
+
+// Note and card attachments (content-addressed file store)
+mod attachments;
+
+use attachments::{AttachResult, Attachment};
+
+// Revision history for notes and card descriptions
+mod revisions;
+
+use revisions::{RevisionDiff, RevisionInfo, RevisionKind};
+
+// Converting between notes, cards and bookmarks
+mod convert;
+
+use convert::{ConvertResult, ExtractResult};
+
+// Markdown task lists synced with checklists
+mod tasks;
+
+use tasks::TaskProgress;
+
+// Daily notes
+mod journal;
+
+use journal::{DailyNoteResult, JournalEntry, JournalSettings};
+
+// Markdown vault (notes mirrored as files)
+mod vault;
+
+use vault::{VaultStatus, VaultSyncResult};
+
+/// How often the vault folder is checked for edits made outside Carbon
+const VAULT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+/// Keeps vault syncs and saves of the data file from running at once
+static VAULT_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 // Data structures matching the JavaScript types
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChecklistItem {
@@ -18,6 +159,15 @@ pub struct ChecklistItem {
     pub completed: bool,
 }
 
+/// A card entering a column, recorded by `write_data` for flow metrics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnTransition {
+    #[serde(rename = "columnId")]
+    pub column_id: String,
+    #[serde(rename = "enteredAt")]
+    pub entered_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Card {
     pub id: String,
@@ -36,6 +186,22 @@ pub struct Card {
     pub archived_at: Option<String>,
     #[serde(rename = "originalColumnId", default)]
     pub original_column_id: Option<String>,
+    #[serde(rename = "columnHistory", default)]
+    pub column_history: Vec<ColumnTransition>,
+    /// Parent card id when this card is a subtask (may be on another board)
+    #[serde(rename = "parentId", default)]
+    pub parent_id: Option<String>,
+    /// Ids of cards that have to be done before this one
+    #[serde(rename = "blockedBy", default)]
+    pub blocked_by: Vec<String>,
+    #[serde(rename = "relatedTo", default)]
+    pub related_to: Vec<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Keep `- [ ]` lines in the description in sync with `checklist`
+    /// (see `tasks`)
+    #[serde(rename = "taskSync", default)]
+    pub task_sync: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -61,6 +227,9 @@ pub struct Collection {
     pub icon: String,
     #[serde(rename = "isCustom", default)]
     pub is_custom: Option<bool>,
+    /// Saved query of a smart collection (see `smart`)
+    #[serde(default)]
+    pub query: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -74,6 +243,18 @@ pub struct Bookmark {
     pub favicon: Option<String>,
     #[serde(default)]
     pub image: Option<String>,
+    /// Local copy of `favicon` in the asset cache
+    #[serde(rename = "faviconAsset", default)]
+    pub favicon_asset: Option<CachedAsset>,
+    /// Local copy of `image` in the asset cache
+    #[serde(rename = "imageAsset", default)]
+    pub image_asset: Option<CachedAsset>,
+    /// Outcome of the last broken link check
+    #[serde(rename = "linkCheck", default)]
+    pub link_check: Option<LinkCheck>,
+    /// Archived copy of the page for offline reading
+    #[serde(default)]
+    pub snapshot: Option<PageSnapshot>,
     #[serde(default)]
     pub tags: Vec<String>,
     #[serde(rename = "collectionId", default)]
@@ -108,6 +289,37 @@ pub struct Note {
     pub created_at: String,
     #[serde(rename = "updatedAt")]
     pub updated_at: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Enclosing notebook; `None` for unfiled notes (see `notebooks`)
+    #[serde(rename = "notebookId", default)]
+    pub notebook_id: Option<String>,
+    /// Keys into `customTags`, shared with bookmarks
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Day (`YYYY-MM-DD`) this is the daily note of (see `journal`)
+    #[serde(rename = "journalDate", default)]
+    pub journal_date: Option<String>,
+    /// Task list of `content`, kept in sync while `task_sync` is on
+    #[serde(default)]
+    pub checklist: Vec<ChecklistItem>,
+    #[serde(rename = "taskSync", default)]
+    pub task_sync: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notebook {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Enclosing notebook; `None` for top-level notebooks
+    #[serde(rename = "parentId", default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub order: Option<i32>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -118,6 +330,9 @@ pub struct BookmarkFolder {
     pub color: Option<String>,
     #[serde(rename = "collectionId", default)]
     pub collection_id: Option<String>,
+    /// Enclosing folder; `None` for top-level folders (see `folders`)
+    #[serde(rename = "parentId", default)]
+    pub parent_id: Option<String>,
     #[serde(default)]
     pub order: Option<i32>,
     #[serde(rename = "createdAt")]
@@ -143,6 +358,8 @@ pub struct AppData {
     pub custom_tags: std::collections::HashMap<String, CustomTag>,
     #[serde(default)]
     pub notes: Vec<Note>,
+    #[serde(default)]
+    pub notebooks: Vec<Notebook>,
     /// Last modified timestamp for sync conflict resolution (ISO 8601)
     #[serde(rename = "lastModified", default = "default_last_modified")]
     pub last_modified: String,
@@ -151,6 +368,27 @@ pub struct AppData {
     pub sync_enabled: bool,
 }
 
+impl AppData {
+    /// Every card on every board, archived cards included
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.boards.iter().flat_map(|b| {
+            b.columns
+                .iter()
+                .flat_map(|c| c.cards.iter())
+                .chain(b.archived_cards.iter())
+        })
+    }
+
+    pub fn cards_mut(&mut self) -> impl Iterator<Item = &mut Card> {
+        self.boards.iter_mut().flat_map(|b| {
+            b.columns
+                .iter_mut()
+                .flat_map(|c| c.cards.iter_mut())
+                .chain(b.archived_cards.iter_mut())
+        })
+    }
+}
+
 /// Data that is synced across devices via CloudKit.
 /// Intentionally excludes purely local UI state like `activeView`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -170,6 +408,8 @@ pub struct SyncData {
     pub custom_tags: std::collections::HashMap<String, CustomTag>,
     #[serde(default)]
     pub notes: Vec<Note>,
+    #[serde(default)]
+    pub notebooks: Vec<Notebook>,
     /// Last modified timestamp for sync conflict resolution (ISO 8601)
     #[serde(rename = "lastModified", default = "default_last_modified")]
     pub last_modified: String,
@@ -197,18 +437,21 @@ fn default_collections() -> Vec<Collection> {
             name: "All Bookmarks".to_string(),
             icon: "bookmark".to_string(),
             is_custom: None,
+            query: None,
         },
         Collection {
             id: "favorites".to_string(),
             name: "Favorites".to_string(),
             icon: "star".to_string(),
             is_custom: None,
+            query: None,
         },
         Collection {
             id: "archive".to_string(),
             name: "Archive".to_string(),
             icon: "archive".to_string(),
             is_custom: None,
+            query: None,
         },
     ]
 }
@@ -231,44 +474,35 @@ fn get_data_file_path() -> PathBuf {
 }
 
 fn get_default_data() -> AppData {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut board = templates::board_from_template(
+        &templates::default_template(),
+        "default-board",
+        "My First Project",
+        &now,
+    );
+    if let Some(backlog) = board.columns.first_mut() {
+        backlog.cards.push(Card {
+            id: "card-1".to_string(),
+            title: "Welcome!".to_string(),
+            description: "This is your first card. Drag it to another column or create new cards to get started.".to_string(),
+            labels: vec![],
+            priority: None,
+            checklist: vec![],
+            created_at: now.clone(),
+            archived_at: None,
+            original_column_id: None,
+            column_history: vec![],
+            parent_id: None,
+            blocked_by: vec![],
+            related_to: vec![],
+            attachments: vec![],
+            task_sync: false,
+        });
+    }
+    
     AppData {
-        boards: vec![Board {
-            id: "default-board".to_string(),
-            name: "My First Project".to_string(),
-            columns: vec![
-                Column {
-                    id: "col-backlog".to_string(),
-                    title: "Backlog".to_string(),
-                    cards: vec![Card {
-                        id: "card-1".to_string(),
-                        title: "Welcome!".to_string(),
-                        description: "This is your first card. Drag it to another column or create new cards to get started.".to_string(),
-                        labels: vec![],
-                        priority: None,
-                        checklist: vec![],
-                        created_at: chrono::Utc::now().to_rfc3339(),
-                        archived_at: None,
-                        original_column_id: None,
-                    }],
-                },
-                Column {
-                    id: "col-todo".to_string(),
-                    title: "To Do".to_string(),
-                    cards: vec![],
-                },
-                Column {
-                    id: "col-progress".to_string(),
-                    title: "In Progress".to_string(),
-                    cards: vec![],
-                },
-                Column {
-                    id: "col-done".to_string(),
-                    title: "Done".to_string(),
-                    cards: vec![],
-                },
-            ],
-            archived_cards: vec![],
-        }],
+        boards: vec![board],
         active_board: Some("default-board".to_string()),
         theme: "dark".to_string(),
         active_view: "boards".to_string(),
@@ -277,13 +511,17 @@ fn get_default_data() -> AppData {
         collections: default_collections(),
         custom_tags: std::collections::HashMap::new(),
         notes: vec![],
-        last_modified: chrono::Utc::now().to_rfc3339(),
+        notebooks: vec![],
+        last_modified: now,
         sync_enabled: false,
     }
 }
 
-#[tauri::command]
-fn read_data() -> Result<AppData, String> {
+/// Load app data from disk, falling back to (and persisting) the defaults.
+///
+/// Only for reading: a command that changes and saves the data must use
+/// `read_data_file`, or an unreadable file would be overwritten by defaults.
+fn load_data() -> AppData {
     let file_path = get_data_file_path();
     
     if !file_path.exists() {
@@ -293,35 +531,49 @@ fn read_data() -> Result<AppData, String> {
         if let Ok(json) = serde_json::to_string_pretty(&default_data) {
             fs::write(&file_path, json).ok();
         }
-        return Ok(default_data);
+        return default_data;
     }
     
-    match fs::read_to_string(&file_path) {
-        Ok(content) => {
-            match serde_json::from_str::<AppData>(&content) {
-                Ok(data) => Ok(data),
-                Err(e) => {
-                    log::error!("Failed to parse data file: {}", e);
-                    // Return default data if parsing fails
-                    Ok(get_default_data())
-                }
-            }
-        }
-        Err(e) => {
-            log::error!("Failed to read data file: {}", e);
-            Ok(get_default_data())
-        }
-    }
+    read_data_file().unwrap_or_else(|e| {
+        log::error!("{}", e);
+        // Return default data if the file cannot be read
+        get_default_data()
+    })
 }
 
-#[tauri::command]
-fn write_data(data: AppData) -> Result<bool, String> {
+/// Load the currently persisted data, if there is any readable data file
+fn load_existing_data() -> Option<AppData> {
+    read_data_file().ok()
+}
+
+/// Read and parse the data file without falling back to the defaults.
+///
+/// Anything that saves the data, or deletes files nothing references
+/// (asset, snapshot and attachment GC), must use this: saving the defaults
+/// would replace the user's data, and judged against them every user file
+/// would look unused.
+fn read_data_file() -> Result<AppData, String> {
+    let content = fs::read_to_string(get_data_file_path())
+        .map_err(|e| format!("Failed to read data file: {}", e))?;
+    let mut data = serde_json::from_str::<AppData>(&content)
+        .map_err(|e| format!("Failed to parse data file: {}", e))?;
+    // Folders saved before nesting, or edited elsewhere, may not form a tree
+    folders::migrate(&mut data);
+    notebooks::migrate(&mut data);
+    Ok(data)
+}
+
+fn save_data(data: &AppData) -> Result<(), String> {
     let file_path = get_data_file_path();
     
-    match serde_json::to_string_pretty(&data) {
+    match serde_json::to_string_pretty(data) {
         Ok(json) => {
             match fs::write(&file_path, json) {
-                Ok(_) => Ok(true),
+                Ok(_) => {
+                    search::update(data);
+                    links::update(data);
+                    Ok(())
+                }
                 Err(e) => {
                     log::error!("Failed to write data file: {}", e);
                     Err(format!("Failed to write data: {}", e))
@@ -335,6 +587,87 @@ fn write_data(data: AppData) -> Result<bool, String> {
     }
 }
 
+/// Wait for a running vault sync, which saves what it read before the sync
+fn lock_vault() -> std::sync::MutexGuard<'static, ()> {
+    // A panicked sync leaves nothing half-written that saving could break
+    VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Read the data file for a command that changes it.
+///
+/// The guard holds `VAULT_LOCK`; keep it until `commit_data` has saved, or
+/// a vault sync could import notes in between that the save would drop.
+fn lock_data() -> Result<(std::sync::MutexGuard<'static, ()>, AppData), String> {
+    let vault = lock_vault();
+    let data = read_data_file()?;
+    Ok((vault, data))
+}
+
+/// Persist data changed by a Rust-side command and hand it back to the frontend.
+///
+/// `label` names the change in the undo history. Callers hold `VAULT_LOCK`
+/// from before they read the data (see `lock_data`).
+fn commit_data(mut data: AppData, label: &str) -> Result<AppData, String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let previous = load_existing_data();
+    metrics::record_column_transitions(previous.as_ref(), &mut data, &now);
+    tasks::sync(previous.as_ref(), &mut data);
+    journal::refresh(&mut data, journal::today(), &now);
+    data.last_modified = now.clone();
+    
+    save_data(&data)?;
+    
+    if let Err(e) = history::record(&get_data_dir(), previous.as_ref(), &data, Some(label), &now) {
+        log::warn!("Failed to record undo history: {}", e);
+    }
+    if let Err(e) = revisions::record(&get_data_dir(), previous.as_ref(), &data, &now) {
+        log::warn!("Failed to record revisions: {}", e);
+    }
+    Ok(data)
+}
+
+fn find_board<'a>(data: &'a AppData, board_id: &str) -> Result<&'a Board, String> {
+    data.boards
+        .iter()
+        .find(|b| b.id == board_id)
+        .ok_or_else(|| format!("Board not found: {}", board_id))
+}
+
+#[tauri::command]
+fn read_data() -> Result<AppData, String> {
+    Ok(load_data())
+}
+
+#[tauri::command]
+fn write_data(app: tauri::AppHandle, mut data: AppData) -> Result<bool, String> {
+    let _vault = lock_vault();
+    let now = chrono::Utc::now().to_rfc3339();
+    // Stamp column changes so flow metrics have transition timestamps
+    let previous = load_existing_data();
+    metrics::record_column_transitions(previous.as_ref(), &mut data, &now);
+    let tasks_synced = tasks::sync(previous.as_ref(), &mut data);
+    // Keep today's rollup in step with the cards
+    let journal_refreshed = journal::refresh(&mut data, journal::today(), &now);
+    
+    save_data(&data)?;
+    if tasks_synced {
+        // The frontend has to reload, or its next write would undo the sync
+        app.emit("tasks-sync", ()).ok();
+    }
+    if journal_refreshed {
+        // Same for the rollup written into today's journal note
+        app.emit("journal-refresh", ()).ok();
+    }
+    
+    if let Err(e) = history::record(&get_data_dir(), previous.as_ref(), &data, None, &now) {
+        log::warn!("Failed to record undo history: {}", e);
+    }
+    if let Err(e) = revisions::record(&get_data_dir(), previous.as_ref(), &data, &now) {
+        log::warn!("Failed to record revisions: {}", e);
+    }
+    Ok(true)
+}
+
 #[tauri::command]
 fn get_data_path() -> String {
     get_data_file_path().to_string_lossy().to_string()
@@ -431,153 +764,1291 @@ async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 // ============================================
-// CLOUDKIT SYNC COMMANDS (macOS only)
+// FLOW METRICS COMMANDS
 // ============================================
 
-/// Check if iCloud account is available
+/// Cycle time, lead time, weekly throughput and cumulative flow for a board
 #[tauri::command]
-#[cfg(target_os = "macos")]
-fn check_icloud_account() -> bool {
-    CloudKit::check_account()
+fn get_board_metrics(board_id: String, options: Option<MetricsOptions>) -> Result<BoardMetrics, String> {
+    let data = load_data();
+    let board = find_board(&data, &board_id)?;
+    Ok(metrics::compute_board_metrics(board, &options.unwrap_or_default(), chrono::Utc::now()))
 }
 
-/// Get detailed iCloud account status (for better UI + debugging)
+// ============================================
+// CARD RELATION COMMANDS
+// ============================================
+
+/// Link two cards (subtask, blocker or related), rejecting cycles
 #[tauri::command]
-#[cfg(target_os = "macos")]
-fn get_icloud_account_status() -> AccountStatusJson {
-    CloudKit::get_account_status().into()
+fn set_card_relation(card_id: String, kind: RelationKind, target_id: String) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    dependencies::set_relation(&mut data, &card_id, kind, &target_id)?;
+    commit_data(data, "Link cards")
 }
 
 #[tauri::command]
-#[cfg(not(target_os = "macos"))]
-fn check_icloud_account() -> bool {
-    false
+fn remove_card_relation(card_id: String, kind: RelationKind, target_id: String) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    dependencies::remove_relation(&mut data, &card_id, kind, &target_id)?;
+    commit_data(data, "Unlink cards")
 }
 
 #[tauri::command]
-#[cfg(not(target_os = "macos"))]
-fn get_icloud_account_status() -> serde_json::Value {
-    serde_json::json!({
-        "available": false,
-        "status": "offline",
-        "error": "CloudKit is only available on macOS"
-    })
+fn get_card_relations(card_id: String) -> Result<CardRelations, String> {
+    dependencies::card_relations(&load_data(), &card_id)
 }
 
-/// Get current sync status
+/// Report dangling references and cycles across all boards
 #[tauri::command]
-#[cfg(target_os = "macos")]
-fn get_sync_status() -> SyncStatusJson {
-    CloudKit::get_status().into()
+fn validate_card_relations() -> Vec<RelationIssue> {
+    dependencies::validate_relations(&load_data())
 }
 
 #[tauri::command]
-#[cfg(not(target_os = "macos"))]
-fn get_sync_status() -> serde_json::Value {
-    serde_json::json!({
-        "status": "offline",
-        "error": "CloudKit is only available on macOS"
-    })
+fn get_blocked_cards(board_id: String) -> Result<Vec<BlockedCard>, String> {
+    let data = load_data();
+    find_board(&data, &board_id)?;
+    Ok(dependencies::blocked_cards(&data, &board_id))
 }
 
-/// Sync data with iCloud - performs bidirectional sync with last-write-wins conflict resolution
+/// Longest chain of open blockers ending on the board, first card first
 #[tauri::command]
-#[cfg(target_os = "macos")]
-async fn sync_to_cloud(data: SyncData) -> Result<SyncResultJson, String> {
-    log::debug!("Starting iCloud sync...");
-
-    // Serialize the data to JSON
-    let json_data = serde_json::to_string(&data)
-        .map_err(|e| format!("Failed to serialize data: {}", e))?;
-    let last_modified = data.last_modified.clone();
-
-    // CloudKit FFI blocks; run it on a blocking thread to avoid UI / event loop stalls.
-    let result = tauri::async_runtime::spawn_blocking(move || CloudKit::sync(&json_data, &last_modified))
-        .await
-        .map_err(|e| format!("Sync task failed: {}", e))?;
+fn get_critical_chain(board_id: String) -> Result<Vec<CardRef>, String> {
+    let data = load_data();
+    find_board(&data, &board_id)?;
+    Ok(dependencies::critical_chain(&data, &board_id))
+}
 
-    if !result.success {
-        log::error!("iCloud sync failed: {:?}", result.error);
-    }
+// ============================================
+// CROSS-BOARD TRANSFER COMMANDS
+// ============================================
 
-    Ok(result.into())
+/// Move or copy cards (or a whole column) to another board in one write
+#[tauri::command]
+fn transfer_cards(request: TransferRequest) -> Result<TransferResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let receipt = transfer::transfer(&mut data, &request, &chrono::Utc::now().to_rfc3339())?;
+    let label = match request.mode {
+        TransferMode::Move => "Move cards between boards",
+        TransferMode::Copy => "Copy cards between boards",
+    };
+    let data = commit_data(data, label)?;
+    Ok(TransferResult { data, receipt })
 }
 
-/// Push local data to iCloud (upload only).
-///
-/// This avoids an extra fetch that `sync_to_cloud` performs, and only falls back
-/// to a pull if the server reports newer data (CAS conflict).
+/// Reverse a transfer using the receipt returned by `transfer_cards`
 #[tauri::command]
-#[cfg(target_os = "macos")]
-async fn push_to_cloud(data: SyncData) -> Result<SyncResultJson, String> {
-    log::debug!("Pushing local data to iCloud...");
-
-    let json_data = serde_json::to_string(&data)
-        .map_err(|e| format!("Failed to serialize data: {}", e))?;
-    let last_modified = data.last_modified.clone();
+fn undo_transfer(receipt: TransferReceipt) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    transfer::undo_transfer(&mut data, &receipt)?;
+    commit_data(data, "Undo transfer")
+}
 
-    let result = tauri::async_runtime::spawn_blocking(move || CloudKit::push(&json_data, &last_modified))
-        .await
-        .map_err(|e| format!("Push task failed: {}", e))?;
+// ============================================
+// BOARD TEMPLATE COMMANDS
+// ============================================
 
-    if result.success {
-        return Ok(result.into());
-    }
+#[tauri::command]
+fn list_board_templates() -> Vec<BoardTemplate> {
+    templates::list_templates(&templates::get_templates_dir(&get_data_dir()))
+}
 
-    // If the server has newer data, pull it so the frontend can update local state.
-    if let Some(err) = result.error.as_deref() {
-        let err_lc = err.to_lowercase();
-        if err_lc.contains("cas failed") || err_lc.contains("server has newer data") {
-            log::debug!("Push conflicted; pulling latest remote data...");
-            let pull = tauri::async_runtime::spawn_blocking(|| CloudKit::pull())
-                .await
-                .map_err(|e| format!("Pull task failed: {}", e))?;
-            return Ok(pull.into());
-        }
-    }
+/// Save a board's columns (and optionally its cards) as a named template
+#[tauri::command]
+fn save_board_as_template(
+    board_id: String,
+    name: String,
+    description: Option<String>,
+    include_cards: Option<bool>,
+) -> Result<BoardTemplate, String> {
+    let data = load_data();
+    let board = find_board(&data, &board_id)?;
+    let mut template = templates::template_from_board(
+        board,
+        &name,
+        include_cards.unwrap_or(false),
+        &chrono::Utc::now().to_rfc3339(),
+    );
+    template.description = description.unwrap_or_default();
+    templates::save_template(&templates::get_templates_dir(&get_data_dir()), &template)?;
+    Ok(template)
+}
 
-    Ok(result.into())
+/// Create a new board from a template and make it the active board
+#[tauri::command]
+fn create_board_from_template(template_id: String, name: Option<String>) -> Result<AppData, String> {
+    let template = templates::find_template(&templates::get_templates_dir(&get_data_dir()), &template_id)?;
+    let (_vault, mut data) = lock_data()?;
+    let board_id = uuid::Uuid::new_v4().to_string();
+    let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| template.name.clone());
+    data.boards.push(templates::board_from_template(
+        &template,
+        &board_id,
+        &name,
+        &chrono::Utc::now().to_rfc3339(),
+    ));
+    data.active_board = Some(board_id);
+    commit_data(data, "Create board from template")
 }
 
 #[tauri::command]
-#[cfg(not(target_os = "macos"))]
-fn push_to_cloud(_data: SyncData) -> Result<serde_json::Value, String> {
-    Ok(serde_json::json!({
-        "success": false,
-        "shouldUpdateLocal": false,
-        "error": "CloudKit is only available on macOS",
-        "data": null,
-        "remoteLastModified": null
-    }))
+fn delete_board_template(template_id: String) -> Result<(), String> {
+    templates::delete_template(&templates::get_templates_dir(&get_data_dir()), &template_id)
 }
 
+/// Write a template to a user-chosen path as standalone JSON
 #[tauri::command]
-#[cfg(not(target_os = "macos"))]
-fn sync_to_cloud(_data: SyncData) -> Result<serde_json::Value, String> {
-    Ok(serde_json::json!({
-        "success": false,
-        "shouldUpdateLocal": false,
-        "error": "CloudKit is only available on macOS",
-        "data": null,
-        "remoteLastModified": null
-    }))
+fn export_board_template(template_id: String, path: String) -> Result<(), String> {
+    let template = templates::find_template(&templates::get_templates_dir(&get_data_dir()), &template_id)?;
+    templates::export_template(&template, &PathBuf::from(path))
 }
 
-/// Pull data from iCloud
 #[tauri::command]
-#[cfg(target_os = "macos")]
-async fn sync_from_cloud() -> Result<SyncResultJson, String> {
-    log::debug!("Pulling data from iCloud...");
+fn import_board_template(path: String) -> Result<BoardTemplate, String> {
+    templates::import_template(
+        &templates::get_templates_dir(&get_data_dir()),
+        &PathBuf::from(path),
+        &chrono::Utc::now().to_rfc3339(),
+    )
+}
 
-    let result = tauri::async_runtime::spawn_blocking(|| CloudKit::pull())
-        .await
-        .map_err(|e| format!("Pull task failed: {}", e))?;
+// ============================================
+// UNDO / REDO COMMANDS
+// ============================================
 
-    if !result.success {
-        log::error!("Failed to pull from iCloud: {:?}", result.error);
+/// Save data restored from history without recording it as a new operation.
+///
+/// Windows get a `history-restore` event so they reload instead of writing
+/// their stale copy back.
+fn restore_data(app: &tauri::AppHandle, mut data: AppData) -> Result<AppData, String> {
+    data.last_modified = chrono::Utc::now().to_rfc3339();
+    save_data(&data)?;
+    if let Err(e) = app.emit("history-restore", ()) {
+        log::warn!("Failed to notify windows of undo/redo: {}", e);
     }
+    Ok(data)
+}
 
-    Ok(result.into())
+/// Undo the last change; returns `None` when there is nothing to undo
+#[tauri::command]
+fn undo(app: tauri::AppHandle) -> Result<Option<AppData>, String> {
+    let _vault = lock_vault();
+    match history::undo(&get_data_dir(), &read_data_file()?)? {
+        Some(data) => restore_data(&app, data).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Redo the last undone change; returns `None` when there is nothing to redo
+#[tauri::command]
+fn redo(app: tauri::AppHandle) -> Result<Option<AppData>, String> {
+    let _vault = lock_vault();
+    match history::redo(&get_data_dir(), &read_data_file()?)? {
+        Some(data) => restore_data(&app, data).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+fn get_history() -> HistoryState {
+    history::state(&get_data_dir())
+}
+
+#[tauri::command]
+fn clear_history() {
+    history::clear(&get_data_dir())
+}
+
+// ============================================
+// BOOKMARK METADATA COMMANDS
+// ============================================
+
+/// Fetch a page and read its title, description, preview image and favicon
+#[tauri::command]
+async fn fetch_bookmark_metadata(url: String) -> Result<BookmarkMetadata, String> {
+    let client = metadata::build_client()?;
+    metadata::fetch_metadata(&client, &url).await.map_err(|e| {
+        log::warn!("Metadata fetch failed for {}: {}", url, e);
+        e
+    })
+}
+
+// ============================================
+// ASSET CACHE COMMANDS
+// ============================================
+
+/// Download bookmark favicons/preview images into the local asset cache.
+///
+/// Only the given bookmarks are processed when `bookmark_ids` is set.
+#[tauri::command]
+async fn cache_bookmark_assets(bookmark_ids: Option<Vec<String>>) -> Result<AssetCacheResult, String> {
+    cache_assets(bookmark_ids.map(|ids| ids.into_iter().collect())).await
+}
+
+async fn cache_assets(only: Option<std::collections::HashSet<String>>) -> Result<AssetCacheResult, String> {
+    let dir = asset_cache::get_assets_dir(&get_data_dir());
+    let client = metadata::build_client()?;
+    let snapshot = load_data();
+    let (assets, report) = asset_cache::cache_bookmarks(&client, &dir, &snapshot.bookmarks, only.as_ref()).await;
+    
+    // Downloads take a while; apply the results to whatever is on disk now
+    let (_vault, mut data) = lock_data()?;
+    for bookmark in &mut data.bookmarks {
+        if let Some((favicon, image)) = assets.get(&bookmark.id) {
+            if favicon.is_some() {
+                bookmark.favicon_asset = favicon.clone();
+            }
+            if image.is_some() {
+                bookmark.image_asset = image.clone();
+            }
+        }
+    }
+    let data = if report.cached > 0 {
+        commit_data(data, "Cache bookmark images")?
+    } else {
+        data
+    };
+    Ok(AssetCacheResult { data, report })
+}
+
+/// Remove cached files that no bookmark references any more
+#[tauri::command]
+fn gc_asset_cache() -> Result<AssetGcReport, String> {
+    let data = read_data_file()?;
+    Ok(asset_cache::collect_garbage(&asset_cache::get_assets_dir(&get_data_dir()), &data))
+}
+
+/// Prefix to put in front of a cached asset's `file` to load it in the webview
+#[tauri::command]
+fn get_asset_base_url() -> String {
+    asset_cache::base_url(asset_cache::ASSET_SCHEME)
+}
+
+// ============================================
+// LINK CHECK COMMANDS
+// ============================================
+
+/// Start checking bookmark URLs in the background.
+///
+/// Checks every non-archived bookmark unless `bookmark_ids` is given. Poll
+/// `get_link_check_progress` while it runs; windows get a `link-check` event
+/// with the final progress once it stops, so they can reload.
+#[tauri::command]
+fn start_link_check(app: tauri::AppHandle, bookmark_ids: Option<Vec<String>>) -> Result<LinkCheckProgress, String> {
+    let client = metadata::build_client()?;
+    let only = bookmark_ids.map(|ids| ids.into_iter().collect::<std::collections::HashSet<_>>());
+    let targets = linkcheck::targets(&load_data().bookmarks, only.as_ref());
+    let (progress, job) = linkcheck::begin(targets.len(), &chrono::Utc::now().to_rfc3339())?;
+    
+    tauri::async_runtime::spawn(async move {
+        {
+            let _job = job;
+            let results = linkcheck::check_links(&client, targets).await;
+            
+            // Apply to whatever is on disk now; the user may have edited meanwhile
+            match lock_data() {
+                Ok((_vault, mut data)) => {
+                    if linkcheck::apply_results(&mut data, results) > 0 {
+                        if let Err(e) = commit_data(data, "Check links") {
+                            log::error!("Failed to save link check results: {}", e);
+                        }
+                    }
+                }
+                Err(e) => log::error!("Failed to save link check results: {}", e),
+            }
+        }
+        if let Err(e) = app.emit("link-check", linkcheck::progress()) {
+            log::warn!("Failed to notify windows of link check: {}", e);
+        }
+    });
+    
+    Ok(progress)
+}
+
+#[tauri::command]
+fn get_link_check_progress() -> LinkCheckProgress {
+    linkcheck::progress()
+}
+
+#[tauri::command]
+fn cancel_link_check() {
+    linkcheck::cancel()
+}
+
+/// Dead, redirected and failing links; pass `state` to list only one kind
+#[tauri::command]
+fn get_link_issues(state: Option<LinkState>) -> Vec<LinkIssue> {
+    linkcheck::link_issues(&load_data(), state)
+}
+
+/// Update redirected bookmarks to their target, or archive bookmarks, in bulk
+#[tauri::command]
+fn resolve_link_issues(bookmark_ids: Vec<String>, action: LinkAction) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    if linkcheck::apply_action(&mut data, &bookmark_ids, action) == 0 {
+        return Ok(data);
+    }
+    let label = match action {
+        LinkAction::UpdateToRedirect => "Update redirected links",
+        LinkAction::Archive => "Archive broken links",
+    };
+    commit_data(data, label)
+}
+
+// ============================================
+// PAGE SNAPSHOT COMMANDS
+// ============================================
+
+/// Save a readable offline copy of a bookmark's page
+#[tauri::command]
+async fn archive_bookmark_page(bookmark_id: String) -> Result<AppData, String> {
+    let url = load_data()
+        .bookmarks
+        .iter()
+        .find(|b| b.id == bookmark_id)
+        .map(|b| b.url.clone())
+        .ok_or_else(|| format!("Bookmark not found: {}", bookmark_id))?;
+    let client = metadata::build_client()?;
+    let dir = snapshot::get_snapshots_dir(&get_data_dir());
+    let now = chrono::Utc::now().to_rfc3339();
+    let page = snapshot::capture(&client, &dir, &bookmark_id, &url, &now).await?;
+    
+    let (_vault, mut data) = lock_data()?;
+    let bookmark = data
+        .bookmarks
+        .iter_mut()
+        .find(|b| b.id == bookmark_id)
+        .ok_or_else(|| format!("Bookmark not found: {}", bookmark_id))?;
+    bookmark.snapshot = Some(page);
+    commit_data(data, "Archive page")
+}
+
+/// The archived copy of a bookmark's page, ready to render offline
+#[tauri::command]
+fn get_reader_view(bookmark_id: String) -> Result<ReaderView, String> {
+    let data = load_data();
+    let bookmark = data
+        .bookmarks
+        .iter()
+        .find(|b| b.id == bookmark_id)
+        .ok_or_else(|| format!("Bookmark not found: {}", bookmark_id))?;
+    snapshot::reader_view(&snapshot::get_snapshots_dir(&get_data_dir()), bookmark)
+}
+
+/// Forget a bookmark's archived copy; the files go at the next clean-up
+#[tauri::command]
+fn delete_page_snapshot(bookmark_id: String) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    let bookmark = data
+        .bookmarks
+        .iter_mut()
+        .find(|b| b.id == bookmark_id)
+        .ok_or_else(|| format!("Bookmark not found: {}", bookmark_id))?;
+    if bookmark.snapshot.take().is_none() {
+        return Ok(data);
+    }
+    commit_data(data, "Delete archived page")
+}
+
+// ============================================
+// DUPLICATE BOOKMARK COMMANDS
+// ============================================
+
+/// Canonical form of a URL (lowercase host, no tracking parameters)
+#[tauri::command]
+fn normalize_bookmark_url(url: String) -> Result<String, String> {
+    duplicates::normalize_url(&url)
+}
+
+/// Existing bookmarks for the same page; call before adding a bookmark
+#[tauri::command]
+fn find_bookmark_duplicates(url: String) -> Vec<DuplicateBookmark> {
+    duplicates::find_matches(&load_data(), &url)
+}
+
+#[tauri::command]
+fn get_duplicate_groups() -> Vec<DuplicateGroup> {
+    duplicates::find_groups(&load_data())
+}
+
+/// Merge the given bookmarks into one, or every duplicate group when no ids are given
+#[tauri::command]
+fn merge_duplicate_bookmarks(bookmark_ids: Option<Vec<String>>) -> Result<MergeResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let groups = match bookmark_ids {
+        Some(ids) => {
+            duplicates::validate_group(&data, &ids)?;
+            vec![ids]
+        }
+        None => duplicates::find_groups(&data)
+            .into_iter()
+            .map(|g| g.bookmarks.into_iter().map(|b| b.id).collect())
+            .collect(),
+    };
+    let now = chrono::Utc::now().to_rfc3339();
+    let results: Vec<duplicates::Merged> = groups
+        .iter()
+        .map(|ids| duplicates::merge(&mut data, ids, &now))
+        .collect();
+    let merged: usize = results.iter().map(|r| r.removed).sum();
+    
+    // Archived pages are stored under the bookmark id, so the survivor needs its own copy
+    let snapshots_dir = snapshot::get_snapshots_dir(&get_data_dir());
+    for (from, to) in results.iter().filter_map(|r| r.snapshot.as_ref()) {
+        snapshot::copy_snapshot(&snapshots_dir, from, to)?;
+    }
+    
+    let data = if merged > 0 {
+        commit_data(data, "Merge duplicate bookmarks")?
+    } else {
+        data
+    };
+    Ok(MergeResult { data, merged })
+}
+
+// ============================================
+// BOOKMARK IMPORT COMMANDS
+// ============================================
+
+/// Import bookmarks from a browser or read-later service export.
+///
+/// The format is detected from the file contents unless given. With
+/// `dry_run` nothing is saved; the report previews what would be imported.
+#[tauri::command]
+fn import_bookmarks(
+    path: String,
+    format: Option<ImportFormat>,
+    dry_run: Option<bool>,
+) -> Result<ImportResult, String> {
+    let items = importer::read_file(std::path::Path::new(&path), format)?;
+    let (_vault, mut data) = lock_data()?;
+    let report = importer::apply(&mut data, items, &chrono::Utc::now().to_rfc3339());
+    
+    if dry_run.unwrap_or(false) {
+        return Ok(ImportResult { data: None, report });
+    }
+    if report.imported == 0 {
+        return Ok(ImportResult { data: Some(data), report });
+    }
+    let data = commit_data(data, "Import bookmarks")?;
+    Ok(ImportResult { data: Some(data), report })
+}
+
+/// Bookmark files of browsers installed on this machine
+#[tauri::command]
+fn find_browser_bookmarks() -> Vec<BrowserSource> {
+    importer::find_browser_sources()
+}
+
+// ============================================
+// BOOKMARK EXPORT COMMANDS
+// ============================================
+
+/// Export bookmarks to a file the user picks in a save dialog.
+///
+/// Returns `None` when the dialog is cancelled.
+#[tauri::command]
+async fn export_bookmarks(
+    app: tauri::AppHandle,
+    format: ExportFormat,
+    filter: Option<ExportFilter>,
+) -> Result<Option<ExportSummary>, String> {
+    let (content, count) = exporter::export(&load_data(), format, &filter.unwrap_or_default())?;
+    
+    let (filter_name, extensions) = exporter::file_filter(format);
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter(filter_name, extensions)
+        .set_file_name(exporter::default_file_name(format))
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file.into_path().map_err(|e| format!("Invalid export path: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write export: {}", e))?;
+    
+    Ok(Some(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        format,
+        bookmarks: count,
+    }))
+}
+
+// ============================================
+// SMART COLLECTION COMMANDS
+// ============================================
+
+/// Bookmark ids matching a query, for previewing it while it is edited
+#[tauri::command]
+fn preview_smart_query(query: String) -> Result<Vec<String>, String> {
+    let query = smart::parse(&query)?;
+    Ok(smart::evaluate(&load_data(), &query, chrono::Utc::now()))
+}
+
+#[tauri::command]
+fn create_smart_collection(name: String, query: String) -> Result<AppData, String> {
+    smart::parse(&query)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A smart collection needs a name".to_string());
+    }
+    let (_vault, mut data) = lock_data()?;
+    data.collections.push(Collection {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        icon: "filter".to_string(),
+        is_custom: Some(true),
+        query: Some(query.trim().to_string()),
+    });
+    commit_data(data, "Create smart collection")
+}
+
+#[tauri::command]
+fn update_smart_collection(collection_id: String, name: Option<String>, query: Option<String>) -> Result<AppData, String> {
+    if let Some(query) = &query {
+        smart::parse(query)?;
+    }
+    let (_vault, mut data) = lock_data()?;
+    let collection = data
+        .collections
+        .iter_mut()
+        .find(|c| c.id == collection_id && c.query.is_some())
+        .ok_or_else(|| format!("Smart collection not found: {}", collection_id))?;
+    if let Some(name) = name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        collection.name = name.to_string();
+    }
+    if let Some(query) = query {
+        collection.query = Some(query.trim().to_string());
+    }
+    commit_data(data, "Edit smart collection")
+}
+
+/// Current members of every smart collection.
+///
+/// Membership is never stored; the frontend calls this again whenever
+/// bookmarks change so the lists stay live.
+#[tauri::command]
+fn get_smart_collections() -> Vec<SmartCollectionMatches> {
+    smart::evaluate_all(&load_data(), chrono::Utc::now())
+}
+
+// ============================================
+// BOOKMARK FOLDER COMMANDS
+// ============================================
+
+/// Folder tree for the sidebar, limited to one collection if given
+#[tauri::command]
+fn get_bookmark_folder_tree(collection_id: Option<String>) -> Vec<FolderNode> {
+    folders::tree(&load_data(), collection_id.as_deref())
+}
+
+/// Move a folder into another folder, or to the top level when `parent_id`
+/// is `None` (optionally into a different collection)
+#[tauri::command]
+fn move_bookmark_folder(folder_id: String, parent_id: Option<String>, collection_id: Option<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    folders::move_folder(&mut data, &folder_id, parent_id.as_deref(), collection_id.as_deref())?;
+    commit_data(data, "Move folder")
+}
+
+#[tauri::command]
+fn delete_bookmark_folder(folder_id: String, mode: DeleteMode) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    folders::delete_folder(&mut data, &folder_id, mode)?;
+    commit_data(data, "Delete folder")
+}
+
+// ============================================
+// NOTEBOOK COMMANDS
+// ============================================
+
+/// Notebook tree with note counts, plus note counts per tag
+#[tauri::command]
+fn get_notebook_tree() -> NotebookTree {
+    notebooks::tree(&load_data())
+}
+
+#[tauri::command]
+fn create_notebook(name: String, parent_id: Option<String>, color: Option<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    notebooks::create(&mut data, &name, parent_id.as_deref(), color, &chrono::Utc::now().to_rfc3339())?;
+    commit_data(data, "Create notebook")
+}
+
+#[tauri::command]
+fn update_notebook(notebook_id: String, name: Option<String>, color: Option<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    notebooks::update(&mut data, &notebook_id, name.as_deref(), color)?;
+    commit_data(data, "Edit notebook")
+}
+
+/// Move a notebook into another notebook, or to the top level when
+/// `parent_id` is `None`
+#[tauri::command]
+fn move_notebook(notebook_id: String, parent_id: Option<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    notebooks::move_notebook(&mut data, &notebook_id, parent_id.as_deref())?;
+    commit_data(data, "Move notebook")
+}
+
+/// Delete a notebook; its notes are kept (see `notebooks::delete`)
+#[tauri::command]
+fn delete_notebook(notebook_id: String, mode: DeleteMode) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    notebooks::delete(&mut data, &notebook_id, mode)?;
+    commit_data(data, "Delete notebook")
+}
+
+/// Move notes into a notebook, or out of any notebook when `notebook_id` is `None`
+#[tauri::command]
+fn move_notes(note_ids: Vec<String>, notebook_id: Option<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    let moved = notebooks::move_notes(&mut data, &note_ids, notebook_id.as_deref(), &chrono::Utc::now().to_rfc3339())?;
+    if moved == 0 {
+        return Ok(data);
+    }
+    commit_data(data, "Move notes")
+}
+
+/// Add tags by name (creating custom tags as needed) and remove tags by key
+#[tauri::command]
+fn tag_notes(note_ids: Vec<String>, add: Vec<String>, remove: Vec<String>) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    let changed = notebooks::tag_notes(&mut data, &note_ids, &add, &remove, &chrono::Utc::now().to_rfc3339());
+    if changed == 0 {
+        return Ok(data);
+    }
+    commit_data(data, "Tag notes")
+}
+
+#[tauri::command]
+fn filter_notes(filter: NoteFilter) -> Vec<Note> {
+    notebooks::filter(&load_data(), &filter)
+}
+
+// ============================================
+// SEARCH COMMANDS
+// ============================================
+
+/// Ranked full-text search across cards, bookmarks and notes
+#[tauri::command]
+fn search(query: String, options: Option<SearchOptions>) -> Vec<SearchHit> {
+    search::search(load_data, &query, &options.unwrap_or_default())
+}
+
+// ============================================
+// QUICK CAPTURE COMMANDS
+// ============================================
+
+/// Show the quick capture window, creating it on first use, or hide it if
+/// it is already showing
+fn toggle_capture_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW) {
+        if window.is_visible()? {
+            window.hide()?;
+        } else {
+            window.show()?;
+            window.set_focus()?;
+        }
+        return Ok(());
+    }
+    
+    tauri::WebviewWindowBuilder::new(app, CAPTURE_WINDOW, tauri::WebviewUrl::App("index.html#capture".into()))
+        .title("Quick Capture")
+        .inner_size(640.0, 160.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build()?;
+    Ok(())
+}
+
+/// Where capture input would go, for showing while it is typed
+#[tauri::command]
+fn preview_quick_capture(input: String) -> Result<CapturePlan, String> {
+    capture::plan(&load_data(), &input)
+}
+
+/// Create a card, note or bookmark from capture input.
+///
+/// Bookmarks without a title get the page's title and description. The main
+/// window is told through a `quick-capture` event so it can reload.
+#[tauri::command]
+async fn quick_capture(app: tauri::AppHandle, input: String) -> Result<CaptureResult, String> {
+    let plan = capture::plan(&load_data(), &input)?;
+    
+    let metadata = match (&plan.url, plan.kind) {
+        (Some(url), capture::CaptureKind::Bookmark) => {
+            let client = metadata::build_client()?;
+            metadata::fetch_metadata(&client, url)
+                .await
+                .map_err(|e| log::warn!("Metadata fetch failed for {}: {}", url, e))
+                .ok()
+        }
+        _ => None,
+    };
+    
+    // Reload in case the data changed while the page was fetched
+    let (_vault, mut data) = lock_data()?;
+    let (id, already_existed) = capture::apply(&mut data, &plan, metadata, &chrono::Utc::now().to_rfc3339())?;
+    let data = if already_existed {
+        data
+    } else {
+        commit_data(data, "Quick capture")?
+    };
+    
+    if let Err(e) = app.emit("quick-capture", &id) {
+        log::warn!("Failed to notify windows of quick capture: {}", e);
+    }
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW) {
+        window.hide().ok();
+    }
+    Ok(CaptureResult { data, kind: plan.kind, id, already_existed })
+}
+
+#[tauri::command]
+fn hide_quick_capture(app: tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW) {
+        window.hide().ok();
+    }
+}
+
+// ============================================
+// LINK COMMANDS
+// ============================================
+
+/// Notes, cards and bookmarks that link to a note, card or bookmark
+#[tauri::command]
+fn get_backlinks(kind: search::DocKind, id: String) -> Vec<Backlink> {
+    links::backlinks(&load_data(), kind, &id)
+}
+
+/// Wiki-links whose note, card or bookmark does not exist
+#[tauri::command]
+fn get_unresolved_links() -> Vec<UnresolvedLink> {
+    links::unresolved(&load_data())
+}
+
+/// Rename a note and rewrite every `[[title]]` link that points at it
+#[tauri::command]
+fn rename_note(note_id: String, title: String) -> Result<RenameResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let rewritten = links::rename_note(&mut data, &note_id, &title, &chrono::Utc::now().to_rfc3339())?;
+    let data = commit_data(data, "Rename note")?;
+    Ok(RenameResult { data, rewritten })
+}
+
+// ============================================
+// ATTACHMENT COMMANDS
+// ============================================
+
+/// Store pasted or dropped bytes (base64) and return the attachment.
+///
+/// Nothing refers to the file yet; the caller adds it to a note or card,
+/// usually as `![name](attachment:<file>)` plus an `attachments` entry.
+#[tauri::command]
+fn store_attachment(name: String, mime_type: Option<String>, data: String) -> Result<Attachment, String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Invalid attachment data: {}", e))?;
+    attachments::store_bytes(
+        &attachments::get_attachments_dir(&get_data_dir()),
+        &bytes,
+        &name,
+        mime_type.as_deref(),
+        &chrono::Utc::now().to_rfc3339(),
+    )
+}
+
+/// Attach a file from disk to a note or card, asking for one if `path` is
+/// not given. Returns `None` when the file dialog is cancelled.
+#[tauri::command]
+async fn attach_file(app: tauri::AppHandle, kind: search::DocKind, id: String, path: Option<String>) -> Result<Option<AttachResult>, String> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let Some(file) = app.dialog().file().set_title("Attach a file").blocking_pick_file() else {
+                return Ok(None);
+            };
+            file.into_path().map_err(|e| format!("Invalid file: {}", e))?
+        }
+    };
+    let now = chrono::Utc::now().to_rfc3339();
+    let attachment = attachments::store_file(&attachments::get_attachments_dir(&get_data_dir()), &path, &now)?;
+    let (_vault, mut data) = lock_data()?;
+    attachments::attach(&mut data, kind, &id, &attachment, &now)?;
+    let data = commit_data(data, "Attach file")?;
+    Ok(Some(AttachResult { data, attachment }))
+}
+
+/// Remove an attachment from a note or card
+#[tauri::command]
+fn remove_attachment(kind: search::DocKind, id: String, file: String) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    attachments::detach(&mut data, kind, &id, &file, &chrono::Utc::now().to_rfc3339())?;
+    commit_data(data, "Remove attachment")
+}
+
+/// Prefix to put in front of an attachment's `file` to load it in the webview
+#[tauri::command]
+fn get_attachment_base_url() -> String {
+    asset_cache::base_url(attachments::ATTACHMENT_SCHEME)
+}
+
+/// Delete stored attachments nothing has used for a while
+#[tauri::command]
+fn gc_attachments() -> Result<AssetGcReport, String> {
+    let data = read_data_file()?;
+    Ok(attachments::collect_garbage(&attachments::get_attachments_dir(&get_data_dir()), &data))
+}
+
+// ============================================
+// REVISION COMMANDS
+// ============================================
+
+/// Saved revisions of a note's content or a card's description, newest first
+#[tauri::command]
+fn list_revisions(kind: RevisionKind, id: String) -> Result<Vec<RevisionInfo>, String> {
+    revisions::list(&get_data_dir(), &load_data(), kind, &id)
+}
+
+/// Line diff between two revisions; a missing revision id means the
+/// current text
+#[tauri::command]
+fn diff_revisions(kind: RevisionKind, id: String, from: Option<String>, to: Option<String>) -> Result<RevisionDiff, String> {
+    let data_dir = get_data_dir();
+    let data = load_data();
+    let text_of = |revision: &Option<String>| match revision {
+        Some(revision_id) => revisions::text(&data_dir, kind, &id, revision_id),
+        None => revisions::current_text(&data, kind, &id),
+    };
+    Ok(revisions::diff(&text_of(&from)?, &text_of(&to)?))
+}
+
+/// Put an earlier revision back; the restore itself becomes a new revision
+#[tauri::command]
+fn restore_revision(kind: RevisionKind, id: String, revision_id: String) -> Result<AppData, String> {
+    let text = revisions::text(&get_data_dir(), kind, &id, &revision_id)?;
+    let (_vault, mut data) = lock_data()?;
+    revisions::restore(&mut data, kind, &id, text, &chrono::Utc::now().to_rfc3339())?;
+    commit_data(data, "Restore revision")
+}
+
+// ============================================
+// CONVERT COMMANDS
+// ============================================
+
+/// Turn a note into a card; task list items become checklist items.
+/// The note is removed unless `keep_original` is set, in which case the
+/// note and the card link to each other.
+#[tauri::command]
+fn convert_note_to_card(note_id: String, board_id: String, column_id: Option<String>, keep_original: bool) -> Result<ConvertResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let id = convert::note_to_card(&mut data, &note_id, &board_id, column_id.as_deref(), keep_original, &chrono::Utc::now().to_rfc3339())?;
+    let data = commit_data(data, "Convert note to card")?;
+    Ok(ConvertResult { data, id })
+}
+
+/// Turn a card into a note; its checklist becomes a task list
+#[tauri::command]
+fn convert_card_to_note(card_id: String, notebook_id: Option<String>, keep_original: bool) -> Result<ConvertResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let id = convert::card_to_note(&mut data, &card_id, notebook_id.as_deref(), keep_original, &chrono::Utc::now().to_rfc3339())?;
+    let data = commit_data(data, "Convert card to note")?;
+    Ok(ConvertResult { data, id })
+}
+
+/// Bookmark every URL in a note or card description; the bookmarks link
+/// back to it
+#[tauri::command]
+fn extract_bookmarks(kind: search::DocKind, id: String) -> Result<ExtractResult, String> {
+    let (_vault, mut data) = lock_data()?;
+    let (created, existing) = convert::extract_bookmarks(&mut data, kind, &id, &chrono::Utc::now().to_rfc3339())?;
+    let data = commit_data(data, "Extract bookmarks")?;
+    Ok(ExtractResult { data, created, existing })
+}
+
+// ============================================
+// TASK LIST COMMANDS
+// ============================================
+
+/// Turn Markdown task list sync on or off for a card or note. Turning it on
+/// merges the task lines and the checklist.
+#[tauri::command]
+fn set_task_sync(kind: search::DocKind, id: String, enabled: bool) -> Result<AppData, String> {
+    let (_vault, mut data) = lock_data()?;
+    tasks::set_sync(&mut data, kind, &id, enabled)?;
+    commit_data(data, if enabled { "Sync task list" } else { "Stop syncing task list" })
+}
+
+#[tauri::command]
+fn get_task_progress(kind: search::DocKind, id: String) -> Result<TaskProgress, String> {
+    tasks::item_progress(&load_data(), kind, &id)
+}
+
+// ============================================
+// DAILY NOTE COMMANDS
+// ============================================
+
+/// The daily note for `date` (`YYYY-MM-DD`, default today), created from the
+/// journal template if it does not exist yet
+#[tauri::command]
+fn get_daily_note(date: Option<String>) -> Result<DailyNoteResult, String> {
+    let date = match date {
+        Some(date) => journal::parse_date(&date)?,
+        None => journal::today(),
+    };
+    let settings = journal::load_settings(&get_data_dir());
+    let (_vault, mut data) = lock_data()?;
+    let (note_id, created, changed) = journal::get_or_create(&mut data, &settings, date, &chrono::Utc::now().to_rfc3339())?;
+    let data = if changed {
+        commit_data(data, if created { "Create daily note" } else { "Update daily rollup" })?
+    } else {
+        data
+    };
+    let note = data
+        .notes
+        .iter()
+        .find(|n| n.id == note_id)
+        .cloned()
+        .ok_or_else(|| format!("Note not found: {}", note_id))?;
+    Ok(DailyNoteResult { data, note, created })
+}
+
+/// Days that have a daily note, optionally limited to a date range
+#[tauri::command]
+fn list_daily_notes(from: Option<String>, to: Option<String>) -> Result<Vec<JournalEntry>, String> {
+    let from = from.as_deref().map(journal::parse_date).transpose()?;
+    let to = to.as_deref().map(journal::parse_date).transpose()?;
+    Ok(journal::entries(&load_data(), from, to))
+}
+
+#[tauri::command]
+fn get_journal_settings() -> JournalSettings {
+    journal::load_settings(&get_data_dir())
+}
+
+#[tauri::command]
+fn set_journal_settings(settings: JournalSettings) -> Result<JournalSettings, String> {
+    journal::save_settings(&get_data_dir(), &settings)?;
+    Ok(settings)
+}
+
+// ============================================
+// VAULT COMMANDS
+// ============================================
+
+/// Sync notes with the vault folder; `None` when vault mode is off
+fn run_vault_sync() -> Result<Option<VaultSyncResult>, String> {
+    let _vault = lock_vault();
+    let data_dir = get_data_dir();
+    let mut state = vault::load_state(&data_dir);
+    let Some(path) = state.path.clone() else {
+        return Ok(None);
+    };
+    
+    // Syncing the default data would delete every file in the vault
+    let mut data = read_data_file()?;
+    let report = vault::sync(
+        std::path::Path::new(&path),
+        &attachments::get_attachments_dir(&data_dir),
+        &mut data,
+        &mut state,
+        &chrono::Utc::now().to_rfc3339(),
+    )?;
+    
+    let data = if report.changed_notes() {
+        commit_data(data, "Sync vault")?
+    } else {
+        data
+    };
+    // Only once the notes are saved, so the state never covers notes that were lost
+    vault::save_state(&data_dir, &state)?;
+    Ok(Some(VaultSyncResult { data, report }))
+}
+
+/// Poll the vault for changes on either side and sync when something moved.
+///
+/// Runs for the life of the app; windows get a `vault-sync` event when notes
+/// changed so they can reload.
+fn watch_vault(app: tauri::AppHandle) {
+    let mut last_signature = String::new();
+    loop {
+        std::thread::sleep(VAULT_POLL_INTERVAL);
+        let Some(path) = vault::load_state(&get_data_dir()).path else {
+            last_signature.clear();
+            continue;
+        };
+        let path = std::path::Path::new(&path);
+        let signature = vault::signature(path, &load_data());
+        if signature == last_signature {
+            continue;
+        }
+        
+        match run_vault_sync() {
+            Ok(Some(result)) => {
+                last_signature = vault::signature(path, &result.data);
+                if result.report.changed_notes() {
+                    if let Err(e) = app.emit("vault-sync", &result.report) {
+                        log::warn!("Failed to notify windows of vault sync: {}", e);
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                // Wait for something to change before trying again
+                log::warn!("Vault sync failed: {}", e);
+                last_signature = signature;
+            }
+        }
+    }
+}
+
+#[tauri::command]
+fn get_vault_status() -> VaultStatus {
+    vault::status(&vault::load_state(&get_data_dir()))
+}
+
+/// Turn on vault mode for a folder, asking for one if `path` is not given.
+///
+/// Existing notes are written out and Markdown files already in the folder
+/// become notes. Returns `None` when the folder dialog is cancelled.
+#[tauri::command]
+async fn enable_vault(app: tauri::AppHandle, path: Option<String>) -> Result<Option<VaultSyncResult>, String> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let Some(folder) = app.dialog().file().set_title("Choose a folder for your notes").blocking_pick_folder() else {
+                return Ok(None);
+            };
+            folder.into_path().map_err(|e| format!("Invalid vault folder: {}", e))?
+        }
+    };
+    if !path.is_dir() {
+        return Err(format!("Vault folder not found: {}", path.display()));
+    }
+    
+    let data_dir = get_data_dir();
+    let mut state = vault::load_state(&data_dir);
+    if state.path.as_deref() != Some(path.to_string_lossy().as_ref()) {
+        // What was synced with another folder says nothing about this one
+        state.files.clear();
+        state.notes.clear();
+        state.last_sync = None;
+    }
+    state.path = Some(path.to_string_lossy().to_string());
+    vault::save_state(&data_dir, &state)?;
+    run_vault_sync()
+}
+
+/// Stop mirroring notes; the files are left where they are
+#[tauri::command]
+fn disable_vault() -> Result<VaultStatus, String> {
+    let data_dir = get_data_dir();
+    let state = vault::VaultState::default();
+    vault::save_state(&data_dir, &state)?;
+    Ok(vault::status(&state))
+}
+
+#[tauri::command]
+fn sync_vault() -> Result<VaultSyncResult, String> {
+    run_vault_sync()?.ok_or_else(|| "Vault mode is off".to_string())
+}
+
+// ============================================
+// CLOUDKIT SYNC COMMANDS (macOS only)
+// ============================================
+
+/// Check if iCloud account is available
+#[tauri::command]
+#[cfg(target_os = "macos")]
+fn check_icloud_account() -> bool {
+    CloudKit::check_account()
+}
+
+/// Get detailed iCloud account status (for better UI + debugging)
+#[tauri::command]
+#[cfg(target_os = "macos")]
+fn get_icloud_account_status() -> AccountStatusJson {
+    CloudKit::get_account_status().into()
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "macos"))]
+fn check_icloud_account() -> bool {
+    false
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "macos"))]
+fn get_icloud_account_status() -> serde_json::Value {
+    serde_json::json!({
+        "available": false,
+        "status": "offline",
+        "error": "CloudKit is only available on macOS"
+    })
+}
+
+/// Get current sync status
+#[tauri::command]
+#[cfg(target_os = "macos")]
+fn get_sync_status() -> SyncStatusJson {
+    CloudKit::get_status().into()
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "macos"))]
+fn get_sync_status() -> serde_json::Value {
+    serde_json::json!({
+        "status": "offline",
+        "error": "CloudKit is only available on macOS"
+    })
+}
+
+/// Carry attachment files along with a CloudKit data sync.
+///
+/// Files used by `local` are uploaded and files used by the data iCloud sent
+/// back are downloaded. Failures are only logged; the next sync retries.
+#[cfg(target_os = "macos")]
+async fn sync_cloud_attachments(local: Option<&SyncData>, result: &SyncResultJson) {
+    if !result.success {
+        return;
+    }
+    let local = local
+        .map(|d| attachments::referenced_in(&d.boards, &d.notes))
+        .unwrap_or_default();
+    let remote = result
+        .data
+        .as_deref()
+        .and_then(|json| serde_json::from_str::<SyncData>(json).ok())
+        .map(|d| attachments::referenced_in(&d.boards, &d.notes))
+        .unwrap_or_default();
+    if local.is_empty() && remote.is_empty() {
+        return;
+    }
+    
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        attachments::sync_cloud(&get_data_dir(), &local, &remote, CloudKit::upload_attachment, CloudKit::download_attachment)
+    });
+    match task.await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::warn!("Failed to sync attachments with iCloud: {}", e),
+        Err(e) => log::warn!("Attachment sync task failed: {}", e),
+    }
+}
+
+/// Sync data with iCloud - performs bidirectional sync with last-write-wins conflict resolution
+#[tauri::command]
+#[cfg(target_os = "macos")]
+async fn sync_to_cloud(data: SyncData) -> Result<SyncResultJson, String> {
+    log::debug!("Starting iCloud sync...");
+
+    // Serialize the data to JSON
+    let json_data = serde_json::to_string(&data)
+        .map_err(|e| format!("Failed to serialize data: {}", e))?;
+    let last_modified = data.last_modified.clone();
+
+    // CloudKit FFI blocks; run it on a blocking thread to avoid UI / event loop stalls.
+    let result = tauri::async_runtime::spawn_blocking(move || CloudKit::sync(&json_data, &last_modified))
+        .await
+        .map_err(|e| format!("Sync task failed: {}", e))?;
+
+    if !result.success {
+        log::error!("iCloud sync failed: {:?}", result.error);
+    }
+
+    let result: SyncResultJson = result.into();
+    sync_cloud_attachments(Some(&data), &result).await;
+    Ok(result)
+}
+
+/// Push local data to iCloud (upload only).
+///
+/// This avoids an extra fetch that `sync_to_cloud` performs, and only falls back
+/// to a pull if the server reports newer data (CAS conflict).
+#[tauri::command]
+#[cfg(target_os = "macos")]
+async fn push_to_cloud(data: SyncData) -> Result<SyncResultJson, String> {
+    log::debug!("Pushing local data to iCloud...");
+
+    let json_data = serde_json::to_string(&data)
+        .map_err(|e| format!("Failed to serialize data: {}", e))?;
+    let last_modified = data.last_modified.clone();
+
+    let result = tauri::async_runtime::spawn_blocking(move || CloudKit::push(&json_data, &last_modified))
+        .await
+        .map_err(|e| format!("Push task failed: {}", e))?;
+
+    if result.success {
+        let result: SyncResultJson = result.into();
+        sync_cloud_attachments(Some(&data), &result).await;
+        return Ok(result);
+    }
+
+    // If the server has newer data, pull it so the frontend can update local state.
+    if let Some(err) = result.error.as_deref() {
+        let err_lc = err.to_lowercase();
+        if err_lc.contains("cas failed") || err_lc.contains("server has newer data") {
+            log::debug!("Push conflicted; pulling latest remote data...");
+            let pull = tauri::async_runtime::spawn_blocking(|| CloudKit::pull())
+                .await
+                .map_err(|e| format!("Pull task failed: {}", e))?;
+            let pull: SyncResultJson = pull.into();
+            sync_cloud_attachments(None, &pull).await;
+            return Ok(pull);
+        }
+    }
+
+    Ok(result.into())
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "macos"))]
+fn push_to_cloud(_data: SyncData) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!({
+        "success": false,
+        "shouldUpdateLocal": false,
+        "error": "CloudKit is only available on macOS",
+        "data": null,
+        "remoteLastModified": null
+    }))
+}
+
+#[tauri::command]
+#[cfg(not(target_os = "macos"))]
+fn sync_to_cloud(_data: SyncData) -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!({
+        "success": false,
+        "shouldUpdateLocal": false,
+        "error": "CloudKit is only available on macOS",
+        "data": null,
+        "remoteLastModified": null
+    }))
+}
+
+/// Pull data from iCloud
+#[tauri::command]
+#[cfg(target_os = "macos")]
+async fn sync_from_cloud() -> Result<SyncResultJson, String> {
+    log::debug!("Pulling data from iCloud...");
+
+    let result = tauri::async_runtime::spawn_blocking(|| CloudKit::pull())
+        .await
+        .map_err(|e| format!("Pull task failed: {}", e))?;
+
+    if !result.success {
+        log::error!("Failed to pull from iCloud: {:?}", result.error);
+    }
+
+    let result: SyncResultJson = result.into();
+    sync_cloud_attachments(None, &result).await;
+    Ok(result)
 }
 
 #[tauri::command]
@@ -638,6 +2109,13 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_dialog::init())
+        .register_uri_scheme_protocol(asset_cache::ASSET_SCHEME, |_ctx, request| {
+            asset_cache::serve(&asset_cache::get_assets_dir(&get_data_dir()), &request)
+        })
+        .register_uri_scheme_protocol(attachments::ATTACHMENT_SCHEME, |_ctx, request| {
+            attachments::serve(&attachments::get_attachments_dir(&get_data_dir()), &request)
+        })
         .setup(|app| {
             // Enable logging in both debug and release builds for troubleshooting
             app.handle().plugin(
@@ -653,6 +2131,50 @@ pub fn run() {
             // Log the data directory location
             log::info!("Data directory: {:?}", get_data_dir());
             
+            // Quick capture from anywhere, even when Carbon is in the background
+            app.handle().plugin(
+                tauri_plugin_global_shortcut::Builder::new()
+                    .with_handler(|app, _shortcut, event| {
+                        if event.state() == ShortcutState::Pressed {
+                            if let Err(e) = toggle_capture_window(app) {
+                                log::error!("Failed to open quick capture: {}", e);
+                            }
+                        }
+                    })
+                    .build(),
+            )?;
+            if let Err(e) = app.global_shortcut().register(CAPTURE_SHORTCUT) {
+                // Usually another app already owns the shortcut
+                log::warn!("Failed to register quick capture shortcut {}: {}", CAPTURE_SHORTCUT, e);
+            }
+            
+            // Keep the notes vault in sync with edits made in other editors
+            let handle = app.handle().clone();
+            std::thread::spawn(move || watch_vault(handle));
+            
+            // Drop cached images, snapshots and attachments nothing uses any more
+            std::thread::spawn(|| {
+                let data = match read_data_file() {
+                    Ok(data) => data,
+                    Err(e) => {
+                        log::warn!("Skipping garbage collection: {}", e);
+                        return;
+                    }
+                };
+                let report = asset_cache::collect_garbage(&asset_cache::get_assets_dir(&get_data_dir()), &data);
+                if report.removed_files > 0 {
+                    log::info!("Removed {} unused cached assets ({} bytes)", report.removed_files, report.freed_bytes);
+                }
+                let removed = snapshot::collect_garbage(&snapshot::get_snapshots_dir(&get_data_dir()), &data);
+                if removed > 0 {
+                    log::info!("Removed {} unused page snapshots", removed);
+                }
+                let report = attachments::collect_garbage(&attachments::get_attachments_dir(&get_data_dir()), &data);
+                if report.removed_files > 0 {
+                    log::info!("Removed {} unused attachments ({} bytes)", report.removed_files, report.freed_bytes);
+                }
+            });
+            
             // Initialize CloudKit on macOS
             #[cfg(all(target_os = "macos", not(debug_assertions)))]
             {
@@ -672,6 +2194,111 @@ pub fn run() {
             get_data_path,
             check_for_updates,
             install_update,
+            // Flow metrics
+            get_board_metrics,
+            // Card relations
+            set_card_relation,
+            remove_card_relation,
+            get_card_relations,
+            validate_card_relations,
+            get_blocked_cards,
+            get_critical_chain,
+            // Cross-board transfers
+            transfer_cards,
+            undo_transfer,
+            // Board templates
+            list_board_templates,
+            save_board_as_template,
+            create_board_from_template,
+            delete_board_template,
+            export_board_template,
+            import_board_template,
+            // Undo / redo
+            undo,
+            redo,
+            get_history,
+            clear_history,
+            // Bookmark metadata
+            fetch_bookmark_metadata,
+            // Asset cache
+            cache_bookmark_assets,
+            gc_asset_cache,
+            get_asset_base_url,
+            // Link checker
+            start_link_check,
+            get_link_check_progress,
+            cancel_link_check,
+            get_link_issues,
+            resolve_link_issues,
+            // Page snapshots
+            archive_bookmark_page,
+            get_reader_view,
+            delete_page_snapshot,
+            // Duplicate bookmarks
+            normalize_bookmark_url,
+            find_bookmark_duplicates,
+            get_duplicate_groups,
+            merge_duplicate_bookmarks,
+            // Bookmark import
+            import_bookmarks,
+            find_browser_bookmarks,
+            // Bookmark export
+            export_bookmarks,
+            // Smart collections
+            preview_smart_query,
+            create_smart_collection,
+            update_smart_collection,
+            get_smart_collections,
+            // Bookmark folders
+            get_bookmark_folder_tree,
+            move_bookmark_folder,
+            delete_bookmark_folder,
+            // Notebooks
+            get_notebook_tree,
+            create_notebook,
+            update_notebook,
+            move_notebook,
+            delete_notebook,
+            move_notes,
+            tag_notes,
+            filter_notes,
+            // Search
+            search,
+            // Quick capture
+            preview_quick_capture,
+            quick_capture,
+            hide_quick_capture,
+            // Links
+            get_backlinks,
+            get_unresolved_links,
+            rename_note,
+            // Attachments
+            store_attachment,
+            attach_file,
+            remove_attachment,
+            get_attachment_base_url,
+            gc_attachments,
+            // Revisions
+            list_revisions,
+            diff_revisions,
+            restore_revision,
+            // Convert
+            convert_note_to_card,
+            convert_card_to_note,
+            extract_bookmarks,
+            // Task lists
+            set_task_sync,
+            get_task_progress,
+            // Daily notes
+            get_daily_note,
+            list_daily_notes,
+            get_journal_settings,
+            set_journal_settings,
+            // Vault
+            get_vault_status,
+            enable_vault,
+            disable_vault,
+            sync_vault,
             // CloudKit sync commands
             check_icloud_account,
             get_icloud_account_status,
@@ -682,6 +2309,12 @@ pub fn run() {
             init_cloudkit,
             delete_cloud_data
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Coalesced edits are only written with the next operation
+                history::flush(&get_data_dir());
+            }
+        });
 }
@@ -0,0 +1,337 @@
+//! Broken link checker for bookmarks
+//!
+//! Checks bookmark URLs with `HEAD` (falling back to `GET` for servers that
+//! reject it) under a concurrency limit, and records the outcome on each
+//! bookmark. The job runs in the background; its progress lives in a static
+//! so the frontend can poll it.
+
+use crate::{AppData, Bookmark};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use url::Url;
+
+const CONCURRENT_CHECKS: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkState {
+    Ok,
+    /// Reachable, but only after following redirects
+    Redirected,
+    /// The server says the page is gone (404, 410, ...)
+    Dead,
+    /// Network failure, timeout or server error; may be temporary
+    Error,
+}
+
+/// Result of the last check of a bookmark's URL
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkCheck {
+    pub state: LinkState,
+    /// HTTP status of the final response
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Where the URL ended up after redirects
+    #[serde(rename = "finalUrl", default)]
+    pub final_url: Option<String>,
+    #[serde(rename = "checkedAt")]
+    pub checked_at: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LinkCheckProgress {
+    pub running: bool,
+    pub total: usize,
+    pub checked: usize,
+    pub dead: usize,
+    pub redirected: usize,
+    pub errors: usize,
+    pub cancelled: bool,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<String>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<String>,
+}
+
+const IDLE: LinkCheckProgress = LinkCheckProgress {
+    running: false,
+    total: 0,
+    checked: 0,
+    dead: 0,
+    redirected: 0,
+    errors: 0,
+    cancelled: false,
+    started_at: None,
+    finished_at: None,
+};
+
+static PROGRESS: Mutex<LinkCheckProgress> = Mutex::new(IDLE);
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+fn with_progress<T>(f: impl FnOnce(&mut LinkCheckProgress) -> T) -> T {
+    let mut progress = PROGRESS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut progress)
+}
+
+pub fn progress() -> LinkCheckProgress {
+    with_progress(|p| p.clone())
+}
+
+/// Marks the job finished when dropped, so a panicking job does not stay
+/// "running" forever
+pub struct Job(());
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        finish(&chrono::Utc::now().to_rfc3339());
+    }
+}
+
+/// Mark a job as started; fails if one is already running.
+///
+/// The job runs until the returned `Job` is dropped.
+pub fn begin(total: usize, now: &str) -> Result<(LinkCheckProgress, Job), String> {
+    with_progress(|p| {
+        if p.running {
+            return Err("A link check is already running".to_string());
+        }
+        CANCEL.store(false, Ordering::SeqCst);
+        *p = LinkCheckProgress {
+            running: true,
+            total,
+            started_at: Some(now.to_string()),
+            ..IDLE
+        };
+        Ok((p.clone(), Job(())))
+    })
+}
+
+fn finish(now: &str) {
+    with_progress(|p| {
+        p.running = false;
+        p.cancelled = CANCEL.load(Ordering::SeqCst);
+        p.finished_at = Some(now.to_string());
+    })
+}
+
+/// Ask a running job to stop; bookmarks checked so far are still saved
+pub fn cancel() {
+    CANCEL.store(true, Ordering::SeqCst);
+}
+
+/// Bookmarks a job should check: the given ids, or every non-archived bookmark
+pub fn targets(bookmarks: &[Bookmark], only: Option<&HashSet<String>>) -> Vec<(String, String)> {
+    bookmarks
+        .iter()
+        .filter(|b| match only {
+            Some(ids) => ids.contains(&b.id),
+            None => !b.is_archived,
+        })
+        .filter(|b| b.url.starts_with("http://") || b.url.starts_with("https://"))
+        .map(|b| (b.id.clone(), b.url.clone()))
+        .collect()
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn classify(status: u16) -> LinkState {
+    match status {
+        200..=399 => LinkState::Ok,
+        // Auth walls and rate limits mean the page exists but we may not see it
+        401 | 403 | 407 | 429 => LinkState::Error,
+        400..=499 => LinkState::Dead,
+        _ => LinkState::Error,
+    }
+}
+
+/// Some servers reject or mishandle `HEAD`; retry those with `GET`
+fn retry_with_get(status: u16) -> bool {
+    matches!(status, 400 | 403 | 404 | 405 | 406 | 501)
+}
+
+async fn send(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    url: &str,
+) -> Result<reqwest::Response, reqwest::Error> {
+    client.request(method, url).send().await
+}
+
+fn describe_error(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        "Timed out".to_string()
+    } else if e.is_redirect() {
+        "Too many redirects".to_string()
+    } else if e.is_connect() {
+        "Could not connect".to_string()
+    } else {
+        e.to_string()
+    }
+}
+
+/// Check a single URL
+pub async fn check_url(client: &reqwest::Client, url: &str, now: &str) -> LinkCheck {
+    let head = send(client, reqwest::Method::HEAD, url).await;
+    let response = match head {
+        Ok(r) if !retry_with_get(r.status().as_u16()) => Ok(r),
+        // The body is never read, so a GET costs little more than the headers
+        _ => send(client, reqwest::Method::GET, url).await,
+    };
+
+    match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let final_url = response.url().to_string();
+            let mut state = classify(status);
+            if state == LinkState::Ok && !same_url(url, &final_url) {
+                state = LinkState::Redirected;
+            }
+            LinkCheck {
+                state,
+                status: Some(status),
+                final_url: Some(final_url),
+                checked_at: now.to_string(),
+                error: None,
+            }
+        }
+        Err(e) => LinkCheck {
+            state: LinkState::Error,
+            status: None,
+            final_url: None,
+            checked_at: now.to_string(),
+            error: Some(describe_error(&e)),
+        },
+    }
+}
+
+/// Check `(bookmark id, url)` pairs, updating the shared progress as they finish.
+///
+/// Stops early when the job is cancelled and returns what was checked.
+pub async fn check_links(
+    client: &reqwest::Client,
+    targets: Vec<(String, String)>,
+) -> Vec<(String, String, LinkCheck)> {
+    stream::iter(targets)
+        .take_while(|_| std::future::ready(!CANCEL.load(Ordering::SeqCst)))
+        .map(|(id, url)| async move {
+            let now = chrono::Utc::now().to_rfc3339();
+            let check = check_url(client, &url, &now).await;
+            with_progress(|p| {
+                p.checked += 1;
+                match check.state {
+                    LinkState::Dead => p.dead += 1,
+                    LinkState::Redirected => p.redirected += 1,
+                    LinkState::Error => p.errors += 1,
+                    LinkState::Ok => {}
+                }
+            });
+            (id, url, check)
+        })
+        .buffer_unordered(CONCURRENT_CHECKS)
+        .collect()
+        .await
+}
+
+/// Store check results on the bookmarks they belong to.
+///
+/// Results are skipped if the bookmark was deleted or its URL edited while
+/// the job ran. Returns the number of bookmarks updated.
+pub fn apply_results(data: &mut AppData, results: Vec<(String, String, LinkCheck)>) -> usize {
+    let mut updated = 0;
+    for (id, url, check) in results {
+        if let Some(bookmark) = data
+            .bookmarks
+            .iter_mut()
+            .find(|b| b.id == id && b.url == url)
+        {
+            bookmark.link_check = Some(check);
+            updated += 1;
+        }
+    }
+    updated
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LinkIssue {
+    #[serde(rename = "bookmarkId")]
+    pub bookmark_id: String,
+    pub title: String,
+    pub url: String,
+    #[serde(rename = "isArchived")]
+    pub is_archived: bool,
+    pub check: LinkCheck,
+}
+
+/// Bookmarks whose last check was not OK, optionally limited to one state
+pub fn link_issues(data: &AppData, state: Option<LinkState>) -> Vec<LinkIssue> {
+    data.bookmarks
+        .iter()
+        .filter_map(|b| {
+            let check = b.link_check.as_ref()?;
+            let wanted = match state {
+                Some(state) => check.state == state,
+                None => check.state != LinkState::Ok,
+            };
+            wanted.then(|| LinkIssue {
+                bookmark_id: b.id.clone(),
+                title: b.title.clone(),
+                url: b.url.clone(),
+                is_archived: b.is_archived,
+                check: check.clone(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkAction {
+    /// Replace the URL with the redirect target
+    UpdateToRedirect,
+    Archive,
+}
+
+/// Apply a bulk action to the given bookmarks; returns how many changed
+pub fn apply_action(data: &mut AppData, bookmark_ids: &[String], action: LinkAction) -> usize {
+    let ids: HashSet<&str> = bookmark_ids.iter().map(String::as_str).collect();
+    let mut changed = 0;
+    for bookmark in data
+        .bookmarks
+        .iter_mut()
+        .filter(|b| ids.contains(b.id.as_str()))
+    {
+        match action {
+            LinkAction::UpdateToRedirect => {
+                let Some(check) = bookmark.link_check.as_mut() else {
+                    continue;
+                };
+                let Some(target) = check.final_url.clone() else {
+                    continue;
+                };
+                if check.state != LinkState::Redirected || same_url(&bookmark.url, &target) {
+                    continue;
+                }
+                bookmark.url = target;
+                check.state = LinkState::Ok;
+                changed += 1;
+            }
+            LinkAction::Archive => {
+                if !bookmark.is_archived {
+                    bookmark.is_archived = true;
+                    changed += 1;
+                }
+            }
+        }
+    }
+    changed
+}
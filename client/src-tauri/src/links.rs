@@ -0,0 +1,652 @@
+//! Wiki-links between notes, cards and bookmarks
+//!
+//! Note content and card and bookmark descriptions can reference other
+//! items with `[[Note title]]`, `[[card:ID]]`, `[[bookmark:ID]]` or
+//! `[[note:ID]]`, and any of these can carry display text after a `|`.
+//! Links inside code spans and fenced code blocks are ignored.
+//!
+//! The parsed links are kept in an in-memory graph that `update` refreshes
+//! whenever data is saved, re-parsing only text that changed. Titles are
+//! resolved when the graph is queried, so renaming a note never leaves the
+//! graph stale.
+
+use crate::search::DocKind;
+use crate::AppData;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::Mutex;
+
+/// Longest line of context returned with a link
+const CONTEXT_LENGTH: usize = 160;
+
+static GRAPH: Mutex<Option<LinkGraph>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Target {
+    Title(String),
+    Id(DocKind, String),
+}
+
+/// A `[[...]]` found in some text
+#[derive(Debug, Clone)]
+struct RawLink {
+    /// Byte range of the whole link, brackets included
+    range: Range<usize>,
+    target: Target,
+    /// Text after the `|`, if any
+    alias: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Link {
+    target: Target,
+    /// The line the link is on, for showing it in context
+    context: String,
+}
+
+#[derive(Debug)]
+struct Source {
+    title: String,
+    board_id: Option<String>,
+    fingerprint: u64,
+    links: Vec<Link>,
+}
+
+#[derive(Debug, Default)]
+struct LinkGraph {
+    sources: HashMap<(DocKind, String), Source>,
+}
+
+/// Something that links to the entity backlinks were asked for
+#[derive(Debug, Serialize, Clone)]
+pub struct Backlink {
+    #[serde(rename = "sourceKind")]
+    pub source_kind: DocKind,
+    #[serde(rename = "sourceId")]
+    pub source_id: String,
+    #[serde(rename = "sourceTitle")]
+    pub source_title: String,
+    /// Board of a linking card
+    #[serde(rename = "boardId")]
+    pub board_id: Option<String>,
+    pub context: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RenameResult {
+    pub data: AppData,
+    /// Notes and cards whose links were rewritten
+    pub rewritten: usize,
+}
+
+/// A link whose target does not exist
+#[derive(Debug, Serialize, Clone)]
+pub struct UnresolvedLink {
+    #[serde(rename = "sourceKind")]
+    pub source_kind: DocKind,
+    #[serde(rename = "sourceId")]
+    pub source_id: String,
+    #[serde(rename = "sourceTitle")]
+    pub source_title: String,
+    #[serde(rename = "boardId")]
+    pub board_id: Option<String>,
+    /// What the link says, e.g. `Meeting notes` or `card:1234`
+    pub target: String,
+    pub context: String,
+}
+
+// ============================================
+// Parsing
+// ============================================
+
+fn parse_target(inner: &str) -> Option<(Target, Option<String>)> {
+    let (target, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target.trim(), Some(alias.trim().to_string())),
+        None => (inner.trim(), None),
+    };
+    let alias = alias.filter(|a| !a.is_empty());
+    if target.is_empty() {
+        return None;
+    }
+    for (prefix, kind) in [
+        ("card:", DocKind::Card),
+        ("bookmark:", DocKind::Bookmark),
+        ("note:", DocKind::Note),
+    ] {
+        let id = target
+            .get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(prefix))
+            .map(|_| target[prefix.len()..].trim());
+        if let Some(id) = id.filter(|id| !id.is_empty()) {
+            return Some((Target::Id(kind, id.to_string()), alias));
+        }
+    }
+    Some((Target::Title(target.to_string()), alias))
+}
+
+/// Every link in `text`, skipping code spans and fenced code blocks
+fn find_links(text: &str) -> Vec<RawLink> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let offset = line_start;
+        line_start += line.len();
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut pos = 0;
+        while pos < line.len() {
+            let rest = &line[pos..];
+            if rest.starts_with('`') {
+                in_code = !in_code;
+                pos += 1;
+                continue;
+            }
+            if !in_code && rest.starts_with("[[") {
+                // Links do not span lines or nest
+                let end = rest[2..]
+                    .find([']', '[', '\n'])
+                    .map(|i| i + 2)
+                    .filter(|&i| rest[i..].starts_with("]]"));
+                if let Some(end) = end {
+                    if let Some((target, alias)) = parse_target(&rest[2..end]) {
+                        out.push(RawLink {
+                            range: offset + pos..offset + pos + end + 2,
+                            target,
+                            alias,
+                        });
+                    }
+                    pos += end + 2;
+                    continue;
+                }
+            }
+            pos += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    out
+}
+
+fn context(text: &str, range: &Range<usize>) -> String {
+    let start = text[..range.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let end = text[range.end..]
+        .find('\n')
+        .map(|i| range.end + i)
+        .unwrap_or(text.len());
+    let line = text[start..end].trim();
+    if line.chars().count() <= CONTEXT_LENGTH {
+        return line.to_string();
+    }
+    let short: String = line.chars().take(CONTEXT_LENGTH).collect();
+    format!("{}…", short.trim_end())
+}
+
+fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ============================================
+// Graph
+// ============================================
+
+/// Text that can contain links: (kind, id, title, board, text)
+fn sources(data: &AppData) -> Vec<(DocKind, &str, &str, Option<&str>, &str)> {
+    let mut out = Vec::new();
+    for note in &data.notes {
+        out.push((
+            DocKind::Note,
+            note.id.as_str(),
+            note.title.as_str(),
+            None,
+            note.content.as_str(),
+        ));
+    }
+    for board in &data.boards {
+        let cards = board
+            .columns
+            .iter()
+            .flat_map(|c| c.cards.iter())
+            .chain(board.archived_cards.iter());
+        for card in cards {
+            out.push((
+                DocKind::Card,
+                card.id.as_str(),
+                card.title.as_str(),
+                Some(board.id.as_str()),
+                card.description.as_str(),
+            ));
+        }
+    }
+    for bookmark in &data.bookmarks {
+        out.push((
+            DocKind::Bookmark,
+            bookmark.id.as_str(),
+            bookmark.title.as_str(),
+            None,
+            bookmark.description.as_str(),
+        ));
+    }
+    out
+}
+
+impl LinkGraph {
+    /// Bring the graph in line with `data`, re-parsing only changed text
+    fn sync(&mut self, data: &AppData) {
+        let mut present = HashSet::new();
+        for (kind, id, title, board_id, text) in sources(data) {
+            let key = (kind, id.to_string());
+            let hash = fingerprint(text);
+            let source = self.sources.entry(key.clone()).or_insert_with(|| Source {
+                title: String::new(),
+                board_id: None,
+                fingerprint: !hash,
+                links: vec![],
+            });
+            source.title = title.to_string();
+            source.board_id = board_id.map(str::to_string);
+            if source.fingerprint != hash {
+                source.fingerprint = hash;
+                source.links = find_links(text)
+                    .into_iter()
+                    .map(|link| Link {
+                        context: context(text, &link.range),
+                        target: link.target,
+                    })
+                    .collect();
+            }
+            present.insert(key);
+        }
+        self.sources.retain(|key, _| present.contains(key));
+    }
+}
+
+/// Lowercased note titles to note ids; the first note wins a shared title
+fn note_titles(data: &AppData) -> HashMap<String, &str> {
+    let mut titles = HashMap::new();
+    for note in &data.notes {
+        titles
+            .entry(note.title.trim().to_lowercase())
+            .or_insert(note.id.as_str());
+    }
+    titles
+}
+
+fn exists(data: &AppData, kind: DocKind, id: &str) -> bool {
+    match kind {
+        DocKind::Note => data.notes.iter().any(|n| n.id == id),
+        DocKind::Bookmark => data.bookmarks.iter().any(|b| b.id == id),
+        DocKind::Card => data.cards().any(|c| c.id == id),
+    }
+}
+
+/// What a link points at, if it exists
+fn resolve(
+    data: &AppData,
+    titles: &HashMap<String, &str>,
+    target: &Target,
+) -> Option<(DocKind, String)> {
+    match target {
+        Target::Title(title) => titles
+            .get(&title.to_lowercase())
+            .map(|id| (DocKind::Note, id.to_string())),
+        Target::Id(kind, id) => exists(data, *kind, id).then(|| (*kind, id.clone())),
+    }
+}
+
+fn with_graph<T>(data: &AppData, f: impl FnOnce(&LinkGraph) -> T) -> Option<T> {
+    let mut graph = GRAPH.lock().ok()?;
+    let graph = graph.get_or_insert_with(LinkGraph::default);
+    // Also picks up changes saved outside `save_data`, e.g. by sync
+    graph.sync(data);
+    Some(f(graph))
+}
+
+/// Re-parse whatever changed in `data`
+pub fn update(data: &AppData) {
+    let Ok(mut graph) = GRAPH.lock() else {
+        return;
+    };
+    graph.get_or_insert_with(LinkGraph::default).sync(data);
+}
+
+/// Notes and cards that link to the given note, card or bookmark
+pub fn backlinks(data: &AppData, kind: DocKind, id: &str) -> Vec<Backlink> {
+    let titles = note_titles(data);
+    let wanted = (kind, id.to_string());
+    with_graph(data, |graph| {
+        let mut out: Vec<Backlink> = Vec::new();
+        for ((source_kind, source_id), source) in &graph.sources {
+            if (*source_kind, source_id.as_str()) == (kind, id) {
+                continue;
+            }
+            for link in &source.links {
+                if resolve(data, &titles, &link.target).as_ref() == Some(&wanted) {
+                    out.push(Backlink {
+                        source_kind: *source_kind,
+                        source_id: source_id.clone(),
+                        source_title: source.title.clone(),
+                        board_id: source.board_id.clone(),
+                        context: link.context.clone(),
+                    });
+                }
+            }
+        }
+        out.sort_by(|a, b| {
+            a.source_title
+                .to_lowercase()
+                .cmp(&b.source_title.to_lowercase())
+                .then_with(|| a.source_id.cmp(&b.source_id))
+        });
+        out
+    })
+    .unwrap_or_default()
+}
+
+/// Links to notes, cards or bookmarks that do not exist
+pub fn unresolved(data: &AppData) -> Vec<UnresolvedLink> {
+    let titles = note_titles(data);
+    with_graph(data, |graph| {
+        let mut out: Vec<UnresolvedLink> = Vec::new();
+        for ((source_kind, source_id), source) in &graph.sources {
+            for link in &source.links {
+                if resolve(data, &titles, &link.target).is_some() {
+                    continue;
+                }
+                let target = match &link.target {
+                    Target::Title(title) => title.clone(),
+                    Target::Id(DocKind::Card, id) => format!("card:{}", id),
+                    Target::Id(DocKind::Bookmark, id) => format!("bookmark:{}", id),
+                    Target::Id(DocKind::Note, id) => format!("note:{}", id),
+                };
+                out.push(UnresolvedLink {
+                    source_kind: *source_kind,
+                    source_id: source_id.clone(),
+                    source_title: source.title.clone(),
+                    board_id: source.board_id.clone(),
+                    target,
+                    context: link.context.clone(),
+                });
+            }
+        }
+        out.sort_by(|a, b| {
+            a.target
+                .to_lowercase()
+                .cmp(&b.target.to_lowercase())
+                .then_with(|| a.source_title.cmp(&b.source_title))
+        });
+        out
+    })
+    .unwrap_or_default()
+}
+
+// ============================================
+// Renaming and retargeting
+// ============================================
+
+/// `text` with every `[[old]]` pointing at `new` instead, display text
+/// kept; `None` if nothing referred to `old`
+fn rewrite_title(text: &str, old: &str, new: &str) -> Option<String> {
+    let old = old.trim().to_lowercase();
+    let links: Vec<RawLink> = find_links(text)
+        .into_iter()
+        .filter(|link| matches!(&link.target, Target::Title(t) if t.to_lowercase() == old))
+        .collect();
+    if links.is_empty() {
+        return None;
+    }
+
+    let mut out = text.to_string();
+    for link in links.iter().rev() {
+        let replacement = match &link.alias {
+            Some(alias) => format!("[[{}|{}]]", new, alias),
+            None => format!("[[{}]]", new),
+        };
+        out.replace_range(link.range.clone(), &replacement);
+    }
+    Some(out)
+}
+
+/// Rename a note and rewrite every `[[title]]` link to it in notes and
+/// card and bookmark descriptions. Links by id need no change.
+pub fn rename_note(
+    data: &mut AppData,
+    note_id: &str,
+    title: &str,
+    now: &str,
+) -> Result<usize, String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("A note needs a title".to_string());
+    }
+    if title.contains("[[") || title.contains("]]") || title.contains('|') {
+        return Err("Note titles cannot contain [[, ]] or |".to_string());
+    }
+    if data
+        .notes
+        .iter()
+        .any(|n| n.id != note_id && n.title.trim().eq_ignore_ascii_case(title))
+    {
+        return Err(format!("Another note is already called {}", title));
+    }
+    // Only links that reached this note by title follow it; with a shared
+    // title the first note owns the links
+    let owned = note_titles(data).values().any(|id| *id == note_id);
+    let note = data
+        .notes
+        .iter_mut()
+        .find(|n| n.id == note_id)
+        .ok_or_else(|| format!("Note not found: {}", note_id))?;
+    let old = std::mem::replace(&mut note.title, title.to_string());
+    note.updated_at = now.to_string();
+    if !owned {
+        return Ok(0);
+    }
+
+    Ok(rewrite_all(data, now, |text| {
+        rewrite_title(text, &old, title)
+    }))
+}
+
+/// `text` with every `[[kind:ID]]` whose id is a key of `moved` pointing at
+/// the value instead, display text kept; `None` if nothing changed
+fn rewrite_ids(text: &str, kind: DocKind, moved: &HashMap<String, String>) -> Option<String> {
+    let links: Vec<(RawLink, &String)> = find_links(text)
+        .into_iter()
+        .filter_map(|link| {
+            let new = match &link.target {
+                Target::Id(k, id) if *k == kind => moved.get(id)?,
+                _ => return None,
+            };
+            Some((link, new))
+        })
+        .collect();
+    if links.is_empty() {
+        return None;
+    }
+
+    let prefix = match kind {
+        DocKind::Card => "card",
+        DocKind::Bookmark => "bookmark",
+        DocKind::Note => "note",
+    };
+    let mut out = text.to_string();
+    for (link, new) in links.iter().rev() {
+        let replacement = match &link.alias {
+            Some(alias) => format!("[[{}:{}|{}]]", prefix, new, alias),
+            None => format!("[[{}:{}]]", prefix, new),
+        };
+        out.replace_range(link.range.clone(), &replacement);
+    }
+    Some(out)
+}
+
+/// Point links by id at another item of the same kind, for items that were
+/// folded into another one (such as merged duplicate bookmarks). `moved`
+/// maps old ids to new ones. Returns the number of notes, cards and
+/// bookmarks whose links were rewritten.
+pub fn retarget(
+    data: &mut AppData,
+    kind: DocKind,
+    moved: &HashMap<String, String>,
+    now: &str,
+) -> usize {
+    if moved.is_empty() {
+        return 0;
+    }
+    rewrite_all(data, now, |text| rewrite_ids(text, kind, moved))
+}
+
+/// Apply `rewrite` to every note and card and bookmark description, keeping
+/// the results that changed; returns how many did
+fn rewrite_all(data: &mut AppData, now: &str, rewrite: impl Fn(&str) -> Option<String>) -> usize {
+    let mut rewritten = 0;
+    for note in &mut data.notes {
+        if let Some(content) = rewrite(&note.content) {
+            note.content = content;
+            note.updated_at = now.to_string();
+            rewritten += 1;
+        }
+    }
+    for card in data.cards_mut() {
+        if let Some(description) = rewrite(&card.description) {
+            card.description = description;
+            rewritten += 1;
+        }
+    }
+    for bookmark in &mut data.bookmarks {
+        if let Some(description) = rewrite(&bookmark.description) {
+            bookmark.description = description;
+            rewritten += 1;
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: &str = "2025-06-01T00:00:00Z";
+
+    fn data(notes: &[(&str, &str, &str)]) -> AppData {
+        let mut data = crate::get_default_data();
+        let notes: Vec<_> = notes
+            .iter()
+            .map(|(id, title, content)| {
+                json!({
+                    "id": id,
+                    "title": title,
+                    "content": content,
+                    "createdAt": "2025-01-01T00:00:00Z",
+                    "updatedAt": "2025-01-01T00:00:00Z",
+                })
+            })
+            .collect();
+        data.notes = serde_json::from_value(json!(notes)).unwrap();
+        data
+    }
+
+    fn content<'a>(data: &'a AppData, id: &str) -> &'a str {
+        &data.notes.iter().find(|n| n.id == id).unwrap().content
+    }
+
+    #[test]
+    fn links_in_code_are_ignored() {
+        let text =
+            "Use `[[Inline]]` here\n```\n[[Fenced]]\n```\n[[Real]] and [[card:1|the card]]\n";
+        let links = find_links(text);
+        let targets: Vec<&Target> = links.iter().map(|l| &l.target).collect();
+        assert_eq!(
+            targets,
+            [
+                &Target::Title("Real".to_string()),
+                &Target::Id(DocKind::Card, "1".to_string()),
+            ]
+        );
+        assert_eq!(&text[links[0].range.clone()], "[[Real]]");
+        assert_eq!(links[1].alias.as_deref(), Some("the card"));
+    }
+
+    #[test]
+    fn links_do_not_span_lines_or_nest() {
+        assert!(find_links("[[Open\nClosed]]").is_empty());
+        let links = find_links("[[outer [[Inner]]]] [[ ]]");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, Target::Title("Inner".to_string()));
+    }
+
+    #[test]
+    fn rename_rewrites_title_links_and_keeps_aliases() {
+        let mut data = data(&[
+            ("plan", "Plan", ""),
+            (
+                "other",
+                "Other",
+                "See [[plan|the plan]], [[Plan]], `[[Plan]]` and [[note:plan]]",
+            ),
+        ]);
+        let rewritten = rename_note(&mut data, "plan", "Roadmap", NOW).unwrap();
+        assert_eq!(rewritten, 1);
+        assert_eq!(
+            content(&data, "other"),
+            "See [[Roadmap|the plan]], [[Roadmap]], `[[Plan]]` and [[note:plan]]"
+        );
+        assert_eq!(data.notes[1].updated_at, NOW);
+    }
+
+    #[test]
+    fn rename_rejects_taken_and_invalid_titles() {
+        let mut data = data(&[("a", "A", ""), ("b", "B", "")]);
+        assert!(rename_note(&mut data, "a", " b ", NOW).is_err());
+        assert!(rename_note(&mut data, "a", "x|y", NOW).is_err());
+        assert!(rename_note(&mut data, "a", "  ", NOW).is_err());
+        assert!(rename_note(&mut data, "missing", "C", NOW).is_err());
+    }
+
+    #[test]
+    fn the_first_note_owns_a_shared_title() {
+        let mut data = data(&[
+            ("first", "Shared", ""),
+            ("second", "shared", ""),
+            ("linker", "Linker", "[[Shared]]"),
+        ]);
+        let from_first = backlinks(&data, DocKind::Note, "first");
+        assert_eq!(from_first.len(), 1);
+        assert_eq!(from_first[0].source_id, "linker");
+        assert!(backlinks(&data, DocKind::Note, "second").is_empty());
+
+        // Renaming the second note leaves the links with the first
+        assert_eq!(rename_note(&mut data, "second", "Mine", NOW).unwrap(), 0);
+        assert_eq!(content(&data, "linker"), "[[Shared]]");
+
+        assert_eq!(rename_note(&mut data, "first", "Theirs", NOW).unwrap(), 1);
+        assert_eq!(content(&data, "linker"), "[[Theirs]]");
+    }
+
+    #[test]
+    fn unresolved_lists_missing_targets() {
+        let data = data(&[("a", "A", "[[A]] [[Missing]]\n[[card:nope]] [[note:a]]")]);
+        let targets: Vec<String> = unresolved(&data).into_iter().map(|u| u.target).collect();
+        assert_eq!(targets, ["card:nope", "Missing"]);
+    }
+
+    #[test]
+    fn retarget_points_id_links_at_the_new_item() {
+        let mut data = data(&[("a", "A", "[[bookmark:old|Docs]] [[card:old]]")]);
+        let moved = HashMap::from([("old".to_string(), "new".to_string())]);
+        assert_eq!(retarget(&mut data, DocKind::Bookmark, &moved, NOW), 1);
+        assert_eq!(content(&data, "a"), "[[bookmark:new|Docs]] [[card:old]]");
+    }
+}
@@ -0,0 +1,545 @@
+//! Bookmark metadata fetching
+//!
+//! Fetches a page and reads its title, description, OpenGraph / Twitter card
+//! data and favicon. Requests are bounded by a timeout, a redirect limit and
+//! a maximum body size; the body is decoded using the charset from the
+//! response headers, a BOM or a `<meta charset>` declaration.
+
+use crate::html;
+use encoding_rs::{Encoding, UTF_8};
+use serde::Serialize;
+use std::time::Duration;
+use url::Url;
+
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+pub const MAX_REDIRECTS: usize = 5;
+/// Only the head of a page is needed; stop reading after this many bytes
+const MAX_HTML_BYTES: usize = 1024 * 1024;
+/// How far into the body to look for a `<meta charset>` declaration
+const CHARSET_SNIFF_BYTES: usize = 4096;
+
+const USER_AGENT: &str = concat!(
+    "Mozilla/5.0 (compatible; Carbon/",
+    env!("CARGO_PKG_VERSION"),
+    ")"
+);
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct BookmarkMetadata {
+    /// The URL that was requested
+    pub url: String,
+    /// The URL after following redirects
+    #[serde(rename = "finalUrl")]
+    pub final_url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "siteName")]
+    pub site_name: Option<String>,
+    pub favicon: Option<String>,
+    pub image: Option<String>,
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+}
+
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+}
+
+/// HTTP client shared by everything that fetches bookmark URLs
+pub fn build_client() -> Result<reqwest::Client, String> {
+    client_builder()
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Read at most `limit` bytes of a response body
+pub async fn read_limited(
+    mut response: reqwest::Response,
+    limit: usize,
+) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?
+    {
+        let remaining = limit - body.len();
+        if chunk.len() >= remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+fn sniff_meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(CHARSET_SNIFF_BYTES)]);
+    html::tags(&head)
+        .filter(|t| t.is_open("meta"))
+        .find_map(|tag| {
+            if let Some(charset) = tag.attr("charset") {
+                return Encoding::for_label(charset.trim().as_bytes());
+            }
+            let is_content_type = tag
+                .attr("http-equiv")
+                .map(|v| v.eq_ignore_ascii_case("content-type"))
+                .unwrap_or(false);
+            if is_content_type {
+                let label = charset_from_content_type(tag.attr("content")?)?;
+                return Encoding::for_label(label.as_bytes());
+            }
+            None
+        })
+}
+
+/// Decode an HTML body: BOM, then HTTP header, then `<meta>`, then UTF-8
+pub fn decode_html(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = Encoding::for_bom(body)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| {
+            content_type
+                .and_then(charset_from_content_type)
+                .and_then(|label| Encoding::for_label(label.as_bytes()))
+        })
+        .or_else(|| sniff_meta_charset(body))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = html::collapse_whitespace(value);
+    (!value.is_empty()).then_some(value)
+}
+
+fn resolve(base: &Url, href: &str) -> Option<String> {
+    base.join(href.trim()).ok().map(|u| u.to_string())
+}
+
+/// Extract metadata from an HTML document.
+///
+/// OpenGraph values win over Twitter cards, which win over plain
+/// `<title>` / `<meta name="description">`.
+pub fn parse_html(document: &str, base: &Url) -> BookmarkMetadata {
+    let mut title_tag = None;
+    let mut description = None;
+    let mut og_title = None;
+    let mut og_description = None;
+    let mut og_image = None;
+    let mut site_name = None;
+    let mut twitter_title = None;
+    let mut twitter_description = None;
+    let mut twitter_image = None;
+    let mut icon = None;
+    let mut touch_icon = None;
+    let mut title_start = None;
+
+    for tag in html::tags(document) {
+        match tag.name.as_str() {
+            "title" if !tag.closing => title_start = Some(tag.end),
+            "title" => {
+                if let Some(start) = title_start.take() {
+                    if title_tag.is_none() {
+                        title_tag = non_empty(&html::decode_entities(&document[start..tag.start]));
+                    }
+                }
+            }
+            "meta" if !tag.closing => {
+                let key = tag
+                    .attr("property")
+                    .or_else(|| tag.attr("name"))
+                    .map(|k| k.to_ascii_lowercase());
+                let Some(content) = tag.attr("content").and_then(non_empty) else {
+                    continue;
+                };
+                let slot = match key.as_deref() {
+                    Some("og:title") => &mut og_title,
+                    Some("og:description") => &mut og_description,
+                    Some("og:image") | Some("og:image:url") | Some("og:image:secure_url") => {
+                        &mut og_image
+                    }
+                    Some("og:site_name") => &mut site_name,
+                    Some("twitter:title") => &mut twitter_title,
+                    Some("twitter:description") => &mut twitter_description,
+                    Some("twitter:image") | Some("twitter:image:src") => &mut twitter_image,
+                    Some("description") => &mut description,
+                    _ => continue,
+                };
+                if slot.is_none() {
+                    *slot = Some(content);
+                }
+            }
+            "link" if !tag.closing => {
+                let (Some(rel), Some(href)) = (tag.attr("rel"), tag.attr("href")) else {
+                    continue;
+                };
+                let rel = rel.to_ascii_lowercase();
+                let rels: Vec<&str> = rel.split_whitespace().collect();
+                if rels.contains(&"icon") && icon.is_none() {
+                    icon = resolve(base, href);
+                } else if rels.iter().any(|r| r.starts_with("apple-touch-icon"))
+                    && touch_icon.is_none()
+                {
+                    touch_icon = resolve(base, href);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    BookmarkMetadata {
+        url: base.to_string(),
+        final_url: base.to_string(),
+        title: og_title.or(twitter_title).or(title_tag),
+        description: og_description.or(twitter_description).or(description),
+        site_name,
+        favicon: icon
+            .or(touch_icon)
+            .or_else(|| resolve(base, "/favicon.ico")),
+        image: og_image
+            .or(twitter_image)
+            .and_then(|src| resolve(base, &src)),
+        content_type: None,
+    }
+}
+
+/// Send a `GET` for a page URL, failing on non-http(s) URLs and error statuses
+pub async fn fetch_page(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Only http and https URLs can be fetched".to_string());
+    }
+
+    let response = client
+        .get(parsed)
+        .header(
+            reqwest::header::ACCEPT,
+            "text/html,application/xhtml+xml;q=0.9,*/*;q=0.8",
+        )
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                "Timed out fetching page".to_string()
+            } else if e.is_redirect() {
+                "Too many redirects".to_string()
+            } else {
+                format!("Failed to fetch page: {}", e)
+            }
+        })?;
+
+    if !response.status().is_success() {
+        return Err(format!("Server responded with {}", response.status()));
+    }
+    Ok(response)
+}
+
+/// Lowercased MIME type of a `Content-Type` header, without parameters
+pub fn mime_type(content_type: Option<&str>) -> String {
+    content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|m| m.trim().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+pub fn is_html_mime(mime: &str) -> bool {
+    mime.is_empty() || mime == "text/html" || mime == "application/xhtml+xml"
+}
+
+/// Fetch `url` and extract its metadata
+pub async fn fetch_metadata(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<BookmarkMetadata, String> {
+    let response = fetch_page(client, url).await?;
+
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mime = mime_type(content_type.as_deref());
+
+    let mut metadata = if is_html_mime(&mime) {
+        let body = read_limited(response, MAX_HTML_BYTES).await?;
+        parse_html(&decode_html(&body, content_type.as_deref()), &final_url)
+    } else {
+        // Not a web page: describe the file itself
+        let name = final_url
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        BookmarkMetadata {
+            title: name,
+            image: mime.starts_with("image/").then(|| final_url.to_string()),
+            favicon: resolve(&final_url, "/favicon.ico"),
+            ..Default::default()
+        }
+    };
+
+    // fetch_page already validated the URL
+    metadata.url = Url::parse(url.trim())
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| url.to_string());
+    metadata.final_url = final_url.to_string();
+    metadata.content_type = content_type;
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Response of the fixture server: status, extra headers and body
+    type Reply = (u16, Vec<(&'static str, String)>, Vec<u8>);
+
+    fn html(content_type: &str, body: impl Into<Vec<u8>>) -> Reply {
+        (
+            200,
+            vec![("Content-Type", content_type.to_string())],
+            body.into(),
+        )
+    }
+
+    fn route(path: &str) -> Reply {
+        match path {
+            "/page" => html(
+                "text/html",
+                r#"<html><head><title>Plain title</title>
+                <meta name="twitter:title" content="Twitter title">
+                <meta property="og:title" content="OG title">
+                <meta name="description" content="Plain description">
+                <meta name="twitter:description" content="Twitter description">
+                </head></html>"#,
+            ),
+            "/redirect" => (302, vec![("Location", "/page".to_string())], vec![]),
+            "/big" => {
+                // The OpenGraph title sits past the size cap
+                let mut body = b"<title>Head</title>".to_vec();
+                body.resize(MAX_HTML_BYTES + 1024, b' ');
+                body.extend_from_slice(br#"<meta property="og:title" content="Tail">"#);
+                html("text/html", body)
+            }
+            "/bom" => {
+                let mut body = b"\xEF\xBB\xBF".to_vec();
+                body.extend_from_slice("<title>Caf\u{e9}</title>".as_bytes());
+                html("text/html; charset=windows-1252", body)
+            }
+            "/header" => html(
+                "text/html; charset=windows-1252",
+                b"<title>Caf\xE9</title>".to_vec(),
+            ),
+            "/meta" => html(
+                "text/html",
+                b"<meta charset=\"windows-1252\"><title>Caf\xE9</title>".to_vec(),
+            ),
+            _ => match path.strip_prefix("/loop/") {
+                Some(n) => {
+                    let next = n.parse::<u32>().unwrap_or(0) + 1;
+                    (302, vec![("Location", format!("/loop/{}", next))], vec![])
+                }
+                None => (404, vec![], vec![]),
+            },
+        }
+    }
+
+    /// Serve `route` on a random localhost port; `/slow` never answers in time
+    async fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    if path == "/slow" {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+
+                    let (status, headers, body) = route(&path);
+                    let mut head = format!(
+                        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        status,
+                        body.len()
+                    );
+                    for (name, value) in headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    head.push_str("\r\n");
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(&body).await;
+                });
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_pages() {
+        let base = serve().await;
+        let client = client_builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let result = fetch_metadata(&client, &format!("{}/slow", base)).await;
+        assert_eq!(result.unwrap_err(), "Timed out fetching page");
+    }
+
+    #[tokio::test]
+    async fn follows_redirects_up_to_the_limit() {
+        let base = serve().await;
+        let client = build_client().unwrap();
+
+        let metadata = fetch_metadata(&client, &format!("{}/redirect", base))
+            .await
+            .unwrap();
+        assert_eq!(metadata.url, format!("{}/redirect", base));
+        assert_eq!(metadata.final_url, format!("{}/page", base));
+
+        let result = fetch_metadata(&client, &format!("{}/loop/0", base)).await;
+        assert_eq!(result.unwrap_err(), "Too many redirects");
+    }
+
+    #[tokio::test]
+    async fn stops_reading_at_the_size_cap() {
+        let base = serve().await;
+        let client = build_client().unwrap();
+
+        let response = fetch_page(&client, &format!("{}/big", base)).await.unwrap();
+        let body = read_limited(response, MAX_HTML_BYTES).await.unwrap();
+        assert_eq!(body.len(), MAX_HTML_BYTES);
+
+        let metadata = fetch_metadata(&client, &format!("{}/big", base))
+            .await
+            .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Head"));
+    }
+
+    #[tokio::test]
+    async fn decodes_using_the_declared_charset() {
+        let base = serve().await;
+        let client = build_client().unwrap();
+        for path in ["/bom", "/header", "/meta"] {
+            let metadata = fetch_metadata(&client, &format!("{}{}", base, path))
+                .await
+                .unwrap();
+            assert_eq!(metadata.title.as_deref(), Some("Caf\u{e9}"), "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn prefers_opengraph_values() {
+        let base = serve().await;
+        let client = build_client().unwrap();
+        let metadata = fetch_metadata(&client, &format!("{}/page", base))
+            .await
+            .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("OG title"));
+        assert_eq!(metadata.description.as_deref(), Some("Twitter description"));
+        assert_eq!(metadata.content_type.as_deref(), Some("text/html"));
+    }
+
+    fn parse(document: &str) -> BookmarkMetadata {
+        parse_html(document, &Url::parse("https://example.com/a/b").unwrap())
+    }
+
+    #[test]
+    fn title_falls_back_from_opengraph_to_twitter_to_title_tag() {
+        let title = r#"<title> Plain &amp; simple </title>"#;
+        let twitter = r#"<meta name="twitter:title" content="Twitter">"#;
+        let og = r#"<meta property="og:title" content="OG">"#;
+
+        assert_eq!(parse(title).title.as_deref(), Some("Plain & simple"));
+        assert_eq!(
+            parse(&format!("{}{}", title, twitter)).title.as_deref(),
+            Some("Twitter")
+        );
+        assert_eq!(
+            parse(&format!("{}{}{}", title, twitter, og))
+                .title
+                .as_deref(),
+            Some("OG")
+        );
+        // Empty values do not count
+        let empty_og = r#"<meta property="og:title" content="  ">"#;
+        assert_eq!(
+            parse(&format!("{}{}", title, empty_og)).title.as_deref(),
+            Some("Plain & simple")
+        );
+    }
+
+    #[test]
+    fn resolves_images_and_icons_against_the_page() {
+        let metadata = parse(
+            r#"<link rel="apple-touch-icon" href="/touch.png">
+            <link rel="shortcut icon" href="icon.png">
+            <meta name="twitter:image" content="/twitter.png">
+            <meta property="og:image" content="og.png">
+            <meta property="og:site_name" content="Example">"#,
+        );
+        assert_eq!(
+            metadata.favicon.as_deref(),
+            Some("https://example.com/a/icon.png")
+        );
+        assert_eq!(
+            metadata.image.as_deref(),
+            Some("https://example.com/a/og.png")
+        );
+        assert_eq!(metadata.site_name.as_deref(), Some("Example"));
+
+        let bare = parse("<title>x</title>");
+        assert_eq!(
+            bare.favicon.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+        assert_eq!(bare.image, None);
+    }
+
+    #[test]
+    fn charset_precedence_is_bom_header_meta() {
+        let latin1 = b"<meta charset=\"windows-1252\"><title>Caf\xE9</title>";
+        assert!(decode_html(latin1, None).contains("Caf\u{e9}"));
+        assert!(
+            decode_html(latin1, Some("text/html; charset=\"ISO-8859-1\"")).contains("Caf\u{e9}")
+        );
+
+        // The header wins over <meta>
+        let utf8 = "<meta charset=\"windows-1252\"><title>Caf\u{e9}</title>".as_bytes();
+        assert!(decode_html(utf8, Some("text/html; charset=utf-8")).contains("Caf\u{e9}"));
+
+        // A BOM wins over the header
+        let mut bom = b"\xEF\xBB\xBF".to_vec();
+        bom.extend_from_slice(utf8);
+        let decoded = decode_html(&bom, Some("text/html; charset=windows-1252"));
+        assert!(decoded.starts_with("<meta"));
+        assert!(decoded.contains("Caf\u{e9}"));
+
+        // No declaration at all: UTF-8
+        assert_eq!(decode_html("\u{2713}".as_bytes(), None), "\u{2713}");
+    }
+}
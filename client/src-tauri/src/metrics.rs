@@ -0,0 +1,663 @@
+//! Kanban flow metrics
+//!
+//! Computes cycle time, lead time, weekly throughput and cumulative flow
+//! series for a board from the per-card column history that `write_data`
+//! records whenever a card changes column.
+
+use crate::{AppData, Board, Card, ColumnTransition};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DEFAULT_THROUGHPUT_WEEKS: u32 = 12;
+const DEFAULT_FLOW_DAYS: u32 = 30;
+
+/// Optional overrides for how a board's workflow is interpreted.
+///
+/// By default the last column is treated as "done" and the column right
+/// before it as the commitment point where cycle time starts.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MetricsOptions {
+    #[serde(rename = "startColumnId", default)]
+    pub start_column_id: Option<String>,
+    #[serde(rename = "doneColumnId", default)]
+    pub done_column_id: Option<String>,
+    #[serde(default)]
+    pub weeks: Option<u32>,
+    #[serde(default)]
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct DurationStats {
+    pub count: usize,
+    #[serde(rename = "averageDays")]
+    pub average_days: Option<f64>,
+    #[serde(rename = "medianDays")]
+    pub median_days: Option<f64>,
+    #[serde(rename = "p85Days")]
+    pub p85_days: Option<f64>,
+    #[serde(rename = "minDays")]
+    pub min_days: Option<f64>,
+    #[serde(rename = "maxDays")]
+    pub max_days: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompletedCard {
+    #[serde(rename = "cardId")]
+    pub card_id: String,
+    pub title: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<String>,
+    #[serde(rename = "completedAt")]
+    pub completed_at: String,
+    #[serde(rename = "cycleTimeDays")]
+    pub cycle_time_days: Option<f64>,
+    #[serde(rename = "leadTimeDays")]
+    pub lead_time_days: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThroughputPoint {
+    /// Monday of the ISO week (YYYY-MM-DD)
+    #[serde(rename = "weekStart")]
+    pub week_start: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FlowSeries {
+    #[serde(rename = "columnId")]
+    pub column_id: String,
+    pub title: String,
+    pub values: Vec<usize>,
+}
+
+/// Cumulative flow diagram data: one value per date for every column.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct CumulativeFlow {
+    pub dates: Vec<String>,
+    pub series: Vec<FlowSeries>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BoardMetrics {
+    #[serde(rename = "boardId")]
+    pub board_id: String,
+    #[serde(rename = "startColumnId")]
+    pub start_column_id: Option<String>,
+    #[serde(rename = "doneColumnId")]
+    pub done_column_id: Option<String>,
+    #[serde(rename = "cycleTime")]
+    pub cycle_time: DurationStats,
+    #[serde(rename = "leadTime")]
+    pub lead_time: DurationStats,
+    pub completed: Vec<CompletedCard>,
+    pub throughput: Vec<ThroughputPoint>,
+    #[serde(rename = "cumulativeFlow")]
+    pub cumulative_flow: CumulativeFlow,
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_seconds().max(0) as f64 / 86_400.0
+}
+
+/// Record column transitions for every card that changed column since the
+/// previously persisted data.
+///
+/// The on-disk history is authoritative because the frontend never edits it;
+/// cards without any history get their current column stamped with
+/// `created_at`, everything else that moved is stamped with `now`.
+pub fn record_column_transitions(previous: Option<&AppData>, data: &mut AppData, now: &str) {
+    let mut known: HashMap<&str, &Vec<ColumnTransition>> = HashMap::new();
+    if let Some(previous) = previous {
+        for board in &previous.boards {
+            let cards = board
+                .columns
+                .iter()
+                .flat_map(|c| c.cards.iter())
+                .chain(board.archived_cards.iter());
+            for card in cards {
+                known.insert(card.id.as_str(), &card.column_history);
+            }
+        }
+    }
+
+    for board in &mut data.boards {
+        for column in &mut board.columns {
+            for card in &mut column.cards {
+                restore_history(card, &known);
+                let moved = card
+                    .column_history
+                    .last()
+                    .map(|t| t.column_id != column.id)
+                    .unwrap_or(true);
+                if moved {
+                    let entered_at = if card.column_history.is_empty() {
+                        card.created_at.clone()
+                    } else {
+                        now.to_string()
+                    };
+                    card.column_history.push(ColumnTransition {
+                        column_id: column.id.clone(),
+                        entered_at,
+                    });
+                }
+            }
+        }
+        for card in &mut board.archived_cards {
+            restore_history(card, &known);
+        }
+    }
+}
+
+fn restore_history(card: &mut Card, known: &HashMap<&str, &Vec<ColumnTransition>>) {
+    if let Some(history) = known.get(card.id.as_str()) {
+        if history.len() >= card.column_history.len() {
+            card.column_history = (*history).clone();
+        }
+    }
+}
+
+/// Card history as timestamps, falling back to a single entry at creation
+/// for cards saved before column history was tracked.
+fn timeline(card: &Card, current_column: Option<&str>) -> Vec<(String, DateTime<Utc>)> {
+    let mut entries: Vec<(String, DateTime<Utc>)> = card
+        .column_history
+        .iter()
+        .filter_map(|t| parse_timestamp(&t.entered_at).map(|at| (t.column_id.clone(), at)))
+        .collect();
+
+    if entries.is_empty() {
+        let column = current_column.or(card.original_column_id.as_deref());
+        if let (Some(column), Some(created)) = (column, parse_timestamp(&card.created_at)) {
+            entries.push((column.to_string(), created));
+        }
+    }
+
+    entries.sort_by_key(|(_, at)| *at);
+    entries
+}
+
+fn duration_stats(mut values: Vec<f64>) -> DurationStats {
+    if values.is_empty() {
+        return DurationStats::default();
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let count = values.len();
+    let percentile = |p: f64| {
+        // Nearest-rank percentile
+        let rank = ((p / 100.0) * count as f64).ceil() as usize;
+        values[rank.clamp(1, count) - 1]
+    };
+
+    DurationStats {
+        count,
+        average_days: Some(values.iter().sum::<f64>() / count as f64),
+        median_days: Some(percentile(50.0)),
+        p85_days: Some(percentile(85.0)),
+        min_days: values.first().copied(),
+        max_days: values.last().copied(),
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Compute flow metrics for a single board as of `now`.
+pub fn compute_board_metrics(
+    board: &Board,
+    options: &MetricsOptions,
+    now: DateTime<Utc>,
+) -> BoardMetrics {
+    let column_index: HashMap<&str, usize> = board
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.as_str(), i))
+        .collect();
+
+    let done_column_id = options
+        .done_column_id
+        .clone()
+        .filter(|id| column_index.contains_key(id.as_str()))
+        .or_else(|| board.columns.last().map(|c| c.id.clone()));
+    let done_index = done_column_id
+        .as_deref()
+        .and_then(|id| column_index.get(id).copied());
+    let start_column_id = options
+        .start_column_id
+        .clone()
+        .filter(|id| column_index.contains_key(id.as_str()))
+        .or_else(|| {
+            done_index
+                .and_then(|i| i.checked_sub(1))
+                .map(|i| board.columns[i].id.clone())
+        })
+        .or_else(|| done_column_id.clone());
+    let start_index = start_column_id
+        .as_deref()
+        .and_then(|id| column_index.get(id).copied());
+
+    // (card, current column, archived) for every card on the board
+    let cards: Vec<(&Card, Option<&str>, bool)> = board
+        .columns
+        .iter()
+        .flat_map(|col| {
+            col.cards
+                .iter()
+                .map(move |card| (card, Some(col.id.as_str()), false))
+        })
+        .chain(board.archived_cards.iter().map(|card| (card, None, true)))
+        .collect();
+
+    // Completed cards: currently done, or archived from the done column
+    let mut completed = Vec::new();
+    if let Some(done_id) = done_column_id.as_deref() {
+        for (card, current, archived) in &cards {
+            let last_column = if *archived {
+                card.original_column_id.as_deref()
+            } else {
+                *current
+            };
+            if last_column != Some(done_id) {
+                continue;
+            }
+            let history = timeline(card, *current);
+            let Some(completed_at) = history
+                .iter()
+                .rev()
+                .find(|(col, _)| col == done_id)
+                .map(|(_, at)| *at)
+            else {
+                continue;
+            };
+            let Some(created_at) = parse_timestamp(&card.created_at) else {
+                continue;
+            };
+            let started_at = start_index.and_then(|start| {
+                history
+                    .iter()
+                    .find(|(col, _)| {
+                        column_index
+                            .get(col.as_str())
+                            .map(|i| *i >= start)
+                            .unwrap_or(false)
+                    })
+                    .map(|(_, at)| *at)
+            });
+
+            completed.push(CompletedCard {
+                card_id: card.id.clone(),
+                title: card.title.clone(),
+                created_at: card.created_at.clone(),
+                started_at: started_at.map(|at| at.to_rfc3339()),
+                completed_at: completed_at.to_rfc3339(),
+                cycle_time_days: started_at.map(|at| days_between(at, completed_at)),
+                lead_time_days: days_between(created_at, completed_at),
+            });
+        }
+    }
+    completed.sort_by(|a, b| a.completed_at.cmp(&b.completed_at));
+
+    let cycle_time = duration_stats(completed.iter().filter_map(|c| c.cycle_time_days).collect());
+    let lead_time = duration_stats(completed.iter().map(|c| c.lead_time_days).collect());
+
+    // Weekly throughput, oldest week first
+    let weeks = options.weeks.unwrap_or(DEFAULT_THROUGHPUT_WEEKS).max(1);
+    let current_week = week_start(now.date_naive());
+    let mut throughput: Vec<ThroughputPoint> = (0..weeks)
+        .rev()
+        .map(|i| ThroughputPoint {
+            week_start: (current_week - Duration::weeks(i as i64)).to_string(),
+            count: 0,
+        })
+        .collect();
+    for card in &completed {
+        if let Some(at) = parse_timestamp(&card.completed_at) {
+            let week = week_start(at.date_naive()).to_string();
+            if let Some(point) = throughput.iter_mut().find(|p| p.week_start == week) {
+                point.count += 1;
+            }
+        }
+    }
+
+    // Cumulative flow: card count per column at the end of each day
+    let days = options.days.unwrap_or(DEFAULT_FLOW_DAYS).max(1);
+    let today = now.date_naive();
+    let timelines: Vec<_> = cards
+        .iter()
+        .map(|(card, current, _)| {
+            let archived_at = card.archived_at.as_deref().and_then(parse_timestamp);
+            (timeline(card, *current), archived_at)
+        })
+        .collect();
+    let mut cumulative_flow = CumulativeFlow {
+        dates: Vec::with_capacity(days as usize),
+        series: board
+            .columns
+            .iter()
+            .map(|c| FlowSeries {
+                column_id: c.id.clone(),
+                title: c.title.clone(),
+                values: Vec::with_capacity(days as usize),
+            })
+            .collect(),
+    };
+    for offset in (0..days).rev() {
+        let date = today - Duration::days(offset as i64);
+        let end_of_day = (date + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc())
+            .unwrap_or(now);
+        let mut counts = vec![0usize; board.columns.len()];
+        for (history, archived_at) in &timelines {
+            let Some((column, _)) = history.iter().rev().find(|(_, at)| *at < end_of_day) else {
+                continue;
+            };
+            let Some(index) = column_index.get(column.as_str()).copied() else {
+                continue;
+            };
+            // Archived cards only keep counting towards "done"
+            let archived = archived_at.map(|at| at < end_of_day).unwrap_or(false);
+            if archived && Some(index) != done_index {
+                continue;
+            }
+            counts[index] += 1;
+        }
+        cumulative_flow.dates.push(date.to_string());
+        for (series, count) in cumulative_flow.series.iter_mut().zip(counts) {
+            series.values.push(count);
+        }
+    }
+
+    BoardMetrics {
+        board_id: board.id.clone(),
+        start_column_id,
+        done_column_id,
+        cycle_time,
+        lead_time,
+        completed,
+        throughput,
+        cumulative_flow,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn card(id: &str, created_at: &str, history: &[(&str, &str)]) -> Card {
+        let history: Vec<_> = history
+            .iter()
+            .map(|(column, at)| json!({ "columnId": column, "enteredAt": at }))
+            .collect();
+        serde_json::from_value(json!({
+            "id": id,
+            "title": id,
+            "createdAt": created_at,
+            "columnHistory": history,
+        }))
+        .unwrap()
+    }
+
+    fn archived(mut card: Card, column: &str, at: &str) -> Card {
+        card.original_column_id = Some(column.to_string());
+        card.archived_at = Some(at.to_string());
+        card
+    }
+
+    fn board(columns: Vec<(&str, Vec<Card>)>, archived_cards: Vec<Card>) -> Board {
+        let columns: Vec<_> = columns
+            .into_iter()
+            .map(|(id, cards)| json!({ "id": id, "title": id.to_uppercase(), "cards": cards }))
+            .collect();
+        serde_json::from_value(json!({
+            "id": "board",
+            "name": "Board",
+            "columns": columns,
+            "archivedCards": archived_cards,
+        }))
+        .unwrap()
+    }
+
+    fn data(board: Board) -> AppData {
+        let mut data = crate::get_default_data();
+        data.boards = vec![board];
+        data
+    }
+
+    fn history(data: &AppData, id: &str) -> Vec<(String, String)> {
+        let board = &data.boards[0];
+        board
+            .columns
+            .iter()
+            .flat_map(|c| c.cards.iter())
+            .chain(board.archived_cards.iter())
+            .find(|c| c.id == id)
+            .unwrap()
+            .column_history
+            .iter()
+            .map(|t| (t.column_id.clone(), t.entered_at.clone()))
+            .collect()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_timestamp(value).unwrap()
+    }
+
+    const CREATED: &str = "2024-05-01T09:00:00+00:00";
+    const NOW: &str = "2024-05-02T10:00:00+00:00";
+
+    #[test]
+    fn first_sight_is_stamped_with_creation_time() {
+        let mut current = data(board(vec![("todo", vec![card("a", CREATED, &[])])], vec![]));
+        record_column_transitions(None, &mut current, NOW);
+        assert_eq!(
+            history(&current, "a"),
+            vec![("todo".into(), CREATED.into())]
+        );
+    }
+
+    #[test]
+    fn move_appends_transition_to_stored_history() {
+        let previous = data(board(
+            vec![
+                ("todo", vec![card("a", CREATED, &[("todo", CREATED)])]),
+                ("doing", vec![]),
+            ],
+            vec![],
+        ));
+        // The frontend may send the card without its history
+        let mut current = data(board(
+            vec![("todo", vec![]), ("doing", vec![card("a", CREATED, &[])])],
+            vec![],
+        ));
+        record_column_transitions(Some(&previous), &mut current, NOW);
+        assert_eq!(
+            history(&current, "a"),
+            vec![
+                ("todo".into(), CREATED.into()),
+                ("doing".into(), NOW.into())
+            ]
+        );
+
+        // Saving again without moving records nothing
+        let previous = current.clone();
+        record_column_transitions(Some(&previous), &mut current, "2024-05-03T10:00:00+00:00");
+        assert_eq!(history(&current, "a").len(), 2);
+    }
+
+    #[test]
+    fn archiving_and_restoring_keep_history() {
+        let moved = [("todo", CREATED), ("doing", NOW)];
+        let previous = data(board(
+            vec![
+                ("todo", vec![]),
+                ("doing", vec![card("a", CREATED, &moved)]),
+            ],
+            vec![],
+        ));
+        let mut current = data(board(
+            vec![("todo", vec![]), ("doing", vec![])],
+            vec![archived(card("a", CREATED, &[]), "doing", NOW)],
+        ));
+        record_column_transitions(Some(&previous), &mut current, NOW);
+        assert_eq!(history(&current, "a").len(), 2);
+
+        // Restored into the column it was archived from: not a move
+        let previous = current;
+        let mut current = data(board(
+            vec![("todo", vec![]), ("doing", vec![card("a", CREATED, &[])])],
+            vec![],
+        ));
+        record_column_transitions(Some(&previous), &mut current, "2024-05-04T10:00:00+00:00");
+        assert_eq!(
+            history(&current, "a"),
+            vec![
+                ("todo".into(), CREATED.into()),
+                ("doing".into(), NOW.into())
+            ]
+        );
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let stats = duration_stats((1..=10).rev().map(f64::from).collect());
+        assert_eq!(stats.count, 10);
+        assert_eq!(stats.median_days, Some(5.0));
+        assert_eq!(stats.p85_days, Some(9.0));
+        assert_eq!(stats.min_days, Some(1.0));
+        assert_eq!(stats.max_days, Some(10.0));
+        assert_eq!(stats.average_days, Some(5.5));
+
+        let single = duration_stats(vec![3.0]);
+        assert_eq!(
+            (single.median_days, single.p85_days),
+            (Some(3.0), Some(3.0))
+        );
+        assert!(duration_stats(vec![]).median_days.is_none());
+    }
+
+    #[test]
+    fn throughput_buckets_by_iso_week() {
+        let done = |id: &str, finished: &'static str| {
+            card(id, CREATED, &[("todo", CREATED), ("done", finished)])
+        };
+        let board = board(
+            vec![
+                ("todo", vec![]),
+                (
+                    "done",
+                    vec![
+                        // Last second of the previous week
+                        done("sunday", "2024-05-12T23:59:59+00:00"),
+                        // First second of the current week
+                        done("monday", "2024-05-13T00:00:00+00:00"),
+                        done("wednesday", "2024-05-15T08:00:00+00:00"),
+                        // Before the window
+                        done("old", "2024-05-05T12:00:00+00:00"),
+                    ],
+                ),
+            ],
+            vec![],
+        );
+        let options = MetricsOptions {
+            weeks: Some(2),
+            ..Default::default()
+        };
+        let metrics = compute_board_metrics(&board, &options, at("2024-05-15T12:00:00+00:00"));
+
+        let weeks: Vec<_> = metrics
+            .throughput
+            .iter()
+            .map(|p| (p.week_start.as_str(), p.count))
+            .collect();
+        assert_eq!(weeks, vec![("2024-05-06", 1), ("2024-05-13", 2)]);
+        assert_eq!(metrics.completed.len(), 4);
+        assert_eq!(metrics.start_column_id.as_deref(), Some("todo"));
+        assert_eq!(metrics.done_column_id.as_deref(), Some("done"));
+    }
+
+    #[test]
+    fn cumulative_flow_counts_cards_per_column_each_day() {
+        let day0 = "2024-05-12T09:00:00+00:00";
+        let board = board(
+            vec![
+                (
+                    "todo",
+                    vec![card(
+                        "new",
+                        "2024-05-14T09:00:00+00:00",
+                        &[("todo", "2024-05-14T09:00:00+00:00")],
+                    )],
+                ),
+                (
+                    "doing",
+                    vec![card(
+                        "moved",
+                        day0,
+                        &[("todo", day0), ("doing", "2024-05-14T10:00:00+00:00")],
+                    )],
+                ),
+                ("done", vec![]),
+            ],
+            vec![
+                // Archived from "done": keeps counting as done
+                archived(
+                    card(
+                        "shipped",
+                        day0,
+                        &[("todo", day0), ("done", "2024-05-13T08:00:00+00:00")],
+                    ),
+                    "done",
+                    "2024-05-14T12:00:00+00:00",
+                ),
+                // Archived from "todo": drops out of the diagram
+                archived(
+                    card("dropped", day0, &[("todo", day0)]),
+                    "todo",
+                    "2024-05-14T01:00:00+00:00",
+                ),
+            ],
+        );
+        let options = MetricsOptions {
+            days: Some(3),
+            ..Default::default()
+        };
+        let metrics = compute_board_metrics(&board, &options, at("2024-05-15T12:00:00+00:00"));
+
+        let flow = &metrics.cumulative_flow;
+        assert_eq!(flow.dates, vec!["2024-05-13", "2024-05-14", "2024-05-15"]);
+        let values: Vec<_> = flow
+            .series
+            .iter()
+            .map(|s| (s.column_id.as_str(), s.values.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("todo", vec![2, 1, 1]),
+                ("doing", vec![0, 1, 1]),
+                ("done", vec![1, 1, 1]),
+            ]
+        );
+
+        assert_eq!(metrics.completed.len(), 1);
+        let shipped = &metrics.completed[0];
+        assert_eq!(shipped.card_id, "shipped");
+        assert!((shipped.lead_time_days - (23.0 / 24.0)).abs() < 1e-9);
+    }
+}
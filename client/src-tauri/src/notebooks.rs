@@ -0,0 +1,401 @@
+//! Notebooks and note tags
+//!
+//! Notebooks nest like bookmark folders: `parent_id` points at another
+//! notebook or is `None` at the top level, and `migrate` repairs anything
+//! that does not form a tree. A note sits in at most one notebook
+//! (`notebook_id`) and carries tag keys from the `customTags` map it shares
+//! with bookmarks.
+
+use crate::folders::DeleteMode;
+use crate::parent_tree::{self, TreeItem};
+use crate::tags;
+use crate::{AppData, Note, Notebook};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A notebook with its sub-notebooks, for the sidebar
+#[derive(Debug, Serialize, Clone)]
+pub struct NotebookNode {
+    #[serde(flatten)]
+    pub notebook: Notebook,
+    /// Notes directly in this notebook
+    #[serde(rename = "noteCount")]
+    pub note_count: usize,
+    /// Notes in this notebook and all sub-notebooks
+    #[serde(rename = "totalCount")]
+    pub total_count: usize,
+    pub children: Vec<NotebookNode>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagCount {
+    pub key: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub count: usize,
+}
+
+/// Everything the notes sidebar shows
+#[derive(Debug, Serialize, Clone)]
+pub struct NotebookTree {
+    pub notebooks: Vec<NotebookNode>,
+    /// Notes in no notebook
+    pub unfiled: usize,
+    pub untagged: usize,
+    pub total: usize,
+    /// Tags used on notes, most used first
+    pub tags: Vec<TagCount>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct NoteFilter {
+    /// Only notes in this notebook
+    #[serde(rename = "notebookId", default)]
+    pub notebook_id: Option<String>,
+    /// With `notebook_id`, also notes in its sub-notebooks (default true)
+    #[serde(rename = "includeNested", default)]
+    pub include_nested: Option<bool>,
+    /// Only notes in no notebook
+    #[serde(default)]
+    pub unfiled: bool,
+    /// Only notes with all of these tag keys
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only notes without tags
+    #[serde(default)]
+    pub untagged: bool,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+}
+
+impl TreeItem for Notebook {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+    fn set_parent_id(&mut self, parent_id: Option<String>) {
+        self.parent_id = parent_id;
+    }
+    fn order(&self) -> Option<i32> {
+        self.order
+    }
+}
+
+fn find<'a>(data: &'a AppData, id: &str) -> Option<&'a Notebook> {
+    parent_tree::find(&data.notebooks, id)
+}
+
+/// Ids of the notebook's ancestors, nearest first
+pub fn ancestors(data: &AppData, id: &str) -> Vec<String> {
+    parent_tree::ancestors(&data.notebooks, id)
+}
+
+/// The notebook and all notebooks below it
+pub fn subtree(data: &AppData, id: &str) -> HashSet<String> {
+    parent_tree::subtree(&data.notebooks, id)
+}
+
+/// Repair notebook parents so they form a tree, and drop note references to
+/// notebooks that no longer exist. Returns whether anything changed.
+pub fn migrate(data: &mut AppData) -> bool {
+    let mut changed = parent_tree::repair(&mut data.notebooks);
+    let ids: HashSet<&str> = data.notebooks.iter().map(|n| n.id.as_str()).collect();
+    for note in &mut data.notes {
+        if note
+            .notebook_id
+            .as_ref()
+            .map(|id| !ids.contains(id.as_str()))
+            .unwrap_or(false)
+        {
+            note.notebook_id = None;
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn next_order(data: &AppData, parent_id: Option<&str>) -> i32 {
+    data.notebooks
+        .iter()
+        .filter(|n| n.parent_id.as_deref() == parent_id)
+        .filter_map(|n| n.order)
+        .max()
+        .map(|o| o + 1)
+        .unwrap_or(0)
+}
+
+fn clean_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A notebook needs a name".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Add a notebook at the end of its parent; returns its id
+pub fn create(
+    data: &mut AppData,
+    name: &str,
+    parent_id: Option<&str>,
+    color: Option<String>,
+    now: &str,
+) -> Result<String, String> {
+    let name = clean_name(name)?;
+    if let Some(parent_id) = parent_id {
+        find(data, parent_id).ok_or_else(|| format!("Notebook not found: {}", parent_id))?;
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    let order = next_order(data, parent_id);
+    data.notebooks.push(Notebook {
+        id: id.clone(),
+        name,
+        parent_id: parent_id.map(str::to_string),
+        color,
+        order: Some(order),
+        created_at: now.to_string(),
+    });
+    Ok(id)
+}
+
+pub fn update(
+    data: &mut AppData,
+    id: &str,
+    name: Option<&str>,
+    color: Option<String>,
+) -> Result<(), String> {
+    let name = name.map(clean_name).transpose()?;
+    let notebook = data
+        .notebooks
+        .iter_mut()
+        .find(|n| n.id == id)
+        .ok_or_else(|| format!("Notebook not found: {}", id))?;
+    if let Some(name) = name {
+        notebook.name = name;
+    }
+    if color.is_some() {
+        notebook.color = color;
+    }
+    Ok(())
+}
+
+/// Move a notebook under `parent_id`, or to the top level if `None`
+pub fn move_notebook(data: &mut AppData, id: &str, parent_id: Option<&str>) -> Result<(), String> {
+    find(data, id).ok_or_else(|| format!("Notebook not found: {}", id))?;
+    if let Some(parent_id) = parent_id {
+        find(data, parent_id).ok_or_else(|| format!("Notebook not found: {}", parent_id))?;
+        if parent_id == id || ancestors(data, parent_id).iter().any(|a| a == id) {
+            return Err("A notebook cannot be moved into itself or its sub-notebooks".to_string());
+        }
+    }
+    let order = next_order(data, parent_id);
+    if let Some(notebook) = data.notebooks.iter_mut().find(|n| n.id == id) {
+        notebook.parent_id = parent_id.map(str::to_string);
+        notebook.order = Some(order);
+    }
+    Ok(())
+}
+
+/// Delete a notebook, either with its sub-notebooks or moving its contents up.
+///
+/// Notes are never deleted: with `Cascade` they become unfiled, with
+/// `Reparent` they move into the parent notebook.
+pub fn delete(data: &mut AppData, id: &str, mode: DeleteMode) -> Result<(), String> {
+    let notebook = find(data, id).ok_or_else(|| format!("Notebook not found: {}", id))?;
+    let parent = notebook.parent_id.clone();
+
+    let removed: HashSet<String> = match mode {
+        DeleteMode::Cascade => subtree(data, id),
+        DeleteMode::Reparent => HashSet::from([id.to_string()]),
+    };
+    let target = match mode {
+        DeleteMode::Cascade => None,
+        DeleteMode::Reparent => parent.clone(),
+    };
+
+    if mode == DeleteMode::Reparent {
+        let mut order = next_order(data, parent.as_deref());
+        for notebook in &mut data.notebooks {
+            if notebook.parent_id.as_deref() == Some(id) {
+                notebook.parent_id = parent.clone();
+                notebook.order = Some(order);
+                order += 1;
+            }
+        }
+    }
+    data.notebooks.retain(|n| !removed.contains(&n.id));
+    for note in &mut data.notes {
+        if note
+            .notebook_id
+            .as_ref()
+            .map(|n| removed.contains(n))
+            .unwrap_or(false)
+        {
+            note.notebook_id = target.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Put notes into a notebook, or take them out of any if `None`
+pub fn move_notes(
+    data: &mut AppData,
+    note_ids: &[String],
+    notebook_id: Option<&str>,
+    now: &str,
+) -> Result<usize, String> {
+    if let Some(notebook_id) = notebook_id {
+        find(data, notebook_id).ok_or_else(|| format!("Notebook not found: {}", notebook_id))?;
+    }
+    let wanted: HashSet<&str> = note_ids.iter().map(String::as_str).collect();
+    let mut moved = 0;
+    for note in data
+        .notes
+        .iter_mut()
+        .filter(|n| wanted.contains(n.id.as_str()))
+    {
+        if note.notebook_id.as_deref() != notebook_id {
+            note.notebook_id = notebook_id.map(str::to_string);
+            note.updated_at = now.to_string();
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+/// Add tags (by name, creating custom tags as needed) to notes and remove
+/// tags (by key) from them. Returns how many notes changed.
+pub fn tag_notes(
+    data: &mut AppData,
+    note_ids: &[String],
+    add: &[String],
+    remove: &[String],
+    now: &str,
+) -> usize {
+    let mut add_keys: Vec<String> = Vec::new();
+    for name in add {
+        if let Some((key, _)) = tags::ensure_tag(data, name) {
+            if !add_keys.contains(&key) {
+                add_keys.push(key);
+            }
+        }
+    }
+
+    let wanted: HashSet<&str> = note_ids.iter().map(String::as_str).collect();
+    let mut changed = 0;
+    for note in data
+        .notes
+        .iter_mut()
+        .filter(|n| wanted.contains(n.id.as_str()))
+    {
+        let before = note.tags.clone();
+        note.tags.retain(|t| !remove.contains(t));
+        for key in &add_keys {
+            if !note.tags.contains(key) {
+                note.tags.push(key.clone());
+            }
+        }
+        if note.tags != before {
+            note.updated_at = now.to_string();
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Notes matching `filter`, pinned first and then most recently updated
+pub fn filter(data: &AppData, filter: &NoteFilter) -> Vec<Note> {
+    let notebooks: Option<HashSet<String>> = filter.notebook_id.as_deref().map(|id| {
+        if filter.include_nested.unwrap_or(true) {
+            subtree(data, id)
+        } else {
+            HashSet::from([id.to_string()])
+        }
+    });
+
+    let mut notes: Vec<Note> = data
+        .notes
+        .iter()
+        .filter(|n| match &notebooks {
+            Some(ids) => n
+                .notebook_id
+                .as_ref()
+                .map(|id| ids.contains(id))
+                .unwrap_or(false),
+            None => true,
+        })
+        .filter(|n| !filter.unfiled || n.notebook_id.is_none())
+        .filter(|n| filter.tags.iter().all(|t| n.tags.contains(t)))
+        .filter(|n| !filter.untagged || n.tags.is_empty())
+        .filter(|n| filter.pinned.map(|p| n.is_pinned == p).unwrap_or(true))
+        .cloned()
+        .collect();
+    notes.sort_by(|a, b| {
+        b.is_pinned
+            .cmp(&a.is_pinned)
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
+    notes
+}
+
+/// Notebook tree with note counts, plus counts per tag
+pub fn tree(data: &AppData) -> NotebookTree {
+    let mut direct: HashMap<&str, usize> = HashMap::new();
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    for note in &data.notes {
+        if let Some(notebook) = note.notebook_id.as_deref() {
+            *direct.entry(notebook).or_default() += 1;
+        }
+        for tag in &note.tags {
+            *tag_counts.entry(tag.as_str()).or_default() += 1;
+        }
+    }
+
+    let roots: Vec<&Notebook> = data
+        .notebooks
+        .iter()
+        .filter(|n| n.parent_id.is_none())
+        .collect();
+    let notebooks = parent_tree::build(
+        &data.notebooks,
+        roots,
+        &|notebook, children: Vec<NotebookNode>| {
+            let note_count = direct.get(notebook.id.as_str()).copied().unwrap_or(0);
+            NotebookNode {
+                notebook: notebook.clone(),
+                note_count,
+                total_count: note_count + children.iter().map(|c| c.total_count).sum::<usize>(),
+                children,
+            }
+        },
+    );
+
+    let mut tags: Vec<TagCount> = tag_counts
+        .into_iter()
+        .map(|(key, count)| {
+            let custom = data.custom_tags.get(key);
+            TagCount {
+                key: key.to_string(),
+                name: custom
+                    .map(|t| t.name.clone())
+                    .unwrap_or_else(|| key.to_string()),
+                color: custom.map(|t| t.color.clone()),
+                count,
+            }
+        })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    NotebookTree {
+        notebooks,
+        unfiled: data
+            .notes
+            .iter()
+            .filter(|n| n.notebook_id.is_none())
+            .count(),
+        untagged: data.notes.iter().filter(|n| n.tags.is_empty()).count(),
+        total: data.notes.len(),
+        tags,
+    }
+}
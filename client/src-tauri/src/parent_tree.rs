@@ -0,0 +1,132 @@
+//! Trees stored as parent pointers
+//!
+//! Bookmark folders and notebooks nest the same way: each item's
+//! `parent_id` names another item of the same list, or is `None` at the top
+//! level. These helpers walk and repair any such list; nothing here assumes
+//! the pointers already form a tree.
+
+use std::collections::HashSet;
+
+/// An item of a list that nests through parent ids
+pub trait TreeItem {
+    fn id(&self) -> &str;
+    fn parent_id(&self) -> Option<&str>;
+    fn set_parent_id(&mut self, parent_id: Option<String>);
+    /// Position among its siblings; unordered items go last
+    fn order(&self) -> Option<i32>;
+}
+
+pub fn find<'a, T: TreeItem>(items: &'a [T], id: &str) -> Option<&'a T> {
+    items.iter().find(|item| item.id() == id)
+}
+
+fn parent_of<T: TreeItem>(items: &[T], id: &str) -> Option<String> {
+    find(items, id)
+        .and_then(|item| item.parent_id())
+        .map(str::to_string)
+}
+
+/// Ids of the item's ancestors, nearest first
+pub fn ancestors<T: TreeItem>(items: &[T], id: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut current = parent_of(items, id);
+    while let Some(parent) = current {
+        // Stop on cycles in data `repair` has not fixed yet
+        if parent == id || out.contains(&parent) {
+            break;
+        }
+        current = parent_of(items, &parent);
+        out.push(parent);
+    }
+    out
+}
+
+/// The item and all items below it
+pub fn subtree<T: TreeItem>(items: &[T], id: &str) -> HashSet<String> {
+    let mut found: HashSet<String> = HashSet::from([id.to_string()]);
+    let mut queue = vec![id.to_string()];
+    while let Some(current) = queue.pop() {
+        for child in items {
+            if child.parent_id() == Some(current.as_str()) && found.insert(child.id().to_string()) {
+                queue.push(child.id().to_string());
+            }
+        }
+    }
+    found
+}
+
+/// Clear parents that no longer exist and parents that would close a cycle,
+/// so the items form a tree. Returns whether anything changed.
+pub fn repair<T: TreeItem>(items: &mut [T]) -> bool {
+    let mut changed = false;
+    let ids: HashSet<String> = items.iter().map(|item| item.id().to_string()).collect();
+    for item in items.iter_mut() {
+        let dangling = item
+            .parent_id()
+            .map(|p| !ids.contains(p) || p == item.id())
+            .unwrap_or(false);
+        if dangling {
+            item.set_parent_id(None);
+            changed = true;
+        }
+    }
+
+    for index in 0..items.len() {
+        let id = items[index].id().to_string();
+        let mut seen = HashSet::from([id.clone()]);
+        let mut current = items[index].parent_id().map(str::to_string);
+        while let Some(parent) = current {
+            if !seen.insert(parent.clone()) {
+                // Walking up came back round; cut the loop where it closes
+                if parent == id {
+                    items[index].set_parent_id(None);
+                    changed = true;
+                }
+                break;
+            }
+            current = parent_of(items, &parent);
+        }
+    }
+    changed
+}
+
+fn sort_siblings<T: TreeItem>(siblings: &mut [&T]) {
+    siblings.sort_by_key(|item| item.order().unwrap_or(i32::MAX));
+}
+
+/// Nest `roots` and everything below them, siblings in order.
+///
+/// `node` turns an item and its already built children into a tree node.
+/// An item reachable twice, through a cycle not yet repaired, appears once.
+pub fn build<'a, T: TreeItem, N>(
+    items: &'a [T],
+    mut roots: Vec<&'a T>,
+    node: &impl Fn(&'a T, Vec<N>) -> N,
+) -> Vec<N> {
+    fn build_node<'a, T: TreeItem, N>(
+        items: &'a [T],
+        item: &'a T,
+        node: &impl Fn(&'a T, Vec<N>) -> N,
+        visited: &mut HashSet<String>,
+    ) -> N {
+        visited.insert(item.id().to_string());
+        let mut children: Vec<&T> = items
+            .iter()
+            .filter(|child| child.parent_id() == Some(item.id()))
+            .filter(|child| !visited.contains(child.id()))
+            .collect();
+        sort_siblings(&mut children);
+        let children = children
+            .into_iter()
+            .map(|child| build_node(items, child, node, visited))
+            .collect();
+        node(item, children)
+    }
+
+    sort_siblings(&mut roots);
+    let mut visited = HashSet::new();
+    roots
+        .into_iter()
+        .map(|root| build_node(items, root, node, &mut visited))
+        .collect()
+}
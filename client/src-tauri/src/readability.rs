@@ -0,0 +1,604 @@
+//! Readability-style main content extraction
+//!
+//! Scores block elements by how much prose they hold (text length, commas,
+//! link density and class/id hints), picks the best container plus related
+//! siblings, and re-serializes them as clean, minimal HTML.
+
+use crate::html::{self, Document, NodeKind};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Paragraphs shorter than this do not count towards a container's score
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Elements that are never part of an article
+const UNWANTED: [&str; 20] = [
+    "head", "script", "style", "noscript", "template", "iframe", "object", "embed", "form", "nav",
+    "footer", "aside", "button", "input", "select", "textarea", "svg", "canvas", "link", "meta",
+];
+
+/// Children that stop a `<div>` from being treated as a paragraph
+const BLOCK_ELEMENTS: [&str; 12] = [
+    "blockquote",
+    "dl",
+    "div",
+    "img",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+    "section",
+    "article",
+    "figure",
+];
+
+/// Elements kept in the output as they are
+const KEPT_ELEMENTS: [&str; 45] = [
+    "p",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "code",
+    "em",
+    "strong",
+    "b",
+    "i",
+    "u",
+    "s",
+    "mark",
+    "small",
+    "sub",
+    "sup",
+    "abbr",
+    "q",
+    "cite",
+    "del",
+    "ins",
+    "kbd",
+    "samp",
+    "var",
+    "time",
+    "figure",
+    "figcaption",
+    "table",
+    "caption",
+    "thead",
+    "tbody",
+    "tfoot",
+    "tr",
+    "td",
+    "th",
+    "div",
+];
+
+const UNLIKELY_HINTS: [&str; 29] = [
+    "banner",
+    "breadcrumb",
+    "combx",
+    "comment",
+    "community",
+    "cookie",
+    "disqus",
+    "footer",
+    "gdpr",
+    "header",
+    "menu",
+    "modal",
+    "newsletter",
+    "pagination",
+    "pager",
+    "popup",
+    "related",
+    "remark",
+    "replies",
+    "rss",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "social",
+    "sponsor",
+    "subscribe",
+    "ad-break",
+    "agegate",
+];
+const MAYBE_HINTS: [&str; 7] = [
+    "and", "article", "body", "column", "content", "main", "shadow",
+];
+const POSITIVE_HINTS: [&str; 12] = [
+    "article", "body", "content", "entry", "hentry", "h-entry", "main", "page", "post", "text",
+    "blog", "story",
+];
+const NEGATIVE_HINTS: [&str; 24] = [
+    "hidden",
+    "banner",
+    "combx",
+    "comment",
+    "com-",
+    "contact",
+    "footer",
+    "footnote",
+    "masthead",
+    "media",
+    "meta",
+    "outbrain",
+    "promo",
+    "related",
+    "scroll",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "sponsor",
+    "shopping",
+    "widget",
+    "ad-",
+    "nav",
+];
+
+#[derive(Debug, Clone)]
+pub struct Article {
+    /// Cleaned HTML fragment of the main content
+    pub content: String,
+    pub byline: Option<String>,
+    pub word_count: usize,
+    /// Absolute URLs of the images referenced by `content`, in order
+    pub images: Vec<String>,
+}
+
+fn hints(doc: &Document, id: usize) -> String {
+    format!(
+        "{} {}",
+        doc.attr(id, "class").unwrap_or_default(),
+        doc.attr(id, "id").unwrap_or_default()
+    )
+    .to_ascii_lowercase()
+}
+
+fn matches_any(hints: &str, words: &[&str]) -> bool {
+    words.iter().any(|w| hints.contains(w))
+}
+
+fn class_weight(doc: &Document, id: usize) -> f64 {
+    let hints = hints(doc, id);
+    let mut weight = 0.0;
+    if matches_any(&hints, &POSITIVE_HINTS) {
+        weight += 25.0;
+    }
+    if matches_any(&hints, &NEGATIVE_HINTS) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+fn is_hidden(doc: &Document, id: usize) -> bool {
+    let style = doc
+        .attr(id, "style")
+        .unwrap_or_default()
+        .replace(' ', "")
+        .to_ascii_lowercase();
+    doc.attr(id, "hidden").is_some()
+        || doc.attr(id, "aria-hidden") == Some("true")
+        || style.contains("display:none")
+        || style.contains("visibility:hidden")
+}
+
+fn is_unwanted(doc: &Document, id: usize) -> bool {
+    let Some(name) = doc.name(id) else {
+        return false;
+    };
+    if UNWANTED.contains(&name) || is_hidden(doc, id) {
+        return true;
+    }
+    if matches!(name, "#root" | "html" | "body" | "article" | "main" | "a") {
+        return false;
+    }
+    let hints = hints(doc, id);
+    matches_any(&hints, &UNLIKELY_HINTS) && !matches_any(&hints, &MAYBE_HINTS)
+}
+
+fn initial_score(doc: &Document, id: usize) -> f64 {
+    let base = match doc.name(id).unwrap_or_default() {
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    base + class_weight(doc, id)
+}
+
+/// Per-node measurements, computed once bottom-up
+struct Stats {
+    skip: Vec<bool>,
+    /// Characters of (whitespace-collapsed) text below the node
+    text_len: Vec<usize>,
+    /// Characters of that text inside links
+    link_len: Vec<usize>,
+    images: Vec<usize>,
+}
+
+impl Stats {
+    fn new(doc: &Document) -> Stats {
+        let n = doc.nodes.len();
+        let mut skip = vec![false; n];
+        for id in 0..n {
+            let parent_skipped = doc.nodes[id].parent.map(|p| skip[p]).unwrap_or(false);
+            skip[id] = parent_skipped || is_unwanted(doc, id);
+        }
+
+        let mut text_len = vec![0; n];
+        let mut link_len = vec![0; n];
+        let mut images = vec![0; n];
+        // Children always come after their parent, so walk backwards
+        for id in (0..n).rev() {
+            if skip[id] {
+                continue;
+            }
+            match &doc.nodes[id].kind {
+                NodeKind::Text(text) => {
+                    text_len[id] = html::collapse_whitespace(text).chars().count();
+                }
+                NodeKind::Element(tag) => {
+                    for &child in &doc.nodes[id].children {
+                        text_len[id] += text_len[child];
+                        link_len[id] += link_len[child];
+                        images[id] += images[child];
+                    }
+                    if tag.name == "a" {
+                        link_len[id] = text_len[id];
+                    } else if tag.name == "img" {
+                        images[id] += 1;
+                    }
+                }
+            }
+        }
+
+        Stats {
+            skip,
+            text_len,
+            link_len,
+            images,
+        }
+    }
+
+    fn link_density(&self, id: usize) -> f64 {
+        if self.text_len[id] == 0 {
+            0.0
+        } else {
+            self.link_len[id] as f64 / self.text_len[id] as f64
+        }
+    }
+}
+
+fn is_paragraph(doc: &Document, id: usize) -> bool {
+    match doc.name(id) {
+        Some("p" | "pre" | "td" | "blockquote") => true,
+        Some("div" | "section") => doc
+            .elements(id)
+            .all(|c| !BLOCK_ELEMENTS.contains(&doc.name(c).unwrap_or_default())),
+        _ => false,
+    }
+}
+
+/// Score containers by the paragraphs inside them and return the best one
+fn top_candidate(doc: &Document, stats: &Stats) -> Option<(usize, HashMap<usize, f64>)> {
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+    for id in 0..doc.nodes.len() {
+        if stats.skip[id] || !is_paragraph(doc, id) || stats.text_len[id] < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let text = doc.text(id);
+        let commas = text.matches([',', '\u{ff0c}']).count();
+        let score = 1.0 + commas as f64 + (stats.text_len[id] as f64 / 100.0).min(3.0);
+
+        let mut ancestor = doc.nodes[id].parent;
+        for level in 0..3 {
+            let Some(candidate) = ancestor.filter(|&a| a != 0) else {
+                break;
+            };
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                _ => level as f64 * 3.0,
+            };
+            *scores
+                .entry(candidate)
+                .or_insert_with(|| initial_score(doc, candidate)) += score / divider;
+            ancestor = doc.nodes[candidate].parent;
+        }
+    }
+
+    for (id, score) in scores.iter_mut() {
+        *score *= 1.0 - stats.link_density(*id);
+    }
+    let top = scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(id, _)| *id)?;
+    Some((top, scores))
+}
+
+/// The top candidate plus siblings that look like part of the same article
+fn select_content(
+    doc: &Document,
+    stats: &Stats,
+    top: usize,
+    scores: &HashMap<usize, f64>,
+) -> Vec<usize> {
+    let Some(parent) = doc.nodes[top].parent.filter(|&p| p != 0) else {
+        return vec![top];
+    };
+    let top_score = scores.get(&top).copied().unwrap_or(0.0);
+    let threshold = (top_score * 0.2).max(10.0);
+    let top_class = doc.attr(top, "class").unwrap_or_default();
+
+    doc.elements(parent)
+        .filter(|&sibling| {
+            if sibling == top {
+                return true;
+            }
+            if stats.skip[sibling] {
+                return false;
+            }
+            let mut score = scores.get(&sibling).copied().unwrap_or(0.0);
+            if !top_class.is_empty() && doc.attr(sibling, "class") == Some(top_class) {
+                score += top_score * 0.2;
+            }
+            if score >= threshold {
+                return true;
+            }
+            if doc.name(sibling) != Some("p") {
+                return false;
+            }
+            let len = stats.text_len[sibling];
+            let density = stats.link_density(sibling);
+            let text = doc.text(sibling);
+            (len > 80 && density < 0.25)
+                || (len > 0 && density == 0.0 && (text.contains(". ") || text.ends_with('.')))
+        })
+        .collect()
+}
+
+/// Replace each run of whitespace with a single space, keeping word boundaries
+fn squeeze_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn resolve_url(base: &Url, href: &str, schemes: &[&str]) -> Option<String> {
+    let url = base.join(href.trim()).ok()?;
+    schemes.contains(&url.scheme()).then(|| url.to_string())
+}
+
+/// The real image source; lazy-loading pages keep it in `data-*` or `srcset`
+fn image_source(doc: &Document, id: usize) -> Option<&str> {
+    let usable = |v: &&str| !v.trim().is_empty() && !v.trim_start().starts_with("data:");
+    doc.attr(id, "src")
+        .filter(usable)
+        .or_else(|| {
+            ["data-src", "data-original", "data-lazy-src", "data-url"]
+                .iter()
+                .find_map(|a| doc.attr(id, a).filter(usable))
+        })
+        .or_else(|| {
+            doc.attr(id, "srcset")
+                .or_else(|| doc.attr(id, "data-srcset"))
+                .and_then(|set| set.split(',').next())
+                .and_then(|candidate| candidate.split_whitespace().next())
+        })
+}
+
+struct Writer<'a> {
+    doc: &'a Document,
+    stats: &'a Stats,
+    base: &'a Url,
+    out: String,
+    images: Vec<String>,
+    seen_images: HashSet<String>,
+    words: usize,
+    pre_depth: usize,
+}
+
+impl Writer<'_> {
+    /// Drop boilerplate blocks inside the chosen content (share bars, link lists...)
+    fn is_clutter(&self, id: usize, name: &str) -> bool {
+        let stats = self.stats;
+        if stats.text_len[id] == 0 && stats.images[id] == 0 {
+            return !matches!(name, "br" | "hr" | "img" | "td" | "th");
+        }
+        if !matches!(name, "div" | "section" | "ul" | "ol" | "table" | "dl") {
+            return false;
+        }
+        let weight = class_weight(self.doc, id);
+        weight < 0.0
+            || (stats.link_density(id) > 0.5 && weight < 25.0)
+            || (stats.text_len[id] < MIN_PARAGRAPH_CHARS && stats.images[id] == 0)
+    }
+
+    fn write_children(&mut self, id: usize) {
+        for &child in &self.doc.nodes[id].children {
+            self.write(child);
+        }
+    }
+
+    fn write(&mut self, id: usize) {
+        let doc = self.doc;
+        if self.stats.skip[id] {
+            return;
+        }
+        let tag = match &doc.nodes[id].kind {
+            NodeKind::Text(text) => {
+                self.words += text.split_whitespace().count();
+                if self.pre_depth > 0 {
+                    self.out.push_str(&html::escape(text));
+                } else {
+                    self.out.push_str(&html::escape(&squeeze_whitespace(text)));
+                }
+                return;
+            }
+            NodeKind::Element(tag) => tag,
+        };
+        let name = tag.name.as_str();
+        if self.is_clutter(id, name) {
+            return;
+        }
+
+        match name {
+            "img" => {
+                let Some(src) = image_source(doc, id)
+                    .and_then(|src| resolve_url(self.base, src, &["http", "https"]))
+                else {
+                    return;
+                };
+                let alt = doc.attr(id, "alt").unwrap_or_default();
+                self.out.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">",
+                    html::escape(&src),
+                    html::escape(alt)
+                ));
+                if self.seen_images.insert(src.clone()) {
+                    self.images.push(src);
+                }
+            }
+            "br" => self.out.push_str("<br>"),
+            "hr" => self.out.push_str("<hr>"),
+            "a" => {
+                let href = doc
+                    .attr(id, "href")
+                    .filter(|h| !h.trim_start().starts_with('#'))
+                    .and_then(|h| resolve_url(self.base, h, &["http", "https", "mailto"]));
+                match href {
+                    Some(href) => {
+                        self.out
+                            .push_str(&format!("<a href=\"{}\">", html::escape(&href)));
+                        self.write_children(id);
+                        self.out.push_str("</a>");
+                    }
+                    None => self.write_children(id),
+                }
+            }
+            _ => {
+                // The page title is shown separately, so demote in-article h1s
+                let output = match name {
+                    "h1" => "h2",
+                    "section" | "article" | "main" => "div",
+                    other if KEPT_ELEMENTS.contains(&other) => other,
+                    _ => {
+                        self.write_children(id);
+                        return;
+                    }
+                };
+                let mut attrs = String::new();
+                if matches!(output, "td" | "th") {
+                    for key in ["colspan", "rowspan"] {
+                        if let Some(value) = doc.attr(id, key) {
+                            attrs.push_str(&format!(" {}=\"{}\"", key, html::escape(value)));
+                        }
+                    }
+                }
+                self.out.push_str(&format!("<{}{}>", output, attrs));
+                if output == "pre" {
+                    self.pre_depth += 1;
+                }
+                self.write_children(id);
+                if output == "pre" {
+                    self.pre_depth -= 1;
+                }
+                self.out.push_str(&format!("</{}>", output));
+            }
+        }
+    }
+}
+
+fn find_byline(doc: &Document) -> Option<String> {
+    let from_meta = (0..doc.nodes.len()).find_map(|id| {
+        if doc.name(id) != Some("meta") {
+            return None;
+        }
+        let key = doc.attr(id, "name").or_else(|| doc.attr(id, "property"))?;
+        matches!(
+            key.to_ascii_lowercase().as_str(),
+            "author" | "article:author"
+        )
+        .then(|| doc.attr(id, "content"))
+        .flatten()
+        .map(html::collapse_whitespace)
+        // article:author is often a profile URL rather than a name
+        .filter(|v| !v.is_empty() && !v.starts_with("http"))
+    });
+    from_meta.or_else(|| {
+        (0..doc.nodes.len()).find_map(|id| {
+            doc.name(id)?;
+            let is_byline = doc.attr(id, "rel") == Some("author")
+                || doc
+                    .attr(id, "itemprop")
+                    .map(|p| p.contains("author"))
+                    .unwrap_or(false)
+                || hints(doc, id).contains("byline");
+            if !is_byline {
+                return None;
+            }
+            let text = doc.text(id);
+            (!text.is_empty() && text.chars().count() < 100).then_some(text)
+        })
+    })
+}
+
+/// Extract the main content of a page
+pub fn extract(document: &str, base: &Url) -> Option<Article> {
+    let doc = Document::parse(document);
+    let stats = Stats::new(&doc);
+
+    let content_nodes = match top_candidate(&doc, &stats) {
+        Some((top, scores)) => select_content(&doc, &stats, top, &scores),
+        None => vec![doc.find("body")?],
+    };
+
+    let mut writer = Writer {
+        doc: &doc,
+        stats: &stats,
+        base,
+        out: String::new(),
+        images: vec![],
+        seen_images: HashSet::new(),
+        words: 0,
+        pre_depth: 0,
+    };
+    for id in content_nodes {
+        writer.write(id);
+    }
+    if writer.words == 0 && writer.images.is_empty() {
+        return None;
+    }
+
+    Some(Article {
+        content: writer.out,
+        byline: find_byline(&doc),
+        word_count: writer.words,
+        images: writer.images,
+    })
+}
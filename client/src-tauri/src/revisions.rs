@@ -0,0 +1,601 @@
+//! Revision history for note content and card descriptions
+//!
+//! Every write that changes a note's content or a card's description adds
+//! a revision, stored deflate-compressed in `revisions/<kind>-<id>.json`
+//! in the data directory. Saves that follow each other quickly (typing)
+//! update the newest revision instead of adding one per keystroke.
+
+use crate::AppData;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const REVISIONS_DIR: &str = "revisions";
+/// Revisions kept per note or card; the oldest are dropped first
+const MAX_REVISIONS: usize = 200;
+/// A save within this long of the newest revision replaces it...
+const COALESCE_WINDOW_SECS: i64 = 60;
+/// ...unless that revision already spans this long of editing
+const MAX_REVISION_SPAN_SECS: i64 = 600;
+/// Diffs bigger than this (changed lines squared) show as replace-all
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum RevisionKind {
+    Note,
+    Card,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct StoredRevision {
+    id: String,
+    /// First save folded into this revision
+    started: String,
+    /// Last save folded into this revision
+    timestamp: String,
+    /// Deflated, base64-encoded text
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct RevisionFile {
+    #[serde(default)]
+    revisions: Vec<StoredRevision>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RevisionInfo {
+    pub id: String,
+    pub started: String,
+    pub timestamp: String,
+    /// Characters in the text
+    pub size: usize,
+    pub lines: usize,
+    /// First non-empty line, for telling revisions apart
+    pub preview: String,
+    /// Whether this is what the note or card says now
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+    /// 1-based line in the older text; `None` for inserted lines
+    #[serde(rename = "oldLine")]
+    pub old_line: Option<usize>,
+    /// 1-based line in the newer text; `None` for deleted lines
+    #[serde(rename = "newLine")]
+    pub new_line: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RevisionDiff {
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+// ============================================
+// Storage
+// ============================================
+
+fn compress(text: &str) -> Result<String, String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(text.as_bytes())
+        .and_then(|_| encoder.finish())
+        .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+        .map_err(|e| format!("Failed to compress revision: {}", e))
+}
+
+fn decompress(encoded: &str) -> Result<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Corrupt revision: {}", e))?;
+    let mut text = String::new();
+    DeflateDecoder::new(bytes.as_slice())
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Corrupt revision: {}", e))?;
+    Ok(text)
+}
+
+fn file_path(data_dir: &Path, kind: RevisionKind, id: &str) -> PathBuf {
+    let prefix = match kind {
+        RevisionKind::Note => "note",
+        RevisionKind::Card => "card",
+    };
+    let safe_id: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    data_dir
+        .join(REVISIONS_DIR)
+        .join(format!("{}-{}.json", prefix, safe_id))
+}
+
+fn load(data_dir: &Path, kind: RevisionKind, id: &str) -> RevisionFile {
+    fs::read_to_string(file_path(data_dir, kind, id))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(data_dir: &Path, kind: RevisionKind, id: &str, file: &RevisionFile) -> Result<(), String> {
+    let path = file_path(data_dir, kind, id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create revisions folder: {}", e))?;
+    }
+    let json =
+        serde_json::to_string(file).map_err(|e| format!("Failed to serialize revisions: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write revisions: {}", e))
+}
+
+// ============================================
+// Recording
+// ============================================
+
+/// Current note content and card descriptions, by kind and id
+fn texts(data: &AppData) -> HashMap<(RevisionKind, &str), &str> {
+    let mut out = HashMap::new();
+    for note in &data.notes {
+        out.insert(
+            (RevisionKind::Note, note.id.as_str()),
+            note.content.as_str(),
+        );
+    }
+    for board in &data.boards {
+        let cards = board
+            .columns
+            .iter()
+            .flat_map(|c| c.cards.iter())
+            .chain(board.archived_cards.iter());
+        for card in cards {
+            out.insert(
+                (RevisionKind::Card, card.id.as_str()),
+                card.description.as_str(),
+            );
+        }
+    }
+    out
+}
+
+fn seconds_between(earlier: &str, later: &str) -> Option<i64> {
+    let earlier = chrono::DateTime::parse_from_rfc3339(earlier).ok()?;
+    let later = chrono::DateTime::parse_from_rfc3339(later).ok()?;
+    Some((later - earlier).num_seconds())
+}
+
+/// Add revisions for every note and card whose text changed since `previous`
+pub fn record(
+    data_dir: &Path,
+    previous: Option<&AppData>,
+    data: &AppData,
+    now: &str,
+) -> Result<(), String> {
+    let before = previous.map(texts).unwrap_or_default();
+    for ((kind, id), text) in texts(data) {
+        let old = before.get(&(kind, id)).copied();
+        if old == Some(text) || (old.is_none() && text.is_empty()) {
+            continue;
+        }
+
+        let mut file = load(data_dir, kind, id);
+        let mut baseline = false;
+        if file.revisions.is_empty() {
+            // Keep what was there before the first recorded edit
+            if let Some(old) = old.filter(|o| !o.is_empty()) {
+                file.revisions.push(StoredRevision {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    started: now.to_string(),
+                    timestamp: now.to_string(),
+                    text: compress(old)?,
+                });
+                baseline = true;
+            }
+        }
+
+        let coalesce = !baseline
+            && file.revisions.last().is_some_and(|last| {
+                seconds_between(&last.timestamp, now).is_some_and(|s| s < COALESCE_WINDOW_SECS)
+                    && seconds_between(&last.started, now)
+                        .is_some_and(|s| s < MAX_REVISION_SPAN_SECS)
+            });
+        match file.revisions.last_mut() {
+            Some(last) if coalesce => {
+                last.timestamp = now.to_string();
+                last.text = compress(text)?;
+            }
+            _ => file.revisions.push(StoredRevision {
+                id: uuid::Uuid::new_v4().to_string(),
+                started: now.to_string(),
+                timestamp: now.to_string(),
+                text: compress(text)?,
+            }),
+        }
+        if file.revisions.len() > MAX_REVISIONS {
+            let excess = file.revisions.len() - MAX_REVISIONS;
+            file.revisions.drain(..excess);
+        }
+        save(data_dir, kind, id, &file)?;
+    }
+    Ok(())
+}
+
+// ============================================
+// Queries
+// ============================================
+
+/// What the note or card says now
+pub fn current_text(data: &AppData, kind: RevisionKind, id: &str) -> Result<String, String> {
+    texts(data)
+        .get(&(kind, id))
+        .map(|t| t.to_string())
+        .ok_or_else(|| match kind {
+            RevisionKind::Note => format!("Note not found: {}", id),
+            RevisionKind::Card => format!("Card not found: {}", id),
+        })
+}
+
+/// Revisions of a note or card, newest first
+pub fn list(
+    data_dir: &Path,
+    data: &AppData,
+    kind: RevisionKind,
+    id: &str,
+) -> Result<Vec<RevisionInfo>, String> {
+    let current = current_text(data, kind, id)?;
+    let file = load(data_dir, kind, id);
+    let mut out = Vec::new();
+    for revision in file.revisions.iter().rev() {
+        let text = decompress(&revision.text)?;
+        out.push(RevisionInfo {
+            id: revision.id.clone(),
+            started: revision.started.clone(),
+            timestamp: revision.timestamp.clone(),
+            size: text.chars().count(),
+            lines: text.lines().count(),
+            preview: text
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty())
+                .unwrap_or_default()
+                .chars()
+                .take(80)
+                .collect(),
+            current: text == current,
+        });
+    }
+    Ok(out)
+}
+
+/// Text of one revision
+pub fn text(
+    data_dir: &Path,
+    kind: RevisionKind,
+    id: &str,
+    revision_id: &str,
+) -> Result<String, String> {
+    let file = load(data_dir, kind, id);
+    let revision = file
+        .revisions
+        .iter()
+        .find(|r| r.id == revision_id)
+        .ok_or_else(|| format!("Revision not found: {}", revision_id))?;
+    decompress(&revision.text)
+}
+
+/// Line diff from `old` to `new`
+pub fn diff(old: &str, new: &str) -> RevisionDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+
+    // Longest common subsequence of the changed middle
+    let mut ops: Vec<DiffOp> = Vec::new();
+    if a.len() * b.len() <= MAX_DIFF_CELLS {
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(DiffOp::Equal);
+                i += 1;
+                j += 1;
+            } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                ops.push(DiffOp::Insert);
+                j += 1;
+            } else {
+                ops.push(DiffOp::Delete);
+                i += 1;
+            }
+        }
+    } else {
+        ops.extend(std::iter::repeat(DiffOp::Delete).take(a.len()));
+        ops.extend(std::iter::repeat(DiffOp::Insert).take(b.len()));
+    }
+
+    let ops = std::iter::repeat(DiffOp::Equal)
+        .take(prefix)
+        .chain(ops)
+        .chain(std::iter::repeat(DiffOp::Equal).take(suffix));
+    let mut result = RevisionDiff {
+        lines: Vec::new(),
+        added: 0,
+        removed: 0,
+    };
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        let line = match op {
+            DiffOp::Equal => {
+                i += 1;
+                j += 1;
+                DiffLine {
+                    op,
+                    text: old_lines[i - 1].to_string(),
+                    old_line: Some(i),
+                    new_line: Some(j),
+                }
+            }
+            DiffOp::Delete => {
+                i += 1;
+                result.removed += 1;
+                DiffLine {
+                    op,
+                    text: old_lines[i - 1].to_string(),
+                    old_line: Some(i),
+                    new_line: None,
+                }
+            }
+            DiffOp::Insert => {
+                j += 1;
+                result.added += 1;
+                DiffLine {
+                    op,
+                    text: new_lines[j - 1].to_string(),
+                    old_line: None,
+                    new_line: Some(j),
+                }
+            }
+        };
+        result.lines.push(line);
+    }
+    result
+}
+
+/// Put a revision's text back into its note or card
+pub fn restore(
+    data: &mut AppData,
+    kind: RevisionKind,
+    id: &str,
+    text: String,
+    now: &str,
+) -> Result<(), String> {
+    match kind {
+        RevisionKind::Note => {
+            let note = data
+                .notes
+                .iter_mut()
+                .find(|n| n.id == id)
+                .ok_or_else(|| format!("Note not found: {}", id))?;
+            note.content = text;
+            note.updated_at = now.to_string();
+        }
+        RevisionKind::Card => {
+            let card = data
+                .cards_mut()
+                .find(|c| c.id == id)
+                .ok_or_else(|| format!("Card not found: {}", id))?;
+            card.description = text;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lines(diff: &RevisionDiff) -> Vec<(DiffOp, &str, Option<usize>, Option<usize>)> {
+        diff.lines
+            .iter()
+            .map(|l| (l.op, l.text.as_str(), l.old_line, l.new_line))
+            .collect()
+    }
+
+    #[test]
+    fn diff_numbers_lines_on_both_sides() {
+        let diff = diff("a\nb\nc\nd", "a\nx\nc\nd\ne");
+        assert_eq!(
+            lines(&diff),
+            [
+                (DiffOp::Equal, "a", Some(1), Some(1)),
+                (DiffOp::Insert, "x", None, Some(2)),
+                (DiffOp::Delete, "b", Some(2), None),
+                (DiffOp::Equal, "c", Some(3), Some(3)),
+                (DiffOp::Equal, "d", Some(4), Some(4)),
+                (DiffOp::Insert, "e", None, Some(5)),
+            ]
+        );
+        assert_eq!((diff.added, diff.removed), (2, 1));
+    }
+
+    #[test]
+    fn diff_keeps_common_lines_in_the_middle() {
+        let diff = diff("head\none\nkeep\ntwo\ntail", "head\nkeep\nthree\ntail");
+        assert_eq!(
+            lines(&diff),
+            [
+                (DiffOp::Equal, "head", Some(1), Some(1)),
+                (DiffOp::Delete, "one", Some(2), None),
+                (DiffOp::Equal, "keep", Some(3), Some(2)),
+                (DiffOp::Insert, "three", None, Some(3)),
+                (DiffOp::Delete, "two", Some(4), None),
+                (DiffOp::Equal, "tail", Some(5), Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_empty_text() {
+        let diff = diff("", "a\nb");
+        assert_eq!((diff.added, diff.removed), (2, 0));
+        assert!(super::diff("same\ntext", "same\ntext")
+            .lines
+            .iter()
+            .all(|l| l.op == DiffOp::Equal));
+    }
+
+    fn data(content: &str) -> AppData {
+        let mut data = crate::get_default_data();
+        data.notes = serde_json::from_value(json!([{
+            "id": "n",
+            "title": "Note",
+            "content": content,
+            "createdAt": "2025-01-01T00:00:00Z",
+            "updatedAt": "2025-01-01T00:00:00Z",
+        }]))
+        .unwrap();
+        data
+    }
+
+    /// Save `versions` in turn at the given seconds past 10:00
+    fn record_all(dir: &Path, versions: &[(&str, i64)]) {
+        for pair in versions.windows(2) {
+            let ((old, _), (new, at)) = (pair[0], pair[1]);
+            let now = format!("2025-01-01T10:{:02}:{:02}Z", at / 60, at % 60);
+            record(dir, Some(&data(old)), &data(new), &now).unwrap();
+        }
+    }
+
+    fn texts_in(dir: &Path) -> Vec<String> {
+        load(dir, RevisionKind::Note, "n")
+            .revisions
+            .iter()
+            .map(|r| decompress(&r.text).unwrap())
+            .collect()
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("carbon-revisions-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn first_edit_keeps_the_old_text() {
+        let dir = temp_dir();
+        record_all(&dir, &[("draft", 0), ("draft two", 1)]);
+        assert_eq!(texts_in(&dir), ["draft", "draft two"]);
+
+        let list = list(&dir, &data("draft two"), RevisionKind::Note, "n").unwrap();
+        assert_eq!(list.len(), 2);
+        assert!(list[0].current);
+        assert!(!list[1].current);
+        assert_eq!(
+            text(&dir, RevisionKind::Note, "n", &list[1].id).unwrap(),
+            "draft"
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn quick_saves_coalesce_within_the_window() {
+        let dir = temp_dir();
+        record_all(
+            &dir,
+            &[("", 0), ("a", 0), ("ab", 30), ("abc", 89), ("abcd", 150)],
+        );
+        // 30 and 89 fall within a minute of the save before; 150 does not
+        assert_eq!(texts_in(&dir), ["abc", "abcd"]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn a_revision_spans_at_most_ten_minutes() {
+        let dir = temp_dir();
+        let texts: Vec<String> = (0..=13).map(|i| "x".repeat(i + 1)).collect();
+        let mut versions: Vec<(&str, i64)> = vec![("", 0)];
+        versions.extend(
+            texts
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (t.as_str(), i as i64 * 50)),
+        );
+        record_all(&dir, &versions);
+
+        // Saves every 50s; the one at 600s starts a new revision
+        let stored = load(&dir, RevisionKind::Note, "n").revisions;
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].started, "2025-01-01T10:00:00Z");
+        assert_eq!(stored[0].timestamp, "2025-01-01T10:09:10Z");
+        assert_eq!(stored[1].started, "2025-01-01T10:10:00Z");
+        assert_eq!(decompress(&stored[1].text).unwrap(), texts[13]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn unchanged_and_new_empty_texts_are_not_recorded() {
+        let dir = temp_dir();
+        record(&dir, None, &data(""), "2025-01-01T10:00:00Z").unwrap();
+        record_all(&dir, &[("same", 0), ("same", 1)]);
+        assert!(texts_in(&dir).is_empty());
+    }
+
+    #[test]
+    fn restore_puts_the_text_back() {
+        let mut data = data("now");
+        restore(
+            &mut data,
+            RevisionKind::Note,
+            "n",
+            "then".to_string(),
+            "2025-02-01T00:00:00Z",
+        )
+        .unwrap();
+        assert_eq!(data.notes[0].content, "then");
+        assert_eq!(data.notes[0].updated_at, "2025-02-01T00:00:00Z");
+        assert!(restore(&mut data, RevisionKind::Card, "n", String::new(), "").is_err());
+    }
+}
@@ -0,0 +1,688 @@
+//! Full-text search across cards, bookmarks and notes
+//!
+//! A small in-memory inverted index, built on the first search and kept up
+//! to date by `update` whenever data is saved. Only documents whose text
+//! changed are re-indexed. Matching is by whole word, prefix and (for
+//! longer words) edit distance, ranked with BM25 over weighted fields.
+
+use crate::AppData;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Bound;
+use std::sync::Mutex;
+
+const FIELDS: usize = 4;
+const TITLE: usize = 0;
+const BODY: usize = 1;
+/// Checklist text for cards, the URL for bookmarks
+const EXTRA: usize = 2;
+const TAGS: usize = 3;
+const FIELD_WEIGHTS: [f32; FIELDS] = [3.0, 1.0, 0.8, 2.0];
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+const PREFIX_WEIGHT: f32 = 0.7;
+const FUZZY_WEIGHT: f32 = 0.5;
+const MAX_EXPANSIONS: usize = 50;
+const DEFAULT_LIMIT: usize = 50;
+/// Characters of context shown before the first match in a snippet
+const SNIPPET_LEAD: usize = 60;
+const SNIPPET_LENGTH: usize = 200;
+
+static INDEX: Mutex<Option<SearchIndex>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum DocKind {
+    Card,
+    Bookmark,
+    Note,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SearchOptions {
+    /// Only search these kinds; everything if `None`
+    #[serde(default)]
+    pub kinds: Option<Vec<DocKind>>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Include archived cards and bookmarks
+    #[serde(rename = "includeArchived", default)]
+    pub include_archived: bool,
+}
+
+/// A run of text, highlighted where it matched the query
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TextPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchHit {
+    pub kind: DocKind,
+    pub id: String,
+    pub title: Vec<TextPart>,
+    /// Context around the first match outside the title; empty if none
+    pub snippet: Vec<TextPart>,
+    pub score: f32,
+    /// Board of a card
+    #[serde(rename = "boardId")]
+    pub board_id: Option<String>,
+    /// Where the result lives, e.g. `Board › Column` or a bookmark's URL
+    pub location: Option<String>,
+    pub archived: bool,
+}
+
+struct Doc {
+    kind: DocKind,
+    id: String,
+    fields: [String; FIELDS],
+    board_id: Option<String>,
+    location: Option<String>,
+    archived: bool,
+    fingerprint: u64,
+}
+
+impl Doc {
+    fn new(
+        kind: DocKind,
+        id: &str,
+        fields: [String; FIELDS],
+        board_id: Option<String>,
+        location: Option<String>,
+        archived: bool,
+    ) -> Doc {
+        let mut hasher = DefaultHasher::new();
+        (&fields, &board_id, &location, archived).hash(&mut hasher);
+        Doc {
+            kind,
+            id: id.to_string(),
+            fields,
+            board_id,
+            location,
+            archived,
+            fingerprint: hasher.finish(),
+        }
+    }
+}
+
+/// Term frequencies of one document, per field
+type Counts = [u32; FIELDS];
+
+#[derive(Default)]
+pub struct SearchIndex {
+    docs: Vec<Option<Doc>>,
+    lengths: Vec<Counts>,
+    free: Vec<usize>,
+    slots: HashMap<(DocKind, String), usize>,
+    postings: BTreeMap<String, HashMap<usize, Counts>>,
+    total_lengths: [u64; FIELDS],
+    live: usize,
+}
+
+// ============================================
+// Tokenizing
+// ============================================
+
+/// Lowercased words with their byte range in `text`
+fn tokens(text: &str) -> Vec<(usize, usize, String)> {
+    let mut out = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                out.push((s, i, text[s..i].to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, text.len(), text[s..].to_lowercase()));
+    }
+    out
+}
+
+/// Levenshtein distance, giving up once it exceeds `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().copied().unwrap_or(0) > max {
+            return None;
+        }
+        previous = current;
+    }
+    previous.last().copied().filter(|d| *d <= max)
+}
+
+// ============================================
+// Documents
+// ============================================
+
+fn documents(data: &AppData) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    for board in &data.boards {
+        let columns = board.columns.iter().flat_map(|c| {
+            c.cards
+                .iter()
+                .map(move |card| (card, c.title.as_str(), false))
+        });
+        let archived = board
+            .archived_cards
+            .iter()
+            .map(|card| (card, "Archive", true));
+        for (card, column, is_archived) in columns.chain(archived) {
+            let checklist: Vec<&str> = card.checklist.iter().map(|i| i.text.as_str()).collect();
+            docs.push(Doc::new(
+                DocKind::Card,
+                &card.id,
+                [
+                    card.title.clone(),
+                    card.description.clone(),
+                    checklist.join("\n"),
+                    String::new(),
+                ],
+                Some(board.id.clone()),
+                Some(format!("{} › {}", board.name, column)),
+                is_archived,
+            ));
+        }
+    }
+
+    for bookmark in &data.bookmarks {
+        let tags: Vec<&str> = bookmark
+            .tags
+            .iter()
+            .map(|key| {
+                data.custom_tags
+                    .get(key)
+                    .map(|t| t.name.as_str())
+                    .unwrap_or(key)
+            })
+            .collect();
+        docs.push(Doc::new(
+            DocKind::Bookmark,
+            &bookmark.id,
+            [
+                bookmark.title.clone(),
+                bookmark.description.clone(),
+                bookmark.url.clone(),
+                tags.join(" "),
+            ],
+            None,
+            Some(bookmark.url.clone()),
+            bookmark.is_archived,
+        ));
+    }
+
+    for note in &data.notes {
+        docs.push(Doc::new(
+            DocKind::Note,
+            &note.id,
+            [
+                note.title.clone(),
+                note.content.clone(),
+                String::new(),
+                String::new(),
+            ],
+            None,
+            None,
+            false,
+        ));
+    }
+    docs
+}
+
+impl SearchIndex {
+    fn insert(&mut self, doc: Doc) {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.docs.push(None);
+                self.lengths.push([0; FIELDS]);
+                self.docs.len() - 1
+            }
+        };
+        let mut lengths = [0; FIELDS];
+        for (field, text) in doc.fields.iter().enumerate() {
+            for (_, _, term) in tokens(text) {
+                self.postings
+                    .entry(term)
+                    .or_default()
+                    .entry(slot)
+                    .or_insert([0; FIELDS])[field] += 1;
+                lengths[field] += 1;
+            }
+            self.total_lengths[field] += u64::from(lengths[field]);
+        }
+        self.slots.insert((doc.kind, doc.id.clone()), slot);
+        self.docs[slot] = Some(doc);
+        self.lengths[slot] = lengths;
+        self.live += 1;
+    }
+
+    fn remove(&mut self, slot: usize) {
+        let Some(doc) = self.docs[slot].take() else {
+            return;
+        };
+        for text in &doc.fields {
+            for (_, _, term) in tokens(text) {
+                if let Some(postings) = self.postings.get_mut(&term) {
+                    postings.remove(&slot);
+                    if postings.is_empty() {
+                        self.postings.remove(&term);
+                    }
+                }
+            }
+        }
+        for field in 0..FIELDS {
+            self.total_lengths[field] -= u64::from(self.lengths[slot][field]);
+        }
+        self.slots.remove(&(doc.kind, doc.id));
+        self.free.push(slot);
+        self.live -= 1;
+    }
+
+    /// Bring the index in line with `data`, re-indexing only changed documents
+    fn sync(&mut self, data: &AppData) {
+        let mut present = HashSet::new();
+        for doc in documents(data) {
+            let key = (doc.kind, doc.id.clone());
+            if let Some(&slot) = self.slots.get(&key) {
+                let unchanged = self.docs[slot]
+                    .as_ref()
+                    .map(|d| d.fingerprint == doc.fingerprint)
+                    .unwrap_or(false);
+                if unchanged {
+                    present.insert(key);
+                    continue;
+                }
+                self.remove(slot);
+            }
+            self.insert(doc);
+            present.insert(key);
+        }
+
+        let stale: Vec<usize> = self
+            .slots
+            .iter()
+            .filter(|(key, _)| !present.contains(*key))
+            .map(|(_, slot)| *slot)
+            .collect();
+        for slot in stale {
+            self.remove(slot);
+        }
+    }
+
+    /// Index terms a query word matches, with how much each match counts
+    fn expand(&self, word: &str) -> Vec<(&str, f32)> {
+        let mut out: Vec<(&str, f32)> = Vec::new();
+        if let Some((term, _)) = self.postings.get_key_value(word) {
+            out.push((term, 1.0));
+        }
+        for (term, _) in self
+            .postings
+            .range::<str, _>((Bound::Included(word), Bound::Unbounded))
+            .skip_while(|(t, _)| t.as_str() == word)
+            .take_while(|(t, _)| t.starts_with(word))
+            .take(MAX_EXPANSIONS)
+        {
+            out.push((term, PREFIX_WEIGHT));
+        }
+
+        let length = word.chars().count();
+        if length >= 4 {
+            let max = if length >= 8 { 2 } else { 1 };
+            let mut fuzzy: Vec<(&str, f32)> = self
+                .postings
+                .keys()
+                .filter(|t| !t.starts_with(word))
+                .filter_map(|t| {
+                    let distance = edit_distance(word, t, max)?;
+                    Some((t.as_str(), FUZZY_WEIGHT / distance as f32))
+                })
+                .collect();
+            fuzzy.sort_by(|a, b| b.1.total_cmp(&a.1));
+            fuzzy.truncate(MAX_EXPANSIONS);
+            out.extend(fuzzy);
+        }
+        out
+    }
+
+    fn field_score(&self, slot: usize, counts: &Counts) -> f32 {
+        let docs = self.live.max(1) as f32;
+        (0..FIELDS)
+            .filter(|f| counts[*f] > 0)
+            .map(|f| {
+                let tf = counts[f] as f32;
+                let average = (self.total_lengths[f] as f32 / docs).max(1.0);
+                let length = self.lengths[slot][f] as f32;
+                FIELD_WEIGHTS[f] * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length / average))
+            })
+            .sum()
+    }
+
+    fn query(&self, query: &str, options: &SearchOptions) -> Vec<SearchHit> {
+        let words: Vec<String> = tokens(query).into_iter().map(|(_, _, t)| t).collect();
+        if words.is_empty() {
+            return vec![];
+        }
+
+        // Every query word has to match; a document's score sums the best
+        // match of each word
+        let mut scores: HashMap<usize, (usize, f32)> = HashMap::new();
+        let mut matched_terms: HashSet<&str> = HashSet::new();
+        for word in &words {
+            let mut best: HashMap<usize, f32> = HashMap::new();
+            for (term, weight) in self.expand(word) {
+                let postings = &self.postings[term];
+                let df = postings.len() as f32;
+                let idf = (1.0 + (self.live as f32 - df + 0.5) / (df + 0.5)).ln();
+                matched_terms.insert(term);
+                for (slot, counts) in postings {
+                    let score = weight * idf * self.field_score(*slot, counts);
+                    let entry = best.entry(*slot).or_insert(0.0);
+                    *entry = entry.max(score);
+                }
+            }
+            for (slot, score) in best {
+                let entry = scores.entry(slot).or_insert((0, 0.0));
+                entry.0 += 1;
+                entry.1 += score;
+            }
+        }
+
+        let mut hits: Vec<(usize, f32)> = scores
+            .into_iter()
+            .filter(|(_, (matched, _))| *matched == words.len())
+            .map(|(slot, (_, score))| (slot, score))
+            .filter(|(slot, _)| {
+                let Some(doc) = &self.docs[*slot] else {
+                    return false;
+                };
+                (options.include_archived || !doc.archived)
+                    && options
+                        .kinds
+                        .as_ref()
+                        .map(|kinds| kinds.contains(&doc.kind))
+                        .unwrap_or(true)
+            })
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        hits.truncate(options.limit.unwrap_or(DEFAULT_LIMIT));
+
+        hits.into_iter()
+            .filter_map(|(slot, score)| {
+                let doc = self.docs[slot].as_ref()?;
+                Some(SearchHit {
+                    kind: doc.kind,
+                    id: doc.id.clone(),
+                    title: highlight(&doc.fields[TITLE], &matched_terms),
+                    snippet: snippet(doc, &matched_terms),
+                    score,
+                    board_id: doc.board_id.clone(),
+                    location: doc.location.clone(),
+                    archived: doc.archived,
+                })
+            })
+            .collect()
+    }
+}
+
+// ============================================
+// Highlighting
+// ============================================
+
+fn highlight(text: &str, terms: &HashSet<&str>) -> Vec<TextPart> {
+    let mut parts: Vec<TextPart> = Vec::new();
+    let mut push = |text: &str, highlight: bool| {
+        if text.is_empty() {
+            return;
+        }
+        match parts.last_mut() {
+            Some(last) if last.highlight == highlight => last.text.push_str(text),
+            _ => parts.push(TextPart {
+                text: text.to_string(),
+                highlight,
+            }),
+        }
+    };
+
+    let mut position = 0;
+    for (start, end, term) in tokens(text) {
+        if terms.contains(term.as_str()) {
+            push(&text[position..start], false);
+            push(&text[start..end], true);
+            position = end;
+        }
+    }
+    push(&text[position..], false);
+    parts
+}
+
+/// A window of text around the first match in the body, checklist/URL or tags
+fn snippet(doc: &Doc, terms: &HashSet<&str>) -> Vec<TextPart> {
+    for field in [BODY, EXTRA, TAGS] {
+        let text = &doc.fields[field];
+        let Some((first, _, _)) = tokens(text)
+            .into_iter()
+            .find(|(_, _, t)| terms.contains(t.as_str()))
+        else {
+            continue;
+        };
+
+        let floor = |i: usize| {
+            (0..=i)
+                .rev()
+                .find(|i| text.is_char_boundary(*i))
+                .unwrap_or(0)
+        };
+        let mut start = floor(first.saturating_sub(SNIPPET_LEAD));
+        // Start at a word boundary unless that loses the whole lead
+        if start > 0 {
+            if let Some(space) = text[start..first].find(char::is_whitespace) {
+                start += space + 1;
+            }
+        }
+        let end = floor((start + SNIPPET_LENGTH).min(text.len()));
+
+        let mut parts = highlight(&text[start..end], terms);
+        for part in &mut parts {
+            part.text = part.text.replace(['\n', '\r'], " ");
+        }
+        if start > 0 {
+            parts.insert(
+                0,
+                TextPart {
+                    text: "…".to_string(),
+                    highlight: false,
+                },
+            );
+        }
+        if end < text.len() {
+            parts.push(TextPart {
+                text: "…".to_string(),
+                highlight: false,
+            });
+        }
+        return parts;
+    }
+    vec![]
+}
+
+// ============================================
+// Shared index
+// ============================================
+
+/// Re-index whatever changed in `data`; a no-op until the first search
+pub fn update(data: &AppData) {
+    let Ok(mut index) = INDEX.lock() else {
+        return;
+    };
+    if let Some(index) = index.as_mut() {
+        index.sync(data);
+    }
+}
+
+/// Search cards, bookmarks and notes, best matches first.
+///
+/// `load` is only called to build the index on the first search; after that
+/// `update` keeps it in step with every save.
+pub fn search(
+    load: impl FnOnce() -> AppData,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<SearchHit> {
+    let Ok(mut index) = INDEX.lock() else {
+        return vec![];
+    };
+    let index = index.get_or_insert_with(|| {
+        let mut index = SearchIndex::default();
+        index.sync(&load());
+        index
+    });
+    index.query(query, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data(notes: &[(&str, &str, &str)]) -> AppData {
+        let notes: Vec<_> = notes
+            .iter()
+            .map(|(id, title, content)| {
+                json!({
+                    "id": id,
+                    "title": title,
+                    "content": content,
+                    "createdAt": "2026-01-01T00:00:00Z",
+                    "updatedAt": "2026-01-01T00:00:00Z",
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({"boards": [], "activeBoard": null, "notes": notes})).unwrap()
+    }
+
+    fn index(notes: &[(&str, &str, &str)]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        index.sync(&data(notes));
+        index
+    }
+
+    fn ids(index: &SearchIndex, query: &str) -> Vec<String> {
+        let mut ids: Vec<String> = index
+            .query(query, &SearchOptions::default())
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn highlighted(parts: &[TextPart]) -> Vec<&str> {
+        parts
+            .iter()
+            .filter(|p| p.highlight)
+            .map(|p| p.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sync_reindexes_changed_documents() {
+        let mut index = index(&[("a", "Groceries", "apples"), ("b", "Work", "report")]);
+        assert_eq!(ids(&index, "apples"), ["a"]);
+
+        index.sync(&data(&[
+            ("a", "Groceries", "pears"),
+            ("b", "Work", "report"),
+        ]));
+        assert!(ids(&index, "apples").is_empty());
+        assert_eq!(ids(&index, "pears"), ["a"]);
+        assert_eq!(index.live, 2);
+        assert!(!index.postings.contains_key("apples"));
+    }
+
+    #[test]
+    fn sync_removes_stale_documents() {
+        let mut index = index(&[("a", "Groceries", "apples"), ("b", "Work", "apples")]);
+        index.sync(&data(&[("b", "Work", "apples")]));
+        assert_eq!(ids(&index, "apples"), ["b"]);
+        assert_eq!(index.live, 1);
+        assert_eq!(index.total_lengths[TITLE], 1);
+
+        // The freed slot is reused
+        index.sync(&data(&[("b", "Work", "apples"), ("c", "New", "apples")]));
+        assert_eq!(index.docs.len(), 2);
+        assert_eq!(ids(&index, "apples"), ["b", "c"]);
+    }
+
+    #[test]
+    fn query_matches_prefixes_and_typos() {
+        let index = index(&[
+            ("a", "Deployment checklist", ""),
+            ("b", "Kubernetes notes", ""),
+            ("c", "Unrelated", ""),
+        ]);
+        assert_eq!(ids(&index, "deploy"), ["a"]);
+        assert_eq!(ids(&index, "kubernetis"), ["b"]);
+        assert_eq!(ids(&index, "chekclist"), ["a"]);
+        // Words under four letters have to match exactly or as a prefix
+        assert_eq!(ids(&index, "kub"), ["b"]);
+        assert!(ids(&index, "nte").is_empty());
+    }
+
+    #[test]
+    fn query_ranks_exact_matches_above_prefixes() {
+        let index = index(&[("a", "Deployments", ""), ("b", "Deploy", "")]);
+        let hits = index.query("deploy", &SearchOptions::default());
+        assert_eq!(hits[0].id, "b");
+        assert_eq!(highlighted(&hits[1].title), ["Deployments"]);
+    }
+
+    #[test]
+    fn query_requires_every_word() {
+        let index = index(&[
+            ("a", "Release plan", "ship the beta"),
+            ("b", "Release notes", "nothing yet"),
+        ]);
+        assert_eq!(ids(&index, "release"), ["a", "b"]);
+        assert_eq!(ids(&index, "release beta"), ["a"]);
+        assert!(ids(&index, "release missing").is_empty());
+    }
+
+    #[test]
+    fn snippet_handles_multibyte_text() {
+        let lead = "é".repeat(100);
+        let content = format!("{} größe straße {}", lead, "ü".repeat(300));
+        let index = index(&[("a", "Umlaute", content.as_str())]);
+        let hits = index.query("straße", &SearchOptions::default());
+        let snippet = &hits[0].snippet;
+        assert_eq!(snippet.first().unwrap().text, "…");
+        assert_eq!(snippet.last().unwrap().text, "…");
+        assert_eq!(highlighted(snippet), ["straße"]);
+    }
+
+    #[test]
+    fn snippet_joins_lines() {
+        let index = index(&[("a", "List", "first line\nsecond match\nthird")]);
+        let hits = index.query("match", &SearchOptions::default());
+        let text: String = hits[0].snippet.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(text, "first line second match third");
+    }
+}
@@ -0,0 +1,373 @@
+//! Read-later service exports
+//!
+//! Parsers for Pocket's HTML and CSV exports, Raindrop.io's CSV export and
+//! Pinboard's JSON export. They produce the same `ImportedBookmark`s as the
+//! browser importers, so `importer::apply` handles tags, collections and
+//! duplicates for all of them.
+
+use crate::html;
+use crate::importer::{split_tags, unix_to_rfc3339, ImportFormat, ImportedBookmark};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Raindrop's folder for bookmarks that are not in a collection
+const RAINDROP_UNSORTED: &str = "Unsorted";
+
+// ============================================
+// CSV
+// ============================================
+
+/// Split CSV text into records (RFC 4180: quoted fields may contain commas,
+/// doubled quotes and line breaks)
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// CSV rows as maps from lowercase header names to values
+fn csv_rows(text: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let mut records = parse_csv(text).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| "The CSV file is empty".to_string())?
+        .into_iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    if !header.iter().any(|h| h == "url") {
+        return Err("The CSV file has no url column".to_string());
+    }
+    Ok(records
+        .map(|record| header.iter().cloned().zip(record).collect())
+        .collect())
+}
+
+fn column<'a>(row: &'a HashMap<String, String>, name: &str) -> &'a str {
+    row.get(name).map(|v| v.trim()).unwrap_or_default()
+}
+
+/// Which service wrote a CSV export, judged by its header line
+pub fn detect_csv(head: &str) -> Option<ImportFormat> {
+    let header = parse_csv(head.lines().next()?).into_iter().next()?;
+    let has = |name: &str| header.iter().any(|h| h.trim().eq_ignore_ascii_case(name));
+    if !has("url") {
+        None
+    } else if has("folder") || has("favorite") {
+        Some(ImportFormat::RaindropCsv)
+    } else if has("time_added") {
+        Some(ImportFormat::PocketCsv)
+    } else {
+        None
+    }
+}
+
+// ============================================
+// Pocket
+// ============================================
+
+/// Pocket's HTML export is a list of links, not a Netscape Bookmark File
+pub fn looks_like_pocket_html(head: &str) -> bool {
+    let lower = head.to_lowercase();
+    lower.contains("pocket export") || (lower.contains("<ul") && !lower.contains("<dl"))
+}
+
+/// Parse Pocket's `ril_export.html`.
+///
+/// Links follow an `<h1>Unread</h1>` or `<h1>Read Archive</h1>` heading;
+/// the archive section maps to archived bookmarks.
+pub fn parse_pocket_html(document: &str) -> Vec<ImportedBookmark> {
+    let mut bookmarks = Vec::new();
+    let mut archived = false;
+    let mut open_heading: Option<usize> = None;
+    let mut open_link: Option<(usize, html::Tag)> = None;
+
+    let text = |start: usize, end: usize| {
+        html::collapse_whitespace(&html::decode_entities(&document[start..end]))
+    };
+
+    for tag in html::tags(document) {
+        match (tag.name.as_str(), tag.closing) {
+            ("h1", false) => open_heading = Some(tag.end),
+            ("h1", true) => {
+                if let Some(start) = open_heading.take() {
+                    archived = text(start, tag.start).to_lowercase().contains("archive");
+                }
+            }
+            ("a", false) => open_link = Some((tag.end, tag)),
+            ("a", true) => {
+                let Some((start, link)) = open_link.take() else {
+                    continue;
+                };
+                let Some(url) = link.attr("href").map(str::trim).filter(|u| !u.is_empty()) else {
+                    continue;
+                };
+                bookmarks.push(ImportedBookmark {
+                    title: text(start, tag.start),
+                    url: url.to_string(),
+                    tags: link.attr("tags").map(split_tags).unwrap_or_default(),
+                    created_at: link
+                        .attr("time_added")
+                        .and_then(|t| t.trim().parse::<i64>().ok())
+                        .and_then(unix_to_rfc3339),
+                    is_archived: archived,
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
+    }
+
+    bookmarks
+}
+
+/// Parse Pocket's CSV export (`title,url,time_added,tags,status`).
+///
+/// Tags are separated by `|` and a status of `archive` marks the item read.
+pub fn parse_pocket_csv(text: &str) -> Result<Vec<ImportedBookmark>, String> {
+    Ok(csv_rows(text)?
+        .iter()
+        .map(|row| ImportedBookmark {
+            title: column(row, "title").to_string(),
+            url: column(row, "url").to_string(),
+            tags: column(row, "tags")
+                .split('|')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            created_at: column(row, "time_added")
+                .parse::<i64>()
+                .ok()
+                .and_then(unix_to_rfc3339),
+            is_archived: column(row, "status").eq_ignore_ascii_case("archive"),
+            ..Default::default()
+        })
+        .collect())
+}
+
+// ============================================
+// Raindrop.io
+// ============================================
+
+/// Parse Raindrop.io's CSV export.
+///
+/// Its `folder` column holds the collection path (`Dev/Rust`), with
+/// `Unsorted` for bookmarks outside any collection.
+pub fn parse_raindrop_csv(text: &str) -> Result<Vec<ImportedBookmark>, String> {
+    Ok(csv_rows(text)?
+        .iter()
+        .map(|row| {
+            let folder = column(row, "folder");
+            let folders = if folder.eq_ignore_ascii_case(RAINDROP_UNSORTED) {
+                vec![]
+            } else {
+                folder
+                    .split('/')
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            };
+            let description = match column(row, "note") {
+                "" => column(row, "excerpt"),
+                note => note,
+            };
+            ImportedBookmark {
+                title: column(row, "title").to_string(),
+                url: column(row, "url").to_string(),
+                description: description.to_string(),
+                folders,
+                tags: split_tags(column(row, "tags")),
+                created_at: chrono::DateTime::parse_from_rfc3339(column(row, "created"))
+                    .ok()
+                    .map(|d| d.with_timezone(&chrono::Utc).to_rfc3339()),
+                is_favorite: column(row, "favorite").eq_ignore_ascii_case("true"),
+                is_archived: false,
+            }
+        })
+        .collect())
+}
+
+// ============================================
+// Pinboard
+// ============================================
+
+#[derive(Deserialize)]
+struct PinboardPost {
+    href: String,
+    /// Pinboard calls the title `description`
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    extended: String,
+    #[serde(default)]
+    time: Option<String>,
+    /// Space separated
+    #[serde(default)]
+    tags: String,
+}
+
+/// Parse Pinboard's JSON export (`/v1/posts/all?format=json`).
+///
+/// Pinboard has no favorites or archive; every post imports as unread.
+pub fn parse_pinboard(json: &str) -> Result<Vec<ImportedBookmark>, String> {
+    let posts: Vec<PinboardPost> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid Pinboard export: {}", e))?;
+    Ok(posts
+        .into_iter()
+        .map(|post| ImportedBookmark {
+            title: post.description,
+            url: post.href,
+            description: post.extended,
+            tags: post.tags.split_whitespace().map(str::to_string).collect(),
+            created_at: post
+                .time
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+                .map(|d| d.with_timezone(&chrono::Utc).to_rfc3339()),
+            ..Default::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_keeps_commas_quotes_and_newlines_in_quoted_fields() {
+        let text = "\u{feff}title,url\r\n\"Hello, world\",https://a.example/\r\n\"Say \"\"hi\"\"\nnow\",https://b.example/\n\n";
+        assert_eq!(
+            parse_csv(text),
+            [
+                vec!["title", "url"],
+                vec!["Hello, world", "https://a.example/"],
+                vec!["Say \"hi\"\nnow", "https://b.example/"],
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_needs_a_url_column() {
+        assert!(csv_rows("").is_err());
+        assert!(csv_rows("title,link\na,b").is_err());
+    }
+
+    #[test]
+    fn detects_csv_exports_by_header() {
+        assert_eq!(
+            detect_csv("id,title,note,excerpt,url,folder,tags,created,cover,highlights,favorite\n"),
+            Some(ImportFormat::RaindropCsv)
+        );
+        assert_eq!(
+            detect_csv("title,url,time_added,tags,status\n"),
+            Some(ImportFormat::PocketCsv)
+        );
+        assert_eq!(detect_csv("name,email\n"), None);
+    }
+
+    #[test]
+    fn raindrop_maps_folders() {
+        let text = "title,note,excerpt,url,folder,tags,created,favorite\n\
+            Unsorted one,,An excerpt,https://a.example/,Unsorted,,2024-01-02T03:04:05.000Z,false\n\
+            Nested,My note,An excerpt,https://b.example/,Dev / Rust,\"rust, async\",,true\n";
+        let items = parse_raindrop_csv(text).unwrap();
+        assert!(items[0].folders.is_empty());
+        assert_eq!(items[0].description, "An excerpt");
+        assert_eq!(
+            items[0].created_at.as_deref(),
+            Some("2024-01-02T03:04:05+00:00")
+        );
+        assert!(!items[0].is_favorite);
+
+        assert_eq!(items[1].folders, ["Dev", "Rust"]);
+        assert_eq!(items[1].description, "My note");
+        assert_eq!(items[1].tags, ["rust", "async"]);
+        assert!(items[1].is_favorite);
+    }
+
+    #[test]
+    fn pocket_html_archives_the_read_section() {
+        let document = r#"<!DOCTYPE html>
+<html><head><title>Pocket Export</title></head><body>
+<h1>Unread</h1>
+<ul>
+  <li><a href="https://a.example/" time_added="1700000000" tags="news,tech">A &amp; B</a></li>
+</ul>
+<h1>Read Archive</h1>
+<ul>
+  <li><a href="https://b.example/" time_added="1700000001" tags="">Old</a></li>
+</ul>
+</body></html>"#;
+        assert!(looks_like_pocket_html(document));
+        let items = parse_pocket_html(document);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "A & B");
+        assert_eq!(items[0].tags, ["news", "tech"]);
+        assert!(!items[0].is_archived);
+        assert_eq!(
+            items[0].created_at.as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert!(items[1].is_archived);
+        assert!(items[1].tags.is_empty());
+    }
+
+    #[test]
+    fn pocket_csv_reads_status_and_tags() {
+        let text = "title,url,time_added,tags,status\n\
+            Unread,https://a.example/,1700000000,news|tech,unread\n\
+            Read,https://b.example/,,,archive\n";
+        let items = parse_pocket_csv(text).unwrap();
+        assert_eq!(items[0].tags, ["news", "tech"]);
+        assert!(!items[0].is_archived);
+        assert!(items[1].is_archived);
+        assert_eq!(items[1].created_at, None);
+    }
+
+    #[test]
+    fn pinboard_reads_posts() {
+        let json = r#"[{"href": "https://a.example/", "description": "Title",
+            "extended": "Notes", "time": "2024-01-02T03:04:05Z", "tags": "rust  async"}]"#;
+        let items = parse_pinboard(json).unwrap();
+        assert_eq!(items[0].title, "Title");
+        assert_eq!(items[0].description, "Notes");
+        assert_eq!(items[0].tags, ["rust", "async"]);
+        assert_eq!(
+            items[0].created_at.as_deref(),
+            Some("2024-01-02T03:04:05+00:00")
+        );
+        assert!(parse_pinboard("{}").is_err());
+    }
+}
@@ -0,0 +1,674 @@
+//! Smart collections: bookmark collections defined by a saved query
+//!
+//! A query is a list of terms combined with `AND`, `OR`, `NOT` (or a leading
+//! `-`) and parentheses; terms next to each other are ANDed. A term is a
+//! bare word or `"quoted phrase"` matched against title, description and
+//! URL, or a `field:value` filter:
+//!
+//! - `tag:rust`, `domain:github.com` (includes subdomains)
+//! - `title:`, `url:`, `desc:` (substring, case-insensitive)
+//! - `added:<30d` (newer than), `added:>1y` (older than), `added:2024-05-01`,
+//!   `added:>=2024-01-01`; units are `h`, `d`, `w`, `m` (30 days) and `y`
+//! - `is:favorite`, `is:archived`
+//! - `has:snapshot`, `has:description`, `has:tags`, `has:folder`
+//! - `collection:Name`, `folder:Name`
+//! - `link:dead` (the last link check's state)
+//!
+//! Like "All Bookmarks", archived bookmarks only match queries that mention
+//! `is:archived`.
+
+use crate::linkcheck::LinkState;
+use crate::tags;
+use crate::{AppData, Bookmark};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DateValue {
+    /// A span back from now
+    Ago(Duration),
+    Day(NaiveDate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Text(String),
+    Tag(String),
+    Domain(String),
+    Title(String),
+    Url(String),
+    Description(String),
+    Added(Comparison, DateValue),
+    Favorite,
+    Archived,
+    HasSnapshot,
+    HasDescription,
+    HasTags,
+    HasFolder,
+    Collection(String),
+    Folder(String),
+    Link(LinkState),
+}
+
+/// A parsed smart collection query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Term(Term),
+    Not(Box<Node>),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+}
+
+/// Bookmarks currently matching one smart collection
+#[derive(Debug, Serialize, Clone)]
+pub struct SmartCollectionMatches {
+    #[serde(rename = "collectionId")]
+    pub collection_id: String,
+    #[serde(rename = "bookmarkIds")]
+    pub bookmark_ids: Vec<String>,
+    /// Set when the saved query no longer parses; nothing matches then
+    pub error: Option<String>,
+}
+
+// ============================================
+// Tokenizer
+// ============================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                // A word runs to whitespace or a parenthesis outside quotes
+                let mut word = String::new();
+                let mut in_quotes = false;
+                while let Some(&c) = chars.peek() {
+                    if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    if c == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                if in_quotes {
+                    return Err(format!("Unclosed quote in {}", word));
+                }
+                tokens.push(match word.as_str() {
+                    "AND" | "&&" => Token::And,
+                    "OR" | "||" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// ============================================
+// Terms
+// ============================================
+
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+fn parse_comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, comparison) in [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (comparison, rest);
+        }
+    }
+    (Comparison::Equal, value)
+}
+
+fn parse_added(value: &str) -> Result<Term, String> {
+    let (comparison, value) = parse_comparison(value.trim());
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(Term::Added(comparison, DateValue::Day(day)));
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid date in added:{}", value))?;
+    let days = |per_unit: i64| amount.checked_mul(per_unit).and_then(Duration::try_days);
+    let span = match unit {
+        "h" => Duration::try_hours(amount),
+        "d" | "" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        "m" => days(30),
+        "y" => days(365),
+        _ => return Err(format!("Unknown time unit in added:{}", value)),
+    }
+    .ok_or_else(|| format!("Time span too long in added:{}", value))?;
+    // `added:7d` reads as "in the last 7 days"
+    let comparison = match comparison {
+        Comparison::Equal => Comparison::Less,
+        other => other,
+    };
+    Ok(Term::Added(comparison, DateValue::Ago(span)))
+}
+
+fn parse_term(word: &str) -> Result<Term, String> {
+    let Some((field, value)) = word.split_once(':') else {
+        return Ok(Term::Text(unquote(word).to_lowercase()));
+    };
+    let value = unquote(value);
+    let lower = value.trim().to_lowercase();
+    let needs_value = |term: Term| {
+        if lower.is_empty() {
+            Err(format!("{}: needs a value", field))
+        } else {
+            Ok(term)
+        }
+    };
+
+    match field.to_lowercase().as_str() {
+        "tag" => needs_value(Term::Tag(tags::tag_key(&value))),
+        "domain" | "site" => {
+            needs_value(Term::Domain(lower.trim_start_matches("www.").to_string()))
+        }
+        "title" => needs_value(Term::Title(lower.clone())),
+        "url" => needs_value(Term::Url(lower.clone())),
+        "desc" | "description" => needs_value(Term::Description(lower.clone())),
+        "added" | "created" => parse_added(&value),
+        "collection" => needs_value(Term::Collection(lower.clone())),
+        "folder" => needs_value(Term::Folder(lower.clone())),
+        "is" => match lower.as_str() {
+            "favorite" | "favourite" | "starred" => Ok(Term::Favorite),
+            "archived" => Ok(Term::Archived),
+            _ => Err(format!("Unknown value in is:{}", value)),
+        },
+        "has" => match lower.as_str() {
+            "snapshot" | "archive" => Ok(Term::HasSnapshot),
+            "description" | "desc" => Ok(Term::HasDescription),
+            "tags" | "tag" => Ok(Term::HasTags),
+            "folder" => Ok(Term::HasFolder),
+            _ => Err(format!("Unknown value in has:{}", value)),
+        },
+        "link" => match lower.as_str() {
+            "ok" => Ok(Term::Link(LinkState::Ok)),
+            "redirected" => Ok(Term::Link(LinkState::Redirected)),
+            "dead" | "broken" => Ok(Term::Link(LinkState::Dead)),
+            "error" => Ok(Term::Link(LinkState::Error)),
+            _ => Err(format!("Unknown value in link:{}", value)),
+        },
+        // `https://...` and similar are plain text, not a misspelt field
+        name if name.chars().all(|c| c.is_ascii_lowercase()) && !value.starts_with('/') => {
+            Err(format!("Unknown field: {}", field))
+        }
+        _ => Ok(Term::Text(unquote(word).to_lowercase())),
+    }
+}
+
+// ============================================
+// Parser
+// ============================================
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// or := and ("OR" and)*
+    fn parse_or(&mut self) -> Result<Node, String> {
+        let mut parts = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            parts.push(self.parse_and()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Node::Or(parts)
+        })
+    }
+
+    /// and := not (["AND"] not)*
+    fn parse_and(&mut self) -> Result<Node, String> {
+        let mut parts = vec![self.parse_not()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Word(_)) | Some(Token::Not) | Some(Token::Open) => {}
+                _ => break,
+            }
+            parts.push(self.parse_not()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Node::And(parts)
+        })
+    }
+
+    /// not := ("NOT" | "-") not | "(" or ")" | term
+    fn parse_not(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Node::Not(Box::new(self.parse_not()?))),
+            Some(Token::Open) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => match word.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => {
+                    Ok(Node::Not(Box::new(Node::Term(parse_term(rest)?))))
+                }
+                _ => Ok(Node::Term(parse_term(&word)?)),
+            },
+            Some(Token::Close) => Err("Unexpected closing parenthesis".to_string()),
+            Some(Token::And) | Some(Token::Or) => {
+                Err("AND/OR needs a search term on both sides".to_string())
+            }
+            None => Err("Expected a search term".to_string()),
+        }
+    }
+}
+
+/// Parse a smart collection query
+pub fn parse(input: &str) -> Result<Query, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err("The query is empty".to_string());
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let query = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(Query(query)),
+        Some(Token::Close) => Err("Unexpected closing parenthesis".to_string()),
+        Some(_) => Err("AND/OR needs a search term on both sides".to_string()),
+    }
+}
+
+// ============================================
+// Evaluation
+// ============================================
+
+impl Node {
+    fn mentions_archived(&self) -> bool {
+        match self {
+            Node::Term(term) => *term == Term::Archived,
+            Node::Not(inner) => inner.mentions_archived(),
+            Node::And(parts) | Node::Or(parts) => parts.iter().any(Node::mentions_archived),
+        }
+    }
+}
+
+fn host(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+fn added_matches(
+    created_at: &str,
+    comparison: Comparison,
+    value: &DateValue,
+    now: DateTime<Utc>,
+) -> bool {
+    let Ok(created) = DateTime::parse_from_rfc3339(created_at) else {
+        return false;
+    };
+    let created = created.with_timezone(&Utc);
+    match value {
+        // "<30d" means less than 30 days old, i.e. created after the threshold
+        DateValue::Ago(span) => {
+            // A span reaching back before the earliest date covers everything
+            let threshold = now
+                .checked_sub_signed(*span)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC);
+            match comparison {
+                Comparison::Less => created > threshold,
+                Comparison::LessOrEqual => created >= threshold,
+                Comparison::Greater => created < threshold,
+                Comparison::GreaterOrEqual => created <= threshold,
+                Comparison::Equal => created > threshold,
+            }
+        }
+        DateValue::Day(day) => {
+            let start = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            let end = start
+                .checked_add_signed(Duration::days(1))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC);
+            match comparison {
+                Comparison::Less => created < start,
+                Comparison::LessOrEqual => created < end,
+                Comparison::Greater => created >= end,
+                Comparison::GreaterOrEqual => created >= start,
+                Comparison::Equal => created >= start && created < end,
+            }
+        }
+    }
+}
+
+fn term_matches(term: &Term, bookmark: &Bookmark, data: &AppData, now: DateTime<Utc>) -> bool {
+    let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(needle);
+    match term {
+        Term::Text(text) => {
+            contains(&bookmark.title, text)
+                || contains(&bookmark.description, text)
+                || contains(&bookmark.url, text)
+        }
+        Term::Tag(key) => bookmark.tags.iter().any(|t| tags::tag_key(t) == *key),
+        Term::Domain(domain) => host(&bookmark.url)
+            .map(|h| h == *domain || h.ends_with(&format!(".{}", domain)))
+            .unwrap_or(false),
+        Term::Title(text) => contains(&bookmark.title, text),
+        Term::Url(text) => contains(&bookmark.url, text),
+        Term::Description(text) => contains(&bookmark.description, text),
+        Term::Added(comparison, value) => {
+            added_matches(&bookmark.created_at, *comparison, value, now)
+        }
+        Term::Favorite => bookmark.is_favorite,
+        Term::Archived => bookmark.is_archived,
+        Term::HasSnapshot => bookmark.snapshot.is_some(),
+        Term::HasDescription => !bookmark.description.trim().is_empty(),
+        Term::HasTags => !bookmark.tags.is_empty(),
+        Term::HasFolder => bookmark.folder_id.is_some(),
+        Term::Collection(name) => bookmark
+            .collection_id
+            .as_deref()
+            .and_then(|id| data.collections.iter().find(|c| c.id == id))
+            .map(|c| c.id.to_lowercase() == *name || c.name.to_lowercase() == *name)
+            .unwrap_or(false),
+        Term::Folder(name) => bookmark
+            .folder_id
+            .as_deref()
+            .and_then(|id| data.bookmark_folders.iter().find(|f| f.id == id))
+            .map(|f| f.name.to_lowercase() == *name)
+            .unwrap_or(false),
+        Term::Link(state) => bookmark
+            .link_check
+            .as_ref()
+            .map(|check| check.state == *state)
+            .unwrap_or(false),
+    }
+}
+
+fn query_matches(query: &Node, bookmark: &Bookmark, data: &AppData, now: DateTime<Utc>) -> bool {
+    match query {
+        Node::Term(term) => term_matches(term, bookmark, data, now),
+        Node::Not(inner) => !query_matches(inner, bookmark, data, now),
+        Node::And(parts) => parts.iter().all(|q| query_matches(q, bookmark, data, now)),
+        Node::Or(parts) => parts.iter().any(|q| query_matches(q, bookmark, data, now)),
+    }
+}
+
+/// Ids of the bookmarks matching `query`, in bookmark order
+pub fn evaluate(data: &AppData, query: &Query, now: DateTime<Utc>) -> Vec<String> {
+    let Query(query) = query;
+    let include_archived = query.mentions_archived();
+    data.bookmarks
+        .iter()
+        .filter(|b| include_archived || !b.is_archived)
+        .filter(|b| query_matches(query, b, data, now))
+        .map(|b| b.id.clone())
+        .collect()
+}
+
+/// Current matches of every smart collection
+pub fn evaluate_all(data: &AppData, now: DateTime<Utc>) -> Vec<SmartCollectionMatches> {
+    data.collections
+        .iter()
+        .filter_map(|collection| {
+            let query = collection.query.as_deref()?;
+            let (bookmark_ids, error) = match parse(query) {
+                Ok(query) => (evaluate(data, &query, now), None),
+                Err(e) => (vec![], Some(e)),
+            };
+            Some(SmartCollectionMatches {
+                collection_id: collection.id.clone(),
+                bookmark_ids,
+                error,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(value: &str) -> Node {
+        Node::Term(Term::Text(value.to_string()))
+    }
+
+    fn parsed(input: &str) -> Node {
+        parse(input).unwrap().0
+    }
+
+    fn bookmark(id: &str, title: &str, url: &str, created_at: &str) -> Bookmark {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": title,
+            "url": url,
+            "createdAt": created_at,
+        }))
+        .unwrap()
+    }
+
+    fn data(bookmarks: Vec<Bookmark>) -> AppData {
+        let mut data = crate::get_default_data();
+        data.bookmarks = bookmarks;
+        data
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parsed("a b OR c AND d"),
+            Node::Or(vec![
+                Node::And(vec![text("a"), text("b")]),
+                Node::And(vec![text("c"), text("d")]),
+            ])
+        );
+        assert_eq!(
+            parsed("a (b OR c)"),
+            Node::And(vec![text("a"), Node::Or(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term_only() {
+        assert_eq!(
+            parsed("NOT a b"),
+            Node::And(vec![Node::Not(Box::new(text("a"))), text("b")])
+        );
+        assert_eq!(
+            parsed("-tag:rust a"),
+            Node::And(vec![
+                Node::Not(Box::new(Node::Term(Term::Tag("rust".to_string())))),
+                text("a"),
+            ])
+        );
+        // A lone dash is just text
+        assert_eq!(parsed("-"), text("-"));
+    }
+
+    #[test]
+    fn quotes_keep_phrases_together() {
+        assert_eq!(parsed("\"Hello World\""), text("hello world"));
+        assert_eq!(
+            parsed("title:\"rust book\" (x)"),
+            Node::And(vec![
+                Node::Term(Term::Title("rust book".to_string())),
+                text("x")
+            ])
+        );
+    }
+
+    #[test]
+    fn urls_are_text_not_fields() {
+        assert_eq!(parsed("https://example.com"), text("https://example.com"));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        for input in [
+            "",
+            "   ",
+            "\"open",
+            "(a",
+            "a)",
+            "OR a",
+            "a AND",
+            "a OR OR b",
+            "()",
+            "nope:x",
+            "tag:",
+            "is:nope",
+            "has:nope",
+            "link:nope",
+            "added:5x",
+            "added:soon",
+        ] {
+            assert!(parse(input).is_err(), "{:?} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn rejects_spans_too_long_to_represent() {
+        for input in [
+            "added:<999999999999d",
+            "added:<99999999999999999h",
+            "added:>9999999999999999w",
+            "added:<999999999999999999m",
+            "added:<999999999999999999y",
+        ] {
+            assert!(parse(input).is_err(), "{:?} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn spans_reaching_before_the_earliest_date_match_everything() {
+        let data = data(vec![bookmark(
+            "1",
+            "Old",
+            "https://a.com",
+            "1990-01-01T00:00:00Z",
+        )]);
+        let query = parse("added:<300000y").unwrap();
+        assert_eq!(evaluate(&data, &query, now()), vec!["1"]);
+        let query = parse("added:>300000y").unwrap();
+        assert!(evaluate(&data, &query, now()).is_empty());
+    }
+
+    #[test]
+    fn added_compares_against_spans_and_days() {
+        let data = data(vec![
+            bookmark("new", "New", "https://a.com", "2025-05-30T12:00:00Z"),
+            bookmark("old", "Old", "https://b.com", "2024-05-01T08:00:00Z"),
+        ]);
+        let ids = |input: &str| evaluate(&data, &parse(input).unwrap(), now());
+        assert_eq!(ids("added:7d"), vec!["new"]);
+        assert_eq!(ids("added:>1m"), vec!["old"]);
+        assert_eq!(ids("added:2024-05-01"), vec!["old"]);
+        assert_eq!(ids("added:>=2025-01-01"), vec!["new"]);
+    }
+
+    #[test]
+    fn evaluates_fields_and_skips_archived_unless_asked() {
+        let mut archived = bookmark(
+            "3",
+            "Rust archive",
+            "https://old.rust-lang.org",
+            "2025-01-01T00:00:00Z",
+        );
+        archived.is_archived = true;
+        let mut tagged = bookmark(
+            "2",
+            "Blog",
+            "https://www.example.com/rust",
+            "2025-01-01T00:00:00Z",
+        );
+        tagged.tags = vec!["rust".to_string()];
+        let data = data(vec![
+            bookmark(
+                "1",
+                "Rust book",
+                "https://doc.rust-lang.org/book",
+                "2025-01-01T00:00:00Z",
+            ),
+            tagged,
+            archived,
+        ]);
+        let ids = |input: &str| evaluate(&data, &parse(input).unwrap(), now());
+        assert_eq!(ids("rust"), vec!["1", "2"]);
+        assert_eq!(ids("domain:rust-lang.org"), vec!["1"]);
+        assert_eq!(ids("domain:example.com -tag:go"), vec!["2"]);
+        assert_eq!(ids("rust is:archived"), vec!["3"]);
+        assert_eq!(ids("tag:rust OR title:book"), vec!["1", "2"]);
+    }
+}
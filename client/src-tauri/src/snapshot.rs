@@ -0,0 +1,245 @@
+//! Offline snapshots of bookmarked pages
+//!
+//! A snapshot is the page's main content (see `readability`) plus its inline
+//! images, stored under `<data dir>/snapshots/<bookmark id>/`. The bookmark
+//! keeps a `PageSnapshot` describing it; the reader view inlines the images
+//! so it renders without any network access.
+
+use crate::asset_cache::{self, CachedAsset};
+use crate::{html, metadata, readability, AppData, Bookmark};
+use base64::Engine;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+const MAX_IMAGES: usize = 40;
+const CONCURRENT_DOWNLOADS: usize = 4;
+const CONTENT_FILE: &str = "index.html";
+
+/// An archived copy of a bookmark's page
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageSnapshot {
+    #[serde(rename = "archivedAt")]
+    pub archived_at: String,
+    /// The page URL after redirects
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub byline: Option<String>,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(rename = "wordCount", default)]
+    pub word_count: usize,
+    /// Images stored next to the content
+    #[serde(default)]
+    pub images: Vec<CachedAsset>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReaderView {
+    #[serde(rename = "bookmarkId")]
+    pub bookmark_id: String,
+    pub url: String,
+    pub title: String,
+    pub byline: Option<String>,
+    #[serde(rename = "archivedAt")]
+    pub archived_at: String,
+    #[serde(rename = "wordCount")]
+    pub word_count: usize,
+    /// Self-contained HTML fragment; images are inlined as data URLs
+    pub html: String,
+}
+
+pub fn get_snapshots_dir(data_dir: &Path) -> PathBuf {
+    let dir = data_dir.join("snapshots");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
+fn safe_id(id: &str) -> String {
+    id.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+fn snapshot_dir(dir: &Path, bookmark_id: &str) -> PathBuf {
+    dir.join(safe_id(bookmark_id))
+}
+
+/// Fetch a page, extract its content and store it with its images.
+///
+/// The snapshot is built in a staging directory and only replaces an
+/// existing one once complete.
+pub async fn capture(
+    client: &reqwest::Client,
+    dir: &Path,
+    bookmark_id: &str,
+    url: &str,
+    now: &str,
+) -> Result<PageSnapshot, String> {
+    let response = metadata::fetch_page(client, url).await?;
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if !metadata::is_html_mime(&metadata::mime_type(content_type.as_deref())) {
+        return Err("Only web pages can be archived".to_string());
+    }
+
+    let body = metadata::read_limited(response, MAX_PAGE_BYTES).await?;
+    let document = metadata::decode_html(&body, content_type.as_deref());
+    let page = metadata::parse_html(&document, &final_url);
+    let article = readability::extract(&document, &final_url)
+        .ok_or_else(|| "Could not find the main content of this page".to_string())?;
+
+    let staging = dir.join(format!("{}.partial", safe_id(bookmark_id)));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).ok();
+    }
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create snapshot: {}", e))?;
+
+    let downloads: Vec<Result<CachedAsset, String>> =
+        stream::iter(article.images.iter().take(MAX_IMAGES))
+            .map(|src| asset_cache::download(client, &staging, src))
+            .buffered(CONCURRENT_DOWNLOADS)
+            .collect()
+            .await;
+
+    // Point stored images at their local file; failed ones keep the remote URL
+    let mut content = article.content;
+    let mut images = Vec::new();
+    for download in downloads {
+        match download {
+            Ok(image) => {
+                content = content.replace(
+                    &format!("src=\"{}\"", html::escape(&image.source)),
+                    &format!("src=\"{}\"", image.file),
+                );
+                images.push(image);
+            }
+            Err(e) => log::warn!("Skipping snapshot image: {}", e),
+        }
+    }
+    fs::write(staging.join(CONTENT_FILE), &content)
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+    let target = snapshot_dir(dir, bookmark_id);
+    if target.exists() {
+        fs::remove_dir_all(&target).map_err(|e| format!("Failed to replace snapshot: {}", e))?;
+    }
+    fs::rename(&staging, &target).map_err(|e| format!("Failed to store snapshot: {}", e))?;
+
+    Ok(PageSnapshot {
+        archived_at: now.to_string(),
+        url: final_url.to_string(),
+        title: page.title,
+        byline: article.byline,
+        excerpt: page.description,
+        word_count: article.word_count,
+        images,
+    })
+}
+
+/// Load a bookmark's snapshot for offline reading
+pub fn reader_view(dir: &Path, bookmark: &Bookmark) -> Result<ReaderView, String> {
+    let snapshot = bookmark
+        .snapshot
+        .as_ref()
+        .ok_or_else(|| "This bookmark has no archived copy".to_string())?;
+    let path = snapshot_dir(dir, &bookmark.id);
+    let mut content = fs::read_to_string(path.join(CONTENT_FILE))
+        .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+
+    for image in &snapshot.images {
+        let Ok(bytes) = fs::read(path.join(&image.file)) else {
+            continue;
+        };
+        let extension = image.file.rsplit('.').next().unwrap_or_default();
+        let data_url = format!(
+            "data:{};base64,{}",
+            asset_cache::content_type_for(extension),
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        );
+        content = content.replace(
+            &format!("src=\"{}\"", image.file),
+            &format!("src=\"{}\"", data_url),
+        );
+    }
+
+    Ok(ReaderView {
+        bookmark_id: bookmark.id.clone(),
+        url: snapshot.url.clone(),
+        title: snapshot
+            .title
+            .clone()
+            .unwrap_or_else(|| bookmark.title.clone()),
+        byline: snapshot.byline.clone(),
+        archived_at: snapshot.archived_at.clone(),
+        word_count: snapshot.word_count,
+        html: content,
+    })
+}
+
+/// Give a bookmark a copy of another bookmark's archived files, for when it
+/// takes over that bookmark's `PageSnapshot`.
+///
+/// The originals stay until the next clean-up, so undoing still finds them.
+pub fn copy_snapshot(dir: &Path, from_id: &str, to_id: &str) -> Result<(), String> {
+    let source = fs::read_dir(snapshot_dir(dir, from_id))
+        .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    let staging = dir.join(format!("{}.partial", safe_id(to_id)));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).ok();
+    }
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create snapshot: {}", e))?;
+    for entry in source.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_file() {
+            fs::copy(&path, staging.join(entry.file_name()))
+                .map_err(|e| format!("Failed to copy snapshot: {}", e))?;
+        }
+    }
+
+    let target = snapshot_dir(dir, to_id);
+    if target.exists() {
+        fs::remove_dir_all(&target).map_err(|e| format!("Failed to replace snapshot: {}", e))?;
+    }
+    fs::rename(&staging, &target).map_err(|e| format!("Failed to store snapshot: {}", e))
+}
+
+/// Delete snapshot directories no bookmark points at any more.
+///
+/// Removing a snapshot only clears the bookmark's field, so it can be undone
+/// until the next clean-up. Returns the number of directories removed.
+pub fn collect_garbage(dir: &Path, data: &AppData) -> usize {
+    let keep: HashSet<String> = data
+        .bookmarks
+        .iter()
+        .filter(|b| b.snapshot.is_some())
+        .map(|b| safe_id(&b.id))
+        .collect();
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if keep.contains(&name) {
+            continue;
+        }
+        match fs::remove_dir_all(entry.path()) {
+            Ok(_) => removed += 1,
+            Err(e) => log::warn!("Failed to remove snapshot {}: {}", name, e),
+        }
+    }
+    removed
+}
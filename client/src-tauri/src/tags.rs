@@ -0,0 +1,83 @@
+//! Bookmark and note tag keys and custom tag creation
+//!
+//! Mirrors the frontend: bookmarks and notes store tag keys (`ui-ux`), the built-in
+//! tags live in `DEFAULT_BOOKMARK_TAGS` and everything else is a
+//! `CustomTag` in `AppData::custom_tags` keyed the same way.
+
+use crate::{AppData, CustomTag};
+
+/// Keep in sync with `TAG_COLORS` in boardStore.js
+pub const TAG_COLORS: [&str; 16] = [
+    "bg-red-500",
+    "bg-orange-500",
+    "bg-amber-500",
+    "bg-yellow-500",
+    "bg-lime-500",
+    "bg-emerald-500",
+    "bg-teal-500",
+    "bg-cyan-500",
+    "bg-sky-500",
+    "bg-blue-500",
+    "bg-indigo-500",
+    "bg-violet-500",
+    "bg-purple-500",
+    "bg-fuchsia-500",
+    "bg-pink-500",
+    "bg-rose-500",
+];
+
+/// Keys of `DEFAULT_BOOKMARK_TAGS` in boardStore.js
+pub const DEFAULT_TAG_KEYS: [&str; 10] = [
+    "react",
+    "typescript",
+    "ui-ux",
+    "nextjs",
+    "tailwind",
+    "tutorial",
+    "documentation",
+    "free",
+    "tool",
+    "inspiration",
+];
+
+/// Key for a tag name, the same way the frontend derives it
+pub fn tag_key(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    let dashed = lower.split_whitespace().collect::<Vec<_>>().join("-");
+    dashed
+        .chars()
+        .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-')
+        .collect()
+}
+
+pub fn is_known(data: &AppData, key: &str) -> bool {
+    DEFAULT_TAG_KEYS.contains(&key) || data.custom_tags.contains_key(key)
+}
+
+/// Next color in `TAG_COLORS`, so new tags cycle through the palette
+fn next_color(data: &AppData) -> &'static str {
+    TAG_COLORS[data.custom_tags.len() % TAG_COLORS.len()]
+}
+
+/// Key for a tag name, creating a `CustomTag` if it does not exist yet.
+///
+/// Returns `None` for names with no usable characters, and whether a tag
+/// was created.
+pub fn ensure_tag(data: &mut AppData, name: &str) -> Option<(String, bool)> {
+    let key = tag_key(name);
+    if key.is_empty() {
+        return None;
+    }
+    if is_known(data, &key) {
+        return Some((key, false));
+    }
+    let color = next_color(data);
+    data.custom_tags.insert(
+        key.clone(),
+        CustomTag {
+            name: name.trim().to_string(),
+            color: color.to_string(),
+        },
+    );
+    Some((key, true))
+}
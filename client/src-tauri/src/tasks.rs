@@ -0,0 +1,641 @@
+//! Markdown task lists and checklists
+//!
+//! Parses `- [ ] item` / `- [x] item` lines (outside fenced code blocks)
+//! and, for cards and notes with `task_sync` on, keeps them in step with
+//! the structured `checklist` both ways. `sync` compares against the data
+//! last written to disk to tell which side was edited: edited text rebuilds
+//! the checklist, an edited checklist rewrites the task lines in place. If
+//! both changed the text wins.
+//!
+//! Checklist item ids survive edits on either side: items are matched by
+//! text first and then by position, so ticking, rewording or reordering an
+//! item keeps its id.
+
+use crate::search::DocKind;
+use crate::{AppData, ChecklistItem};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TaskProgress {
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// A task list line: where it is and what it says
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskLine {
+    line: usize,
+    /// Indentation and bullet, e.g. `  - `
+    prefix: String,
+    completed: bool,
+    text: String,
+}
+
+/// Whether a line opens or closes a fenced code block
+pub(crate) fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Parse a Markdown task list item into (prefix length, completed, text)
+fn parse_task_line(line: &str) -> Option<(usize, bool, &str)> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix([' ', '\t'])?.trim_start();
+    let prefix = line.len() - rest.len();
+    let (completed, rest) = if let Some(rest) = rest.strip_prefix("[ ]") {
+        (false, rest)
+    } else if let Some(rest) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, rest)
+    } else {
+        return None;
+    };
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let text = rest.trim();
+    (!text.is_empty()).then_some((prefix, completed, text))
+}
+
+fn task_lines(text: &str) -> Vec<TaskLine> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    for (index, line) in text.lines().enumerate() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some((prefix, completed, text)) = parse_task_line(line) {
+            out.push(TaskLine {
+                line: index,
+                prefix: line[..prefix].to_string(),
+                completed,
+                text: text.to_string(),
+            });
+        }
+    }
+    out
+}
+
+/// Task list items in Markdown text, in order
+pub fn tasks(text: &str) -> Vec<(bool, String)> {
+    task_lines(text)
+        .into_iter()
+        .map(|t| (t.completed, t.text))
+        .collect()
+}
+
+fn format_task(prefix: &str, item: &ChecklistItem) -> String {
+    let mark = if item.completed { 'x' } else { ' ' };
+    format!("{}[{}] {}", prefix, mark, item.text.replace('\n', " "))
+}
+
+/// Checklist items as a Markdown task list
+pub fn task_list(items: &[ChecklistItem]) -> String {
+    items
+        .iter()
+        .map(|item| format_task("- ", item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split task list items out of Markdown. Returns the remaining text and
+/// the items as new checklist entries.
+pub fn split_tasks(content: &str) -> (String, Vec<ChecklistItem>) {
+    let lines: HashSet<usize> = task_lines(content).iter().map(|t| t.line).collect();
+    let mut kept: Vec<&str> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if lines.contains(&index) {
+            continue;
+        }
+        // Removing a list leaves a gap; keep one blank line of it
+        let blank = line.trim().is_empty();
+        if !(blank && kept.last().map(|l| l.trim().is_empty()).unwrap_or(true)) {
+            kept.push(line);
+        }
+    }
+    let items = tasks(content)
+        .into_iter()
+        .map(|(completed, text)| ChecklistItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            text,
+            completed,
+        })
+        .collect();
+    (kept.join("\n").trim_end().to_string(), items)
+}
+
+pub fn progress(items: &[ChecklistItem]) -> TaskProgress {
+    TaskProgress {
+        completed: items.iter().filter(|i| i.completed).count(),
+        total: items.len(),
+    }
+}
+
+/// Checklist for `tasks`, reusing the ids of `existing` items: by equal
+/// text first, then (when `by_position`) the leftovers in order. Also
+/// returns the existing items nothing matched.
+fn checklist_from(
+    tasks: &[(bool, String)],
+    existing: &[ChecklistItem],
+    by_position: bool,
+) -> (Vec<ChecklistItem>, Vec<ChecklistItem>) {
+    let mut used = vec![false; existing.len()];
+    let mut ids: Vec<Option<String>> = tasks
+        .iter()
+        .map(|(_, text)| {
+            let found = existing
+                .iter()
+                .enumerate()
+                .find(|(i, item)| !used[*i] && item.text.trim() == text.trim())
+                .map(|(i, _)| i)?;
+            used[found] = true;
+            Some(existing[found].id.clone())
+        })
+        .collect();
+    if by_position {
+        let mut free = (0..existing.len())
+            .filter(|i| !used[*i])
+            .collect::<Vec<_>>()
+            .into_iter();
+        for id in ids.iter_mut().filter(|id| id.is_none()) {
+            let Some(index) = free.next() else {
+                break;
+            };
+            used[index] = true;
+            *id = Some(existing[index].id.clone());
+        }
+    }
+
+    let items = tasks
+        .iter()
+        .zip(ids)
+        .map(|((completed, text), id)| ChecklistItem {
+            id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            text: text.clone(),
+            completed: *completed,
+        })
+        .collect();
+    let unmatched = existing
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(item, _)| item.clone())
+        .collect();
+    (items, unmatched)
+}
+
+/// Rewrite the task lines of `text` to show `items`.
+///
+/// `slot_ids` says which item id each task line showed before (by index
+/// into the task lines). Lines of items that are gone are dropped, new
+/// items go right after the item before them, and other lines are left
+/// alone. When the items were reordered the lines are refilled in order.
+fn rewrite_text(text: &str, slot_ids: &[Option<String>], items: &[ChecklistItem]) -> String {
+    let slots = task_lines(text);
+    let position: HashMap<&str, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.id.as_str(), i))
+        .collect();
+    let mut assigned: Vec<Option<usize>> = slots
+        .iter()
+        .enumerate()
+        .map(|(i, _)| {
+            slot_ids
+                .get(i)
+                .and_then(|id| id.as_deref())
+                .and_then(|id| position.get(id).copied())
+        })
+        .collect();
+    let in_order = assigned
+        .iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .windows(2)
+        .all(|w| w[0] < w[1]);
+    if !in_order {
+        assigned = (0..slots.len())
+            .map(|i| (i < items.len()).then_some(i))
+            .collect();
+    }
+
+    // Items without a line follow the nearest earlier item that has one
+    let placed: HashSet<usize> = assigned.iter().flatten().copied().collect();
+    let mut leading: Vec<usize> = Vec::new();
+    let mut after: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut previous: Option<usize> = None;
+    for index in 0..items.len() {
+        if placed.contains(&index) {
+            previous = Some(index);
+        } else {
+            match previous {
+                Some(previous) => after.entry(previous).or_default().push(index),
+                None => leading.push(index),
+            }
+        }
+    }
+
+    let by_line: HashMap<usize, (&TaskLine, Option<usize>)> = slots
+        .iter()
+        .zip(&assigned)
+        .map(|(slot, item)| (slot.line, (slot, *item)))
+        .collect();
+    let default_prefix = slots
+        .first()
+        .map(|s| s.prefix.clone())
+        .unwrap_or_else(|| "- ".to_string());
+    let mut out: Vec<String> = Vec::new();
+    let mut leading_done = leading.is_empty();
+    for (index, line) in text.lines().enumerate() {
+        let Some((slot, item)) = by_line.get(&index) else {
+            out.push(line.to_string());
+            continue;
+        };
+        if !leading_done {
+            out.extend(
+                leading
+                    .iter()
+                    .map(|i| format_task(&slot.prefix, &items[*i])),
+            );
+            leading_done = true;
+        }
+        if let Some(item) = item {
+            out.push(format_task(&slot.prefix, &items[*item]));
+            if let Some(following) = after.get(item) {
+                out.extend(
+                    following
+                        .iter()
+                        .map(|i| format_task(&slot.prefix, &items[*i])),
+                );
+            }
+        }
+    }
+
+    let mut text = out.join("\n");
+    if !leading_done {
+        // No task list yet: start one at the end
+        let list: Vec<String> = leading
+            .iter()
+            .map(|i| format_task(&default_prefix, &items[*i]))
+            .collect();
+        let trimmed = text.trim_end().len();
+        text.truncate(trimmed);
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&list.join("\n"));
+    }
+    text
+}
+
+fn summary(items: &[ChecklistItem]) -> Vec<(bool, String)> {
+    items
+        .iter()
+        .map(|i| (i.completed, i.text.trim().to_string()))
+        .collect()
+}
+
+/// Bring text and checklist in line when sync is first turned on: task
+/// lines become items (keeping ids of items with the same text) and items
+/// not in the text are added to it. Returns whether anything changed.
+pub fn merge(text: &mut String, checklist: &mut Vec<ChecklistItem>) -> bool {
+    let tasks = tasks(text);
+    if summary(checklist) == tasks {
+        return false;
+    }
+    let (mut items, unmatched) = checklist_from(&tasks, checklist, false);
+    items.extend(unmatched);
+    let slot_ids: Vec<Option<String>> = items
+        .iter()
+        .take(tasks.len())
+        .map(|i| Some(i.id.clone()))
+        .collect();
+    let rewritten = rewrite_text(text, &slot_ids, &items);
+    *text = rewritten;
+    *checklist = items;
+    true
+}
+
+/// Sync one text/checklist pair against what was saved before
+fn sync_pair(
+    previous: Option<(&str, &[ChecklistItem])>,
+    text: &mut String,
+    checklist: &mut Vec<ChecklistItem>,
+) -> bool {
+    let Some((old_text, old_checklist)) = previous else {
+        return merge(text, checklist);
+    };
+    let current = tasks(text);
+    if current != tasks(old_text) {
+        let (items, _) = checklist_from(&current, checklist, true);
+        if items
+            .iter()
+            .map(|i| (&i.id, i.completed, &i.text))
+            .eq(checklist.iter().map(|i| (&i.id, i.completed, &i.text)))
+        {
+            return false;
+        }
+        *checklist = items;
+        return true;
+    }
+    let changed = old_checklist.len() != checklist.len()
+        || old_checklist
+            .iter()
+            .zip(checklist.iter())
+            .any(|(a, b)| a.id != b.id || a.completed != b.completed || a.text != b.text);
+    if !changed {
+        // Neither side was edited; repair anything that drifted apart
+        return merge(text, checklist);
+    }
+
+    // The old checklist matched the old task lines one to one when in sync
+    let slot_ids: Vec<Option<String>> = if summary(old_checklist) == current {
+        old_checklist.iter().map(|i| Some(i.id.clone())).collect()
+    } else {
+        Vec::new()
+    };
+    let rewritten = rewrite_text(text, &slot_ids, checklist);
+    if rewritten == *text {
+        return false;
+    }
+    *text = rewritten;
+    true
+}
+
+/// Sync task lists and checklists of every card and note with `task_sync`
+/// on, comparing with `previous` (the data on disk). Returns whether
+/// anything changed.
+pub fn sync(previous: Option<&AppData>, data: &mut AppData) -> bool {
+    let mut old_cards: HashMap<&str, (&str, &[ChecklistItem])> = HashMap::new();
+    let mut old_notes: HashMap<&str, (&str, &[ChecklistItem])> = HashMap::new();
+    if let Some(previous) = previous {
+        for card in previous.cards().filter(|c| c.task_sync) {
+            old_cards.insert(&card.id, (&card.description, &card.checklist));
+        }
+        for note in previous.notes.iter().filter(|n| n.task_sync) {
+            old_notes.insert(&note.id, (&note.content, &note.checklist));
+        }
+    }
+
+    let mut changed = false;
+    for card in data.cards_mut().filter(|c| c.task_sync) {
+        let old = old_cards.get(card.id.as_str()).copied();
+        changed |= sync_pair(old, &mut card.description, &mut card.checklist);
+    }
+    for note in data.notes.iter_mut().filter(|n| n.task_sync) {
+        let old = old_notes.get(note.id.as_str()).copied();
+        changed |= sync_pair(old, &mut note.content, &mut note.checklist);
+    }
+    changed
+}
+
+/// Turn task sync on or off for a card or note; turning it on merges the
+/// task list and the checklist
+pub fn set_sync(data: &mut AppData, kind: DocKind, id: &str, enabled: bool) -> Result<(), String> {
+    match kind {
+        DocKind::Card => {
+            let card = data
+                .cards_mut()
+                .find(|c| c.id == id)
+                .ok_or_else(|| format!("Card not found: {}", id))?;
+            card.task_sync = enabled;
+            if enabled {
+                merge(&mut card.description, &mut card.checklist);
+            }
+        }
+        DocKind::Note => {
+            let note = data
+                .notes
+                .iter_mut()
+                .find(|n| n.id == id)
+                .ok_or_else(|| format!("Note not found: {}", id))?;
+            note.task_sync = enabled;
+            if enabled {
+                merge(&mut note.content, &mut note.checklist);
+            }
+        }
+        DocKind::Bookmark => return Err("Bookmarks have no task lists".to_string()),
+    }
+    Ok(())
+}
+
+/// Checklist progress of a card or note.
+///
+/// Cards count their checklist, plus any task lines when sync is off;
+/// notes count their task lines. With sync on both sides agree anyway.
+pub fn item_progress(data: &AppData, kind: DocKind, id: &str) -> Result<TaskProgress, String> {
+    let from_text = |text: &str| {
+        let tasks = tasks(text);
+        TaskProgress {
+            completed: tasks.iter().filter(|(done, _)| *done).count(),
+            total: tasks.len(),
+        }
+    };
+    match kind {
+        DocKind::Card => {
+            let card = data
+                .cards()
+                .find(|c| c.id == id)
+                .ok_or_else(|| format!("Card not found: {}", id))?;
+            let mut total = progress(&card.checklist);
+            if !card.task_sync {
+                let text = from_text(&card.description);
+                total.completed += text.completed;
+                total.total += text.total;
+            }
+            Ok(total)
+        }
+        DocKind::Note => {
+            let note = data
+                .notes
+                .iter()
+                .find(|n| n.id == id)
+                .ok_or_else(|| format!("Note not found: {}", id))?;
+            Ok(if note.task_sync {
+                progress(&note.checklist)
+            } else {
+                from_text(&note.content)
+            })
+        }
+        DocKind::Bookmark => Err("Bookmarks have no task lists".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, text: &str, completed: bool) -> ChecklistItem {
+        ChecklistItem {
+            id: id.to_string(),
+            text: text.to_string(),
+            completed,
+        }
+    }
+
+    fn ids(items: &[ChecklistItem]) -> Vec<&str> {
+        items.iter().map(|i| i.id.as_str()).collect()
+    }
+
+    /// Sync `text`/`checklist` against a previous state that was in step
+    fn sync_from(
+        old: (&str, &[ChecklistItem]),
+        text: &str,
+        checklist: Vec<ChecklistItem>,
+    ) -> (bool, String, Vec<ChecklistItem>) {
+        let mut text = text.to_string();
+        let mut checklist = checklist;
+        let changed = sync_pair(Some(old), &mut text, &mut checklist);
+        (changed, text, checklist)
+    }
+
+    #[test]
+    fn parses_task_lines() {
+        let text = "- [ ] open\n* [x] done\n+ [X] shouted\n  - [ ] nested\n- [] no\n-[ ] no\n- [ ]\n- [x]glued\n- plain";
+        assert_eq!(
+            tasks(text),
+            vec![
+                (false, "open".to_string()),
+                (true, "done".to_string()),
+                (true, "shouted".to_string()),
+                (false, "nested".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_tasks_in_fenced_blocks() {
+        let text = "- [ ] before\n```\n- [ ] code\n```\n~~~md\n- [x] tilde\n~~~\n- [x] after";
+        assert_eq!(
+            tasks(text),
+            vec![(false, "before".to_string()), (true, "after".to_string())]
+        );
+    }
+
+    #[test]
+    fn split_tasks_removes_the_list_and_one_gap() {
+        let (rest, items) = split_tasks("Intro\n\n- [ ] a\n- [x] b\n\nOutro\n");
+        assert_eq!(rest, "Intro\n\nOutro");
+        assert_eq!(
+            items
+                .iter()
+                .map(|i| (i.completed, i.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(false, "a"), (true, "b")]
+        );
+    }
+
+    #[test]
+    fn reordered_text_keeps_ids() {
+        let old = vec![
+            item("1", "a", false),
+            item("2", "b", false),
+            item("3", "c", true),
+        ];
+        let old_text = "- [ ] a\n- [ ] b\n- [x] c";
+        let (changed, _, checklist) =
+            sync_from((old_text, &old), "- [x] c\n- [ ] a\n- [ ] b", old.clone());
+        assert!(changed);
+        assert_eq!(ids(&checklist), vec!["3", "1", "2"]);
+    }
+
+    #[test]
+    fn reworded_and_ticked_text_keeps_ids_by_position() {
+        let old = vec![item("1", "a", false), item("2", "b", false)];
+        let old_text = "- [ ] a\n- [ ] b";
+        let (changed, _, checklist) =
+            sync_from((old_text, &old), "- [ ] a\n- [x] b, reworded", old.clone());
+        assert!(changed);
+        assert_eq!(ids(&checklist), vec!["1", "2"]);
+        assert_eq!(checklist[1].text, "b, reworded");
+        assert!(checklist[1].completed);
+    }
+
+    #[test]
+    fn ticked_item_rewrites_its_line_in_place() {
+        let old = vec![item("1", "a", false), item("2", "b", false)];
+        let old_text = "Notes\n  * [ ] a\n  * [ ] b\nEnd";
+        let mut checklist = old.clone();
+        checklist[1].completed = true;
+        let (changed, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert!(changed);
+        assert_eq!(text, "Notes\n  * [ ] a\n  * [x] b\nEnd");
+    }
+
+    #[test]
+    fn removed_item_drops_its_line() {
+        let old = vec![
+            item("1", "a", false),
+            item("2", "b", false),
+            item("3", "c", false),
+        ];
+        let old_text = "- [ ] a\n- [ ] b\n- [ ] c";
+        let checklist = vec![old[0].clone(), old[2].clone()];
+        let (_, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert_eq!(text, "- [ ] a\n- [ ] c");
+    }
+
+    #[test]
+    fn new_items_follow_the_item_before_them() {
+        let old = vec![item("1", "a", false), item("2", "b", false)];
+        let old_text = "- [ ] a\nbetween\n- [ ] b";
+        let checklist = vec![old[0].clone(), item("new", "a2", false), old[1].clone()];
+        let (_, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert_eq!(text, "- [ ] a\n- [ ] a2\nbetween\n- [ ] b");
+    }
+
+    #[test]
+    fn leading_insert_goes_before_the_first_task_line() {
+        let old = vec![item("1", "a", false)];
+        let old_text = "Intro\n- [ ] a";
+        let checklist = vec![item("new", "first", true), old[0].clone()];
+        let (_, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert_eq!(text, "Intro\n- [x] first\n- [ ] a");
+    }
+
+    #[test]
+    fn reordered_checklist_refills_lines_in_order() {
+        let old = vec![item("1", "a", false), item("2", "b", true)];
+        let old_text = "- [ ] a\ntext\n- [x] b";
+        let checklist = vec![old[1].clone(), old[0].clone()];
+        let (_, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert_eq!(text, "- [x] b\ntext\n- [ ] a");
+    }
+
+    #[test]
+    fn rewrite_leaves_fenced_task_lines_alone() {
+        let old = vec![item("1", "a", false)];
+        let old_text = "```\n- [ ] a\n```\n- [ ] a";
+        let checklist = vec![item("1", "a", true)];
+        let (_, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert_eq!(text, "```\n- [ ] a\n```\n- [x] a");
+    }
+
+    #[test]
+    fn items_start_a_list_when_the_text_has_none() {
+        let old = vec![];
+        let old_text = "Some text\n\n";
+        let checklist = vec![item("1", "a", false)];
+        let (changed, text, _) = sync_from((old_text, &old), old_text, checklist);
+        assert!(changed);
+        assert_eq!(text, "Some text\n\n- [ ] a");
+    }
+
+    #[test]
+    fn merge_keeps_ids_and_adds_missing_items_to_the_text() {
+        let mut text = "- [ ] a\n- [ ] b".to_string();
+        let mut checklist = vec![item("2", "b", false), item("3", "c", true)];
+        assert!(merge(&mut text, &mut checklist));
+        assert_eq!(text, "- [ ] a\n- [ ] b\n- [x] c");
+        assert_eq!(checklist[1].id, "2");
+        assert_eq!(checklist[2].id, "3");
+        assert!(!merge(&mut text, &mut checklist));
+    }
+}
@@ -0,0 +1,343 @@
+//! Board templates
+//!
+//! Built-in templates live in code; user templates are stored as one JSON
+//! file each under `<data dir>/templates`, which doubles as the standalone
+//! export format.
+
+use crate::{Board, Card, ChecklistItem, Column};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateCard {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// Checklist item texts; every item starts out incomplete
+    #[serde(default)]
+    pub checklist: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateColumn {
+    pub title: String,
+    #[serde(default)]
+    pub cards: Vec<TemplateCard>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BoardTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub columns: Vec<TemplateColumn>,
+    #[serde(rename = "builtIn", default)]
+    pub built_in: bool,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: Option<String>,
+}
+
+fn column(title: &str) -> TemplateColumn {
+    TemplateColumn {
+        title: title.to_string(),
+        cards: vec![],
+    }
+}
+
+fn seed_card(title: &str, description: &str, checklist: &[&str]) -> TemplateCard {
+    TemplateCard {
+        title: title.to_string(),
+        description: description.to_string(),
+        labels: vec![],
+        priority: None,
+        checklist: checklist.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+fn builtin(id: &str, name: &str, description: &str, columns: Vec<TemplateColumn>) -> BoardTemplate {
+    BoardTemplate {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        columns,
+        built_in: true,
+        created_at: None,
+    }
+}
+
+/// Templates that ship with the app; the first one is the default
+pub fn builtin_templates() -> Vec<BoardTemplate> {
+    let mut triage = column("New");
+    triage.cards.push(seed_card(
+        "How to triage",
+        "Reproduce the bug, add a priority and the bug label, then move it to Triaged.",
+        &[
+            "Steps to reproduce",
+            "Expected vs actual behaviour",
+            "Affected version",
+        ],
+    ));
+
+    let mut sprint = column("Product Backlog");
+    sprint.cards.push(seed_card(
+        "Sprint planning",
+        "Pick the stories for this sprint and move them to Sprint Backlog.",
+        &[
+            "Review velocity",
+            "Agree on sprint goal",
+            "Break stories into tasks",
+        ],
+    ));
+
+    vec![
+        builtin(
+            "builtin-kanban",
+            "Kanban",
+            "A simple Backlog / To Do / In Progress / Done flow.",
+            vec![
+                column("Backlog"),
+                column("To Do"),
+                column("In Progress"),
+                column("Done"),
+            ],
+        ),
+        builtin(
+            "builtin-scrum",
+            "Scrum",
+            "Sprint-based flow with review before done.",
+            vec![
+                sprint,
+                column("Sprint Backlog"),
+                column("In Progress"),
+                column("In Review"),
+                column("Done"),
+            ],
+        ),
+        builtin(
+            "builtin-bug-triage",
+            "Bug Triage",
+            "Collect, triage and fix incoming bugs.",
+            vec![
+                triage,
+                column("Triaged"),
+                column("In Progress"),
+                column("Needs Verification"),
+                column("Fixed"),
+            ],
+        ),
+        builtin(
+            "builtin-content-calendar",
+            "Content Calendar",
+            "Plan posts from idea to publication.",
+            vec![
+                column("Ideas"),
+                column("Drafting"),
+                column("Editing"),
+                column("Scheduled"),
+                column("Published"),
+            ],
+        ),
+    ]
+}
+
+/// The built-in template used for the first board of a fresh install
+pub fn default_template() -> BoardTemplate {
+    builtin_templates().swap_remove(0)
+}
+
+pub fn get_templates_dir(data_dir: &Path) -> PathBuf {
+    let dir = data_dir.join("templates");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
+fn template_path(dir: &Path, id: &str) -> PathBuf {
+    // Ids are uuids for user templates, but never trust them as paths
+    let safe: String = id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    dir.join(format!("{}.json", safe))
+}
+
+/// Built-in templates followed by the user's saved templates
+pub fn list_templates(dir: &Path) -> Vec<BoardTemplate> {
+    let mut templates = builtin_templates();
+    let mut saved: Vec<BoardTemplate> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map(|ext| ext == "json").unwrap_or(false))
+                .filter_map(|p| match read_template_file(&p) {
+                    Ok(t) => Some(t),
+                    Err(e) => {
+                        log::warn!("Skipping template {:?}: {}", p, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    saved.sort_by_key(|t| t.name.to_lowercase());
+    templates.extend(saved);
+    templates
+}
+
+pub fn find_template(dir: &Path, id: &str) -> Result<BoardTemplate, String> {
+    list_templates(dir)
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("Template not found: {}", id))
+}
+
+fn read_template_file(path: &Path) -> Result<BoardTemplate, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read template: {}", e))?;
+    let mut template: BoardTemplate =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse template: {}", e))?;
+    template.built_in = false;
+    Ok(template)
+}
+
+fn write_template_file(path: &Path, template: &BoardTemplate) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(template)
+        .map_err(|e| format!("Failed to serialize template: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write template: {}", e))
+}
+
+/// Capture a board's columns (and optionally its cards) as a template
+pub fn template_from_board(
+    board: &Board,
+    name: &str,
+    include_cards: bool,
+    now: &str,
+) -> BoardTemplate {
+    BoardTemplate {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        description: String::new(),
+        columns: board
+            .columns
+            .iter()
+            .map(|col| TemplateColumn {
+                title: col.title.clone(),
+                cards: if include_cards {
+                    col.cards
+                        .iter()
+                        .map(|card| TemplateCard {
+                            title: card.title.clone(),
+                            description: card.description.clone(),
+                            labels: card.labels.clone(),
+                            priority: card.priority.clone(),
+                            checklist: card.checklist.iter().map(|i| i.text.clone()).collect(),
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                },
+            })
+            .collect(),
+        built_in: false,
+        created_at: Some(now.to_string()),
+    }
+}
+
+/// Instantiate a template as a new board with fresh ids
+pub fn board_from_template(
+    template: &BoardTemplate,
+    board_id: &str,
+    name: &str,
+    now: &str,
+) -> Board {
+    let new_id = || uuid::Uuid::new_v4().to_string();
+    Board {
+        id: board_id.to_string(),
+        name: name.to_string(),
+        columns: template
+            .columns
+            .iter()
+            .map(|col| Column {
+                id: new_id(),
+                title: col.title.clone(),
+                cards: col
+                    .cards
+                    .iter()
+                    .map(|card| Card {
+                        id: new_id(),
+                        title: card.title.clone(),
+                        description: card.description.clone(),
+                        labels: card.labels.clone(),
+                        priority: card.priority.clone(),
+                        checklist: card
+                            .checklist
+                            .iter()
+                            .map(|text| ChecklistItem {
+                                id: new_id(),
+                                text: text.clone(),
+                                completed: false,
+                            })
+                            .collect(),
+                        created_at: now.to_string(),
+                        archived_at: None,
+                        original_column_id: None,
+                        column_history: vec![],
+                        parent_id: None,
+                        blocked_by: vec![],
+                        related_to: vec![],
+                        attachments: vec![],
+                        task_sync: false,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        archived_cards: vec![],
+    }
+}
+
+pub fn save_template(dir: &Path, template: &BoardTemplate) -> Result<(), String> {
+    if template.built_in || builtin_templates().iter().any(|t| t.id == template.id) {
+        return Err("Built-in templates cannot be overwritten".to_string());
+    }
+    write_template_file(&template_path(dir, &template.id), template)
+}
+
+pub fn delete_template(dir: &Path, id: &str) -> Result<(), String> {
+    if builtin_templates().iter().any(|t| t.id == id) {
+        return Err("Built-in templates cannot be deleted".to_string());
+    }
+    let path = template_path(dir, id);
+    if !path.exists() {
+        return Err(format!("Template not found: {}", id));
+    }
+    fs::remove_file(path).map_err(|e| format!("Failed to delete template: {}", e))
+}
+
+/// Write a template as a standalone JSON file
+pub fn export_template(template: &BoardTemplate, path: &Path) -> Result<(), String> {
+    let mut exported = template.clone();
+    exported.built_in = false;
+    write_template_file(path, &exported)
+}
+
+/// Read a standalone template file and store it as a user template.
+///
+/// The template gets a new id so importing never replaces an existing one.
+pub fn import_template(dir: &Path, path: &Path, now: &str) -> Result<BoardTemplate, String> {
+    let mut template = read_template_file(path)?;
+    if template.columns.is_empty() {
+        return Err("Template has no columns".to_string());
+    }
+    template.id = uuid::Uuid::new_v4().to_string();
+    template.created_at = Some(now.to_string());
+    save_template(dir, &template)?;
+    Ok(template)
+}
@@ -0,0 +1,583 @@
+//! Moving and copying cards or whole columns between boards
+//!
+//! A transfer is applied to `AppData` in one go and produces a receipt that
+//! carries everything needed to undo it later.
+
+use crate::{AppData, Board, Card, Column, ColumnTransition};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Labels the frontend ships with (`LABELS` in boardStore.js)
+pub(crate) const BUILT_IN_LABELS: [&str; 6] =
+    ["bug", "feature", "urgent", "idea", "docs", "design"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    #[serde(rename = "move")]
+    Move,
+    #[serde(rename = "copy")]
+    Copy,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TransferRequest {
+    pub mode: TransferMode,
+    #[serde(rename = "sourceBoardId")]
+    pub source_board_id: String,
+    #[serde(rename = "targetBoardId")]
+    pub target_board_id: String,
+    /// Cards to transfer; ignored when `columnId` is set
+    #[serde(rename = "cardIds", default)]
+    pub card_ids: Vec<String>,
+    /// Transfer this whole column instead of individual cards
+    #[serde(rename = "columnId", default)]
+    pub column_id: Option<String>,
+    /// Destination column for cards (defaults to the first column)
+    #[serde(rename = "targetColumnId", default)]
+    pub target_column_id: Option<String>,
+    /// Insert position in the destination column, or among the columns
+    #[serde(rename = "targetIndex", default)]
+    pub target_index: Option<usize>,
+    /// Replacement for labels the target board does not use. Every such
+    /// label needs an entry; map a label to itself to keep it.
+    #[serde(rename = "labelMap", default)]
+    pub label_map: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferredCard {
+    #[serde(rename = "cardId")]
+    pub card_id: String,
+    /// Same as `cardId` for moves, a fresh id for copies
+    #[serde(rename = "newCardId")]
+    pub new_card_id: String,
+    #[serde(rename = "sourceColumnId")]
+    pub source_column_id: String,
+    #[serde(rename = "sourceIndex")]
+    pub source_index: usize,
+    #[serde(rename = "originalLabels")]
+    pub original_labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferredColumn {
+    #[serde(rename = "columnId")]
+    pub column_id: String,
+    #[serde(rename = "newColumnId")]
+    pub new_column_id: String,
+    #[serde(rename = "sourceIndex")]
+    pub source_index: usize,
+}
+
+/// Everything needed to undo a transfer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferReceipt {
+    pub id: String,
+    pub mode: TransferMode,
+    #[serde(rename = "sourceBoardId")]
+    pub source_board_id: String,
+    #[serde(rename = "targetBoardId")]
+    pub target_board_id: String,
+    pub cards: Vec<TransferredCard>,
+    #[serde(default)]
+    pub column: Option<TransferredColumn>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+/// Updated data plus the receipt the frontend keeps for undo
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferResult {
+    pub data: AppData,
+    pub receipt: TransferReceipt,
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn board_index(data: &AppData, board_id: &str) -> Result<usize, String> {
+    data.boards
+        .iter()
+        .position(|b| b.id == board_id)
+        .ok_or_else(|| format!("Board not found: {}", board_id))
+}
+
+/// Labels the target board already uses, plus the built-in set
+fn label_vocabulary(board: &Board) -> HashSet<String> {
+    let mut labels: HashSet<String> = BUILT_IN_LABELS.iter().map(|l| l.to_string()).collect();
+    let cards = board
+        .columns
+        .iter()
+        .flat_map(|c| c.cards.iter())
+        .chain(board.archived_cards.iter());
+    for card in cards {
+        labels.extend(card.labels.iter().cloned());
+    }
+    labels
+}
+
+/// Make sure every label of `cards` exists on the target board or has a
+/// replacement in `label_map`
+fn check_labels<'a>(
+    cards: impl Iterator<Item = &'a Card>,
+    vocabulary: &HashSet<String>,
+    label_map: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut unmapped: Vec<&str> = Vec::new();
+    for label in cards.flat_map(|c| c.labels.iter()) {
+        if !vocabulary.contains(label)
+            && !label_map.contains_key(label)
+            && !unmapped.contains(&label.as_str())
+        {
+            unmapped.push(label);
+        }
+    }
+    if unmapped.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The target board does not use these labels; choose a replacement: {}",
+            unmapped.join(", ")
+        ))
+    }
+}
+
+/// Replace labels the target board does not use (see `check_labels`)
+fn remap_labels(
+    card: &mut Card,
+    vocabulary: &HashSet<String>,
+    label_map: &HashMap<String, String>,
+) {
+    let mut labels: Vec<String> = Vec::with_capacity(card.labels.len());
+    for label in &card.labels {
+        let label = match label_map.get(label) {
+            Some(mapped) if !vocabulary.contains(label) => mapped.clone(),
+            _ => label.clone(),
+        };
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    card.labels = labels;
+}
+
+/// Prepare a copy of a card: new id, fresh column history in `column_id`.
+///
+/// Relations stay with the original; the other cards only point back at it.
+fn copy_card(card: &Card, column_id: &str, now: &str) -> Card {
+    let mut copy = card.clone();
+    copy.id = new_id();
+    copy.parent_id = None;
+    copy.blocked_by.clear();
+    copy.related_to.clear();
+    copy.column_history = vec![ColumnTransition {
+        column_id: column_id.to_string(),
+        entered_at: now.to_string(),
+    }];
+    copy
+}
+
+/// Apply a transfer to `data`; nothing is changed if validation fails
+pub fn transfer(
+    data: &mut AppData,
+    request: &TransferRequest,
+    now: &str,
+) -> Result<TransferReceipt, String> {
+    let source = board_index(data, &request.source_board_id)?;
+    let target = board_index(data, &request.target_board_id)?;
+    let vocabulary = label_vocabulary(&data.boards[target]);
+
+    let mut receipt = TransferReceipt {
+        id: new_id(),
+        mode: request.mode,
+        source_board_id: request.source_board_id.clone(),
+        target_board_id: request.target_board_id.clone(),
+        cards: Vec::new(),
+        column: None,
+        created_at: now.to_string(),
+    };
+
+    if let Some(column_id) = &request.column_id {
+        let source_index = data.boards[source]
+            .columns
+            .iter()
+            .position(|c| &c.id == column_id)
+            .ok_or_else(|| format!("Column not found: {}", column_id))?;
+        if request.mode == TransferMode::Move {
+            if source == target {
+                return Err("A column cannot be moved onto its own board".to_string());
+            }
+            if data.boards[source].columns.len() == 1 {
+                return Err("Cannot move the only column of a board".to_string());
+            }
+        }
+        check_labels(
+            data.boards[source].columns[source_index].cards.iter(),
+            &vocabulary,
+            &request.label_map,
+        )?;
+
+        let mut column = match request.mode {
+            TransferMode::Move => data.boards[source].columns.remove(source_index),
+            TransferMode::Copy => data.boards[source].columns[source_index].clone(),
+        };
+        let original_column_id = column.id.clone();
+        if request.mode == TransferMode::Copy {
+            column.id = new_id();
+        }
+
+        let mut cards = Vec::with_capacity(column.cards.len());
+        for (index, card) in column.cards.iter().enumerate() {
+            let mut card = match request.mode {
+                TransferMode::Move => card.clone(),
+                TransferMode::Copy => copy_card(card, &column.id, now),
+            };
+            let original_labels = card.labels.clone();
+            remap_labels(&mut card, &vocabulary, &request.label_map);
+            receipt.cards.push(TransferredCard {
+                card_id: column.cards[index].id.clone(),
+                new_card_id: card.id.clone(),
+                source_column_id: original_column_id.clone(),
+                source_index: index,
+                original_labels,
+            });
+            cards.push(card);
+        }
+        column.cards = cards;
+
+        receipt.column = Some(TransferredColumn {
+            column_id: original_column_id,
+            new_column_id: column.id.clone(),
+            source_index,
+        });
+        let columns = &mut data.boards[target].columns;
+        let at = request
+            .target_index
+            .unwrap_or(columns.len())
+            .min(columns.len());
+        columns.insert(at, column);
+        return Ok(receipt);
+    }
+
+    if request.card_ids.is_empty() {
+        return Err("No cards selected".to_string());
+    }
+    // A card listed twice would be moved twice, leaving a duplicate id behind
+    let mut seen = HashSet::new();
+    if let Some(card_id) = request.card_ids.iter().find(|id| !seen.insert(*id)) {
+        return Err(format!("Card selected more than once: {}", card_id));
+    }
+    let target_column = match &request.target_column_id {
+        Some(id) => data.boards[target]
+            .columns
+            .iter()
+            .position(|c| &c.id == id)
+            .ok_or_else(|| format!("Column not found: {}", id))?,
+        None if data.boards[target].columns.is_empty() => {
+            return Err("Target board has no columns".to_string());
+        }
+        None => 0,
+    };
+    let target_column_id = data.boards[target].columns[target_column].id.clone();
+
+    // Locate every card before touching anything so the transfer is all-or-nothing
+    let mut located: Vec<(String, usize, usize)> = Vec::new();
+    for card_id in &request.card_ids {
+        let found = data.boards[source]
+            .columns
+            .iter()
+            .enumerate()
+            .find_map(|(ci, col)| {
+                col.cards
+                    .iter()
+                    .position(|c| &c.id == card_id)
+                    .map(|pi| (ci, pi))
+            })
+            .ok_or_else(|| format!("Card not found: {}", card_id))?;
+        located.push((card_id.clone(), found.0, found.1));
+    }
+    check_labels(
+        located
+            .iter()
+            .map(|(_, ci, pi)| &data.boards[source].columns[*ci].cards[*pi]),
+        &vocabulary,
+        &request.label_map,
+    )?;
+
+    let mut moved: Vec<Card> = Vec::with_capacity(located.len());
+    for (card_id, column_index, card_index) in &located {
+        let column = &data.boards[source].columns[*column_index];
+        let original = &column.cards[*card_index];
+        let mut card = match request.mode {
+            TransferMode::Move => original.clone(),
+            TransferMode::Copy => copy_card(original, &target_column_id, now),
+        };
+        let original_labels = card.labels.clone();
+        remap_labels(&mut card, &vocabulary, &request.label_map);
+        receipt.cards.push(TransferredCard {
+            card_id: card_id.clone(),
+            new_card_id: card.id.clone(),
+            source_column_id: column.id.clone(),
+            source_index: *card_index,
+            original_labels,
+        });
+        moved.push(card);
+    }
+
+    if request.mode == TransferMode::Move {
+        let ids: HashSet<&String> = request.card_ids.iter().collect();
+        for column in &mut data.boards[source].columns {
+            column.cards.retain(|c| !ids.contains(&c.id));
+        }
+    }
+
+    let cards = &mut data.boards[target].columns[target_column].cards;
+    let at = request.target_index.unwrap_or(cards.len()).min(cards.len());
+    cards.splice(at..at, moved);
+
+    Ok(receipt)
+}
+
+fn take_card(board: &mut Board, card_id: &str) -> Option<Card> {
+    board.columns.iter_mut().find_map(|col| {
+        col.cards
+            .iter()
+            .position(|c| c.id == card_id)
+            .map(|i| col.cards.remove(i))
+    })
+}
+
+/// Reverse a transfer described by `receipt`.
+///
+/// Cards that were edited after the transfer keep their edits (apart from
+/// labels, which are restored); cards or columns deleted since are skipped.
+/// Moved cards go back to their column, or the source board's first column
+/// if that is gone.
+pub fn undo_transfer(data: &mut AppData, receipt: &TransferReceipt) -> Result<(), String> {
+    let source = board_index(data, &receipt.source_board_id)?;
+    let target = board_index(data, &receipt.target_board_id)?;
+    if receipt.mode == TransferMode::Move
+        && receipt.column.is_none()
+        && data.boards[source].columns.is_empty()
+    {
+        return Err("The source board has no column to put the cards back in".to_string());
+    }
+    let original_labels: HashMap<&str, &Vec<String>> = receipt
+        .cards
+        .iter()
+        .map(|c| (c.new_card_id.as_str(), &c.original_labels))
+        .collect();
+
+    if let Some(transferred) = &receipt.column {
+        let columns = &mut data.boards[target].columns;
+        let Some(position) = columns
+            .iter()
+            .position(|c| c.id == transferred.new_column_id)
+        else {
+            log::warn!(
+                "Transferred column {} no longer exists",
+                transferred.new_column_id
+            );
+            return Ok(());
+        };
+        let mut column: Column = columns.remove(position);
+        if receipt.mode == TransferMode::Move {
+            for card in &mut column.cards {
+                if let Some(labels) = original_labels.get(card.id.as_str()) {
+                    card.labels = (*labels).clone();
+                }
+            }
+            let columns = &mut data.boards[source].columns;
+            let at = transferred.source_index.min(columns.len());
+            columns.insert(at, column);
+        }
+        return Ok(());
+    }
+
+    let mut restored: Vec<(&TransferredCard, Card)> = Vec::new();
+    for transferred in &receipt.cards {
+        match take_card(&mut data.boards[target], &transferred.new_card_id) {
+            Some(mut card) => {
+                card.labels = transferred.original_labels.clone();
+                restored.push((transferred, card));
+            }
+            None => log::warn!(
+                "Transferred card {} no longer exists",
+                transferred.new_card_id
+            ),
+        }
+    }
+    if receipt.mode == TransferMode::Copy {
+        return Ok(());
+    }
+
+    // Reinsert in ascending original position so indices line up again
+    restored.sort_by_key(|(t, _)| t.source_index);
+    for (transferred, card) in restored {
+        let board = &mut data.boards[source];
+        // Checked above: the board has at least one column to fall back to
+        let column = board
+            .columns
+            .iter()
+            .position(|c| c.id == transferred.source_column_id)
+            .unwrap_or(0);
+        let cards = &mut board.columns[column].cards;
+        let at = transferred.source_index.min(cards.len());
+        cards.insert(at, card);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: &str = "2025-01-01T00:00:00Z";
+
+    fn card(id: &str, labels: &[&str]) -> Card {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": id,
+            "labels": labels,
+            "createdAt": NOW,
+        }))
+        .unwrap()
+    }
+
+    fn board(id: &str, columns: Vec<(&str, Vec<Card>)>) -> Board {
+        let columns: Vec<_> = columns
+            .into_iter()
+            .map(|(id, cards)| json!({ "id": id, "title": id, "cards": cards }))
+            .collect();
+        serde_json::from_value(json!({ "id": id, "name": id, "columns": columns })).unwrap()
+    }
+
+    fn data() -> AppData {
+        let mut data = crate::get_default_data();
+        data.boards = vec![
+            board(
+                "a",
+                vec![
+                    ("a1", vec![card("1", &["bug"]), card("2", &["team"])]),
+                    ("a2", vec![card("3", &[])]),
+                ],
+            ),
+            board("b", vec![("b1", vec![card("9", &["ops"])]), ("b2", vec![])]),
+        ];
+        data
+    }
+
+    fn request(mode: TransferMode, card_ids: &[&str]) -> TransferRequest {
+        TransferRequest {
+            mode,
+            source_board_id: "a".to_string(),
+            target_board_id: "b".to_string(),
+            card_ids: card_ids.iter().map(|id| id.to_string()).collect(),
+            column_id: None,
+            target_column_id: None,
+            target_index: None,
+            label_map: HashMap::new(),
+        }
+    }
+
+    fn column_cards(data: &AppData, board: usize, column: usize) -> Vec<&str> {
+        data.boards[board].columns[column]
+            .cards
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect()
+    }
+
+    fn find<'a>(data: &'a AppData, id: &str) -> &'a Card {
+        data.cards().find(|c| c.id == id).unwrap()
+    }
+
+    #[test]
+    fn move_and_undo_round_trip() {
+        let mut data = data();
+        let mut request = request(TransferMode::Move, &["2", "1"]);
+        request
+            .label_map
+            .insert("team".to_string(), "ops".to_string());
+        let receipt = transfer(&mut data, &request, NOW).unwrap();
+        assert_eq!(column_cards(&data, 0, 0), Vec::<&str>::new());
+        assert_eq!(column_cards(&data, 1, 0), vec!["9", "2", "1"]);
+        assert_eq!(find(&data, "2").labels, vec!["ops"]);
+
+        undo_transfer(&mut data, &receipt).unwrap();
+        assert_eq!(column_cards(&data, 0, 0), vec!["1", "2"]);
+        assert_eq!(column_cards(&data, 1, 0), vec!["9"]);
+        assert_eq!(find(&data, "2").labels, vec!["team"]);
+    }
+
+    #[test]
+    fn copy_and_undo_round_trip() {
+        let mut data = data();
+        let mut request = request(TransferMode::Copy, &["1"]);
+        request.target_column_id = Some("b2".to_string());
+        let receipt = transfer(&mut data, &request, NOW).unwrap();
+        let copy_id = receipt.cards[0].new_card_id.clone();
+        assert_ne!(copy_id, "1");
+        assert_eq!(column_cards(&data, 0, 0), vec!["1", "2"]);
+        assert_eq!(column_cards(&data, 1, 1), vec![copy_id.as_str()]);
+        assert_eq!(find(&data, &copy_id).column_history[0].column_id, "b2");
+
+        undo_transfer(&mut data, &receipt).unwrap();
+        assert_eq!(column_cards(&data, 0, 0), vec!["1", "2"]);
+        assert!(data.boards[1].columns[1].cards.is_empty());
+    }
+
+    #[test]
+    fn column_move_and_undo_round_trip() {
+        let mut data = data();
+        let mut request = request(TransferMode::Move, &[]);
+        request.column_id = Some("a2".to_string());
+        request.target_index = Some(0);
+        let receipt = transfer(&mut data, &request, NOW).unwrap();
+        assert_eq!(data.boards[0].columns.len(), 1);
+        assert_eq!(data.boards[1].columns[0].id, "a2");
+
+        undo_transfer(&mut data, &receipt).unwrap();
+        assert_eq!(data.boards[0].columns[1].id, "a2");
+        assert_eq!(column_cards(&data, 0, 1), vec!["3"]);
+        assert_eq!(data.boards[1].columns.len(), 2);
+    }
+
+    #[test]
+    fn unknown_labels_need_a_mapping() {
+        let mut data = data();
+        let before = serde_json::to_value(&data).unwrap();
+        let error = transfer(&mut data, &request(TransferMode::Move, &["2"]), NOW).unwrap_err();
+        assert!(error.contains("team"));
+        assert_eq!(serde_json::to_value(&data).unwrap(), before);
+
+        // Built-in labels and labels the target uses need none
+        transfer(&mut data, &request(TransferMode::Move, &["1"]), NOW).unwrap();
+    }
+
+    #[test]
+    fn copies_drop_relations() {
+        let mut data = data();
+        data.boards[0].columns[0].cards[0].related_to = vec!["3".to_string()];
+        data.boards[0].columns[0].cards[0].blocked_by = vec!["2".to_string()];
+        data.boards[0].columns[0].cards[0].parent_id = Some("3".to_string());
+        let receipt = transfer(&mut data, &request(TransferMode::Copy, &["1"]), NOW).unwrap();
+        let copy = find(&data, &receipt.cards[0].new_card_id);
+        assert!(copy.related_to.is_empty());
+        assert!(copy.blocked_by.is_empty());
+        assert!(copy.parent_id.is_none());
+        assert_eq!(find(&data, "1").related_to, vec!["3"]);
+    }
+
+    #[test]
+    fn undo_refuses_when_the_source_has_no_columns() {
+        let mut data = data();
+        let receipt = transfer(&mut data, &request(TransferMode::Move, &["1"]), NOW).unwrap();
+        data.boards[0].columns.clear();
+        assert!(undo_transfer(&mut data, &receipt).is_err());
+        assert_eq!(column_cards(&data, 1, 0), vec!["9", "1"]);
+    }
+}
@@ -0,0 +1,683 @@
+//! Vault mode: notes mirrored as Markdown files
+//!
+//! Each note is a `.md` file with YAML front matter (`id`, `title`,
+//! `pinned`, `created`, `updated`) in a folder the user picks, so notes can
+//! be edited in any editor and kept in git. `sync` runs both ways: it
+//! remembers the hash of every file and note as of the last sync, so it can
+//! tell which side changed. When both did, the newer one wins and the other
+//! is kept in `.carbon-conflicts/`.
+//!
+//! Attachments the notes use are copied into the vault's `attachments/`
+//! folder, and `attachment:<file>` links are written as `attachments/<file>`
+//! so other editors can show them.
+//!
+//! Vault settings are per machine and live in `vault.json` in the data
+//! directory rather than in `AppData`, which is synced across devices.
+
+use crate::attachments;
+use crate::{AppData, Note};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const STATE_FILE: &str = "vault.json";
+const CONFLICTS_DIR: &str = ".carbon-conflicts";
+/// Vault folder attachments are mirrored into
+const ATTACHMENTS_DIR: &str = "attachments";
+/// Front matter keys Carbon owns; any others are kept as they are
+const OWN_KEYS: [&str; 5] = ["id", "title", "pinned", "created", "updated"];
+
+/// Per-machine vault settings and what the last sync saw
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VaultState {
+    /// Vault folder; vault mode is off when `None`
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(rename = "lastSync", default)]
+    pub last_sync: Option<String>,
+    /// By note id
+    #[serde(default)]
+    pub files: HashMap<String, VaultEntry>,
+    /// Ids of the notes Carbon had when the last sync finished
+    #[serde(default)]
+    pub notes: HashSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultEntry {
+    /// Path relative to the vault, with `/` separators
+    pub file: String,
+    #[serde(rename = "fileHash")]
+    pub file_hash: String,
+    #[serde(rename = "noteHash")]
+    pub note_hash: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct VaultReport {
+    /// Notes written out to files
+    pub written: usize,
+    /// Files that became new notes
+    pub imported: usize,
+    /// Notes updated from edited files
+    pub updated: usize,
+    #[serde(rename = "deletedNotes")]
+    pub deleted_notes: usize,
+    #[serde(rename = "deletedFiles")]
+    pub deleted_files: usize,
+    /// Notes changed on both sides since the last sync
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct VaultSyncResult {
+    pub data: AppData,
+    pub report: VaultReport,
+}
+
+impl VaultReport {
+    /// Whether the sync changed `AppData`
+    pub fn changed_notes(&self) -> bool {
+        self.imported + self.updated + self.deleted_notes > 0
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub path: Option<String>,
+    #[serde(rename = "lastSync")]
+    pub last_sync: Option<String>,
+    pub files: usize,
+}
+
+pub fn load_state(data_dir: &Path) -> VaultState {
+    fs::read_to_string(data_dir.join(STATE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_state(data_dir: &Path, state: &VaultState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize vault state: {}", e))?;
+    fs::write(data_dir.join(STATE_FILE), json)
+        .map_err(|e| format!("Failed to write vault state: {}", e))
+}
+
+pub fn status(state: &VaultState) -> VaultStatus {
+    VaultStatus {
+        enabled: state.path.is_some(),
+        path: state.path.clone(),
+        last_sync: state.last_sync.clone(),
+        files: state.files.len(),
+    }
+}
+
+fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn note_hash(note: &Note) -> String {
+    hash(format!("{}\0{}\0{}", note.title, note.content, note.is_pinned).as_bytes())
+}
+
+fn modified_at(path: &Path) -> Option<String> {
+    let modified: SystemTime = fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+}
+
+fn is_newer(a: &str, b: &str) -> bool {
+    let parse = |s: &str| chrono::DateTime::parse_from_rfc3339(s).ok();
+    match (parse(a), parse(b)) {
+        (Some(a), Some(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+// ============================================
+// Front matter
+// ============================================
+
+#[derive(Debug, Default)]
+struct MarkdownFile {
+    id: Option<String>,
+    title: Option<String>,
+    pinned: Option<bool>,
+    created: Option<String>,
+    updated: Option<String>,
+    /// Front matter lines Carbon does not own, verbatim
+    extra: Vec<String>,
+    body: String,
+}
+
+fn yaml_value(raw: &str) -> String {
+    let raw = raw.trim();
+    if raw.starts_with('"') {
+        // JSON strings are valid double-quoted YAML
+        if let Ok(value) = serde_json::from_str::<String>(raw) {
+            return value;
+        }
+    }
+    if let Some(inner) = raw.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+        return inner.replace("''", "'");
+    }
+    raw.to_string()
+}
+
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+fn parse_markdown(text: &str) -> MarkdownFile {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut file = MarkdownFile::default();
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        file.body = from_vault_links(text);
+        return file;
+    };
+    let Some((front, body)) = rest
+        .split_once("\n---\n")
+        .or_else(|| rest.split_once("\r\n---\r\n"))
+        .or_else(|| rest.strip_suffix("\n---").map(|f| (f, "")))
+    else {
+        file.body = from_vault_links(text);
+        return file;
+    };
+
+    // Indented lines continue the key above them (lists, nested maps)
+    let mut in_own_key = false;
+    for line in front.lines() {
+        let continues = line.starts_with(' ') || line.starts_with('\t');
+        if continues {
+            if !in_own_key {
+                file.extra.push(line.to_string());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            file.extra.push(line.to_string());
+            in_own_key = false;
+            continue;
+        };
+        let key = key.trim();
+        in_own_key = OWN_KEYS.contains(&key);
+        let value = yaml_value(value);
+        match key {
+            "id" => file.id = Some(value).filter(|v| !v.is_empty()),
+            "title" => file.title = Some(value).filter(|v| !v.is_empty()),
+            "pinned" => file.pinned = Some(value == "true"),
+            "created" => file.created = Some(value).filter(|v| !v.is_empty()),
+            "updated" => file.updated = Some(value).filter(|v| !v.is_empty()),
+            _ => file.extra.push(line.to_string()),
+        }
+    }
+    file.body = from_vault_links(
+        body.strip_prefix("\r\n")
+            .or_else(|| body.strip_prefix('\n'))
+            .unwrap_or(body),
+    );
+    file
+}
+
+/// Attachment links as the app stores them, from the form used in files
+fn from_vault_links(body: &str) -> String {
+    body.replace(
+        &format!("]({}/", ATTACHMENTS_DIR),
+        &format!("]({}", attachments::LINK_PREFIX),
+    )
+}
+
+fn to_vault_links(content: &str) -> String {
+    content.replace(
+        &format!("]({}", attachments::LINK_PREFIX),
+        &format!("]({}/", ATTACHMENTS_DIR),
+    )
+}
+
+fn render_markdown(note: &Note, extra: &[String]) -> String {
+    let mut out = String::from("---\n");
+    out.push_str(&format!("id: {}\n", yaml_string(&note.id)));
+    out.push_str(&format!("title: {}\n", yaml_string(&note.title)));
+    out.push_str(&format!("pinned: {}\n", note.is_pinned));
+    out.push_str(&format!("created: {}\n", yaml_string(&note.created_at)));
+    out.push_str(&format!("updated: {}\n", yaml_string(&note.updated_at)));
+    for line in extra {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("---\n\n");
+    out.push_str(&to_vault_links(&note.content));
+    if !note.content.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+// ============================================
+// Files
+// ============================================
+
+struct VaultFile {
+    path: PathBuf,
+    hash: String,
+    modified: Option<String>,
+    parsed: MarkdownFile,
+}
+
+/// Markdown files in the vault by relative path, skipping hidden folders
+fn scan(root: &Path) -> HashMap<String, VaultFile> {
+    let mut files = HashMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !name.to_lowercase().ends_with(".md") {
+                continue;
+            }
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let relative: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            files.insert(
+                relative.join("/"),
+                VaultFile {
+                    hash: hash(&bytes),
+                    modified: modified_at(&path),
+                    parsed: parse_markdown(&String::from_utf8_lossy(&bytes)),
+                    path,
+                },
+            );
+        }
+    }
+    files
+}
+
+/// A cheap fingerprint of the vault's files and the notes, to decide
+/// whether a sync is needed at all
+pub fn signature(root: &Path, data: &AppData) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if name.to_lowercase().ends_with(".md") {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0);
+                parts.push(format!(
+                    "{}:{}:{}",
+                    entry.path().display(),
+                    metadata.len(),
+                    modified
+                ));
+            }
+        }
+    }
+    parts.sort();
+    parts.extend(
+        data.notes
+            .iter()
+            .map(|n| format!("{}:{}", n.id, note_hash(n))),
+    );
+    hash(parts.join("\n").as_bytes())
+}
+
+/// File name for a note title, without characters editors or file systems
+/// reject
+fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let stem = stem.split_whitespace().collect::<Vec<_>>().join(" ");
+    let stem = stem.trim_start_matches('.').trim().to_string();
+    if stem.is_empty() {
+        "Untitled".to_string()
+    } else {
+        stem.chars().take(120).collect()
+    }
+}
+
+/// Whether a file is named after `title`, allowing a ` 2`-style suffix
+fn named_after(relative: &str, title: &str) -> bool {
+    let name = relative.rsplit('/').next().unwrap_or(relative);
+    let stem = name.strip_suffix(".md").unwrap_or(name);
+    let expected = file_stem(title);
+    stem == expected
+        || stem
+            .strip_prefix(&expected)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .map(|n| n.parse::<u32>().is_ok())
+            .unwrap_or(false)
+}
+
+/// A free relative path for a note named `title` in folder `dir` ("" for root)
+fn free_path(dir: &str, title: &str, taken: &HashSet<String>) -> String {
+    let stem = file_stem(title);
+    let join = |name: String| {
+        if dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir, name)
+        }
+    };
+    let mut candidate = join(format!("{}.md", stem));
+    let mut n = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = join(format!("{} {}.md", stem, n));
+        n += 1;
+    }
+    candidate
+}
+
+fn absolute(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('/')
+        .fold(root.to_path_buf(), |p, c| p.join(c))
+}
+
+fn write_file(root: &Path, relative: &str, content: &str) -> Result<String, String> {
+    let path = absolute(root, relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", relative, e))?;
+    Ok(hash(content.as_bytes()))
+}
+
+/// Keep the losing side of a conflict where it will not be synced
+fn save_conflict(root: &Path, title: &str, content: &str, now: &str) {
+    let stamp: String = now
+        .chars()
+        .map(|c| if c.is_ascii_digit() { c } else { '-' })
+        .take(19)
+        .collect();
+    let relative = format!("{}/{} {}.md", CONFLICTS_DIR, file_stem(title), stamp);
+    if let Err(e) = write_file(root, &relative, content) {
+        log::warn!("Failed to keep conflicting note: {}", e);
+    }
+}
+
+// ============================================
+// Sync
+// ============================================
+
+/// Copy an edited file's title, content and pinned state into its note
+fn update_from_file(note: &mut Note, relative: &str, file: &VaultFile, now: &str) {
+    let stem = relative
+        .rsplit('/')
+        .next()
+        .and_then(|n| n.strip_suffix(".md"))
+        .unwrap_or(relative);
+    // A renamed file renames the note; otherwise the front matter title
+    // keeps characters file names cannot hold
+    note.title = match &file.parsed.title {
+        Some(title) if named_after(relative, title) => title.clone(),
+        _ => stem.to_string(),
+    };
+    note.content = file.parsed.body.clone();
+    if let Some(pinned) = file.parsed.pinned {
+        note.is_pinned = pinned;
+    }
+    note.updated_at = file.modified.clone().unwrap_or_else(|| now.to_string());
+}
+
+fn note_from_file(id: String, relative: &str, file: &VaultFile, now: &str) -> Note {
+    let mut note = Note {
+        id,
+        title: String::new(),
+        content: String::new(),
+        is_pinned: false,
+        created_at: file
+            .parsed
+            .created
+            .clone()
+            .or_else(|| file.modified.clone())
+            .unwrap_or_else(|| now.to_string()),
+        updated_at: now.to_string(),
+        attachments: vec![],
+        notebook_id: None,
+        tags: vec![],
+        journal_date: None,
+        checklist: vec![],
+        task_sync: false,
+    };
+    update_from_file(&mut note, relative, file, now);
+    note
+}
+
+/// Sync notes and the vault folder in both directions; `attachments_dir`
+/// is the app's attachment store
+pub fn sync(
+    root: &Path,
+    attachments_dir: &Path,
+    data: &mut AppData,
+    state: &mut VaultState,
+    now: &str,
+) -> Result<VaultReport, String> {
+    if !root.is_dir() {
+        return Err(format!("Vault folder not found: {}", root.display()));
+    }
+    let mut report = VaultReport::default();
+    let files = scan(root);
+    let mut taken: HashSet<String> = files.keys().map(|f| f.to_lowercase()).collect();
+
+    // Files are matched to notes by the id in their front matter, so
+    // renaming or moving a file keeps its note; copies get a new id
+    let mut by_id: HashMap<String, String> = HashMap::new();
+    let mut paths: Vec<&String> = files.keys().collect();
+    paths.sort();
+    for relative in paths {
+        if let Some(id) = &files[relative].parsed.id {
+            by_id.entry(id.clone()).or_insert_with(|| relative.clone());
+        }
+    }
+    let mut claimed: HashSet<String> = HashSet::new();
+    let synced_notes = std::mem::take(&mut state.notes);
+
+    let mut removed_notes: HashSet<String> = HashSet::new();
+    for note in data.notes.iter_mut() {
+        let entry = state.files.get(&note.id).cloned();
+        let found = by_id.get(&note.id).cloned().or_else(|| {
+            // The front matter may have been stripped by hand
+            entry
+                .as_ref()
+                .map(|e| e.file.clone())
+                .filter(|f| files.get(f).map(|f| f.parsed.id.is_none()).unwrap_or(false))
+        });
+        let current_hash = note_hash(note);
+        let app_changed = entry
+            .as_ref()
+            .map(|e| e.note_hash != current_hash)
+            .unwrap_or(true);
+
+        let Some(relative) = found else {
+            if entry.is_some() && !app_changed {
+                // Deleted outside Carbon
+                removed_notes.insert(note.id.clone());
+                report.deleted_notes += 1;
+                continue;
+            }
+            let relative = free_path("", &note.title, &taken);
+            taken.insert(relative.to_lowercase());
+            let file_hash = write_file(root, &relative, &render_markdown(note, &[]))?;
+            report.written += 1;
+            state.files.insert(
+                note.id.clone(),
+                VaultEntry {
+                    file: relative,
+                    file_hash,
+                    note_hash: current_hash,
+                },
+            );
+            continue;
+        };
+        claimed.insert(relative.clone());
+        let file = &files[&relative];
+        let file_changed = entry
+            .as_ref()
+            .map(|e| e.file_hash != file.hash)
+            .unwrap_or(true);
+
+        let file_wins = match (file_changed, app_changed) {
+            (false, false) => None,
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            (true, true) => {
+                let file_time = file.modified.clone().unwrap_or_default();
+                let file_wins = is_newer(&file_time, &note.updated_at);
+                let same = file.parsed.body == note.content
+                    && file.parsed.pinned.unwrap_or(false) == note.is_pinned;
+                // Also when the pair was never synced, e.g. a vault shared
+                // with another machine: neither side is known to be older
+                if !same {
+                    report.conflicts += 1;
+                    if file_wins {
+                        save_conflict(root, &note.title, &render_markdown(note, &[]), now);
+                    } else if let Ok(text) = fs::read_to_string(&file.path) {
+                        save_conflict(root, &note.title, &text, now);
+                    }
+                }
+                Some(file_wins)
+            }
+        };
+
+        let (file, file_hash) = match file_wins {
+            None => (relative, file.hash.clone()),
+            Some(true) => {
+                update_from_file(note, &relative, file, now);
+                report.updated += 1;
+                (relative, file.hash.clone())
+            }
+            Some(false) => {
+                // Follow title changes with the file name
+                let target = if named_after(&relative, &note.title) {
+                    relative.clone()
+                } else {
+                    let dir = relative.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+                    let target = free_path(dir, &note.title, &taken);
+                    taken.insert(target.to_lowercase());
+                    target
+                };
+                let file_hash =
+                    write_file(root, &target, &render_markdown(note, &file.parsed.extra))?;
+                if target != relative {
+                    fs::remove_file(&file.path).ok();
+                }
+                report.written += 1;
+                (target, file_hash)
+            }
+        };
+        state.files.insert(
+            note.id.clone(),
+            VaultEntry {
+                file,
+                file_hash,
+                note_hash: note_hash(note),
+            },
+        );
+    }
+    data.notes.retain(|n| !removed_notes.contains(&n.id));
+    for id in &removed_notes {
+        state.files.remove(id);
+    }
+
+    // Files without a note: deleted in Carbon, or new
+    let note_ids: HashSet<String> = data.notes.iter().map(|n| n.id.clone()).collect();
+    let mut unclaimed: Vec<&String> = files.keys().filter(|f| !claimed.contains(*f)).collect();
+    unclaimed.sort();
+    for relative in unclaimed {
+        let file = &files[relative];
+        let known_id = file
+            .parsed
+            .id
+            .clone()
+            .filter(|id| by_id.get(id) == Some(relative) && !note_ids.contains(id));
+        if let Some(id) = &known_id {
+            // A note Carbon never had since the last sync was not deleted
+            // in Carbon; it may have been lost to a failed save instead
+            let entry = state.files.get(id).filter(|_| synced_notes.contains(id));
+            if let Some(entry) = entry {
+                if entry.file_hash == file.hash {
+                    // Deleted in Carbon and untouched since
+                    fs::remove_file(&file.path).ok();
+                    state.files.remove(id);
+                    report.deleted_files += 1;
+                    continue;
+                }
+            }
+        }
+
+        let id = known_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let note = note_from_file(id, relative, file, now);
+        // Write the front matter back so the file keeps its note
+        let file_hash = write_file(root, relative, &render_markdown(&note, &file.parsed.extra))?;
+        state.files.insert(
+            note.id.clone(),
+            VaultEntry {
+                file: relative.clone(),
+                file_hash,
+                note_hash: note_hash(&note),
+            },
+        );
+        data.notes.insert(0, note);
+        report.imported += 1;
+    }
+
+    // Bring over files the notes use, in whichever direction they are missing
+    let used = attachments::referenced_in(&[], &data.notes);
+    attachments::mirror(attachments_dir, &root.join(ATTACHMENTS_DIR), &used)?;
+    for note in &mut data.notes {
+        attachments::adopt_references(attachments_dir, note);
+    }
+
+    state.notes = data.notes.iter().map(|n| n.id.clone()).collect();
+    state.files.retain(|id, _| state.notes.contains(id));
+    state.last_sync = Some(now.to_string());
+    Ok(report)
+}
@@ -195,6 +195,70 @@ public func cloudkit_setup_subscriptions() -> Bool {
     return result
 }
 
+/// Upload an attachment file to CloudKit
+/// - Parameters:
+///   - name: Content-addressed file name (`<sha256>.<ext>`)
+///   - path: Path of the file to upload
+///   - outError: pointer to store error string (caller must free with cloudkit_free_string)
+@_cdecl("cloudkit_upload_attachment")
+public func cloudkit_upload_attachment(
+    name: UnsafePointer<CChar>,
+    path: UnsafePointer<CChar>,
+    outError: UnsafeMutablePointer<UnsafeMutablePointer<CChar>?>
+) -> Bool {
+    let nameString = String(cString: name)
+    let pathString = String(cString: path)
+    outError.pointee = nil
+    
+    var result = false
+    let semaphore = DispatchSemaphore(value: 0)
+    
+    Task {
+        let (success, error) = await CloudKitManager.shared.uploadAttachment(
+            name: nameString,
+            path: pathString
+        )
+        result = success
+        outError.pointee = strdup_safe(error)
+        semaphore.signal()
+    }
+    
+    semaphore.wait()
+    return result
+}
+
+/// Download an attachment file from CloudKit
+/// - Parameters:
+///   - name: Content-addressed file name (`<sha256>.<ext>`)
+///   - destination: Path to write the file to
+///   - outError: pointer to store error string (caller must free with cloudkit_free_string)
+@_cdecl("cloudkit_download_attachment")
+public func cloudkit_download_attachment(
+    name: UnsafePointer<CChar>,
+    destination: UnsafePointer<CChar>,
+    outError: UnsafeMutablePointer<UnsafeMutablePointer<CChar>?>
+) -> Bool {
+    let nameString = String(cString: name)
+    let destinationString = String(cString: destination)
+    outError.pointee = nil
+    
+    var result = false
+    let semaphore = DispatchSemaphore(value: 0)
+    
+    Task {
+        let (success, error) = await CloudKitManager.shared.downloadAttachment(
+            name: nameString,
+            destination: destinationString
+        )
+        result = success
+        outError.pointee = strdup_safe(error)
+        semaphore.signal()
+    }
+    
+    semaphore.wait()
+    return result
+}
+
 /// Free a string allocated by the Swift side
 @_cdecl("cloudkit_free_string")
 public func cloudkit_free_string(ptr: UnsafeMutablePointer<CChar>?) {
@@ -36,6 +36,7 @@ public class CloudKitManager {
     private let bookmarkRecordType = "Bookmark"
     private let noteRecordType = "Note"
     private let appDataRecordType = "AppData"
+    private let attachmentRecordType = "Attachment"
     
     // Change tokens for incremental sync
     private var serverChangeToken: CKServerChangeToken?
@@ -256,6 +257,69 @@ public class CloudKitManager {
         }
     }
     
+    // MARK: - Attachments
+    
+    private func attachmentRecordID(_ name: String) -> CKRecord.ID {
+        return CKRecord.ID(recordName: "attachment-\(name)")
+    }
+    
+    /// Upload an attachment file as a CKAsset.
+    /// Records are named after the file's content hash, so an existing record
+    /// already holds the same bytes and is left alone.
+    public func uploadAttachment(name: String, path: String) async -> (success: Bool, error: String?) {
+        guard await checkAccountStatus() else {
+            return (false, "iCloud account not available")
+        }
+        
+        let recordID = attachmentRecordID(name)
+        do {
+            _ = try await privateDatabase.record(for: recordID)
+            return (true, nil)
+        } catch let error as CKError where error.code == .unknownItem {
+            // Not uploaded yet
+        } catch {
+            return (false, error.localizedDescription)
+        }
+        
+        let record = CKRecord(recordType: attachmentRecordType, recordID: recordID)
+        record["name"] = name as CKRecordValue
+        record["file"] = CKAsset(fileURL: URL(fileURLWithPath: path))
+        do {
+            _ = try await privateDatabase.save(record)
+            return (true, nil)
+        } catch let error as CKError {
+            if error.code == .serverRecordChanged {
+                // Another device uploaded the same file in the meantime
+                return (true, nil)
+            }
+            return (false, ckErrorSummary(error))
+        } catch {
+            return (false, error.localizedDescription)
+        }
+    }
+    
+    /// Download an attachment file to `destination`
+    public func downloadAttachment(name: String, destination: String) async -> (success: Bool, error: String?) {
+        guard await checkAccountStatus() else {
+            return (false, "iCloud account not available")
+        }
+        
+        do {
+            let record = try await privateDatabase.record(for: attachmentRecordID(name))
+            guard let asset = record["file"] as? CKAsset, let fileURL = asset.fileURL else {
+                return (false, "Attachment record has no file")
+            }
+            let destinationURL = URL(fileURLWithPath: destination)
+            try? FileManager.default.removeItem(at: destinationURL)
+            try FileManager.default.copyItem(at: fileURL, to: destinationURL)
+            return (true, nil)
+        } catch let error as CKError {
+            return (false, ckErrorSummary(error))
+        } catch {
+            return (false, error.localizedDescription)
+        }
+    }
+    
     // MARK: - Subscriptions for Push Notifications
     
     public func setupSubscription() async -> Bool {
@@ -1,4 +1,5 @@
 import { useEffect, useState, useCallback } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { useBoardStore } from './store/boardStore';
 import Sidebar from './components/Sidebar';
 import Board from './components/Board';
@@ -9,13 +10,31 @@ import UpdateNotification from './components/UpdateNotification';
 import { useHotkeys, HOTKEYS } from './hooks/useHotkeys';
 
 function App() {
-  const { fetchData, isLoading, error, theme, activeView, setActiveView, toggleTheme } = useBoardStore();
+  const { fetchData, cacheBookmarkAssets, isLoading, error, theme, activeView, setActiveView, toggleTheme } = useBoardStore();
   const [sidebarOpen, setSidebarOpen] = useState(false);
   const [sidebarCollapsed, setSidebarCollapsed] = useState(false);
   const [showHotkeyHint, setShowHotkeyHint] = useState(false);
 
+  // Cache images of bookmarks added before the asset cache existed (or whose
+  // download failed last time); already cached files are skipped
   useEffect(() => {
-    fetchData();
+    fetchData().then(() => cacheBookmarkAssets());
+  }, [fetchData, cacheBookmarkAssets]);
+
+  // Reload when the data on disk was changed outside this window
+  useEffect(() => {
+    const events = [
+      'quick-capture', // added from the quick capture window
+      'vault-sync', // notes edited in the vault folder
+      'tasks-sync', // task lists and checklists synced on save
+      'journal-refresh', // today's journal rollup rewritten on save
+      'history-restore', // undo/redo replaced the data
+      'link-check', // a link check saved its results
+    ];
+    const unlisteners = events.map((event) => listen(event, () => fetchData()));
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
   }, [fetchData]);
 
   // Toggle sidebar collapsed state
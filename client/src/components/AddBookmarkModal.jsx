@@ -3,7 +3,7 @@ import { createPortal } from 'react-dom';
 import { useBoardStore, BOOKMARK_TAGS, TAG_COLORS } from '../store/boardStore';
 
 function AddBookmarkModal({ bookmark, onClose }) {
-  const { theme, addBookmark, updateBookmark, collections, addCustomTag, getBookmarkTags, addCollection } = useBoardStore();
+  const { theme, addBookmark, findBookmarkDuplicates, fetchBookmarkMetadata, updateBookmark, collections, addCustomTag, getBookmarkTags, addCollection } = useBoardStore();
   
   const [url, setUrl] = useState(bookmark?.url || 'https://');
   const [title, setTitle] = useState(bookmark?.title || '');
@@ -12,6 +12,9 @@ function AddBookmarkModal({ bookmark, onClose }) {
   const [collectionId, setCollectionId] = useState(bookmark?.collectionId || null);
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState('');
+  const [metadata, setMetadata] = useState(null);
+  // Bookmarks already saved for this URL; adding again needs a second click
+  const [duplicates, setDuplicates] = useState([]);
   
   // New tag creation state
   const [isCreatingTag, setIsCreatingTag] = useState(false);
@@ -50,13 +53,29 @@ function AddBookmarkModal({ bookmark, onClose }) {
     return () => document.removeEventListener('keydown', handleEscape);
   }, [onClose, isCreatingTag, isCreatingFolder]);
 
-  // Auto-fetch metadata when URL changes (only for new bookmarks)
+  // Auto-fetch metadata when URL changes (only for new bookmarks), once the
+  // user stops typing
   useEffect(() => {
-    if (!isEditing && url && isValidUrl(url) && !title) {
-      fetchMetadata(url);
-    }
+    if (isEditing || !url || !isValidUrl(url) || title) return;
+    let cancelled = false;
+    const timer = setTimeout(async () => {
+      const fetched = await fetchBookmarkMetadata(url.trim());
+      if (cancelled || !fetched) return;
+      setMetadata(fetched);
+      // Never overwrite what the user typed meanwhile
+      if (fetched.title) setTitle(prev => prev || fetched.title);
+      if (fetched.description) setDescription(prev => prev || fetched.description);
+    }, 500);
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
   }, [url, isEditing]);
 
+  useEffect(() => {
+    setDuplicates([]);
+  }, [url]);
+
   const isValidUrl = (string) => {
     try {
       new URL(string);
@@ -66,17 +85,6 @@ function AddBookmarkModal({ bookmark, onClose }) {
     }
   };
 
-  const fetchMetadata = async (targetUrl) => {
-    try {
-      const domain = new URL(targetUrl).hostname.replace('www.', '');
-      if (!title) {
-        setTitle(domain.charAt(0).toUpperCase() + domain.slice(1).split('.')[0]);
-      }
-    } catch {
-      // Ignore errors
-    }
-  };
-
   const handleToggleTag = (tagKey) => {
     setSelectedTags(prev => 
       prev.includes(tagKey) 
@@ -164,10 +172,21 @@ function AddBookmarkModal({ bookmark, onClose }) {
           collectionId,
         });
       } else {
+        if (duplicates.length === 0) {
+          const existing = await findBookmarkDuplicates(url.trim());
+          if (existing.length > 0) {
+            setDuplicates(existing);
+            return;
+          }
+        }
+        // Images only belong to the page they were fetched from
+        const page = metadata?.url === url.trim() ? metadata : null;
         await addBookmark({
           url: url.trim(),
           title: title.trim() || new URL(url).hostname,
           description: description.trim(),
+          favicon: page?.favicon,
+          image: page?.image,
           tags: selectedTags,
           collectionId,
         });
@@ -225,6 +244,21 @@ function AddBookmarkModal({ bookmark, onClose }) {
             </div>
           )}
 
+          {/* Duplicate warning */}
+          {duplicates.length > 0 && (
+            <div className="px-4 py-3 rounded-lg bg-amber-500/10 border border-amber-500/20 text-amber-400 text-sm font-mono space-y-1">
+              <p>This page is already bookmarked:</p>
+              <ul className="list-disc list-inside">
+                {duplicates.map((d) => (
+                  <li key={d.id} className="truncate" title={d.url}>
+                    {d.title || d.url}
+                  </li>
+                ))}
+              </ul>
+              <p>Click Add Anyway to save it again.</p>
+            </div>
+          )}
+
           {/* URL */}
           <div>
             <label className={`block text-sm font-mono font-medium mb-1.5 ${theme === 'dark' ? 'text-gray-300' : 'text-gray-700'}`}>
@@ -557,7 +591,7 @@ function AddBookmarkModal({ bookmark, onClose }) {
                 Saving...
               </>
             ) : (
-              isEditing ? 'Save Changes' : 'Add Bookmark'
+              isEditing ? 'Save Changes' : duplicates.length > 0 ? 'Add Anyway' : 'Add Bookmark'
             )}
           </button>
         </div>
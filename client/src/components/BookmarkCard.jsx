@@ -1,6 +1,6 @@
 import { useState } from "react";
 import { open } from "@tauri-apps/plugin-shell";
-import { useBoardStore } from "../store/boardStore";
+import { useBoardStore, cachedAssetUrl } from "../store/boardStore";
 
 function BookmarkCard({ bookmark, onEdit, isDropTarget }) {
   const {
@@ -10,6 +10,7 @@ function BookmarkCard({ bookmark, onEdit, isDropTarget }) {
     restoreBookmark,
     deleteBookmark,
     getBookmarkTags,
+    assetBaseUrl,
   } = useBoardStore();
 
   const BOOKMARK_TAGS = getBookmarkTags();
@@ -37,7 +38,12 @@ function BookmarkCard({ bookmark, onEdit, isDropTarget }) {
   };
 
   const domain = getDomain(bookmark.url);
-  const faviconUrl = bookmark.favicon || getFaviconUrl(bookmark.url);
+  // Prefer the local copies so the grid works offline
+  const faviconUrl =
+    cachedAssetUrl(assetBaseUrl, bookmark.faviconAsset) ||
+    bookmark.favicon ||
+    getFaviconUrl(bookmark.url);
+  const imageUrl = cachedAssetUrl(assetBaseUrl, bookmark.imageAsset);
   const tags = (bookmark.tags || [])
     .map((key) => BOOKMARK_TAGS[key])
     .filter(Boolean);
@@ -123,6 +129,18 @@ function BookmarkCard({ bookmark, onEdit, isDropTarget }) {
         </svg>
       </div>
 
+      {/* Preview image (only once cached) */}
+      {imageUrl && (
+        <img
+          src={imageUrl}
+          alt=""
+          className="w-full h-24 object-cover rounded-md mb-2"
+          onError={(e) => {
+            e.target.style.display = "none";
+          }}
+        />
+      )}
+
       {/* Tags */}
       {tags.length > 0 && (
         <div className="flex flex-wrap gap-1 mb-2">
@@ -2,7 +2,7 @@ import { useState } from "react";
 import { useSortable } from "@dnd-kit/sortable";
 import { CSS } from "@dnd-kit/utilities";
 import { open } from "@tauri-apps/plugin-shell";
-import { useBoardStore } from "../store/boardStore";
+import { useBoardStore, cachedAssetUrl } from "../store/boardStore";
 import EditFolderModal from "./EditFolderModal";
 
 function BookmarkFolderCard({ folder, isDropTarget, isDragOverlay, onClick }) {
@@ -11,6 +11,7 @@ function BookmarkFolderCard({ folder, isDropTarget, isDragOverlay, onClick }) {
     getBookmarksInFolder,
     deleteBookmarkFolder,
     setActiveBookmarkFolder,
+    assetBaseUrl,
   } = useBoardStore();
 
   const [showEditModal, setShowEditModal] = useState(false);
@@ -204,7 +205,10 @@ function FolderCardContent({
       {/* Stacked list of bookmarks with icons and names */}
       <div className="space-y-2 mb-2">
         {previewBookmarks.map((bookmark, index) => {
-          const faviconUrl = bookmark?.favicon || getFaviconUrl(bookmark.url);
+          const faviconUrl =
+            cachedAssetUrl(assetBaseUrl, bookmark.faviconAsset) ||
+            bookmark.favicon ||
+            getFaviconUrl(bookmark.url);
 
           const handleBookmarkClick = async (e) => {
             e.stopPropagation();
@@ -1,12 +1,13 @@
 import { useState, useEffect } from "react";
 import { createPortal } from "react-dom";
-import { useBoardStore, TAG_COLORS } from "../store/boardStore";
+import { useBoardStore, TAG_COLORS, cachedAssetUrl } from "../store/boardStore";
 
 // Folder-specific colors (includes cyber-cyan as default)
 const FOLDER_COLORS = ["bg-cyber-cyan", ...TAG_COLORS];
 
 function CreateFolderModal({ bookmarkIds, onClose, onSuccess }) {
-  const { theme, bookmarks, createBookmarkFolder } = useBoardStore();
+  const { theme, bookmarks, createBookmarkFolder, assetBaseUrl } =
+    useBoardStore();
 
   const [folderName, setFolderName] = useState("");
   const [folderColor, setFolderColor] = useState("bg-cyber-cyan");
@@ -125,7 +126,9 @@ function CreateFolderModal({ bookmarkIds, onClose, onSuccess }) {
             <div className="flex items-center gap-3">
               {selectedBookmarks.map((bookmark) => {
                 const faviconUrl =
-                  bookmark.favicon || getFaviconUrl(bookmark.url);
+                  cachedAssetUrl(assetBaseUrl, bookmark.faviconAsset) ||
+                  bookmark.favicon ||
+                  getFaviconUrl(bookmark.url);
                 return (
                   <div key={bookmark.id} className="flex items-center gap-2">
                     {faviconUrl && (
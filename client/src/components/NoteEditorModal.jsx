@@ -1,7 +1,19 @@
-import { useState, useEffect } from 'react';
+import { useState, useEffect, useRef } from 'react';
 import { createPortal } from 'react-dom';
+import { invoke } from '@tauri-apps/api/core';
 import { useBoardStore } from '../store/boardStore';
-import ReactMarkdown from 'react-markdown';
+import ReactMarkdown, { defaultUrlTransform } from 'react-markdown';
+
+const ATTACHMENT_PREFIX = 'attachment:';
+
+// Base64 contents of a pasted or dropped file
+const readAsBase64 = (file) =>
+  new Promise((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = () => resolve(String(reader.result).split(',')[1] || '');
+    reader.onerror = () => reject(reader.error);
+    reader.readAsDataURL(file);
+  });
 
 function NoteEditorModal({ note, onClose }) {
   const { theme, addNote, updateNote } = useBoardStore();
@@ -9,8 +21,15 @@ function NoteEditorModal({ note, onClose }) {
 
   const [title, setTitle] = useState(note?.title || '');
   const [content, setContent] = useState(note?.content || '');
+  const [attachments, setAttachments] = useState(note?.attachments || []);
+  const [attachmentBaseUrl, setAttachmentBaseUrl] = useState('');
   const [activeTab, setActiveTab] = useState('write'); // 'write' or 'preview'
   const [isSaving, setIsSaving] = useState(false);
+  const textareaRef = useRef(null);
+
+  useEffect(() => {
+    invoke('get_attachment_base_url').then(setAttachmentBaseUrl).catch(() => {});
+  }, []);
 
   // Focus title input on mount for new notes
   useEffect(() => {
@@ -29,9 +48,9 @@ function NoteEditorModal({ note, onClose }) {
     setIsSaving(true);
     try {
       if (isEditing) {
-        await updateNote(note.id, { title: title.trim() || 'Untitled Note', content });
+        await updateNote(note.id, { title: title.trim() || 'Untitled Note', content, attachments });
       } else {
-        await addNote({ title: title.trim() || 'Untitled Note', content });
+        await addNote({ title: title.trim() || 'Untitled Note', content, attachments });
       }
       onClose();
     } catch (error) {
@@ -41,6 +60,53 @@ function NoteEditorModal({ note, onClose }) {
     }
   };
 
+  // Store pasted or dropped files and link them at the cursor
+  const insertFiles = async (files) => {
+    const textarea = textareaRef.current;
+    const start = textarea?.selectionStart ?? content.length;
+    const end = textarea?.selectionEnd ?? content.length;
+    const links = [];
+    for (const file of files) {
+      try {
+        const attachment = await invoke('store_attachment', {
+          name: file.name || 'pasted-image.png',
+          mimeType: file.type || null,
+          data: await readAsBase64(file),
+        });
+        setAttachments((prev) =>
+          prev.some((a) => a.file === attachment.file) ? prev : [...prev, attachment]
+        );
+        const embed = attachment.mimeType.startsWith('image/') ? '!' : '';
+        links.push(`${embed}[${attachment.name}](${ATTACHMENT_PREFIX}${attachment.file})`);
+      } catch (error) {
+        console.error('Failed to attach file:', error);
+      }
+    }
+    if (links.length > 0) {
+      setContent((prev) => prev.slice(0, start) + links.join('\n') + prev.slice(end));
+    }
+  };
+
+  const handlePaste = (e) => {
+    const files = Array.from(e.clipboardData?.files || []);
+    if (files.length === 0) return;
+    e.preventDefault();
+    insertFiles(files);
+  };
+
+  const handleDrop = (e) => {
+    const files = Array.from(e.dataTransfer?.files || []);
+    if (files.length === 0) return;
+    e.preventDefault();
+    insertFiles(files);
+  };
+
+  // Point attachment links at the attachment store
+  const transformUrl = (url) =>
+    url.startsWith(ATTACHMENT_PREFIX)
+      ? `${attachmentBaseUrl}${url.slice(ATTACHMENT_PREFIX.length)}`
+      : defaultUrlTransform(url);
+
   const handleKeyDown = (e) => {
     // Save on Cmd/Ctrl + Enter
     if ((e.metaKey || e.ctrlKey) && e.key === 'Enter') {
@@ -125,8 +191,11 @@ function NoteEditorModal({ note, onClose }) {
         <div className="flex-1 overflow-hidden">
           {activeTab === 'write' ? (
             <textarea
+              ref={textareaRef}
               value={content}
               onChange={(e) => setContent(e.target.value)}
+              onPaste={handlePaste}
+              onDrop={handleDrop}
               placeholder="Start writing... (Markdown supported)"
               className={`
                 w-full h-full p-6 resize-none bg-transparent border-none outline-none font-mono text-sm leading-relaxed
@@ -140,6 +209,7 @@ function NoteEditorModal({ note, onClose }) {
             `}>
               {content ? (
                 <ReactMarkdown
+                  urlTransform={transformUrl}
                   components={{
                     h1: ({ children }) => <h1 className={`text-2xl font-bold mb-4 ${theme === 'dark' ? 'text-white' : 'text-gray-900'}`}>{children}</h1>,
                     h2: ({ children }) => <h2 className={`text-xl font-bold mb-3 ${theme === 'dark' ? 'text-white' : 'text-gray-900'}`}>{children}</h2>,
@@ -157,6 +227,7 @@ function NoteEditorModal({ note, onClose }) {
                     pre: ({ children }) => <pre className={`mb-4 rounded-lg overflow-hidden ${theme === 'dark' ? 'bg-charcoal-900' : 'bg-gray-100'}`}>{children}</pre>,
                     blockquote: ({ children }) => <blockquote className={`border-l-4 pl-4 italic mb-4 ${theme === 'dark' ? 'border-cyber-cyan text-gray-400' : 'border-cyan-500 text-gray-600'}`}>{children}</blockquote>,
                     a: ({ href, children }) => <a href={href} target="_blank" rel="noopener noreferrer" className="text-cyber-cyan hover:underline">{children}</a>,
+                    img: ({ src, alt }) => <img src={src} alt={alt} className="max-w-full rounded-lg mb-4" />,
                     hr: () => <hr className={`my-6 ${theme === 'dark' ? 'border-charcoal-600' : 'border-gray-200'}`} />,
                     strong: ({ children }) => <strong className="font-bold">{children}</strong>,
                     em: ({ children }) => <em className="italic">{children}</em>,
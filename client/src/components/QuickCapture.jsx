@@ -0,0 +1,111 @@
+import { useState, useEffect, useRef } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const KIND_NAMES = { card: 'Card', note: 'Note', bookmark: 'Bookmark' };
+
+/**
+ * Contents of the quick capture window opened by the global shortcut.
+ * Parsing and saving happen in Rust (see capture.rs); this only shows where
+ * the input will go and submits it on Enter.
+ */
+function QuickCapture() {
+  const [input, setInput] = useState('');
+  const [plan, setPlan] = useState(null);
+  const [error, setError] = useState('');
+  const [isSaving, setIsSaving] = useState(false);
+  const inputRef = useRef(null);
+
+  // Refocus whenever the window is shown again
+  useEffect(() => {
+    const focus = () => inputRef.current?.focus();
+    focus();
+    window.addEventListener('focus', focus);
+    return () => window.removeEventListener('focus', focus);
+  }, []);
+
+  // Preview where the input goes while typing
+  useEffect(() => {
+    if (!input.trim()) {
+      setPlan(null);
+      setError('');
+      return;
+    }
+    const timeoutId = setTimeout(async () => {
+      try {
+        setPlan(await invoke('preview_quick_capture', { input }));
+        setError('');
+      } catch (e) {
+        setPlan(null);
+        setError(String(e));
+      }
+    }, 150);
+    return () => clearTimeout(timeoutId);
+  }, [input]);
+
+  const hide = () => {
+    setInput('');
+    invoke('hide_quick_capture');
+  };
+
+  const submit = async () => {
+    if (!input.trim() || isSaving) return;
+    setIsSaving(true);
+    try {
+      await invoke('quick_capture', { input });
+      setInput('');
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleKeyDown = (e) => {
+    if (e.key === 'Escape') {
+      hide();
+    } else if (e.key === 'Enter' && !e.shiftKey) {
+      e.preventDefault();
+      submit();
+    }
+  };
+
+  const destination = () => {
+    if (!plan) return null;
+    if (plan.kind === 'card') {
+      return `${plan.boardName} / ${plan.columnName}`;
+    }
+    return plan.url || null;
+  };
+
+  return (
+    <div className="h-screen p-3 bg-charcoal-900 text-white flex flex-col gap-2">
+      <textarea
+        ref={inputRef}
+        value={input}
+        onChange={(e) => setInput(e.target.value)}
+        onKeyDown={handleKeyDown}
+        rows={2}
+        placeholder="card: Fix login #bug !high @board:Backend — or paste a URL"
+        className="w-full flex-1 resize-none bg-charcoal-800 rounded-xl px-4 py-3 text-sm outline-none border border-white/10 focus:border-cyber-cyan/60 placeholder:text-gray-500"
+      />
+      <div className="flex items-center justify-between text-xs px-1 min-h-[1rem]">
+        {error ? (
+          <span className="text-red-400 truncate">{error}</span>
+        ) : plan ? (
+          <span className="text-gray-400 truncate">
+            <span className="text-cyber-cyan">{KIND_NAMES[plan.kind]}</span>
+            {destination() && <> → {destination()}</>}
+            {plan.labels.length > 0 && <> · {plan.labels.map((l) => `#${l}`).join(' ')}</>}
+            {plan.tags.length > 0 && <> · {plan.tags.map((t) => `#${t}`).join(' ')}</>}
+            {plan.priority && <> · !{plan.priority}</>}
+          </span>
+        ) : (
+          <span className="text-gray-500">Enter to save · Shift+Enter for a new line · Esc to close</span>
+        )}
+        {isSaving && <span className="text-gray-400">Saving…</span>}
+      </div>
+    </div>
+  );
+}
+
+export default QuickCapture;
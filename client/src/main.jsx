@@ -1,10 +1,12 @@
 import React from 'react'
 import ReactDOM from 'react-dom/client'
 import App from './App.jsx'
+import QuickCapture from './components/QuickCapture.jsx'
 import './index.css'
 
 ReactDOM.createRoot(document.getElementById('root')).render(
   <React.StrictMode>
-    <App />
+    {/* The quick capture window loads index.html#capture */}
+    {window.location.hash === '#capture' ? <QuickCapture /> : <App />}
   </React.StrictMode>,
 )
@@ -195,6 +195,10 @@ const api = {
   },
 };
 
+// URL of a cached bookmark image, or null when it is not cached
+export const cachedAssetUrl = (assetBaseUrl, asset) =>
+  assetBaseUrl && asset?.file ? `${assetBaseUrl}${asset.file}` : null;
+
 export const useBoardStore = create((set, get) => ({
   // State
   boards: [],
@@ -214,9 +218,11 @@ export const useBoardStore = create((set, get) => ({
   activeTag: null,
   bookmarkSearch: "",
   bookmarkSort: "date", // 'date', 'title', 'domain'
+  assetBaseUrl: null, // Prefix of cached favicons/preview images (Tauri only)
 
   // Notes state
   notes: [],
+  notebooks: [], // Nested notebooks for grouping notes
   noteSearch: "",
 
   // View state
@@ -249,6 +255,16 @@ export const useBoardStore = create((set, get) => ({
 
       const data = await api.readData();
 
+      // Cached bookmark images are served by the backend's asset protocol
+      let assetBaseUrl = get().assetBaseUrl;
+      if (isTauri() && !assetBaseUrl) {
+        try {
+          assetBaseUrl = await invoke("get_asset_base_url");
+        } catch (e) {
+          console.log("Asset base URL unavailable:", e);
+        }
+      }
+
       // Merge custom tags with default tags
       const customTags = data.customTags || {};
       BOOKMARK_TAGS = { ...DEFAULT_BOOKMARK_TAGS, ...customTags };
@@ -274,6 +290,7 @@ export const useBoardStore = create((set, get) => ({
         theme: data.theme || "dark",
         bookmarks: data.bookmarks || [],
         bookmarkFolders: data.bookmarkFolders || [],
+        assetBaseUrl,
         collections: data.collections || [
           { id: "all", name: "All Bookmarks", icon: "bookmark" },
           { id: "favorites", name: "Favorites", icon: "star" },
@@ -281,6 +298,7 @@ export const useBoardStore = create((set, get) => ({
         ],
         customTags: customTags,
         notes: data.notes || [],
+        notebooks: data.notebooks || [],
         activeView: data.activeView || "boards",
         syncEnabled: data.syncEnabled || false,
         lastModified: data.lastModified || new Date().toISOString(),
@@ -324,6 +342,7 @@ export const useBoardStore = create((set, get) => ({
       collections,
       customTags,
       notes,
+      notebooks,
       activeView,
       syncEnabled,
     } = get();
@@ -339,6 +358,7 @@ export const useBoardStore = create((set, get) => ({
         collections,
         customTags,
         notes,
+        notebooks,
         activeView,
         lastModified,
         syncEnabled,
@@ -1159,6 +1179,30 @@ export const useBoardStore = create((set, get) => ({
     };
   },
 
+  // Existing bookmarks for the same page as `url` (ignoring scheme, www.
+  // and tracking parameters), to warn about before adding another
+  findBookmarkDuplicates: async (url) => {
+    if (!isTauri() || !invoke) return [];
+    try {
+      return await invoke("find_bookmark_duplicates", { url });
+    } catch (error) {
+      console.error("Failed to look for duplicate bookmarks:", error);
+      return [];
+    }
+  },
+
+  // Title, description, favicon and preview image of the page at `url`,
+  // or null when it cannot be fetched
+  fetchBookmarkMetadata: async (url) => {
+    if (!isTauri() || !invoke) return null;
+    try {
+      return await invoke("fetch_bookmark_metadata", { url });
+    } catch (error) {
+      console.error("Failed to fetch bookmark metadata:", error);
+      return null;
+    }
+  },
+
   // Add bookmark
   addBookmark: async (bookmarkData) => {
     const newBookmark = {
@@ -1179,9 +1223,33 @@ export const useBoardStore = create((set, get) => ({
       bookmarks: [newBookmark, ...state.bookmarks],
     }));
     await get().saveData();
+    get().cacheBookmarkAssets([newBookmark.id]);
     return newBookmark;
   },
 
+  // Download favicons/preview images into the local cache and pick up the
+  // cached files without touching anything edited meanwhile. Every bookmark
+  // is considered when no ids are given.
+  cacheBookmarkAssets: async (bookmarkIds) => {
+    if (!isTauri() || !invoke) return;
+    try {
+      const result = await invoke("cache_bookmark_assets", { bookmarkIds });
+      if (!result?.report?.cached) return;
+      const cached = new Map(result.data.bookmarks.map((b) => [b.id, b]));
+      set((state) => ({
+        bookmarks: state.bookmarks.map((b) => {
+          const fresh = cached.get(b.id);
+          return fresh
+            ? { ...b, faviconAsset: fresh.faviconAsset, imageAsset: fresh.imageAsset }
+            : b;
+        }),
+        lastModified: result.data.lastModified,
+      }));
+    } catch (error) {
+      console.error("Failed to cache bookmark images:", error);
+    }
+  },
+
   // Update bookmark
   updateBookmark: async (bookmarkId, updates) => {
     set((state) => ({
@@ -1498,7 +1566,7 @@ export const useBoardStore = create((set, get) => ({
     // Remove from global BOOKMARK_TAGS
     delete BOOKMARK_TAGS[tagKey];
 
-    // Remove tag from all bookmarks that have it
+    // Remove tag from all bookmarks and notes that have it
     set((state) => {
       const newCustomTags = { ...state.customTags };
       delete newCustomTags[tagKey];
@@ -1509,6 +1577,11 @@ export const useBoardStore = create((set, get) => ({
           ...b,
           tags: (b.tags || []).filter((t) => t !== tagKey),
         })),
+        notes: state.notes.map((n) =>
+          n.tags?.includes(tagKey)
+            ? { ...n, tags: n.tags.filter((t) => t !== tagKey) }
+            : n
+        ),
       };
     });
     await get().saveData();
@@ -1521,7 +1594,12 @@ export const useBoardStore = create((set, get) => ({
       return { error: "Cannot update default tags" };
     }
 
-    const currentTag = BOOKMARK_TAGS[tagKey];
+    // Notes can carry a tag key without a custom tag behind it (the notes
+    // sidebar shows it by key), so give it one instead of failing
+    const usedByNote = get().notes.some((n) => n.tags?.includes(tagKey));
+    const currentTag =
+      BOOKMARK_TAGS[tagKey] ||
+      (usedByNote ? { name: tagKey, color: TAG_COLORS[0] } : null);
     if (!currentTag) {
       return { error: "Tag not found" };
     }
@@ -1577,6 +1655,9 @@ export const useBoardStore = create((set, get) => ({
       id: uuidv4(),
       title: noteData.title || "Untitled Note",
       content: noteData.content || "",
+      attachments: noteData.attachments || [],
+      notebookId: noteData.notebookId || null,
+      tags: noteData.tags || [],
       isPinned: false,
       createdAt: now,
       updatedAt: now,
@@ -1670,6 +1751,7 @@ export const useBoardStore = create((set, get) => ({
       collections,
       customTags,
       notes,
+      notebooks,
       activeView,
       lastModified,
     } = get();
@@ -1698,6 +1780,7 @@ export const useBoardStore = create((set, get) => ({
         collections,
         customTags,
         notes,
+        notebooks,
         activeView,
         lastModified,
         syncEnabled,
@@ -1732,6 +1815,7 @@ export const useBoardStore = create((set, get) => ({
               collections: remoteData.collections || get().collections,
               customTags: remoteTags,
               notes: remoteData.notes || [],
+              notebooks: remoteData.notebooks || [],
               // Do not sync UI navigation across devices
               activeView: currentActiveView,
               lastModified:
@@ -1813,6 +1897,7 @@ export const useBoardStore = create((set, get) => ({
           collections: remoteData.collections || get().collections,
           customTags: remoteTags,
           notes: remoteData.notes || [],
+          notebooks: remoteData.notebooks || [],
           // Do not sync UI navigation across devices
           activeView: currentActiveView,
           lastModified: result.remoteLastModified || remoteData.lastModified,